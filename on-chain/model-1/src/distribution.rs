@@ -0,0 +1,201 @@
+//! Fixed-point prize pool distribution
+use crate::{error::LotteryError, state::MAX_LOTTERY_CHARITIES};
+use solana_program::{entrypoint::ProgramResult, msg, program_error::ProgramError};
+
+/// Basis points in the whole amount
+pub const MAX_BPS: u16 = 10_000;
+
+/// Share of the prize pool split to winners by the prize table
+pub const WINNERS_SHARE_BPS: u16 = 6_500;
+/// Share of the prize pool split between the charities with the most votes
+pub const CHARITY_SHARE_BPS: u16 = 3_000;
+/// Share of the prize pool transferred to the Sollotto rewards wallet
+pub const SOLLOTTO_REWARDS_SHARE_BPS: u16 = 400;
+/// Share of the prize pool transferred to the SLOT holders rewards wallet
+pub const SLOT_HOLDERS_SHARE_BPS: u16 = 60;
+/// Share of the prize pool transferred to the Sollotto labs wallet
+pub const SOLLOTTO_LABS_SHARE_BPS: u16 = 40;
+/// Max share of the ticket price accrued to the ticket referrer
+pub const MAX_REFERRAL_SHARE_BPS: u16 = 1_000;
+
+/// Way the charity pool is split by the charity votes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CharityMode {
+    /// Charity with the most votes takes the pool, tied charities split it equally
+    WinnerTakesAll,
+    /// Every charity takes the share of the pool matching its share of the votes
+    Proportional,
+    /// Up to this count of charities with the most votes split the pool equally,
+    /// tied charities are taken in the lottery charities order
+    TopK(u8),
+}
+
+impl Default for CharityMode {
+    fn default() -> Self {
+        CharityMode::WinnerTakesAll
+    }
+}
+
+impl CharityMode {
+    /// Checks the top charities count
+    pub fn validate(&self) -> ProgramResult {
+        if let CharityMode::TopK(count) = self {
+            if *count == 0 || *count as usize > MAX_LOTTERY_CHARITIES {
+                msg!(
+                    "Top charities count must be from 1 to {}",
+                    MAX_LOTTERY_CHARITIES
+                );
+                return Err(LotteryError::InvalidCharityMode.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits the charity pool by the charity votes, the rewards are in the lottery charities order.
+/// Without any votes the pool is split equally.
+pub fn charity_rewards(
+    charity_pool: u64,
+    votes: &[u32],
+    mode: CharityMode,
+) -> Result<Vec<u64>, ProgramError> {
+    mode.validate()?;
+
+    let mut rewards = vec![0; votes.len()];
+    let total_votes: u64 = votes.iter().map(|votes| *votes as u64).sum();
+    let winners: Vec<usize> = match mode {
+        CharityMode::Proportional if total_votes != 0 => {
+            for (reward, votes) in rewards.iter_mut().zip(votes.iter()) {
+                *reward = (charity_pool as u128 * *votes as u128 / total_votes as u128) as u64;
+            }
+            return Ok(rewards);
+        }
+        CharityMode::Proportional => (0..votes.len()).collect(),
+        CharityMode::WinnerTakesAll => {
+            let max_votes = votes.iter().max().unwrap_or(&0);
+            (0..votes.len())
+                .filter(|i| votes[*i] == *max_votes)
+                .collect()
+        }
+        CharityMode::TopK(count) => {
+            let mut order: Vec<usize> = (0..votes.len()).collect();
+            // Stable sort keeps tied charities in order
+            order.sort_by(|a, b| votes[*b].cmp(&votes[*a]));
+            order.truncate(count as usize);
+            order
+        }
+    };
+
+    let (reward, _) = split_evenly(charity_pool, winners.len() as u32)?;
+    for i in winners {
+        rewards[i] = reward;
+    }
+    Ok(rewards)
+}
+
+/// Checks the shares don't add up to more than the whole amount
+pub fn validate_shares(shares: &[u16]) -> ProgramResult {
+    let total: u32 = shares.iter().map(|share| *share as u32).sum();
+    if total > MAX_BPS as u32 {
+        msg!("Shares add up to {} basis points", total);
+        return Err(LotteryError::InvalidShares.into());
+    }
+    Ok(())
+}
+
+/// Share of the amount in basis points, rounded down
+pub fn share_of(amount: u64, share_bps: u16) -> Result<u64, ProgramError> {
+    if share_bps > MAX_BPS {
+        msg!("Share {} is over {} basis points", share_bps, MAX_BPS);
+        return Err(LotteryError::InvalidShares.into());
+    }
+    let share = amount as u128 * share_bps as u128 / MAX_BPS as u128;
+    Ok(share as u64)
+}
+
+/// Splits the amount into equal parts, returns a part and the remainder dust
+pub fn split_evenly(amount: u64, parts: u32) -> Result<(u64, u64), ProgramError> {
+    if parts == 0 {
+        return Ok((0, amount));
+    }
+    let part = amount / parts as u64;
+    let dust = amount
+        .checked_sub(part * parts as u64)
+        .ok_or(LotteryError::AmountOverflow)?;
+    Ok((part, dust))
+}
+
+// Unit tests
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pool_shares() {
+        validate_shares(&[
+            WINNERS_SHARE_BPS,
+            CHARITY_SHARE_BPS,
+            SOLLOTTO_REWARDS_SHARE_BPS,
+            SLOT_HOLDERS_SHARE_BPS,
+            SOLLOTTO_LABS_SHARE_BPS,
+        ])
+        .unwrap();
+        assert_eq!(
+            Err(LotteryError::InvalidShares.into()),
+            validate_shares(&[6_500, 5_000, 400])
+        );
+    }
+
+    #[test]
+    fn test_share_of() {
+        assert_eq!(share_of(200_000_000, 6_500), Ok(130_000_000));
+        assert_eq!(share_of(999, 3_000), Ok(299));
+        assert_eq!(share_of(u64::MAX, MAX_BPS), Ok(u64::MAX));
+        assert_eq!(
+            Err(LotteryError::InvalidShares.into()),
+            share_of(1, MAX_BPS + 1)
+        );
+    }
+
+    #[test]
+    fn test_charity_rewards() {
+        let votes = [5, 3, 5, 0];
+        // Top charities count may be above the lottery charities count
+        assert_eq!(
+            charity_rewards(1_000, &votes, CharityMode::TopK(8)),
+            Ok(vec![250, 250, 250, 250])
+        );
+        assert_eq!(
+            charity_rewards(1_000, &votes, CharityMode::WinnerTakesAll),
+            Ok(vec![500, 0, 500, 0])
+        );
+        assert_eq!(
+            charity_rewards(1_000, &votes, CharityMode::Proportional),
+            Ok(vec![384, 230, 384, 0])
+        );
+        assert_eq!(
+            charity_rewards(1_000, &votes, CharityMode::TopK(3)),
+            Ok(vec![333, 333, 333, 0])
+        );
+        assert_eq!(
+            charity_rewards(1_000, &[1, 2, 2, 3], CharityMode::TopK(2)),
+            Ok(vec![0, 500, 0, 500])
+        );
+        // Without votes the pool is split equally
+        assert_eq!(
+            charity_rewards(1_000, &[0; 4], CharityMode::Proportional),
+            Ok(vec![250; 4])
+        );
+        assert_eq!(
+            Err(LotteryError::InvalidCharityMode.into()),
+            charity_rewards(1_000, &votes, CharityMode::TopK(9))
+        );
+    }
+
+    #[test]
+    fn test_split_evenly() {
+        assert_eq!(split_evenly(100, 3), Ok((33, 1)));
+        assert_eq!(split_evenly(100, 0), Ok((0, 100)));
+        assert_eq!(split_evenly(u64::MAX, 1), Ok((u64::MAX, 0)));
+    }
+}
@@ -41,6 +41,108 @@ pub enum LotteryError {
     /// Invalid vrf result
     #[error("Invalid random result")]
     InvalidRandomResult,
+    /// Invalid sale or draw schedule
+    #[error("Invalid lottery schedule")]
+    InvalidSchedule,
+    /// Ticket sale is not started yet
+    #[error("Ticket sale is not started")]
+    SaleNotStarted,
+    /// Ticket sale is already closed
+    #[error("Ticket sale is closed")]
+    SaleClosed,
+    /// Draw time is not reached yet
+    #[error("Draw time is not reached")]
+    DrawNotReady,
+    /// Account address does not match its program derived address
+    #[error("Invalid program derived account address")]
+    InvalidAccountAddress,
+    /// Signer is not the lottery authority
+    #[error("Invalid lottery authority")]
+    InvalidAuthority,
+    /// Ticket is already processed by this settlement step
+    #[error("Ticket is already processed")]
+    TicketAlreadyProcessed,
+    /// Not all tickets are tallied yet
+    #[error("Settlement tally is not complete")]
+    TallyNotComplete,
+    /// Not all tickets are paid out yet
+    #[error("Settlement payout is not complete")]
+    PayoutNotComplete,
+    /// Ticket has no prize recorded
+    #[error("Nothing to claim")]
+    NothingToClaim,
+    /// Ticket prize is already claimed
+    #[error("Prize is already claimed")]
+    PrizeAlreadyClaimed,
+    /// Claim period is over
+    #[error("Claim period is over")]
+    ClaimExpired,
+    /// Claim period is not over yet
+    #[error("Claim period is not over")]
+    ClaimPeriodNotOver,
+    /// Unclaimed prizes are already swept
+    #[error("Unclaimed prizes are already swept")]
+    AlreadySwept,
+    /// Invalid lottery number format
+    #[error("Invalid number format")]
+    InvalidNumberFormat,
+    /// Main numbers are not unique
+    #[error("Duplicate numbers")]
+    DuplicateNumbers,
+    /// Invalid prize table
+    #[error("Invalid prize table")]
+    InvalidPrizeTable,
+    /// Rollover can't be taken from the lottery
+    #[error("Invalid rollover")]
+    InvalidRollover,
+    /// Shares add up to more than 10000 basis points
+    #[error("Invalid shares")]
+    InvalidShares,
+    /// Amount overflow
+    #[error("Amount overflow")]
+    AmountOverflow,
+    /// Invalid ticket price
+    #[error("Invalid ticket price")]
+    InvalidTicketPrice,
+    /// Invalid charity mode
+    #[error("Invalid charity mode")]
+    InvalidCharityMode,
+    /// Ticket is not settled
+    #[error("Ticket is not settled")]
+    TicketNotSettled,
+    /// Invalid randomness provider
+    #[error("Invalid randomness provider")]
+    InvalidRandomnessProvider,
+    /// Randomness result is already used by a draw
+    #[error("Stale randomness")]
+    StaleRandomness,
+    /// Revealed secret doesn't match the commitment
+    #[error("Invalid commitment")]
+    InvalidCommitment,
+    /// Commit-reveal deadline is passed
+    #[error("Reveal expired")]
+    RevealExpired,
+    /// Ticket can't be refunded
+    #[error("Refund not available")]
+    RefundNotAvailable,
+    /// Ticket is already refunded
+    #[error("Already refunded")]
+    AlreadyRefunded,
+    /// Lottery is cancelled
+    #[error("Lottery is cancelled")]
+    IsCancelled,
+    /// Token account has another mint or owner
+    #[error("Invalid token account")]
+    InvalidTokenAccount,
+    /// Account is not the lottery vault, or the vault can't be changed once the lottery has funds
+    #[error("Invalid vault")]
+    InvalidVault,
+    /// Lifetime Ticket mint is not minted by the Lifetime Ticket authority
+    #[error("Invalid lifetime ticket mint")]
+    InvalidLifetimeTicketMint,
+    /// Referrer is the ticket buyer, or the lottery doesn't accept referrals
+    #[error("Invalid referrer")]
+    InvalidReferrer,
 }
 
 impl From<LotteryError> for ProgramError {
@@ -1,10 +1,24 @@
 //! Instruction types
+use crate::distribution::CharityMode;
 use crate::error::LotteryError::InvalidInstruction;
+use crate::numbers::{to_lottery_numbers, LotteryNumbers, NUMBERS_LEN};
 use crate::processor::check_program_account;
+use crate::randomness::RandomnessProvider;
+use crate::state::{
+    find_charity_registry_address, find_lifetime_ticket_authority_address, find_lottery_address,
+    find_lottery_result_address, find_prize_table_address, find_referral_address,
+    find_reveal_address, find_settlement_address, find_ticket_address, find_token_vault_address,
+    find_vault_address, find_vault_authority_address, pack_charity_mode, pack_randomness_provider,
+    unpack_charity_mode, unpack_randomness_provider, PrizeTier, MAX_LOTTERY_CHARITIES,
+    MAX_PRIZE_TIERS, MAX_TICKET_LINES,
+};
 use solana_program::{
+    bpf_loader_upgradeable,
+    clock::UnixTimestamp,
     instruction::{AccountMeta, Instruction},
     msg,
     program_error::ProgramError,
+    program_pack::Pack,
     pubkey::Pubkey,
     sysvar,
 };
@@ -15,85 +29,422 @@ use std::{convert::TryInto, mem::size_of};
 #[derive(Clone, Debug, PartialEq)]
 pub enum LotteryInstruction {
     /// Initialize new lottery data
+    /// Lottery data account is created by the program
     /// Accounts expected by this instruction:
     ///
-    /// 0. `[writable, signer]` Lottery data account
-    /// 1. `[]` Rent sysvar
+    /// 0. `[writable]` Lottery data account (PDA of lottery id)
+    /// 1. `[writable, signer]` Payer of lottery data account rent
+    /// 2. `[signer]` Lottery authority, must be the program upgrade authority
+    /// 3. `[]` Rent sysvar
+    /// 4. `[]` Clock sysvar
+    /// 5. `[]` System program account
+    /// 6. `[]` Charity registry data account (PDA)
+    /// 7. `[writable]` Vault of the lottery (PDA of lottery), the payer funds its rent
+    /// 8. `[]` Program data account of the program, holding its upgrade authority
+    /// 9. `[writable]` Optional, previous lottery data account, already reset by `RewardWinners`,
+    ///    or cancelled or abandoned with every ticket refunded.
+    ///    Its rollover amount is added to the prize pool.
+    /// 10. `[writable]` Vault of the previous lottery, required with the previous lottery.
+    ///     Rollover lamports are moved from it into the lottery vault, rollover tokens stay in
+    ///     the vault token account shared with the previous lottery.
     InitLottery {
         lottery_id: u32,
-        charity_1: Pubkey,
-        charity_2: Pubkey,
-        charity_3: Pubkey,
-        charity_4: Pubkey,
-        holding_wallet: Pubkey,
+        /// Up to `MAX_LOTTERY_CHARITIES` active charities, by index in the charity registry
+        charities: Vec<u16>,
         rewards_wallet: Pubkey,
         slot_holders_rewards_wallet: Pubkey,
         sollotto_labs_wallet: Pubkey,
         randomness_account: Pubkey,
+        sale_start: UnixTimestamp,
+        sale_end: UnixTimestamp,
+        draw_time: UnixTimestamp,
+        claim_period: i64,
+        main_pool_size: u8,
+        bonus_pool_size: u8,
+        pick_count: u8,
+        /// Price of a single ticket line, in lamports or base units of the mint set by `InitVault`
+        ticket_price: u64,
+        charity_mode: CharityMode,
+        /// Kind of the randomness account
+        randomness_provider: RandomnessProvider,
+        /// Hash of the operator secret for the commit-reveal randomness provider
+        commitment: [u8; 32],
     },
 
     /// User purchases new ticket for lottery
+    /// Ticket data account is created by the program
     /// Accounts expected by this instruction:
     ///
-    /// 0. `[writable, signer]` Lottery data account
-    /// 1. `[writable]` Users ticket data account
-    /// 2. `[writable,signer]` User funding account (must be a system account)
-    /// 3. `[writable]` Sollotto holding wallet account (must be a system account)
-    /// 4. `[wirtable]` User's SolLotto Lifetime Ticket Account
-    /// 5. `[signer]` SolLotto Lifetime Ticket mint authority
-    /// 6. `[writable]` SolLotto Lifetime Ticket Mint
-    /// 7. `[]` Rent sysvar
-    /// 8. `[]` System program account
-    /// 9. `[]` SPL Token program account
+    /// 0. `[writable]` Lottery data account (PDA of lottery id)
+    /// 1. `[writable]` Users ticket data account (PDA of lottery, user wallet and ticket nonce)
+    /// 2. `[writable,signer]` User funding account, pays ticket price and rent (must be a system account)
+    /// 3. `[writable]` Vault of the lottery (PDA of lottery), vault token account of a token lottery
+    /// 4. `[]` Rent sysvar
+    /// 5. `[]` Clock sysvar
+    /// 6. `[]` System program account
+    /// 7. `[]` SPL Token program account
+    ///
+    /// Lotteries minting Lifetime Tickets take next:
+    ///
+    /// 8. `[writable]` User's SolLotto Lifetime Ticket token account
+    /// 9. `[writable]` SolLotto Lifetime Ticket mint of the lottery
+    /// 10. `[]` Lifetime Ticket authority (PDA)
+    ///
+    /// Token lotteries take the user token account paying the ticket price as the last account.
+    ///
+    /// Lamport lotteries take an optional referrer as the last accounts,
+    /// the referral share of the ticket price is accrued to the referrer:
+    ///
+    /// 0. `[]` Referrer wallet
+    /// 1. `[writable]` Referral ledger of the referrer (PDA of lottery and referrer),
+    /// created by the program paid by the user
     PurchaseTicket {
-        charity: Pubkey,
+        /// Index of the voted charity in the charity registry
+        charity_index: u16,
         user_wallet_pk: Pubkey,
-        ticket_number_arr: [u8; 6],
+        ticket_number_arr: LotteryNumbers,
+        ticket_nonce: u32,
     },
 
     /// Store the winning combination into lottery data account
     /// Accounts expected by this instruction:
     ///
-    /// 0. `[writable, signer]` Lottery data account
-    /// 1. `[]` Vrf account
+    /// 0. `[writable]` Lottery data account (PDA of lottery id)
+    /// 1. `[signer]` Lottery authority
+    /// 2. `[]` Randomness account of the lottery, read by the lottery randomness provider
+    /// 3. `[]` Clock sysvar
     StoreWinningNumbers {},
 
-    /// Check users number combinations and find the lottery winner.
-    /// Information obout winner sotored in LotteryResultData account,
+    /// Finish the lottery settlement after all tickets are tallied and paid out:
+    /// transfer charity and sollotto rewards and reset the lottery.
+    /// Audit record of the draw is stored in LotteryResultData account,
+    /// which is created by the program.
     /// Accounts expected by this instruction:
     ///
-    /// 0. `[writable, signer]` Lottery data account
-    /// 1. `[writable]` Lottery result data account
-    /// 2. `[writable, signer]` Sollotto holding wallet account (must be a system account)
-    /// 3. `[writable]` Solloto rewards wallet account (must be a system account)
-    /// 4. `[writable]` SLOT holders wallet account (must be a system account)
-    /// 5. `[writable]` Solloto labs wallet account (must be a system account)
-    /// 6-9. `[writable]` Charities wallet accounts (must be a system account)
-    /// 10. `[]` System program account
-    /// 10 + N*2. `[]` N*2 readonly percipients accounts pairs: (ticket_acc, user_wallet_acc (system account))
+    /// 0. `[writable]` Lottery data account (PDA of lottery id)
+    /// 1. `[writable]` Lottery result data account (PDA of lottery id)
+    /// 2. `[]` Settlement data account (PDA of lottery)
+    /// 3. `[writable, signer]` Payer of lottery result data account rent
+    /// 4. `[signer]` Lottery authority
+    /// 5. `[writable]` Vault of the lottery (PDA of lottery)
+    /// 6. `[writable]` Solloto rewards wallet account (must be a system account)
+    /// 7. `[writable]` SLOT holders wallet account (must be a system account)
+    /// 8. `[writable]` Solloto labs wallet account (must be a system account)
+    /// 9. `[]` Charity registry data account (PDA)
+    /// 10. `[]` Rent sysvar
+    /// 11. `[]` System program account
+    /// 12+. `[writable]` Charities wallet accounts, in the lottery charities order
+    ///    (must be a system account)
+    ///
+    /// Token lotteries take the vault token account instead of the vault, and token accounts
+    /// of the mint owned by the rewards and charity wallets.
+    /// The charity accounts are followed by:
+    ///
+    /// 12 + N. `[]` Vault authority (PDA)
+    /// 13 + N. `[]` SPL Token program account
     RewardWinners {},
 
-    /// Update charity wallets in lottery data account
+    /// Update charities of the lottery, votes of the kept charities are kept.
+    /// Charities are not updated once the winning numbers are stored
     /// Accounts expected by this instruction:
     ///
-    /// 0. `[writable, signer]` Lottery data account
+    /// 0. `[writable]` Lottery data account (PDA of lottery id)
+    /// 1. `[signer]` Lottery authority
+    /// 2. `[]` Charity registry data account (PDA)
     UpdateCharity {
-        charity_1: Pubkey,
-        charity_2: Pubkey,
-        charity_3: Pubkey,
-        charity_4: Pubkey,
+        /// Up to `MAX_LOTTERY_CHARITIES` active charities, by index in the charity registry
+        charities: Vec<u16>,
     },
 
     /// Update sollotto wallets in lottery data account
     /// Accounts expected by this instruction:
     ///
-    /// 0. `[writable, signer]` Lottery data account
+    /// 0. `[writable]` Lottery data account (PDA of lottery id)
+    /// 1. `[signer]` Lottery authority
     UpdateSollottoWallets {
-        holding_wallet: Pubkey,
         rewards_wallet: Pubkey,
         slot_holders_rewards_wallet: Pubkey,
         sollotto_labs_wallet: Pubkey,
     },
+
+    /// Propose a new lottery authority, it takes control only after `AcceptAuthority`.
+    /// Default pubkey cancels the pending proposal.
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Lottery data account (PDA of lottery id)
+    /// 1. `[signer]` Lottery authority
+    SetAuthority { new_authority: Pubkey },
+
+    /// Pending lottery authority accepts the control over the lottery
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Lottery data account (PDA of lottery id)
+    /// 1. `[signer]` Pending lottery authority
+    AcceptAuthority {},
+
+    /// Count matched numbers of a batch of tickets into the settlement data account,
+    /// which is created by the program on the first batch.
+    /// Tier rewards are fixed when the last ticket is tallied.
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[]` Lottery data account (PDA of lottery id)
+    /// 1. `[writable]` Settlement data account (PDA of lottery)
+    /// 2. `[]` Prize table data account (PDA of lottery)
+    /// 3. `[writable, signer]` Payer of settlement data account rent
+    /// 4. `[signer]` Lottery authority
+    /// 5. `[]` Rent sysvar
+    /// 6. `[]` System program account
+    /// 7 + N. `[writable]` N ticket data accounts
+    TallyBatch {},
+
+    /// Record tier prizes of a batch of tallied tickets and move them
+    /// from the lottery vault into the settlement account,
+    /// where winners claim them with `ClaimPrize`.
+    /// The claim period starts when the batch paying the last winner is processed
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Lottery data account (PDA of lottery id)
+    /// 1. `[writable]` Settlement data account (PDA of lottery)
+    /// 2. `[]` Prize table data account (PDA of lottery)
+    /// 3. `[signer]` Lottery authority
+    /// 4. `[writable]` Vault of the lottery (PDA of lottery),
+    ///    vault token account of a token lottery, which keeps the prizes
+    /// 5. `[]` System program account
+    /// 6. `[]` Clock sysvar
+    /// 7 + N. `[writable]` N ticket data accounts
+    PayoutBatch {},
+
+    /// Ticket owner withdraws the ticket prize from the settlement account
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Settlement data account (PDA of lottery)
+    /// 1. `[writable]` Ticket data account (PDA of lottery, user wallet and ticket nonce)
+    /// 2. `[writable, signer]` User wallet account
+    /// 3. `[]` Clock sysvar
+    /// 4. `[writable]` Vault token account, only for token lotteries
+    /// 5. `[writable]` User token account receiving the prize, only for token lotteries
+    /// 6. `[]` Vault authority (PDA), only for token lotteries
+    /// 7. `[]` SPL Token program account, only for token lotteries
+    ClaimPrize {},
+
+    /// Move prizes which are not claimed in the claim period to the rewards wallet
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[]` Lottery data account (PDA of lottery id), already reset by `RewardWinners`
+    /// 1. `[writable]` Settlement data account (PDA of lottery)
+    /// 2. `[signer]` Lottery authority
+    /// 3. `[writable]` Solloto rewards wallet account, its token account for token lotteries
+    /// 4. `[]` Clock sysvar
+    /// 5. `[writable]` Vault token account, only for token lotteries
+    /// 6. `[]` Vault authority (PDA), only for token lotteries
+    /// 7. `[]` SPL Token program account, only for token lotteries
+    SweepUnclaimed {},
+
+    /// Set prize tiers of the lottery, before the winning numbers are stored.
+    /// Prize table data account is created by the program.
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[]` Lottery data account (PDA of lottery id)
+    /// 1. `[writable]` Prize table data account (PDA of lottery)
+    /// 2. `[writable, signer]` Payer of prize table data account rent
+    /// 3. `[signer]` Lottery authority
+    /// 4. `[]` Rent sysvar
+    /// 5. `[]` System program account
+    SetPrizeTable { tiers: Vec<PrizeTier> },
+
+    /// User purchases new ticket with several number lines for lottery,
+    /// paying the ticket price for every line
+    /// Ticket data account is created by the program
+    /// Accounts expected by this instruction are the same as for `PurchaseTicket`
+    PurchaseTickets {
+        charity_index: u16,
+        user_wallet_pk: Pubkey,
+        /// Up to `MAX_TICKET_LINES` number lines
+        ticket_numbers: Vec<LotteryNumbers>,
+        ticket_nonce: u32,
+    },
+
+    /// User purchases new ticket with a number line generated by the program
+    /// from the most recent slot hash, user wallet and purchase counter
+    /// Ticket data account is created by the program
+    /// Accounts expected by this instruction:
+    ///
+    /// 0-7. Same accounts as for `PurchaseTicket`
+    /// 8. `[]` SlotHashes sysvar
+    /// 9+. Lifetime Ticket accounts, the user token account and the referrer accounts,
+    /// as for `PurchaseTicket`
+    PurchaseQuickPick {
+        charity_index: u16,
+        user_wallet_pk: Pubkey,
+        ticket_nonce: u32,
+    },
+
+    /// Initialize the charity registry shared by all lotteries
+    /// Charity registry data account is created by the program
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Charity registry data account (PDA)
+    /// 1. `[writable, signer]` Payer of charity registry data account rent
+    /// 2. `[signer]` Charity registry authority, the upgrade authority of the program
+    /// 3. `[]` Rent sysvar
+    /// 4. `[]` System program account
+    /// 5. `[]` Program data account of the program, holding its upgrade authority
+    InitCharityRegistry {},
+
+    /// Add an active charity to the charity registry
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Charity registry data account (PDA)
+    /// 1. `[signer]` Charity registry authority
+    AddCharity {
+        /// Hash of the charity name
+        name_hash: [u8; 32],
+        /// Payout wallet of the charity
+        wallet: Pubkey,
+    },
+
+    /// Update the payout wallet and the active flag of a registry charity
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Charity registry data account (PDA)
+    /// 1. `[signer]` Charity registry authority
+    UpdateRegistryCharity {
+        index: u16,
+        wallet: Pubkey,
+        is_active: bool,
+    },
+
+    /// Ticket owner closes the settled ticket and reclaims its rent.
+    /// The draw must be finished by `RewardWinners`, a ticket prize must be claimed
+    /// or the claim period must be over. Refunded tickets are closed at any time.
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Ticket data account (PDA of lottery, user wallet and ticket nonce)
+    /// 1. `[writable, signer]` User wallet account
+    /// 2. `[]` Lottery result data account (PDA of lottery id)
+    /// 3. `[]` Settlement data account (PDA of lottery)
+    /// 4. `[]` Clock sysvar
+    CloseTicket {},
+
+    /// Lottery authority reveals the commit-reveal secret after the reveal slot is locked,
+    /// the secret is mixed with the hash of the reveal slot by `StoreWinningNumbers`.
+    /// The hash is read from the SlotHashes sysvar, it keeps the hashes of the last 512 slots.
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[]` Lottery data account (PDA of lottery id)
+    /// 1. `[writable]` Commit-reveal account (PDA of lottery)
+    /// 2. `[signer]` Lottery authority
+    /// 3. `[]` Clock sysvar
+    /// 4. `[]` SlotHashes sysvar
+    RevealSecret {
+        /// Secret hashing to the lottery commitment
+        secret: [u8; 32],
+    },
+
+    /// Return the ticket price of every ticket line to the ticket owner,
+    /// when the lottery is cancelled or the commit-reveal lottery is not drawn
+    /// before the reveal deadline
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Lottery data account (PDA of lottery id)
+    /// 1. `[writable]` Ticket data account (PDA of lottery, user wallet and ticket nonce)
+    /// 2. `[writable]` User wallet account, the ticket owner
+    /// 3. `[writable]` Vault of the lottery (PDA of lottery), vault token account of a token lottery
+    /// 4. `[]` Clock sysvar
+    /// 5. `[]` System program account
+    /// 6. `[writable]` Referral ledger of the ticket referrer (PDA of lottery and referrer),
+    ///    only for tickets purchased with a referrer
+    /// 7. `[writable]` Ticket owner token account, only for token lotteries
+    /// 8. `[]` Vault authority (PDA), only for token lotteries
+    /// 9. `[]` SPL Token program account, only for token lotteries
+    RefundTicket {},
+
+    /// Lottery authority cancels the draw before the winning numbers are stored,
+    /// the tickets are refunded with `RefundTicket`
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Lottery data account (PDA of lottery id)
+    /// 1. `[signer]` Lottery authority
+    CancelLottery {},
+
+    /// Lottery authority sets the mint of the ticket currency, before any ticket is sold
+    /// and while the referral share is zero.
+    /// Vault token account of the lottery is created by the program,
+    /// it is owned by the vault authority.
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Lottery data account (PDA of lottery id)
+    /// 1. `[writable]` Vault token account (PDA of lottery)
+    /// 2. `[]` Mint of the ticket currency
+    /// 3. `[]` Vault authority (PDA)
+    /// 4. `[writable, signer]` Payer of vault token account rent
+    /// 5. `[signer]` Lottery authority
+    /// 6. `[]` Rent sysvar
+    /// 7. `[]` System program account
+    /// 8. `[]` SPL Token program account
+    InitVault {},
+
+    /// Lottery authority sets the Lifetime Ticket mint of the lottery, or stops minting
+    /// Lifetime Tickets when the mint is not passed.
+    /// The mint authority of the mint must be the Lifetime Ticket authority.
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Lottery data account (PDA of lottery id)
+    /// 1. `[signer]` Lottery authority
+    /// 2. `[]` Optional, SolLotto Lifetime Ticket mint
+    SetLifetimeTicket {
+        /// Lifetime Ticket amount minted per ticket line, in base units of the mint
+        amount: u64,
+    },
+
+    /// Lottery authority sets the share of the ticket price accrued to the ticket referrers,
+    /// zero stops the referral rewards. Referral rewards are accrued in lamports only,
+    /// so a token lottery accepts no share but zero
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Lottery data account (PDA of lottery id)
+    /// 1. `[signer]` Lottery authority
+    SetReferralShare {
+        /// Share of the ticket price, up to `MAX_REFERRAL_SHARE_BPS`
+        share_bps: u16,
+    },
+
+    /// Referrer claims the referral rewards accrued in its referral ledger of a draw,
+    /// once the draw is settled and its tickets can't be refunded anymore
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Referral ledger of the referrer (PDA of lottery and referrer)
+    /// 1. `[writable, signer]` Referrer wallet
+    /// 2. `[]` Lottery result data account of the draw (PDA of lottery id)
+    ClaimReferralRewards {},
+
+    /// Lottery authority locks the reveal slot of a commit-reveal lottery after the sale is closed,
+    /// the hash of the slot the instruction is executed in is mixed with the secret.
+    /// The slot is locked once, so the secret must be revealed while SlotHashes keeps its hash.
+    /// Commit-reveal account is created by the program
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[]` Lottery data account (PDA of lottery id)
+    /// 1. `[writable]` Commit-reveal account (PDA of lottery)
+    /// 2. `[writable, signer]` Payer, pays rent of the commit-reveal account
+    /// 3. `[signer]` Lottery authority
+    /// 4. `[]` Rent sysvar
+    /// 5. `[]` Clock sysvar
+    /// 6. `[]` System program account
+    LockRevealSlot {},
+
+    /// Lottery authority requests the draw after the sale is closed,
+    /// before the randomness account is asked for a new result.
+    /// The result the account holds at the request is not accepted by `StoreWinningNumbers`,
+    /// so the winning numbers come from a result produced after the sale.
+    /// Commit-reveal lotteries lock the reveal slot instead
+    /// Accounts expected by this instruction:
+    ///
+    /// 0. `[writable]` Lottery data account (PDA of lottery id)
+    /// 1. `[signer]` Lottery authority
+    /// 2. `[]` Randomness account of the lottery
+    /// 3. `[]` Clock sysvar
+    RequestDraw {},
 }
 
 impl LotteryInstruction {
@@ -101,46 +452,58 @@ impl LotteryInstruction {
         let (tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
         Ok(match tag {
             0 => {
-                let (lottery_id, rest) = rest.split_at(4);
-                let lottery_id = lottery_id
-                    .try_into()
-                    .ok()
-                    .map(u32::from_le_bytes)
-                    .ok_or(InvalidInstruction)?;
-
-                let (charity_1, rest) = Self::unpack_pubkey(rest).unwrap();
-                let (charity_2, rest) = Self::unpack_pubkey(rest).unwrap();
-                let (charity_3, rest) = Self::unpack_pubkey(rest).unwrap();
-                let (charity_4, rest) = Self::unpack_pubkey(rest).unwrap();
-                let (holding_wallet, rest) = Self::unpack_pubkey(rest).unwrap();
-                let (rewards_wallet, rest) = Self::unpack_pubkey(rest).unwrap();
-                let (slot_holders_rewards_wallet, rest) = Self::unpack_pubkey(rest).unwrap();
-                let (sollotto_labs_wallet, res) = Self::unpack_pubkey(rest).unwrap();
-                let (randomness_account, _) = Self::unpack_pubkey(rest).unwrap();
+                let (lottery_id, rest) = Self::unpack_u32(rest)?;
+                let (charities, rest) = Self::unpack_charities(rest)?;
+                let (rewards_wallet, rest) = Self::unpack_pubkey(rest)?;
+                let (slot_holders_rewards_wallet, rest) = Self::unpack_pubkey(rest)?;
+                let (sollotto_labs_wallet, rest) = Self::unpack_pubkey(rest)?;
+                let (randomness_account, rest) = Self::unpack_pubkey(rest)?;
+                let (sale_start, rest) = Self::unpack_i64(rest)?;
+                let (sale_end, rest) = Self::unpack_i64(rest)?;
+                let (draw_time, rest) = Self::unpack_i64(rest)?;
+                let (claim_period, rest) = Self::unpack_i64(rest)?;
+                let (main_pool_size, rest) = Self::unpack_u8(rest)?;
+                let (bonus_pool_size, rest) = Self::unpack_u8(rest)?;
+                let (pick_count, rest) = Self::unpack_u8(rest)?;
+                let (ticket_price, rest) = Self::unpack_u64(rest)?;
+                let (charity_mode, rest) = Self::unpack_charity_mode(rest)?;
+                let (randomness_provider, rest) = Self::unpack_u8(rest)?;
+                let randomness_provider = unpack_randomness_provider(&[randomness_provider])
+                    .map_err(|_| InvalidInstruction)?;
+                let (commitment, _) = Self::unpack_hash(rest)?;
 
                 Self::InitLottery {
                     lottery_id,
-                    charity_1,
-                    charity_2,
-                    charity_3,
-                    charity_4,
-                    holding_wallet,
+                    charities,
                     rewards_wallet,
                     slot_holders_rewards_wallet,
                     sollotto_labs_wallet,
                     randomness_account,
+                    sale_start,
+                    sale_end,
+                    draw_time,
+                    claim_period,
+                    main_pool_size,
+                    bonus_pool_size,
+                    pick_count,
+                    ticket_price,
+                    charity_mode,
+                    randomness_provider,
+                    commitment,
                 }
             }
 
             1 => {
-                let (charity, rest) = Self::unpack_pubkey(rest).unwrap();
-                let (user_wallet_pk, rest) = Self::unpack_pubkey(rest).unwrap();
-                let (ticket_number_arr, _) = Self::unpack_ticket_number_arr(rest).unwrap();
+                let (charity_index, rest) = Self::unpack_u16(rest)?;
+                let (user_wallet_pk, rest) = Self::unpack_pubkey(rest)?;
+                let (ticket_number_arr, rest) = Self::unpack_ticket_number_arr(rest)?;
+                let (ticket_nonce, _) = Self::unpack_u32(rest)?;
 
                 Self::PurchaseTicket {
-                    charity,
+                    charity_index,
                     user_wallet_pk,
                     ticket_number_arr: *ticket_number_arr,
+                    ticket_nonce,
                 }
             }
 
@@ -149,33 +512,139 @@ impl LotteryInstruction {
             3 => Self::RewardWinners {},
 
             4 => {
-                let (charity_1, rest) = Self::unpack_pubkey(rest).unwrap();
-                let (charity_2, rest) = Self::unpack_pubkey(rest).unwrap();
-                let (charity_3, rest) = Self::unpack_pubkey(rest).unwrap();
-                let (charity_4, _) = Self::unpack_pubkey(rest).unwrap();
-
-                Self::UpdateCharity {
-                    charity_1,
-                    charity_2,
-                    charity_3,
-                    charity_4,
-                }
+                let (charities, _) = Self::unpack_charities(rest)?;
+                Self::UpdateCharity { charities }
             }
 
             5 => {
-                let (holding_wallet, rest) = Self::unpack_pubkey(rest).unwrap();
-                let (rewards_wallet, rest) = Self::unpack_pubkey(rest).unwrap();
-                let (slot_holders_rewards_wallet, rest) = Self::unpack_pubkey(rest).unwrap();
-                let (sollotto_labs_wallet, _) = Self::unpack_pubkey(rest).unwrap();
+                let (rewards_wallet, rest) = Self::unpack_pubkey(rest)?;
+                let (slot_holders_rewards_wallet, rest) = Self::unpack_pubkey(rest)?;
+                let (sollotto_labs_wallet, _) = Self::unpack_pubkey(rest)?;
 
                 Self::UpdateSollottoWallets {
-                    holding_wallet,
                     rewards_wallet,
                     slot_holders_rewards_wallet,
                     sollotto_labs_wallet,
                 }
             }
 
+            6 => {
+                let (new_authority, _) = Self::unpack_pubkey(rest)?;
+                Self::SetAuthority { new_authority }
+            }
+
+            7 => Self::AcceptAuthority {},
+
+            8 => Self::TallyBatch {},
+
+            9 => Self::PayoutBatch {},
+
+            10 => Self::ClaimPrize {},
+
+            11 => Self::SweepUnclaimed {},
+
+            12 => {
+                let (tiers_count, mut rest) = Self::unpack_u8(rest)?;
+                if tiers_count as usize > MAX_PRIZE_TIERS {
+                    msg!("Too many prize tiers: {}", tiers_count);
+                    return Err(InvalidInstruction.into());
+                }
+                let mut tiers = Vec::with_capacity(tiers_count as usize);
+                for _ in 0..tiers_count {
+                    let (tier, tail) = Self::unpack_prize_tier(rest)?;
+                    tiers.push(tier);
+                    rest = tail;
+                }
+                Self::SetPrizeTable { tiers }
+            }
+
+            13 => {
+                let (charity_index, rest) = Self::unpack_u16(rest)?;
+                let (user_wallet_pk, rest) = Self::unpack_pubkey(rest)?;
+                let (ticket_nonce, rest) = Self::unpack_u32(rest)?;
+                let (lines_count, mut rest) = Self::unpack_u8(rest)?;
+                if lines_count == 0 || lines_count as usize > MAX_TICKET_LINES {
+                    msg!("Invalid ticket lines count: {}", lines_count);
+                    return Err(InvalidInstruction.into());
+                }
+                let mut ticket_numbers = Vec::with_capacity(lines_count as usize);
+                for _ in 0..lines_count {
+                    let (ticket_number_arr, tail) = Self::unpack_ticket_number_arr(rest)?;
+                    ticket_numbers.push(*ticket_number_arr);
+                    rest = tail;
+                }
+                Self::PurchaseTickets {
+                    charity_index,
+                    user_wallet_pk,
+                    ticket_numbers,
+                    ticket_nonce,
+                }
+            }
+
+            14 => {
+                let (charity_index, rest) = Self::unpack_u16(rest)?;
+                let (user_wallet_pk, rest) = Self::unpack_pubkey(rest)?;
+                let (ticket_nonce, _) = Self::unpack_u32(rest)?;
+                Self::PurchaseQuickPick {
+                    charity_index,
+                    user_wallet_pk,
+                    ticket_nonce,
+                }
+            }
+
+            15 => Self::InitCharityRegistry {},
+
+            16 => {
+                if rest.len() < 32 {
+                    msg!("Name hash cannot be unpacked");
+                    return Err(InvalidInstruction.into());
+                }
+                let (name_hash, rest) = rest.split_at(32);
+                let name_hash = name_hash.try_into().map_err(|_| InvalidInstruction)?;
+                let (wallet, _) = Self::unpack_pubkey(rest)?;
+                Self::AddCharity { name_hash, wallet }
+            }
+
+            17 => {
+                let (index, rest) = Self::unpack_u16(rest)?;
+                let (wallet, rest) = Self::unpack_pubkey(rest)?;
+                let (is_active, _) = Self::unpack_u8(rest)?;
+                Self::UpdateRegistryCharity {
+                    index,
+                    wallet,
+                    is_active: is_active != 0,
+                }
+            }
+
+            18 => Self::CloseTicket {},
+
+            19 => {
+                let (secret, _) = Self::unpack_hash(rest)?;
+                Self::RevealSecret { secret }
+            }
+
+            20 => Self::RefundTicket {},
+
+            21 => Self::CancelLottery {},
+
+            22 => Self::InitVault {},
+
+            23 => {
+                let (amount, _) = Self::unpack_u64(rest)?;
+                Self::SetLifetimeTicket { amount }
+            }
+
+            24 => {
+                let (share_bps, _) = Self::unpack_u16(rest)?;
+                Self::SetReferralShare { share_bps }
+            }
+
+            25 => Self::ClaimReferralRewards {},
+
+            26 => Self::LockRevealSlot {},
+
+            27 => Self::RequestDraw {},
+
             _ => return Err(InvalidInstruction.into()),
         })
     }
@@ -186,38 +655,58 @@ impl LotteryInstruction {
         match self {
             Self::InitLottery {
                 lottery_id,
-                charity_1,
-                charity_2,
-                charity_3,
-                charity_4,
-                holding_wallet,
+                charities,
                 rewards_wallet,
                 slot_holders_rewards_wallet,
                 sollotto_labs_wallet,
                 randomness_account,
+                sale_start,
+                sale_end,
+                draw_time,
+                claim_period,
+                main_pool_size,
+                bonus_pool_size,
+                pick_count,
+                ticket_price,
+                charity_mode,
+                randomness_provider,
+                commitment,
             } => {
                 buf.push(0);
                 buf.extend_from_slice(&lottery_id.to_le_bytes());
-                buf.extend_from_slice(charity_1.as_ref());
-                buf.extend_from_slice(charity_2.as_ref());
-                buf.extend_from_slice(charity_3.as_ref());
-                buf.extend_from_slice(charity_4.as_ref());
-                buf.extend_from_slice(holding_wallet.as_ref());
+                Self::pack_charities(charities, &mut buf);
                 buf.extend_from_slice(rewards_wallet.as_ref());
                 buf.extend_from_slice(slot_holders_rewards_wallet.as_ref());
                 buf.extend_from_slice(sollotto_labs_wallet.as_ref());
                 buf.extend_from_slice(randomness_account.as_ref());
+                buf.extend_from_slice(&sale_start.to_le_bytes());
+                buf.extend_from_slice(&sale_end.to_le_bytes());
+                buf.extend_from_slice(&draw_time.to_le_bytes());
+                buf.extend_from_slice(&claim_period.to_le_bytes());
+                buf.push(*main_pool_size);
+                buf.push(*bonus_pool_size);
+                buf.push(*pick_count);
+                buf.extend_from_slice(&ticket_price.to_le_bytes());
+                let mut charity_mode_buf = [0; 2];
+                pack_charity_mode(charity_mode, &mut charity_mode_buf);
+                buf.extend_from_slice(&charity_mode_buf);
+                let mut randomness_provider_buf = [0; 1];
+                pack_randomness_provider(randomness_provider, &mut randomness_provider_buf);
+                buf.extend_from_slice(&randomness_provider_buf);
+                buf.extend_from_slice(commitment);
             }
 
             Self::PurchaseTicket {
-                charity,
+                charity_index,
                 user_wallet_pk,
                 ticket_number_arr,
+                ticket_nonce,
             } => {
                 buf.push(1);
-                buf.extend_from_slice(charity.as_ref());
+                buf.extend_from_slice(&charity_index.to_le_bytes());
                 buf.extend_from_slice(user_wallet_pk.as_ref());
                 buf.extend_from_slice(&ticket_number_arr.as_ref());
+                buf.extend_from_slice(&ticket_nonce.to_le_bytes());
             }
 
             Self::StoreWinningNumbers {} => {
@@ -228,31 +717,147 @@ impl LotteryInstruction {
                 buf.push(3);
             }
 
-            Self::UpdateCharity {
-                charity_1,
-                charity_2,
-                charity_3,
-                charity_4,
-            } => {
+            Self::UpdateCharity { charities } => {
                 buf.push(4);
-                buf.extend_from_slice(charity_1.as_ref());
-                buf.extend_from_slice(charity_2.as_ref());
-                buf.extend_from_slice(charity_3.as_ref());
-                buf.extend_from_slice(charity_4.as_ref());
+                Self::pack_charities(charities, &mut buf);
             }
 
             Self::UpdateSollottoWallets {
-                holding_wallet,
                 rewards_wallet,
                 slot_holders_rewards_wallet,
                 sollotto_labs_wallet,
             } => {
                 buf.push(5);
-                buf.extend_from_slice(holding_wallet.as_ref());
                 buf.extend_from_slice(rewards_wallet.as_ref());
                 buf.extend_from_slice(slot_holders_rewards_wallet.as_ref());
                 buf.extend_from_slice(sollotto_labs_wallet.as_ref());
             }
+
+            Self::SetAuthority { new_authority } => {
+                buf.push(6);
+                buf.extend_from_slice(new_authority.as_ref());
+            }
+
+            Self::AcceptAuthority {} => {
+                buf.push(7);
+            }
+
+            Self::TallyBatch {} => {
+                buf.push(8);
+            }
+
+            Self::PayoutBatch {} => {
+                buf.push(9);
+            }
+
+            Self::ClaimPrize {} => {
+                buf.push(10);
+            }
+
+            Self::SweepUnclaimed {} => {
+                buf.push(11);
+            }
+
+            Self::SetPrizeTable { tiers } => {
+                buf.push(12);
+                buf.push(tiers.len() as u8);
+                for tier in tiers {
+                    let mut tier_buf = [0; PrizeTier::LEN];
+                    tier.pack_into_slice(&mut tier_buf);
+                    buf.extend_from_slice(&tier_buf);
+                }
+            }
+
+            Self::PurchaseTickets {
+                charity_index,
+                user_wallet_pk,
+                ticket_numbers,
+                ticket_nonce,
+            } => {
+                buf.push(13);
+                buf.extend_from_slice(&charity_index.to_le_bytes());
+                buf.extend_from_slice(user_wallet_pk.as_ref());
+                buf.extend_from_slice(&ticket_nonce.to_le_bytes());
+                buf.push(ticket_numbers.len() as u8);
+                for ticket_number_arr in ticket_numbers {
+                    buf.extend_from_slice(ticket_number_arr.as_ref());
+                }
+            }
+
+            Self::PurchaseQuickPick {
+                charity_index,
+                user_wallet_pk,
+                ticket_nonce,
+            } => {
+                buf.push(14);
+                buf.extend_from_slice(&charity_index.to_le_bytes());
+                buf.extend_from_slice(user_wallet_pk.as_ref());
+                buf.extend_from_slice(&ticket_nonce.to_le_bytes());
+            }
+
+            Self::InitCharityRegistry {} => {
+                buf.push(15);
+            }
+
+            Self::AddCharity { name_hash, wallet } => {
+                buf.push(16);
+                buf.extend_from_slice(name_hash);
+                buf.extend_from_slice(wallet.as_ref());
+            }
+
+            Self::UpdateRegistryCharity {
+                index,
+                wallet,
+                is_active,
+            } => {
+                buf.push(17);
+                buf.extend_from_slice(&index.to_le_bytes());
+                buf.extend_from_slice(wallet.as_ref());
+                buf.push(*is_active as u8);
+            }
+
+            Self::CloseTicket {} => {
+                buf.push(18);
+            }
+
+            Self::RevealSecret { secret } => {
+                buf.push(19);
+                buf.extend_from_slice(secret);
+            }
+
+            Self::RefundTicket {} => {
+                buf.push(20);
+            }
+
+            Self::CancelLottery {} => {
+                buf.push(21);
+            }
+
+            Self::InitVault {} => {
+                buf.push(22);
+            }
+
+            Self::SetLifetimeTicket { amount } => {
+                buf.push(23);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+
+            Self::SetReferralShare { share_bps } => {
+                buf.push(24);
+                buf.extend_from_slice(&share_bps.to_le_bytes());
+            }
+
+            Self::ClaimReferralRewards {} => {
+                buf.push(25);
+            }
+
+            Self::LockRevealSlot {} => {
+                buf.push(26);
+            }
+
+            Self::RequestDraw {} => {
+                buf.push(27);
+            }
         };
         buf
     }
@@ -267,49 +872,200 @@ impl LotteryInstruction {
         Ok((pk, rest))
     }
 
-    fn unpack_ticket_number_arr(input: &[u8]) -> Result<(&[u8; 6], &[u8]), ProgramError> {
-        if input.len() < 6 {
+    fn unpack_u16(input: &[u8]) -> Result<(u16, &[u8]), ProgramError> {
+        if input.len() < 2 {
+            msg!("u16 cannot be unpacked");
+            return Err(InvalidInstruction.into());
+        }
+        let (bytes, rest) = input.split_at(2);
+        let value = bytes
+            .try_into()
+            .ok()
+            .map(u16::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok((value, rest))
+    }
+
+    fn unpack_u32(input: &[u8]) -> Result<(u32, &[u8]), ProgramError> {
+        if input.len() < 4 {
+            msg!("u32 cannot be unpacked");
+            return Err(InvalidInstruction.into());
+        }
+        let (bytes, rest) = input.split_at(4);
+        let value = bytes
+            .try_into()
+            .ok()
+            .map(u32::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok((value, rest))
+    }
+
+    fn unpack_i64(input: &[u8]) -> Result<(i64, &[u8]), ProgramError> {
+        if input.len() < 8 {
+            msg!("i64 cannot be unpacked");
+            return Err(InvalidInstruction.into());
+        }
+        let (bytes, rest) = input.split_at(8);
+        let value = bytes
+            .try_into()
+            .ok()
+            .map(i64::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok((value, rest))
+    }
+
+    fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
+        if input.len() < 8 {
+            msg!("u64 cannot be unpacked");
+            return Err(InvalidInstruction.into());
+        }
+        let (bytes, rest) = input.split_at(8);
+        let value = bytes
+            .try_into()
+            .ok()
+            .map(u64::from_le_bytes)
+            .ok_or(InvalidInstruction)?;
+        Ok((value, rest))
+    }
+
+    fn unpack_hash(input: &[u8]) -> Result<([u8; 32], &[u8]), ProgramError> {
+        if input.len() < 32 {
+            msg!("Hash cannot be unpacked");
+            return Err(InvalidInstruction.into());
+        }
+        let (bytes, rest) = input.split_at(32);
+        Ok((bytes.try_into().map_err(|_| InvalidInstruction)?, rest))
+    }
+
+    fn unpack_u8(input: &[u8]) -> Result<(u8, &[u8]), ProgramError> {
+        let (&value, rest) = input.split_first().ok_or(InvalidInstruction)?;
+        Ok((value, rest))
+    }
+
+    fn unpack_ticket_number_arr(input: &[u8]) -> Result<(&LotteryNumbers, &[u8]), ProgramError> {
+        if input.len() < NUMBERS_LEN {
             msg!("Cannot be unpacked");
             return Err(InvalidInstruction.into());
         }
-        let (bytes, rest) = input.split_at(6);
+        let (bytes, rest) = input.split_at(NUMBERS_LEN);
         Ok((bytes.try_into().map_err(|_| InvalidInstruction)?, rest))
     }
+
+    fn unpack_charity_mode(input: &[u8]) -> Result<(CharityMode, &[u8]), ProgramError> {
+        if input.len() < 2 {
+            msg!("Charity mode cannot be unpacked");
+            return Err(InvalidInstruction.into());
+        }
+        let (bytes, rest) = input.split_at(2);
+        let charity_mode = unpack_charity_mode(bytes.try_into().map_err(|_| InvalidInstruction)?)
+            .map_err(|_| InvalidInstruction)?;
+        Ok((charity_mode, rest))
+    }
+
+    fn unpack_charities(input: &[u8]) -> Result<(Vec<u16>, &[u8]), ProgramError> {
+        let (charities_count, mut rest) = Self::unpack_u8(input)?;
+        if charities_count == 0 || charities_count as usize > MAX_LOTTERY_CHARITIES {
+            msg!("Invalid charities count: {}", charities_count);
+            return Err(InvalidInstruction.into());
+        }
+        let mut charities = Vec::with_capacity(charities_count as usize);
+        for _ in 0..charities_count {
+            let (charity_index, tail) = Self::unpack_u16(rest)?;
+            charities.push(charity_index);
+            rest = tail;
+        }
+        Ok((charities, rest))
+    }
+
+    fn pack_charities(charities: &[u16], buf: &mut Vec<u8>) {
+        buf.push(charities.len() as u8);
+        for charity_index in charities {
+            buf.extend_from_slice(&charity_index.to_le_bytes());
+        }
+    }
+
+    fn unpack_prize_tier(input: &[u8]) -> Result<(PrizeTier, &[u8]), ProgramError> {
+        if input.len() < PrizeTier::LEN {
+            msg!("Prize tier cannot be unpacked");
+            return Err(InvalidInstruction.into());
+        }
+        let (bytes, rest) = input.split_at(PrizeTier::LEN);
+        let tier = PrizeTier::unpack_from_slice(bytes).map_err(|_| InvalidInstruction)?;
+        Ok((tier, rest))
+    }
 }
 
 /// Creates a `InitLottery` instruction
 pub fn initialize_lottery(
     program_id: &Pubkey,
     lottery_id: u32,
-    charity_1: &Pubkey,
-    charity_2: &Pubkey,
-    charity_3: &Pubkey,
-    charity_4: &Pubkey,
-    holding_wallet: &Pubkey,
+    charities: &[u16],
     rewards_wallet: &Pubkey,
     slot_holders_rewards_wallet: &Pubkey,
     sollotto_labs_wallet: &Pubkey,
     randomness_account: &Pubkey,
+    sale_start: UnixTimestamp,
+    sale_end: UnixTimestamp,
+    draw_time: UnixTimestamp,
+    claim_period: i64,
+    main_pool_size: u8,
+    bonus_pool_size: u8,
+    pick_count: u8,
+    ticket_price: u64,
+    charity_mode: CharityMode,
+    randomness_provider: RandomnessProvider,
+    commitment: &[u8; 32],
+    previous_lottery_id: Option<u32>,
+    payer: &Pubkey,
     lottery_authority: &Pubkey,
 ) -> Result<Instruction, ProgramError> {
     check_program_account(program_id)?;
     let data = LotteryInstruction::InitLottery {
         lottery_id: lottery_id,
-        charity_1: *charity_1,
-        charity_2: *charity_2,
-        charity_3: *charity_3,
-        charity_4: *charity_4,
-        holding_wallet: *holding_wallet,
+        charities: charities.to_vec(),
         rewards_wallet: *rewards_wallet,
         slot_holders_rewards_wallet: *slot_holders_rewards_wallet,
         sollotto_labs_wallet: *sollotto_labs_wallet,
         randomness_account: *randomness_account,
+        sale_start,
+        sale_end,
+        draw_time,
+        claim_period,
+        main_pool_size,
+        bonus_pool_size,
+        pick_count,
+        ticket_price,
+        charity_mode,
+        randomness_provider,
+        commitment: *commitment,
     }
     .pack();
 
-    let mut accounts = Vec::with_capacity(2);
-    accounts.push(AccountMeta::new(*lottery_authority, true));
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (vault_key, _) = find_vault_address(program_id, &lottery_key);
+    let (charity_registry_key, _) = find_charity_registry_address(program_id);
+    let (program_data_key, _) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+
+    let mut accounts = Vec::with_capacity(11);
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new(*payer, true));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
     accounts.push(AccountMeta::new_readonly(sysvar::rent::id(), false));
+    accounts.push(AccountMeta::new_readonly(sysvar::clock::id(), false));
+    accounts.push(AccountMeta::new_readonly(
+        solana_program::system_program::id(),
+        false,
+    ));
+    accounts.push(AccountMeta::new_readonly(charity_registry_key, false));
+    accounts.push(AccountMeta::new(vault_key, false));
+    accounts.push(AccountMeta::new_readonly(program_data_key, false));
+    if let Some(previous_lottery_id) = previous_lottery_id {
+        let (previous_lottery_key, _) = find_lottery_address(program_id, previous_lottery_id);
+        let (previous_vault_key, _) = find_vault_address(program_id, &previous_lottery_key);
+        accounts.push(AccountMeta::new(previous_lottery_key, false));
+        accounts.push(AccountMeta::new(previous_vault_key, false));
+    }
 
     Ok(Instruction {
         program_id: *program_id,
@@ -318,41 +1074,99 @@ pub fn initialize_lottery(
     })
 }
 
+/// Adds the Lifetime Ticket accounts of a purchase: the Lifetime Ticket mint
+/// and the user's Lifetime Ticket token account, for lotteries minting Lifetime Tickets
+fn push_lifetime_ticket_accounts(
+    program_id: &Pubkey,
+    lifetime_ticket: Option<(&Pubkey, &Pubkey)>,
+    accounts: &mut Vec<AccountMeta>,
+) {
+    if let Some((lifetime_ticket_mint, user_lifetime_ticket_account)) = lifetime_ticket {
+        let (lifetime_ticket_authority_key, _) = find_lifetime_ticket_authority_address(program_id);
+        accounts.push(AccountMeta::new(*user_lifetime_ticket_account, false));
+        accounts.push(AccountMeta::new(*lifetime_ticket_mint, false));
+        accounts.push(AccountMeta::new_readonly(
+            lifetime_ticket_authority_key,
+            false,
+        ));
+    }
+}
+
+/// Adds the user token account paying the ticket price of a token lottery
+fn push_user_token_account(
+    token_accounts: Option<(&Pubkey, &Pubkey)>,
+    accounts: &mut Vec<AccountMeta>,
+) {
+    if let Some((_, user_token_account)) = token_accounts {
+        accounts.push(AccountMeta::new(*user_token_account, false));
+    }
+}
+
+/// Adds the accounts signing the transfers out of the vault token account of a token lottery
+fn push_vault_authority_accounts(program_id: &Pubkey, accounts: &mut Vec<AccountMeta>) {
+    let (vault_authority_key, _) = find_vault_authority_address(program_id);
+    accounts.push(AccountMeta::new_readonly(vault_authority_key, false));
+    accounts.push(AccountMeta::new_readonly(spl_token::id(), false));
+}
+
+/// Adds the referrer accounts of a purchase: the referrer wallet and its referral ledger
+fn push_referral_accounts(
+    program_id: &Pubkey,
+    lottery_key: &Pubkey,
+    referrer: Option<&Pubkey>,
+    accounts: &mut Vec<AccountMeta>,
+) {
+    if let Some(referrer) = referrer {
+        let (referral_key, _) = find_referral_address(program_id, lottery_key, referrer);
+        accounts.push(AccountMeta::new_readonly(*referrer, false));
+        accounts.push(AccountMeta::new(referral_key, false));
+    }
+}
+
 /// Creates a `PurchaseTicket` instruction
 pub fn purchase_ticket(
     program_id: &Pubkey,
-    charity: &Pubkey,
+    lottery_id: u32,
+    charity_index: u16,
     user_wallet_pk: &Pubkey,
-    ticket_number_arr: &[u8; 6],
-    user_ticket_key: &Pubkey,
-    holding_wallet_key: &Pubkey,
-    lottery_authority: &Pubkey,
-    user_lifetime_ticket_account: &Pubkey,
-    lifetime_ticket_owner: &Pubkey,
-    lifetime_ticket_mint: &Pubkey,
+    ticket_numbers: &[u8],
+    ticket_nonce: u32,
+    lifetime_ticket: Option<(&Pubkey, &Pubkey)>,
+    token_accounts: Option<(&Pubkey, &Pubkey)>,
+    referrer: Option<&Pubkey>,
 ) -> Result<Instruction, ProgramError> {
     check_program_account(program_id)?;
     let data = LotteryInstruction::PurchaseTicket {
-        charity: *charity,
+        charity_index,
         user_wallet_pk: *user_wallet_pk,
-        ticket_number_arr: *ticket_number_arr,
+        ticket_number_arr: to_lottery_numbers(ticket_numbers)?,
+        ticket_nonce,
     }
     .pack();
 
-    let mut accounts = Vec::with_capacity(9);
-    accounts.push(AccountMeta::new(*lottery_authority, true));
-    accounts.push(AccountMeta::new(*user_ticket_key, false));
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (user_ticket_key, _) =
+        find_ticket_address(program_id, &lottery_key, user_wallet_pk, ticket_nonce);
+    let vault_key = match token_accounts {
+        Some((vault_token_account, _)) => *vault_token_account,
+        None => find_vault_address(program_id, &lottery_key).0,
+    };
+
+    let mut accounts = Vec::with_capacity(14);
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new(user_ticket_key, false));
     accounts.push(AccountMeta::new(*user_wallet_pk, true));
-    accounts.push(AccountMeta::new(*holding_wallet_key, false));
-    accounts.push(AccountMeta::new(*user_lifetime_ticket_account, false));
-    accounts.push(AccountMeta::new_readonly(*lifetime_ticket_owner, true));
-    accounts.push(AccountMeta::new(*lifetime_ticket_mint, false));
+    accounts.push(AccountMeta::new(vault_key, false));
     accounts.push(AccountMeta::new_readonly(sysvar::rent::id(), false));
+    accounts.push(AccountMeta::new_readonly(sysvar::clock::id(), false));
     accounts.push(AccountMeta::new_readonly(
         solana_program::system_program::id(),
         false,
     ));
     accounts.push(AccountMeta::new_readonly(spl_token::id(), false));
+    push_lifetime_ticket_accounts(program_id, lifetime_ticket, &mut accounts);
+    push_user_token_account(token_accounts, &mut accounts);
+    push_referral_accounts(program_id, &lottery_key, referrer, &mut accounts);
 
     Ok(Instruction {
         program_id: *program_id,
@@ -361,57 +1175,182 @@ pub fn purchase_ticket(
     })
 }
 
-/// Creates a `StoreWinningNumbers` instruction
-pub fn store_winning_numbers(
+/// Creates a `PurchaseTickets` instruction
+pub fn purchase_tickets(
     program_id: &Pubkey,
-    winning_numbers_arr: &[u8; 6],
-    lottery_authority: &Pubkey,
+    lottery_id: u32,
+    charity_index: u16,
+    user_wallet_pk: &Pubkey,
+    ticket_numbers: &[&[u8]],
+    ticket_nonce: u32,
+    lifetime_ticket: Option<(&Pubkey, &Pubkey)>,
+    token_accounts: Option<(&Pubkey, &Pubkey)>,
+    referrer: Option<&Pubkey>,
 ) -> Result<Instruction, ProgramError> {
     check_program_account(program_id)?;
-    let data = LotteryInstruction::StoreWinningNumbers {}.pack();
+    let data = LotteryInstruction::PurchaseTickets {
+        charity_index,
+        user_wallet_pk: *user_wallet_pk,
+        ticket_numbers: ticket_numbers
+            .iter()
+            .map(|numbers| to_lottery_numbers(numbers))
+            .collect::<Result<_, _>>()?,
+        ticket_nonce,
+    }
+    .pack();
 
-    let mut accounts = Vec::with_capacity(1);
-    accounts.push(AccountMeta::new(*lottery_authority, true));
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (user_ticket_key, _) =
+        find_ticket_address(program_id, &lottery_key, user_wallet_pk, ticket_nonce);
+    let vault_key = match token_accounts {
+        Some((vault_token_account, _)) => *vault_token_account,
+        None => find_vault_address(program_id, &lottery_key).0,
+    };
 
-    Ok(Instruction {
-        program_id: *program_id,
-        accounts,
-        data,
-    })
+    let mut accounts = Vec::with_capacity(14);
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new(user_ticket_key, false));
+    accounts.push(AccountMeta::new(*user_wallet_pk, true));
+    accounts.push(AccountMeta::new(vault_key, false));
+    accounts.push(AccountMeta::new_readonly(sysvar::rent::id(), false));
+    accounts.push(AccountMeta::new_readonly(sysvar::clock::id(), false));
+    accounts.push(AccountMeta::new_readonly(
+        solana_program::system_program::id(),
+        false,
+    ));
+    accounts.push(AccountMeta::new_readonly(spl_token::id(), false));
+    push_lifetime_ticket_accounts(program_id, lifetime_ticket, &mut accounts);
+    push_user_token_account(token_accounts, &mut accounts);
+    push_referral_accounts(program_id, &lottery_key, referrer, &mut accounts);
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `PurchaseQuickPick` instruction
+pub fn purchase_quick_pick(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    charity_index: u16,
+    user_wallet_pk: &Pubkey,
+    ticket_nonce: u32,
+    lifetime_ticket: Option<(&Pubkey, &Pubkey)>,
+    token_accounts: Option<(&Pubkey, &Pubkey)>,
+    referrer: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::PurchaseQuickPick {
+        charity_index,
+        user_wallet_pk: *user_wallet_pk,
+        ticket_nonce,
+    }
+    .pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (user_ticket_key, _) =
+        find_ticket_address(program_id, &lottery_key, user_wallet_pk, ticket_nonce);
+    let vault_key = match token_accounts {
+        Some((vault_token_account, _)) => *vault_token_account,
+        None => find_vault_address(program_id, &lottery_key).0,
+    };
+
+    let mut accounts = Vec::with_capacity(15);
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new(user_ticket_key, false));
+    accounts.push(AccountMeta::new(*user_wallet_pk, true));
+    accounts.push(AccountMeta::new(vault_key, false));
+    accounts.push(AccountMeta::new_readonly(sysvar::rent::id(), false));
+    accounts.push(AccountMeta::new_readonly(sysvar::clock::id(), false));
+    accounts.push(AccountMeta::new_readonly(
+        solana_program::system_program::id(),
+        false,
+    ));
+    accounts.push(AccountMeta::new_readonly(spl_token::id(), false));
+    accounts.push(AccountMeta::new_readonly(sysvar::slot_hashes::id(), false));
+    push_lifetime_ticket_accounts(program_id, lifetime_ticket, &mut accounts);
+    push_user_token_account(token_accounts, &mut accounts);
+    push_referral_accounts(program_id, &lottery_key, referrer, &mut accounts);
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `StoreWinningNumbers` instruction
+pub fn store_winning_numbers(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    lottery_authority: &Pubkey,
+    randomness_account: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::StoreWinningNumbers {}.pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+
+    let mut accounts = Vec::with_capacity(4);
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
+    accounts.push(AccountMeta::new_readonly(*randomness_account, false));
+    accounts.push(AccountMeta::new_readonly(sysvar::clock::id(), false));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
 }
 
 /// Creates a `RewardWinners` instruction
 pub fn reward_winners(
     program_id: &Pubkey,
+    lottery_id: u32,
+    payer: &Pubkey,
     lottery_authority: &Pubkey,
-    lottery_result: &Pubkey,
-    holding_wallet: &Pubkey,
     rewards_wallet: &Pubkey,
     slot_holders_wallet: &Pubkey,
     sollotto_labs_wallet: &Pubkey,
-    charities: &[Pubkey; 4],
-    participants: &Vec<(Pubkey, Pubkey)>,
+    charities: &[Pubkey],
+    token_vault: Option<&Pubkey>,
 ) -> Result<Instruction, ProgramError> {
     check_program_account(program_id)?;
     let data = LotteryInstruction::RewardWinners {}.pack();
 
-    let mut accounts = Vec::with_capacity(9 + participants.len());
-    accounts.push(AccountMeta::new(*lottery_authority, true));
-    accounts.push(AccountMeta::new(*lottery_result, false));
-    accounts.push(AccountMeta::new(*holding_wallet, true));
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (lottery_result_key, _) = find_lottery_result_address(program_id, lottery_id);
+    let (settlement_key, _) = find_settlement_address(program_id, &lottery_key);
+    let vault_key = match token_vault {
+        Some(vault_token_account) => *vault_token_account,
+        None => find_vault_address(program_id, &lottery_key).0,
+    };
+    let (charity_registry_key, _) = find_charity_registry_address(program_id);
+
+    let mut accounts = Vec::with_capacity(14 + charities.len());
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new(lottery_result_key, false));
+    accounts.push(AccountMeta::new_readonly(settlement_key, false));
+    accounts.push(AccountMeta::new(*payer, true));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
+    accounts.push(AccountMeta::new(vault_key, false));
     accounts.push(AccountMeta::new(*rewards_wallet, false));
     accounts.push(AccountMeta::new(*slot_holders_wallet, false));
     accounts.push(AccountMeta::new(*sollotto_labs_wallet, false));
-    for charity in charities {
-        accounts.push(AccountMeta::new(*charity, false));
-    }
+    accounts.push(AccountMeta::new_readonly(charity_registry_key, false));
+    accounts.push(AccountMeta::new_readonly(sysvar::rent::id(), false));
     accounts.push(AccountMeta::new_readonly(
         solana_program::system_program::id(),
         false,
     ));
-    for participant in participants {
-        accounts.push(AccountMeta::new_readonly(participant.0, false));
-        accounts.push(AccountMeta::new(participant.1, false));
+    for charity in charities {
+        accounts.push(AccountMeta::new(*charity, false));
+    }
+    if token_vault.is_some() {
+        push_vault_authority_accounts(program_id, &mut accounts);
     }
 
     Ok(Instruction {
@@ -424,23 +1363,23 @@ pub fn reward_winners(
 /// Creates a `UpdateCharity` instruction
 pub fn update_charity(
     program_id: &Pubkey,
-    charity_1: &Pubkey,
-    charity_2: &Pubkey,
-    charity_3: &Pubkey,
-    charity_4: &Pubkey,
+    lottery_id: u32,
+    charities: &[u16],
     lottery_authority: &Pubkey,
 ) -> Result<Instruction, ProgramError> {
     check_program_account(program_id)?;
     let data = LotteryInstruction::UpdateCharity {
-        charity_1: *charity_1,
-        charity_2: *charity_2,
-        charity_3: *charity_3,
-        charity_4: *charity_4,
+        charities: charities.to_vec(),
     }
     .pack();
 
-    let mut accounts = Vec::with_capacity(1);
-    accounts.push(AccountMeta::new(*lottery_authority, true));
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (charity_registry_key, _) = find_charity_registry_address(program_id);
+
+    let mut accounts = Vec::with_capacity(3);
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
+    accounts.push(AccountMeta::new_readonly(charity_registry_key, false));
 
     Ok(Instruction {
         program_id: *program_id,
@@ -452,7 +1391,7 @@ pub fn update_charity(
 /// Creates a `UpdateSollottoWallets` instruction
 pub fn update_sollotto_wallets(
     program_id: &Pubkey,
-    holding_wallet: &Pubkey,
+    lottery_id: u32,
     rewards_wallet: &Pubkey,
     slot_holders_rewards_wallet: &Pubkey,
     sollotto_labs_wallet: &Pubkey,
@@ -460,15 +1399,625 @@ pub fn update_sollotto_wallets(
 ) -> Result<Instruction, ProgramError> {
     check_program_account(program_id)?;
     let data = LotteryInstruction::UpdateSollottoWallets {
-        holding_wallet: *holding_wallet,
         rewards_wallet: *rewards_wallet,
         slot_holders_rewards_wallet: *slot_holders_rewards_wallet,
         sollotto_labs_wallet: *sollotto_labs_wallet,
     }
     .pack();
 
-    let mut accounts = Vec::with_capacity(1);
-    accounts.push(AccountMeta::new(*lottery_authority, true));
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+
+    let mut accounts = Vec::with_capacity(2);
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `SetAuthority` instruction
+pub fn set_authority(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    new_authority: &Pubkey,
+    lottery_authority: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::SetAuthority {
+        new_authority: *new_authority,
+    }
+    .pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+
+    let mut accounts = Vec::with_capacity(2);
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `AcceptAuthority` instruction
+pub fn accept_authority(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    pending_authority: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::AcceptAuthority {}.pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+
+    let mut accounts = Vec::with_capacity(2);
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new_readonly(*pending_authority, true));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `TallyBatch` instruction
+pub fn tally_batch(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    payer: &Pubkey,
+    lottery_authority: &Pubkey,
+    tickets: &[Pubkey],
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::TallyBatch {}.pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (settlement_key, _) = find_settlement_address(program_id, &lottery_key);
+    let (prize_table_key, _) = find_prize_table_address(program_id, &lottery_key);
+
+    let mut accounts = Vec::with_capacity(7 + tickets.len());
+    accounts.push(AccountMeta::new_readonly(lottery_key, false));
+    accounts.push(AccountMeta::new(settlement_key, false));
+    accounts.push(AccountMeta::new_readonly(prize_table_key, false));
+    accounts.push(AccountMeta::new(*payer, true));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
+    accounts.push(AccountMeta::new_readonly(sysvar::rent::id(), false));
+    accounts.push(AccountMeta::new_readonly(
+        solana_program::system_program::id(),
+        false,
+    ));
+    for ticket in tickets {
+        accounts.push(AccountMeta::new(*ticket, false));
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `PayoutBatch` instruction
+pub fn payout_batch(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    lottery_authority: &Pubkey,
+    tickets: &[Pubkey],
+    token_vault: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::PayoutBatch {}.pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (settlement_key, _) = find_settlement_address(program_id, &lottery_key);
+    let (prize_table_key, _) = find_prize_table_address(program_id, &lottery_key);
+    let vault_key = match token_vault {
+        Some(vault_token_account) => *vault_token_account,
+        None => find_vault_address(program_id, &lottery_key).0,
+    };
+
+    let mut accounts = Vec::with_capacity(7 + tickets.len());
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new(settlement_key, false));
+    accounts.push(AccountMeta::new_readonly(prize_table_key, false));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
+    accounts.push(AccountMeta::new(vault_key, false));
+    accounts.push(AccountMeta::new_readonly(
+        solana_program::system_program::id(),
+        false,
+    ));
+    accounts.push(AccountMeta::new_readonly(sysvar::clock::id(), false));
+    for ticket in tickets {
+        accounts.push(AccountMeta::new(*ticket, false));
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `ClaimPrize` instruction
+pub fn claim_prize(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    user_wallet_pk: &Pubkey,
+    ticket_nonce: u32,
+    token_accounts: Option<(&Pubkey, &Pubkey)>,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::ClaimPrize {}.pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (settlement_key, _) = find_settlement_address(program_id, &lottery_key);
+    let (ticket_key, _) =
+        find_ticket_address(program_id, &lottery_key, user_wallet_pk, ticket_nonce);
+
+    let mut accounts = Vec::with_capacity(8);
+    accounts.push(AccountMeta::new(settlement_key, false));
+    accounts.push(AccountMeta::new(ticket_key, false));
+    accounts.push(AccountMeta::new(*user_wallet_pk, true));
+    accounts.push(AccountMeta::new_readonly(sysvar::clock::id(), false));
+    if let Some((vault_token_account, user_token_account)) = token_accounts {
+        accounts.push(AccountMeta::new(*vault_token_account, false));
+        accounts.push(AccountMeta::new(*user_token_account, false));
+        push_vault_authority_accounts(program_id, &mut accounts);
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `SweepUnclaimed` instruction
+pub fn sweep_unclaimed(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    lottery_authority: &Pubkey,
+    rewards_wallet: &Pubkey,
+    token_vault: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::SweepUnclaimed {}.pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (settlement_key, _) = find_settlement_address(program_id, &lottery_key);
+
+    let mut accounts = Vec::with_capacity(8);
+    accounts.push(AccountMeta::new_readonly(lottery_key, false));
+    accounts.push(AccountMeta::new(settlement_key, false));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
+    accounts.push(AccountMeta::new(*rewards_wallet, false));
+    accounts.push(AccountMeta::new_readonly(sysvar::clock::id(), false));
+    if let Some(vault_token_account) = token_vault {
+        accounts.push(AccountMeta::new(*vault_token_account, false));
+        push_vault_authority_accounts(program_id, &mut accounts);
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `SetPrizeTable` instruction
+pub fn set_prize_table(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    tiers: &[PrizeTier],
+    payer: &Pubkey,
+    lottery_authority: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::SetPrizeTable {
+        tiers: tiers.to_vec(),
+    }
+    .pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (prize_table_key, _) = find_prize_table_address(program_id, &lottery_key);
+
+    let mut accounts = Vec::with_capacity(6);
+    accounts.push(AccountMeta::new_readonly(lottery_key, false));
+    accounts.push(AccountMeta::new(prize_table_key, false));
+    accounts.push(AccountMeta::new(*payer, true));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
+    accounts.push(AccountMeta::new_readonly(sysvar::rent::id(), false));
+    accounts.push(AccountMeta::new_readonly(
+        solana_program::system_program::id(),
+        false,
+    ));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `InitCharityRegistry` instruction
+pub fn init_charity_registry(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    registry_authority: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::InitCharityRegistry {}.pack();
+
+    let (charity_registry_key, _) = find_charity_registry_address(program_id);
+
+    let (program_data_key, _) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+
+    let mut accounts = Vec::with_capacity(6);
+    accounts.push(AccountMeta::new(charity_registry_key, false));
+    accounts.push(AccountMeta::new(*payer, true));
+    accounts.push(AccountMeta::new_readonly(*registry_authority, true));
+    accounts.push(AccountMeta::new_readonly(sysvar::rent::id(), false));
+    accounts.push(AccountMeta::new_readonly(
+        solana_program::system_program::id(),
+        false,
+    ));
+    accounts.push(AccountMeta::new_readonly(program_data_key, false));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `AddCharity` instruction
+pub fn add_charity(
+    program_id: &Pubkey,
+    name_hash: &[u8; 32],
+    wallet: &Pubkey,
+    registry_authority: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::AddCharity {
+        name_hash: *name_hash,
+        wallet: *wallet,
+    }
+    .pack();
+
+    let (charity_registry_key, _) = find_charity_registry_address(program_id);
+
+    let mut accounts = Vec::with_capacity(2);
+    accounts.push(AccountMeta::new(charity_registry_key, false));
+    accounts.push(AccountMeta::new_readonly(*registry_authority, true));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `UpdateRegistryCharity` instruction
+pub fn update_registry_charity(
+    program_id: &Pubkey,
+    index: u16,
+    wallet: &Pubkey,
+    is_active: bool,
+    registry_authority: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::UpdateRegistryCharity {
+        index,
+        wallet: *wallet,
+        is_active,
+    }
+    .pack();
+
+    let (charity_registry_key, _) = find_charity_registry_address(program_id);
+
+    let mut accounts = Vec::with_capacity(2);
+    accounts.push(AccountMeta::new(charity_registry_key, false));
+    accounts.push(AccountMeta::new_readonly(*registry_authority, true));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `CloseTicket` instruction
+pub fn close_ticket(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    user_wallet_pk: &Pubkey,
+    ticket_nonce: u32,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::CloseTicket {}.pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (lottery_result_key, _) = find_lottery_result_address(program_id, lottery_id);
+    let (settlement_key, _) = find_settlement_address(program_id, &lottery_key);
+    let (ticket_key, _) =
+        find_ticket_address(program_id, &lottery_key, user_wallet_pk, ticket_nonce);
+
+    let mut accounts = Vec::with_capacity(5);
+    accounts.push(AccountMeta::new(ticket_key, false));
+    accounts.push(AccountMeta::new(*user_wallet_pk, true));
+    accounts.push(AccountMeta::new_readonly(lottery_result_key, false));
+    accounts.push(AccountMeta::new_readonly(settlement_key, false));
+    accounts.push(AccountMeta::new_readonly(sysvar::clock::id(), false));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `RevealSecret` instruction
+pub fn reveal_secret(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    secret: &[u8; 32],
+    lottery_authority: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::RevealSecret { secret: *secret }.pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (reveal_key, _) = find_reveal_address(program_id, &lottery_key);
+
+    let mut accounts = Vec::with_capacity(5);
+    accounts.push(AccountMeta::new_readonly(lottery_key, false));
+    accounts.push(AccountMeta::new(reveal_key, false));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
+    accounts.push(AccountMeta::new_readonly(sysvar::clock::id(), false));
+    accounts.push(AccountMeta::new_readonly(sysvar::slot_hashes::id(), false));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `RefundTicket` instruction
+pub fn refund_ticket(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    user_wallet_pk: &Pubkey,
+    ticket_nonce: u32,
+    token_accounts: Option<(&Pubkey, &Pubkey)>,
+    referrer: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::RefundTicket {}.pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (ticket_key, _) =
+        find_ticket_address(program_id, &lottery_key, user_wallet_pk, ticket_nonce);
+    let vault_key = match token_accounts {
+        Some((vault_token_account, _)) => *vault_token_account,
+        None => find_vault_address(program_id, &lottery_key).0,
+    };
+
+    let mut accounts = Vec::with_capacity(10);
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new(ticket_key, false));
+    accounts.push(AccountMeta::new(*user_wallet_pk, false));
+    accounts.push(AccountMeta::new(vault_key, false));
+    accounts.push(AccountMeta::new_readonly(sysvar::clock::id(), false));
+    accounts.push(AccountMeta::new_readonly(
+        solana_program::system_program::id(),
+        false,
+    ));
+    if let Some(referrer) = referrer {
+        let (referral_key, _) = find_referral_address(program_id, &lottery_key, referrer);
+        accounts.push(AccountMeta::new(referral_key, false));
+    }
+    if let Some((_, user_token_account)) = token_accounts {
+        accounts.push(AccountMeta::new(*user_token_account, false));
+        push_vault_authority_accounts(program_id, &mut accounts);
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `CancelLottery` instruction
+pub fn cancel_lottery(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    lottery_authority: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::CancelLottery {}.pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+
+    let mut accounts = Vec::with_capacity(2);
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `InitVault` instruction
+pub fn init_vault(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    mint: &Pubkey,
+    payer: &Pubkey,
+    lottery_authority: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::InitVault {}.pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (vault_key, _) = find_token_vault_address(program_id, &lottery_key);
+    let (vault_authority_key, _) = find_vault_authority_address(program_id);
+
+    let mut accounts = Vec::with_capacity(9);
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new(vault_key, false));
+    accounts.push(AccountMeta::new_readonly(*mint, false));
+    accounts.push(AccountMeta::new_readonly(vault_authority_key, false));
+    accounts.push(AccountMeta::new(*payer, true));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
+    accounts.push(AccountMeta::new_readonly(sysvar::rent::id(), false));
+    accounts.push(AccountMeta::new_readonly(
+        solana_program::system_program::id(),
+        false,
+    ));
+    accounts.push(AccountMeta::new_readonly(spl_token::id(), false));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `SetLifetimeTicket` instruction
+pub fn set_lifetime_ticket(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    lifetime_ticket_mint: Option<&Pubkey>,
+    amount: u64,
+    lottery_authority: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::SetLifetimeTicket { amount }.pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+
+    let mut accounts = Vec::with_capacity(3);
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
+    if let Some(lifetime_ticket_mint) = lifetime_ticket_mint {
+        accounts.push(AccountMeta::new_readonly(*lifetime_ticket_mint, false));
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `SetReferralShare` instruction
+pub fn set_referral_share(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    share_bps: u16,
+    lottery_authority: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::SetReferralShare { share_bps }.pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+
+    let mut accounts = Vec::with_capacity(2);
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `ClaimReferralRewards` instruction
+pub fn claim_referral_rewards(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    referrer: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::ClaimReferralRewards {}.pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (referral_key, _) = find_referral_address(program_id, &lottery_key, referrer);
+    let (lottery_result_key, _) = find_lottery_result_address(program_id, lottery_id);
+
+    let mut accounts = Vec::with_capacity(3);
+    accounts.push(AccountMeta::new(referral_key, false));
+    accounts.push(AccountMeta::new(*referrer, true));
+    accounts.push(AccountMeta::new_readonly(lottery_result_key, false));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `LockRevealSlot` instruction
+pub fn lock_reveal_slot(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    payer: &Pubkey,
+    lottery_authority: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::LockRevealSlot {}.pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+    let (reveal_key, _) = find_reveal_address(program_id, &lottery_key);
+
+    let mut accounts = Vec::with_capacity(7);
+    accounts.push(AccountMeta::new_readonly(lottery_key, false));
+    accounts.push(AccountMeta::new(reveal_key, false));
+    accounts.push(AccountMeta::new(*payer, true));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
+    accounts.push(AccountMeta::new_readonly(sysvar::rent::id(), false));
+    accounts.push(AccountMeta::new_readonly(sysvar::clock::id(), false));
+    accounts.push(AccountMeta::new_readonly(
+        solana_program::system_program::id(),
+        false,
+    ));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a `RequestDraw` instruction
+pub fn request_draw(
+    program_id: &Pubkey,
+    lottery_id: u32,
+    lottery_authority: &Pubkey,
+    randomness_account: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    check_program_account(program_id)?;
+    let data = LotteryInstruction::RequestDraw {}.pack();
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_id);
+
+    let mut accounts = Vec::with_capacity(4);
+    accounts.push(AccountMeta::new(lottery_key, false));
+    accounts.push(AccountMeta::new_readonly(*lottery_authority, true));
+    accounts.push(AccountMeta::new_readonly(*randomness_account, false));
+    accounts.push(AccountMeta::new_readonly(sysvar::clock::id(), false));
 
     Ok(Instruction {
         program_id: *program_id,
@@ -1,6 +1,9 @@
 //! Lottery program
+pub mod distribution;
 mod entrypoint;
 pub mod error;
 pub mod instruction;
+pub mod numbers;
 pub mod processor;
+pub mod randomness;
 pub mod state;
@@ -0,0 +1,419 @@
+//! Lottery number format
+use crate::error::LotteryError;
+use solana_program::{
+    entrypoint::ProgramResult, hash::hashv, msg, program_error::ProgramError, pubkey::Pubkey,
+};
+
+/// Max count of main numbers in a ticket
+pub const MAX_PICK_COUNT: usize = 7;
+/// Size of numbers arrays: main numbers and the bonus number
+pub const NUMBERS_LEN: usize = MAX_PICK_COUNT + 1;
+
+/// Lottery numbers: `pick_count` main numbers followed by the bonus number,
+/// unused trailing positions are zero
+pub type LotteryNumbers = [u8; NUMBERS_LEN];
+
+/// Number format of the lottery, e.g. 5 numbers of 1..=69 plus a bonus number of 1..=26
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NumberFormat {
+    /// Main numbers are drawn from 1..=main_pool_size
+    pub main_pool_size: u8,
+    /// Bonus number is drawn from 1..=bonus_pool_size, zero means no bonus number
+    pub bonus_pool_size: u8,
+    /// Count of main numbers in a ticket
+    pub pick_count: u8,
+}
+
+/// Matches of the ticket numbers with the winning numbers
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Matches {
+    /// Count of the ticket main numbers among the winning main numbers
+    pub main: u8,
+    /// Ticket bonus number is the winning bonus number
+    pub bonus: bool,
+}
+
+impl NumberFormat {
+    /// Checks the format can be drawn
+    pub fn validate(&self) -> ProgramResult {
+        if self.pick_count == 0 || self.pick_count as usize > MAX_PICK_COUNT {
+            msg!("Pick count must be from 1 to {}", MAX_PICK_COUNT);
+            return Err(LotteryError::InvalidNumberFormat.into());
+        }
+        if self.main_pool_size < self.pick_count {
+            msg!("Main pool is smaller than pick count");
+            return Err(LotteryError::InvalidNumberFormat.into());
+        }
+        Ok(())
+    }
+
+    /// Whether the format has a bonus number
+    pub fn has_bonus(&self) -> bool {
+        self.bonus_pool_size != 0
+    }
+
+    /// Count of used positions in numbers arrays
+    pub fn numbers_count(&self) -> usize {
+        self.pick_count as usize + self.has_bonus() as usize
+    }
+
+    /// Checks every number is in its pool, main numbers are unique and unused positions are zero
+    pub fn check_numbers(&self, numbers: &LotteryNumbers) -> ProgramResult {
+        let pick_count = self.pick_count as usize;
+        for (i, number) in numbers.iter().enumerate() {
+            let max = if i < pick_count {
+                self.main_pool_size
+            } else if i == pick_count && self.has_bonus() {
+                self.bonus_pool_size
+            } else {
+                if *number != 0 {
+                    msg!("Unexpected number at position {}", i + 1);
+                    return Err(LotteryError::InvalidNumber.into());
+                }
+                continue;
+            };
+
+            if *number < 1 || *number > max {
+                msg!(
+                    "Invalid value for number {}, must be from 1 to {}",
+                    i + 1,
+                    max
+                );
+                return Err(LotteryError::InvalidNumber.into());
+            }
+        }
+
+        let main_numbers = &numbers[..pick_count];
+        for (i, number) in main_numbers.iter().enumerate() {
+            if main_numbers[i + 1..].contains(number) {
+                msg!("Duplicate main number {}", number);
+                return Err(LotteryError::DuplicateNumbers.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Sorts main numbers in ascending order, the bonus number keeps its position
+    pub fn sort_main_numbers(&self, numbers: &mut LotteryNumbers) {
+        numbers[..self.pick_count as usize].sort_unstable();
+    }
+
+    /// Matches ticket numbers with the winning numbers: main numbers match in any order,
+    /// the bonus number only matches the winning bonus number
+    pub fn count_matches(
+        &self,
+        ticket_numbers: &LotteryNumbers,
+        winning_numbers: &LotteryNumbers,
+    ) -> Matches {
+        let pick_count = self.pick_count as usize;
+        let winning_main_numbers = &winning_numbers[..pick_count];
+        let mut matches = Matches::default();
+        for number in &ticket_numbers[..pick_count] {
+            if winning_main_numbers.contains(number) {
+                matches.main += 1;
+            }
+        }
+        matches.bonus =
+            self.has_bonus() && ticket_numbers[pick_count] == winning_numbers[pick_count];
+        matches
+    }
+}
+
+/// Converts numbers slice into the numbers array, padded with zeros
+pub fn to_lottery_numbers(numbers: &[u8]) -> Result<LotteryNumbers, ProgramError> {
+    if numbers.len() > NUMBERS_LEN {
+        msg!("Too many numbers: {}", numbers.len());
+        return Err(LotteryError::InvalidNumber.into());
+    }
+    let mut result = [0; NUMBERS_LEN];
+    result[..numbers.len()].copy_from_slice(numbers);
+    Ok(result)
+}
+
+/// Derives the winning numbers from the published randomness.
+/// Main numbers are sampled without replacement, the bonus number is sampled from its own pool.
+/// Every number is taken by rejection sampling, so there is no modulo bias.
+/// The function is pure, so anyone can recompute the winning numbers from the randomness.
+pub fn derive_winning_numbers(
+    randomness: &[u8],
+    number_format: &NumberFormat,
+) -> Result<LotteryNumbers, ProgramError> {
+    number_format.validate()?;
+    if randomness.is_empty() {
+        msg!("Randomness is empty");
+        return Err(LotteryError::InvalidRandomResult.into());
+    }
+
+    let mut stream = RandomStream::new(randomness);
+    let pick_count = number_format.pick_count as usize;
+    let mut numbers = [0; NUMBERS_LEN];
+    for i in 0..pick_count {
+        // Index among the numbers which are not drawn yet
+        let mut index = stream.next_below(number_format.main_pool_size - i as u8);
+        let mut number = 1;
+        loop {
+            if !numbers[..i].contains(&number) {
+                if index == 0 {
+                    break;
+                }
+                index -= 1;
+            }
+            number += 1;
+        }
+        numbers[i] = number;
+    }
+    if number_format.has_bonus() {
+        numbers[pick_count] = stream.next_below(number_format.bonus_pool_size) + 1;
+    }
+
+    Ok(numbers)
+}
+
+/// Derives quick pick numbers of a ticket from a recent slot hash mixed with the buyer key
+/// and the purchase counter, so purchases in the same slot get different lines.
+/// The buyer can predict them, which gives no edge as any numbers can be picked by hand.
+pub fn derive_quick_pick_numbers(
+    slot_hash: &[u8],
+    user_wallet: &Pubkey,
+    purchase_counter: u32,
+    number_format: &NumberFormat,
+) -> Result<LotteryNumbers, ProgramError> {
+    let seed = hashv(&[
+        slot_hash,
+        user_wallet.as_ref(),
+        &purchase_counter.to_le_bytes(),
+    ]);
+    derive_winning_numbers(seed.as_ref(), number_format)
+}
+
+/// Stream of random bytes: the randomness itself followed by
+/// `sha256(randomness || counter)` blocks once it is used up
+struct RandomStream<'a> {
+    randomness: &'a [u8],
+    block: Vec<u8>,
+    position: usize,
+    counter: u64,
+}
+
+impl<'a> RandomStream<'a> {
+    fn new(randomness: &'a [u8]) -> Self {
+        Self {
+            randomness,
+            block: randomness.to_vec(),
+            position: 0,
+            counter: 0,
+        }
+    }
+
+    fn next_byte(&mut self) -> u8 {
+        if self.position == self.block.len() {
+            self.block = hashv(&[self.randomness, &self.counter.to_le_bytes()])
+                .to_bytes()
+                .to_vec();
+            self.counter += 1;
+            self.position = 0;
+        }
+        let byte = self.block[self.position];
+        self.position += 1;
+        byte
+    }
+
+    /// Uniform value from 0..bound, bytes beyond the largest multiple of bound are rejected
+    fn next_below(&mut self, bound: u8) -> u8 {
+        let bound = bound as u16;
+        let limit = 256 - 256 % bound;
+        loop {
+            let byte = self.next_byte() as u16;
+            if byte < limit {
+                return (byte % bound) as u8;
+            }
+        }
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const POWERBALL: NumberFormat = NumberFormat {
+        main_pool_size: 69,
+        bonus_pool_size: 26,
+        pick_count: 5,
+    };
+
+    fn randomness(seed: u32) -> [u8; 32] {
+        hashv(&[b"randomness", &seed.to_le_bytes()]).to_bytes()
+    }
+
+    #[test]
+    fn test_check_numbers() {
+        POWERBALL
+            .check_numbers(&[10, 20, 30, 40, 50, 26, 0, 0])
+            .unwrap();
+        assert_eq!(
+            Err(LotteryError::DuplicateNumbers.into()),
+            POWERBALL.check_numbers(&[5, 5, 5, 5, 5, 1, 0, 0])
+        );
+        // Bonus number may repeat a main number
+        POWERBALL.check_numbers(&[1, 2, 3, 4, 5, 5, 0, 0]).unwrap();
+
+        let mut numbers = [50, 10, 40, 20, 30, 26, 0, 0];
+        POWERBALL.sort_main_numbers(&mut numbers);
+        assert_eq!(numbers, [10, 20, 30, 40, 50, 26, 0, 0]);
+    }
+
+    #[test]
+    fn test_count_matches() {
+        let winning_numbers = [10, 20, 30, 40, 50, 26, 0, 0];
+        assert_eq!(
+            POWERBALL.count_matches(&[50, 40, 30, 20, 10, 26, 0, 0], &winning_numbers),
+            Matches {
+                main: 5,
+                bonus: true
+            }
+        );
+        // Bonus number doesn't match winning main numbers
+        let matches = POWERBALL.count_matches(&[10, 20, 30, 1, 2, 50, 0, 0], &winning_numbers);
+        assert_eq!(
+            matches,
+            Matches {
+                main: 3,
+                bonus: false
+            }
+        );
+        assert_eq!(
+            POWERBALL.count_matches(&[1, 2, 3, 4, 5, 26, 0, 0], &winning_numbers),
+            Matches {
+                main: 0,
+                bonus: true
+            }
+        );
+
+        let no_bonus = NumberFormat {
+            main_pool_size: 49,
+            bonus_pool_size: 0,
+            pick_count: 6,
+        };
+        let matches = no_bonus.count_matches(&[6, 5, 4, 3, 2, 1, 0, 0], &[1, 2, 3, 4, 5, 6, 0, 0]);
+        assert_eq!(
+            matches,
+            Matches {
+                main: 6,
+                bonus: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_derive_winning_numbers() {
+        assert_eq!(
+            Err(LotteryError::InvalidRandomResult.into()),
+            derive_winning_numbers(&[], &POWERBALL)
+        );
+        assert_eq!(
+            Err(LotteryError::InvalidNumberFormat.into()),
+            derive_winning_numbers(&randomness(0), &NumberFormat::default())
+        );
+
+        // Same randomness always gives the same numbers
+        assert_eq!(
+            derive_winning_numbers(&randomness(1), &POWERBALL).unwrap(),
+            derive_winning_numbers(&randomness(1), &POWERBALL).unwrap()
+        );
+
+        // Bytes above the largest multiple of the pool size are rejected,
+        // accepted bytes index the numbers which are not drawn yet
+        let numbers = derive_winning_numbers(&[255, 254, 3, 4, 5, 6, 7, 8], &POWERBALL).unwrap();
+        assert_eq!(numbers, [4, 6, 8, 10, 12, 9, 0, 0]);
+
+        // Short randomness is extended by hashing
+        let numbers = derive_winning_numbers(&[0], &POWERBALL).unwrap();
+        assert_eq!(numbers[0], 1);
+        POWERBALL.check_numbers(&numbers).unwrap();
+    }
+
+    #[test]
+    fn test_derived_numbers_are_unique() {
+        let formats = [
+            POWERBALL,
+            NumberFormat {
+                main_pool_size: 7,
+                bonus_pool_size: 0,
+                pick_count: 7,
+            },
+            NumberFormat {
+                main_pool_size: 10,
+                bonus_pool_size: 10,
+                pick_count: 6,
+            },
+            NumberFormat {
+                main_pool_size: 255,
+                bonus_pool_size: 255,
+                pick_count: 7,
+            },
+        ];
+        for number_format in formats.iter() {
+            for seed in 0..1_000 {
+                let numbers = derive_winning_numbers(&randomness(seed), number_format).unwrap();
+                number_format.check_numbers(&numbers).unwrap();
+
+                let main_numbers = &numbers[..number_format.pick_count as usize];
+                for (i, number) in main_numbers.iter().enumerate() {
+                    assert!(!main_numbers[i + 1..].contains(number));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_derive_quick_pick_numbers() {
+        let slot_hash = randomness(0);
+        let user_wallet = Pubkey::new_unique();
+        let numbers = derive_quick_pick_numbers(&slot_hash, &user_wallet, 0, &POWERBALL).unwrap();
+        POWERBALL.check_numbers(&numbers).unwrap();
+        assert_eq!(
+            Ok(numbers),
+            derive_quick_pick_numbers(&slot_hash, &user_wallet, 0, &POWERBALL)
+        );
+        // Next purchase in the same slot gets another line
+        assert_ne!(
+            Ok(numbers),
+            derive_quick_pick_numbers(&slot_hash, &user_wallet, 1, &POWERBALL)
+        );
+        assert_ne!(
+            Ok(numbers),
+            derive_quick_pick_numbers(&slot_hash, &Pubkey::new_unique(), 0, &POWERBALL)
+        );
+    }
+
+    #[test]
+    fn test_derived_numbers_are_uniform() {
+        let number_format = NumberFormat {
+            main_pool_size: 10,
+            bonus_pool_size: 3,
+            pick_count: 3,
+        };
+        let draws = 30_000;
+        let mut main_counts = [0u32; 10];
+        let mut bonus_counts = [0u32; 3];
+        for seed in 0..draws {
+            let numbers = derive_winning_numbers(&randomness(seed), &number_format).unwrap();
+            for number in &numbers[..3] {
+                main_counts[*number as usize - 1] += 1;
+            }
+            bonus_counts[numbers[3] as usize - 1] += 1;
+        }
+
+        // Every main number is drawn in 3 of 10 draws, every bonus number in 1 of 3 draws
+        let expected_main = draws * 3 / 10;
+        for count in main_counts.iter() {
+            assert!(*count > expected_main * 95 / 100 && *count < expected_main * 105 / 100);
+        }
+        let expected_bonus = draws / 3;
+        for count in bonus_counts.iter() {
+            assert!(*count > expected_bonus * 95 / 100 && *count < expected_bonus * 105 / 100);
+        }
+    }
+}
@@ -1,31 +1,58 @@
 //! Program state processor
 use std::collections::HashMap;
+use std::convert::TryInto;
 
 use crate::{
+    distribution::{
+        charity_rewards, share_of, split_evenly, validate_shares, CharityMode, CHARITY_SHARE_BPS,
+        MAX_REFERRAL_SHARE_BPS, SLOT_HOLDERS_SHARE_BPS, SOLLOTTO_LABS_SHARE_BPS,
+        SOLLOTTO_REWARDS_SHARE_BPS, WINNERS_SHARE_BPS,
+    },
     error::LotteryError,
     instruction::LotteryInstruction,
-    state::{LotteryData, LotteryResultData, TicketData},
+    numbers::{
+        derive_quick_pick_numbers, derive_winning_numbers, LotteryNumbers, NumberFormat,
+        NUMBERS_LEN,
+    },
+    randomness::{load_randomness, RandomnessProvider},
+    state::{
+        find_charity_registry_address, find_lifetime_ticket_authority_address,
+        find_lottery_address, find_lottery_result_address, find_prize_table_address,
+        find_referral_address, find_reveal_address, find_settlement_address, find_ticket_address,
+        find_token_vault_address, find_vault_address, find_vault_authority_address,
+        CharityRegistryData, CommitRevealData, LotteryData, LotteryResultData, Prize,
+        PrizeTableData, PrizeTier, ReferralData, RegistryCharity, SettlementData, TicketData,
+        TicketLine, CHARITY_REGISTRY_SEED, LIFETIME_TICKET_AUTHORITY_SEED, LOTTERY_RESULT_SEED,
+        LOTTERY_RESULT_VERSION, LOTTERY_SEED, MAX_LOTTERY_CHARITIES, MAX_PRIZE_TIERS,
+        MAX_RANDOMNESS_LEN, MAX_REGISTRY_CHARITIES, MAX_TICKET_LINES, PRIZE_TABLE_SEED,
+        REFERRAL_SEED, REVEAL_PERIOD, REVEAL_SEED, SETTLEMENT_SEED, TICKET_SEED, TOKEN_VAULT_SEED,
+        VAULT_AUTHORITY_SEED, VAULT_SEED,
+    },
 };
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    bpf_loader_upgradeable,
+    clock::{Clock, UnixTimestamp},
     entrypoint::ProgramResult,
+    hash::hashv,
     msg,
-    native_token::{lamports_to_sol, sol_to_lamports},
-    program::invoke,
+    program::{invoke, invoke_signed},
     program_error::ProgramError,
+    program_option::COption,
     program_pack::Pack,
     pubkey::Pubkey,
     rent::Rent,
     system_instruction,
-    sysvar::Sysvar,
+    sysvar::{self, Sysvar},
 };
-use spl_token::{state::Mint, ui_amount_to_amount};
-
-use switchboard_program::VrfAccount;
+use spl_token::state::{Account as TokenAccount, Mint};
 
 // Sollotto program_id
 solana_program::declare_id!("urNhxed8ocNiFApoooLSAJ1xnWSMUiC9S6fKcRon1rk");
 
+/// Count of the accounts every ticket purchase takes before the optional ones
+const PURCHASE_ACCOUNTS_LEN: usize = 8;
+
 /// Checks that the supplied program ID is the correct
 pub fn check_program_account(program_id: &Pubkey) -> ProgramResult {
     if program_id != &id() {
@@ -34,12 +61,572 @@ pub fn check_program_account(program_id: &Pubkey) -> ProgramResult {
     Ok(())
 }
 
+/// Checks that the supplied account is the expected program derived address
+fn check_account_address(account_info: &AccountInfo, expected: &Pubkey) -> ProgramResult {
+    if account_info.key != expected {
+        msg!(
+            "Account {} is not the expected address {}",
+            account_info.key,
+            expected
+        );
+        return Err(LotteryError::InvalidAccountAddress.into());
+    }
+    Ok(())
+}
+
+/// Checks that the lottery authority signed the instruction
+fn check_authority(authority_account: &AccountInfo, lottery_data: &LotteryData) -> ProgramResult {
+    if !authority_account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if *authority_account.key != lottery_data.authority {
+        msg!(
+            "Account {} is not the lottery authority",
+            authority_account.key
+        );
+        return Err(LotteryError::InvalidAuthority.into());
+    }
+    Ok(())
+}
+
+/// Checks that the charity registry authority signed the instruction
+fn check_registry_authority(
+    authority_account: &AccountInfo,
+    registry: &CharityRegistryData,
+) -> ProgramResult {
+    if !authority_account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if *authority_account.key != registry.authority {
+        msg!(
+            "Account {} is not the charity registry authority",
+            authority_account.key
+        );
+        return Err(LotteryError::InvalidAuthority.into());
+    }
+    Ok(())
+}
+
+/// Loads the charity registry shared by all lotteries
+fn load_charity_registry(
+    program_id: &Pubkey,
+    charity_registry_account: &AccountInfo,
+) -> Result<CharityRegistryData, ProgramError> {
+    let (charity_registry_key, _) = find_charity_registry_address(program_id);
+    check_account_address(charity_registry_account, &charity_registry_key)?;
+    if charity_registry_account.owner != program_id {
+        msg!("Charity registry is not initialized");
+        return Err(LotteryError::NotInitialized.into());
+    }
+    let registry = CharityRegistryData::unpack(&charity_registry_account.data.borrow())?;
+    Ok(registry)
+}
+
+/// Checks lottery charities are active registry charities, each one only once
+fn check_lottery_charities(charities: &[u16], registry: &CharityRegistryData) -> ProgramResult {
+    if charities.is_empty() || charities.len() > MAX_LOTTERY_CHARITIES {
+        msg!(
+            "Lottery must have from 1 to {} charities",
+            MAX_LOTTERY_CHARITIES
+        );
+        return Err(LotteryError::InvalidCharity.into());
+    }
+    for (i, charity_index) in charities.iter().enumerate() {
+        if registry.active_charity(*charity_index).is_none() {
+            msg!("Charity {} is not active in the registry", charity_index);
+            return Err(LotteryError::InvalidCharity.into());
+        }
+        if charities[..i].contains(charity_index) {
+            msg!("Charity {} is duplicated", charity_index);
+            return Err(LotteryError::InvalidCharity.into());
+        }
+    }
+    Ok(())
+}
+
+/// Loads the purchased ticket, its address is derived from the ticket lottery
+fn load_ticket(
+    program_id: &Pubkey,
+    ticket_account: &AccountInfo,
+) -> Result<TicketData, ProgramError> {
+    if ticket_account.owner != program_id {
+        msg!("Ticket Data account does not have the correct program id");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let ticket = TicketData::unpack_unchecked(&ticket_account.data.borrow())?;
+    if !ticket.is_purchased {
+        msg!("Ticket data account is not purchased");
+        return Err(LotteryError::NotInitialized.into());
+    }
+
+    let (lottery_key, _) = find_lottery_address(program_id, ticket.lottery_id);
+    let (ticket_key, _) = find_ticket_address(
+        program_id,
+        &lottery_key,
+        &ticket.user_wallet_pk,
+        ticket.ticket_nonce,
+    );
+    check_account_address(ticket_account, &ticket_key)?;
+    Ok(ticket)
+}
+
+/// Reads the most recent entry of the SlotHashes sysvar without deserializing all of it
+fn most_recent_slot_hash(
+    slot_hashes_account: &AccountInfo,
+) -> Result<(u64, [u8; 32]), ProgramError> {
+    check_account_address(slot_hashes_account, &sysvar::slot_hashes::id())?;
+    let data = slot_hashes_account.data.borrow();
+    // Entries count followed by the (slot, hash) entries, the most recent first
+    if data.len() < 48 || data[..8] == [0; 8] {
+        msg!("Slot hashes are empty");
+        return Err(LotteryError::InvalidRandomResult.into());
+    }
+    let slot = u64::from_le_bytes(data[8..16].try_into().unwrap());
+    let slot_hash = data[16..48].try_into().unwrap();
+    Ok((slot, slot_hash))
+}
+
+/// Looks the hash of the slot up in the SlotHashes sysvar
+fn slot_hash_at(slot_hashes_account: &AccountInfo, slot: u64) -> Result<[u8; 32], ProgramError> {
+    check_account_address(slot_hashes_account, &sysvar::slot_hashes::id())?;
+    let data = slot_hashes_account.data.borrow();
+    if data.len() < 8 {
+        msg!("Slot hashes are empty");
+        return Err(LotteryError::InvalidRandomResult.into());
+    }
+    let count = u64::from_le_bytes(data[..8].try_into().unwrap()) as usize;
+    match data[8..]
+        .chunks_exact(40)
+        .take(count)
+        .find(|entry| entry[..8] == slot.to_le_bytes())
+    {
+        Some(entry) => Ok(entry[8..].try_into().unwrap()),
+        None => {
+            msg!("Hash of slot {} is not in the slot hashes", slot);
+            Err(LotteryError::InvalidRandomResult.into())
+        }
+    }
+}
+
+/// Loads the undrawn commit-reveal lottery
+fn load_commit_reveal_lottery(
+    program_id: &Pubkey,
+    lottery_data_account: &AccountInfo,
+) -> Result<LotteryData, ProgramError> {
+    if lottery_data_account.owner != program_id {
+        msg!("Lottery Data account does not have the correct program id");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
+    if !lottery_data.is_initialized {
+        msg!("Lottery Data account is not initialized");
+        return Err(LotteryError::NotInitialized.into());
+    }
+    if lottery_data.is_finaled {
+        msg!("Lottery Data account already finaled");
+        return Err(LotteryError::IsFinaled.into());
+    }
+    if lottery_data.is_cancelled {
+        msg!("Lottery is cancelled");
+        return Err(LotteryError::IsCancelled.into());
+    }
+
+    let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+    check_account_address(lottery_data_account, &lottery_key)?;
+
+    if lottery_data.randomness_provider != RandomnessProvider::CommitReveal {
+        msg!("Lottery randomness is not commit-reveal");
+        return Err(LotteryError::InvalidRandomnessProvider.into());
+    }
+    Ok(lottery_data)
+}
+
+/// Reads the upgrade authority of the program from its program data account
+fn load_upgrade_authority(
+    program_id: &Pubkey,
+    program_data_account: &AccountInfo,
+) -> Result<Pubkey, ProgramError> {
+    let (program_data_key, _) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    check_account_address(program_data_account, &program_data_key)?;
+    if *program_data_account.owner != bpf_loader_upgradeable::id() {
+        msg!("Program data account is not owned by the upgradeable loader");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let data = program_data_account.data.borrow();
+    // ProgramData state tag, deployment slot and the optional upgrade authority
+    if data.len() < 45 || data[..4] != 3u32.to_le_bytes() {
+        msg!("Program data account is not initialized");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if data[12] != 1 {
+        msg!("Program has no upgrade authority");
+        return Err(LotteryError::InvalidAuthority.into());
+    }
+    Ok(Pubkey::new_from_array(data[13..45].try_into().unwrap()))
+}
+
+/// Checks the ticket is purchased for the lottery
+fn check_ticket_lottery(ticket: &TicketData, lottery_id: u32) -> ProgramResult {
+    if ticket.lottery_id != lottery_id {
+        msg!(
+            "Ticket is purchased for lottery {}, not {}",
+            ticket.lottery_id,
+            lottery_id
+        );
+        return Err(LotteryError::InvalidParticipantsAccounts.into());
+    }
+    Ok(())
+}
+
+/// Checks prize tiers fit the lottery number format
+fn check_prize_tiers(tiers: &[PrizeTier], number_format: &NumberFormat) -> ProgramResult {
+    if tiers.is_empty() || tiers.len() > MAX_PRIZE_TIERS {
+        msg!("Prize table must have from 1 to {} tiers", MAX_PRIZE_TIERS);
+        return Err(LotteryError::InvalidPrizeTable.into());
+    }
+    for (i, tier) in tiers.iter().enumerate() {
+        if tier.main_matches > number_format.pick_count
+            || (tier.bonus_match && !number_format.has_bonus())
+            || (tier.main_matches == 0 && !tier.bonus_match)
+        {
+            msg!("Prize tier {} doesn't fit the number format", i);
+            return Err(LotteryError::InvalidPrizeTable.into());
+        }
+        if tiers[..i].iter().any(|other| {
+            other.main_matches == tier.main_matches && other.bonus_match == tier.bonus_match
+        }) {
+            msg!("Prize tier {} is duplicated", i);
+            return Err(LotteryError::InvalidPrizeTable.into());
+        }
+    }
+
+    let shares: Vec<u16> = tiers
+        .iter()
+        .filter_map(|tier| match tier.prize {
+            Prize::Share(share) => Some(share),
+            Prize::Fixed(_) => None,
+        })
+        .collect();
+    validate_shares(&shares)
+}
+
+/// Loads the prize table of the lottery, it must be set before the settlement
+fn load_prize_table(
+    program_id: &Pubkey,
+    prize_table_account: &AccountInfo,
+    lottery_key: &Pubkey,
+) -> Result<PrizeTableData, ProgramError> {
+    let (prize_table_key, _) = find_prize_table_address(program_id, lottery_key);
+    check_account_address(prize_table_account, &prize_table_key)?;
+    if prize_table_account.owner != program_id {
+        msg!("Prize table is not set");
+        return Err(LotteryError::NotInitialized.into());
+    }
+    let prize_table = PrizeTableData::unpack(&prize_table_account.data.borrow())?;
+    Ok(prize_table)
+}
+
+/// Sets reward of a single winner per tier from the prize table.
+/// Winners share 65% of the prize pool: fixed prizes are paid first, share tiers split the rest.
+/// When fixed prizes exceed the winners pool, they are reduced proportionally.
+fn calculate_tier_rewards(
+    settlement: &mut SettlementData,
+    prize_table: &PrizeTableData,
+) -> ProgramResult {
+    let winners_pool = share_of(settlement.prize_pool_amount, WINNERS_SHARE_BPS)?;
+    msg!("Winners pool: {}", winners_pool);
+
+    let mut fixed_total: u128 = 0;
+    for (tier, winners) in prize_table.tiers.iter().zip(settlement.winners.iter()) {
+        if let Prize::Fixed(amount) = tier.prize {
+            fixed_total = fixed_total
+                .checked_add(amount as u128 * *winners as u128)
+                .ok_or(LotteryError::AmountOverflow)?;
+        }
+    }
+    let shares_pool = (winners_pool as u128).saturating_sub(fixed_total) as u64;
+
+    for (i, tier) in prize_table.tiers.iter().enumerate() {
+        let winners = settlement.winners[i];
+        settlement.rewards[i] = match tier.prize {
+            _ if winners == 0 => 0,
+            // Reduced prize is below the fixed amount, so it fits u64
+            Prize::Fixed(amount) if fixed_total > winners_pool as u128 => {
+                (amount as u128 * winners_pool as u128 / fixed_total) as u64
+            }
+            Prize::Fixed(amount) => amount,
+            Prize::Share(share) => split_evenly(share_of(shares_pool, share)?, winners)?.0,
+        };
+        msg!(
+            "Winners(tier {}+{}) number {}, reward: {}",
+            tier.main_matches,
+            tier.bonus_match as u8,
+            winners,
+            settlement.rewards[i]
+        );
+    }
+    Ok(())
+}
+
+/// Creates the program owned account at a program derived address.
+/// Anyone can send lamports to the address before, so an account already holding lamports
+/// is topped up to the rent exemption, then allocated and assigned instead
+fn create_program_account<'a>(
+    program_id: &Pubkey,
+    payer_account: &AccountInfo<'a>,
+    new_account: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    rent: &Rent,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let required_lamports = rent.minimum_balance(space);
+    if new_account.lamports() == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                payer_account.key,
+                new_account.key,
+                required_lamports,
+                space as u64,
+                program_id,
+            ),
+            &[
+                payer_account.clone(),
+                new_account.clone(),
+                system_program_info.clone(),
+            ],
+            &[signer_seeds],
+        );
+    }
+
+    let top_up_lamports = required_lamports.saturating_sub(new_account.lamports());
+    if top_up_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(payer_account.key, new_account.key, top_up_lamports),
+            &[
+                payer_account.clone(),
+                new_account.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(new_account.key, space as u64),
+        &[new_account.clone(), system_program_info.clone()],
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(new_account.key, program_id),
+        &[new_account.clone(), system_program_info.clone()],
+        &[signer_seeds],
+    )
+}
+
+/// Loads the token account of the mint owned by the wallet
+fn load_token_account(
+    token_account: &AccountInfo,
+    mint: &Pubkey,
+    wallet: &Pubkey,
+) -> Result<TokenAccount, ProgramError> {
+    if *token_account.owner != spl_token::id() {
+        msg!(
+            "Token account {} is not owned by the token program",
+            token_account.key
+        );
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let account = TokenAccount::unpack(&token_account.data.borrow())?;
+    if account.mint != *mint || account.owner != *wallet {
+        msg!(
+            "Token account {} is not the {} account of {}",
+            token_account.key,
+            mint,
+            wallet
+        );
+        return Err(LotteryError::InvalidTokenAccount.into());
+    }
+    Ok(account)
+}
+
+/// Checks the account paid on behalf of the wallet: the wallet itself,
+/// or its token account of the lottery mint
+fn check_payout_account(
+    lottery_data: &LotteryData,
+    account: &AccountInfo,
+    wallet: &Pubkey,
+) -> ProgramResult {
+    if lottery_data.is_token_lottery() {
+        load_token_account(account, &lottery_data.mint, wallet)?;
+    } else if account.key != wallet {
+        return Err(LotteryError::InvalidSollottoAccount.into());
+    }
+    Ok(())
+}
+
+/// Checks the account holding the lottery funds: the lottery vault,
+/// or the vault token account of a token lottery
+fn check_vault_account(vault_account: &AccountInfo, lottery_data: &LotteryData) -> ProgramResult {
+    if *vault_account.key != lottery_data.vault {
+        msg!("Missing lottery vault");
+        return Err(LotteryError::InvalidVault.into());
+    }
+    Ok(())
+}
+
+/// Funds of the lottery: lamports of the vault, or tokens of the vault token account
+fn vault_balance(
+    vault_account: &AccountInfo,
+    lottery_data: &LotteryData,
+) -> Result<u64, ProgramError> {
+    if !lottery_data.is_token_lottery() {
+        return Ok(vault_account.lamports());
+    }
+    if *vault_account.owner != spl_token::id() {
+        msg!("Vault is not owned by the token program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(TokenAccount::unpack(&vault_account.data.borrow())?.amount)
+}
+
+/// Checks the vault holds at least the prize pool of the lottery
+fn check_vault_funds(vault_account: &AccountInfo, lottery_data: &LotteryData) -> ProgramResult {
+    if vault_balance(vault_account, lottery_data)? < lottery_data.prize_pool_amount {
+        msg!("Vault balance is below the prize pool");
+        return Err(ProgramError::InsufficientFunds);
+    }
+    Ok(())
+}
+
+/// Transfers lamports from the vault of the lottery, signed by the vault seeds
+fn transfer_lamports_from_vault<'a>(
+    program_id: &Pubkey,
+    lottery_key: &Pubkey,
+    vault_account: &AccountInfo<'a>,
+    destination_account: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    amount: u64,
+) -> ProgramResult {
+    let (vault_key, bump_seed) = find_vault_address(program_id, lottery_key);
+    check_account_address(vault_account, &vault_key)?;
+    invoke_signed(
+        &system_instruction::transfer(vault_account.key, destination_account.key, amount),
+        &[
+            vault_account.clone(),
+            destination_account.clone(),
+            system_program_info.clone(),
+        ],
+        &[&[VAULT_SEED, lottery_key.as_ref(), &[bump_seed]]],
+    )
+}
+
+/// Transfers tokens from the vault, signed by the vault authority
+fn transfer_from_vault<'a>(
+    program_id: &Pubkey,
+    vault_account: &AccountInfo<'a>,
+    destination_account: &AccountInfo<'a>,
+    vault_authority_account: &AccountInfo<'a>,
+    spl_token_info: &AccountInfo<'a>,
+    amount: u64,
+) -> ProgramResult {
+    let (vault_authority_key, bump_seed) = find_vault_authority_address(program_id);
+    check_account_address(vault_authority_account, &vault_authority_key)?;
+    if *spl_token_info.key != spl_token::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    invoke_signed(
+        &spl_token::instruction::transfer(
+            &spl_token::id(),
+            vault_account.key,
+            destination_account.key,
+            vault_authority_account.key,
+            &[],
+            amount,
+        )?,
+        &[
+            vault_account.clone(),
+            destination_account.clone(),
+            vault_authority_account.clone(),
+            spl_token_info.clone(),
+        ],
+        &[&[VAULT_AUTHORITY_SEED, &[bump_seed]]],
+    )
+}
+
+/// Pays from the vault of the lottery, the vault token account is paid out
+/// when the vault authority and the token program are passed for a token lottery
+fn pay_from_vault<'a>(
+    program_id: &Pubkey,
+    lottery_key: &Pubkey,
+    vault_account: &AccountInfo<'a>,
+    recipient_account: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    token_accounts: Option<(&AccountInfo<'a>, &AccountInfo<'a>)>,
+    amount: u64,
+) -> ProgramResult {
+    match token_accounts {
+        Some((vault_authority_account, spl_token_info)) => transfer_from_vault(
+            program_id,
+            vault_account,
+            recipient_account,
+            vault_authority_account,
+            spl_token_info,
+            amount,
+        ),
+        None => transfer_lamports_from_vault(
+            program_id,
+            lottery_key,
+            vault_account,
+            recipient_account,
+            system_program_info,
+            amount,
+        ),
+    }
+}
+
+/// Mints Lifetime Tickets to the user, signed by the Lifetime Ticket authority
+fn mint_lifetime_tickets<'a>(
+    program_id: &Pubkey,
+    lifetime_ticket_mint_account: &AccountInfo<'a>,
+    user_lifetime_ticket_account: &AccountInfo<'a>,
+    lifetime_ticket_authority_account: &AccountInfo<'a>,
+    spl_token_info: &AccountInfo<'a>,
+    amount: u64,
+) -> ProgramResult {
+    let (lifetime_ticket_authority_key, bump_seed) =
+        find_lifetime_ticket_authority_address(program_id);
+    check_account_address(
+        lifetime_ticket_authority_account,
+        &lifetime_ticket_authority_key,
+    )?;
+    if *spl_token_info.key != spl_token::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    invoke_signed(
+        &spl_token::instruction::mint_to(
+            &spl_token::id(),
+            lifetime_ticket_mint_account.key,
+            user_lifetime_ticket_account.key,
+            lifetime_ticket_authority_account.key,
+            &[],
+            amount,
+        )?,
+        &[
+            lifetime_ticket_mint_account.clone(),
+            user_lifetime_ticket_account.clone(),
+            lifetime_ticket_authority_account.clone(),
+            spl_token_info.clone(),
+        ],
+        &[&[LIFETIME_TICKET_AUTHORITY_SEED, &[bump_seed]]],
+    )
+}
+
 /// Program state handler.
 pub struct Processor;
-impl<'a> Processor {
+impl Processor {
     pub fn process(
         program_id: &Pubkey,
-        accounts: &'a[AccountInfo<'a>],
+        accounts: &[AccountInfo],
         instruction_data: &[u8],
     ) -> ProgramResult {
         check_program_account(program_id)?;
@@ -48,45 +635,63 @@ impl<'a> Processor {
         match instruction {
             LotteryInstruction::InitLottery {
                 lottery_id,
-                charity_1,
-                charity_2,
-                charity_3,
-                charity_4,
-                holding_wallet,
+                charities,
                 rewards_wallet,
                 slot_holders_rewards_wallet,
                 sollotto_labs_wallet,
                 randomness_account,
+                sale_start,
+                sale_end,
+                draw_time,
+                claim_period,
+                main_pool_size,
+                bonus_pool_size,
+                pick_count,
+                ticket_price,
+                charity_mode,
+                randomness_provider,
+                commitment,
             } => {
                 msg!("Instruction: InitLottery");
                 Self::process_init_lottery(
                     program_id,
                     accounts,
                     lottery_id,
-                    charity_1,
-                    charity_2,
-                    charity_3,
-                    charity_4,
-                    holding_wallet,
+                    charities,
                     rewards_wallet,
                     slot_holders_rewards_wallet,
                     sollotto_labs_wallet,
                     randomness_account,
+                    sale_start,
+                    sale_end,
+                    draw_time,
+                    claim_period,
+                    NumberFormat {
+                        main_pool_size,
+                        bonus_pool_size,
+                        pick_count,
+                    },
+                    ticket_price,
+                    charity_mode,
+                    randomness_provider,
+                    commitment,
                 )
             }
 
             LotteryInstruction::PurchaseTicket {
-                charity,
+                charity_index,
                 user_wallet_pk,
                 ticket_number_arr,
+                ticket_nonce,
             } => {
                 msg!("Instruction: PurchaseTicket");
                 Self::process_ticket_purchase(
                     program_id,
                     accounts,
-                    charity,
+                    charity_index,
                     user_wallet_pk,
-                    ticket_number_arr,
+                    vec![ticket_number_arr],
+                    ticket_nonce,
                 )
             }
 
@@ -100,20 +705,12 @@ impl<'a> Processor {
                 Self::process_reward_winners(program_id, accounts)
             }
 
-            LotteryInstruction::UpdateCharity {
-                charity_1,
-                charity_2,
-                charity_3,
-                charity_4,
-            } => {
+            LotteryInstruction::UpdateCharity { charities } => {
                 msg!("Instrction: update charity");
-                Self::process_update_charity(
-                    program_id, accounts, charity_1, charity_2, charity_3, charity_4,
-                )
+                Self::process_update_charity(program_id, accounts, charities)
             }
 
             LotteryInstruction::UpdateSollottoWallets {
-                holding_wallet,
                 rewards_wallet,
                 slot_holders_rewards_wallet,
                 sollotto_labs_wallet,
@@ -122,12 +719,149 @@ impl<'a> Processor {
                 Self::process_update_sollotto_wallets(
                     program_id,
                     accounts,
-                    holding_wallet,
                     rewards_wallet,
                     slot_holders_rewards_wallet,
                     sollotto_labs_wallet,
                 )
             }
+
+            LotteryInstruction::SetAuthority { new_authority } => {
+                msg!("Instruction: set authority");
+                Self::process_set_authority(program_id, accounts, new_authority)
+            }
+
+            LotteryInstruction::AcceptAuthority {} => {
+                msg!("Instruction: accept authority");
+                Self::process_accept_authority(program_id, accounts)
+            }
+
+            LotteryInstruction::TallyBatch {} => {
+                msg!("Instruction: tally batch");
+                Self::process_tally_batch(program_id, accounts)
+            }
+
+            LotteryInstruction::PayoutBatch {} => {
+                msg!("Instruction: payout batch");
+                Self::process_payout_batch(program_id, accounts)
+            }
+
+            LotteryInstruction::ClaimPrize {} => {
+                msg!("Instruction: claim prize");
+                Self::process_claim_prize(program_id, accounts)
+            }
+
+            LotteryInstruction::SweepUnclaimed {} => {
+                msg!("Instruction: sweep unclaimed");
+                Self::process_sweep_unclaimed(program_id, accounts)
+            }
+
+            LotteryInstruction::SetPrizeTable { tiers } => {
+                msg!("Instruction: set prize table");
+                Self::process_set_prize_table(program_id, accounts, tiers)
+            }
+
+            LotteryInstruction::PurchaseTickets {
+                charity_index,
+                user_wallet_pk,
+                ticket_numbers,
+                ticket_nonce,
+            } => {
+                msg!("Instruction: PurchaseTickets");
+                Self::process_ticket_purchase(
+                    program_id,
+                    accounts,
+                    charity_index,
+                    user_wallet_pk,
+                    ticket_numbers,
+                    ticket_nonce,
+                )
+            }
+
+            LotteryInstruction::PurchaseQuickPick {
+                charity_index,
+                user_wallet_pk,
+                ticket_nonce,
+            } => {
+                msg!("Instruction: PurchaseQuickPick");
+                Self::process_quick_pick_purchase(
+                    program_id,
+                    accounts,
+                    charity_index,
+                    user_wallet_pk,
+                    ticket_nonce,
+                )
+            }
+
+            LotteryInstruction::InitCharityRegistry {} => {
+                msg!("Instruction: init charity registry");
+                Self::process_init_charity_registry(program_id, accounts)
+            }
+
+            LotteryInstruction::AddCharity { name_hash, wallet } => {
+                msg!("Instruction: add charity");
+                Self::process_add_charity(program_id, accounts, name_hash, wallet)
+            }
+
+            LotteryInstruction::UpdateRegistryCharity {
+                index,
+                wallet,
+                is_active,
+            } => {
+                msg!("Instruction: update registry charity");
+                Self::process_update_registry_charity(
+                    program_id, accounts, index, wallet, is_active,
+                )
+            }
+
+            LotteryInstruction::CloseTicket {} => {
+                msg!("Instruction: close ticket");
+                Self::process_close_ticket(program_id, accounts)
+            }
+
+            LotteryInstruction::RevealSecret { secret } => {
+                msg!("Instruction: reveal secret");
+                Self::process_reveal_secret(program_id, accounts, secret)
+            }
+
+            LotteryInstruction::RefundTicket {} => {
+                msg!("Instruction: refund ticket");
+                Self::process_refund_ticket(program_id, accounts)
+            }
+
+            LotteryInstruction::CancelLottery {} => {
+                msg!("Instruction: cancel lottery");
+                Self::process_cancel_lottery(program_id, accounts)
+            }
+
+            LotteryInstruction::InitVault {} => {
+                msg!("Instruction: init vault");
+                Self::process_init_vault(program_id, accounts)
+            }
+
+            LotteryInstruction::SetLifetimeTicket { amount } => {
+                msg!("Instruction: set lifetime ticket");
+                Self::process_set_lifetime_ticket(program_id, accounts, amount)
+            }
+
+            LotteryInstruction::SetReferralShare { share_bps } => {
+                msg!("Instruction: set referral share");
+                Self::process_set_referral_share(program_id, accounts, share_bps)
+            }
+
+            LotteryInstruction::ClaimReferralRewards {} => {
+                msg!("Instruction: claim referral rewards");
+                Self::process_claim_referral_rewards(program_id, accounts)
+            }
+
+            LotteryInstruction::LockRevealSlot {} => {
+                msg!("Instruction: lock reveal slot");
+                Self::process_lock_reveal_slot(program_id, accounts)
+            }
+
+            LotteryInstruction::RequestDraw {} => {
+                msg!("Instruction: request draw");
+                Self::process_request_draw(program_id, accounts)
+            }
         }
     }
 
@@ -135,37 +869,120 @@ impl<'a> Processor {
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         lottery_id: u32,
-        charity_1: Pubkey,
-        charity_2: Pubkey,
-        charity_3: Pubkey,
-        charity_4: Pubkey,
-        holding_wallet: Pubkey,
+        charities: Vec<u16>,
         rewards_wallet: Pubkey,
         slot_holders_rewards_wallet: Pubkey,
         sollotto_labs_wallet: Pubkey,
         randomness_account: Pubkey,
+        sale_start: UnixTimestamp,
+        sale_end: UnixTimestamp,
+        draw_time: UnixTimestamp,
+        claim_period: i64,
+        number_format: NumberFormat,
+        ticket_price: u64,
+        charity_mode: CharityMode,
+        randomness_provider: RandomnessProvider,
+        commitment: [u8; 32],
     ) -> ProgramResult {
         let accounts_iter = &mut accounts.iter();
 
         // lottery data account
         let lottery_data_account = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+        let rent = &Rent::from_account_info(next_account_info(accounts_iter)?)?;
+        let clock = &Clock::from_account_info(next_account_info(accounts_iter)?)?;
+        let system_program_info = next_account_info(accounts_iter)?;
+        let charity_registry_account = next_account_info(accounts_iter)?;
+        let vault_account = next_account_info(accounts_iter)?;
+        let program_data_account = next_account_info(accounts_iter)?;
+        let previous_lottery_account = next_account_info(accounts_iter).ok();
 
-        // Check if program owns data account
-        if lottery_data_account.owner != program_id {
-            msg!("Lottery Data account does not have the correct program id");
-            return Err(ProgramError::IncorrectProgramId);
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
         }
-
-        if !lottery_data_account.is_signer {
+        if !authority_account.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
+        // Lottery address is derived from its id only, so nobody else can take the next ids
+        if *authority_account.key != load_upgrade_authority(program_id, program_data_account)? {
+            msg!("Lottery authority must be the program upgrade authority");
+            return Err(LotteryError::InvalidAuthority.into());
+        }
 
-        let rent = &Rent::from_account_info(next_account_info(accounts_iter)?)?;
-        if !rent.is_exempt(
-            lottery_data_account.lamports(),
-            lottery_data_account.data_len(),
-        ) {
-            return Err(LotteryError::NotRentExempt.into());
+        let (lottery_key, bump_seed) = find_lottery_address(program_id, lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+        let (vault_key, _) = find_vault_address(program_id, &lottery_key);
+        check_account_address(vault_account, &vault_key)?;
+
+        if sale_start >= sale_end || sale_end > draw_time {
+            msg!("Sale window must close before the draw time");
+            return Err(LotteryError::InvalidSchedule.into());
+        }
+        if sale_end <= clock.unix_timestamp {
+            msg!("Sale end is already in the past");
+            return Err(LotteryError::InvalidSchedule.into());
+        }
+        if claim_period <= 0 {
+            msg!("Claim period must be positive");
+            return Err(LotteryError::InvalidSchedule.into());
+        }
+        number_format.validate()?;
+        if ticket_price == 0 {
+            msg!("Ticket price must be positive");
+            return Err(LotteryError::InvalidTicketPrice.into());
+        }
+        charity_mode.validate()?;
+        randomness_provider.validate()?;
+        let reveal_deadline = if randomness_provider == RandomnessProvider::CommitReveal {
+            if commitment == [0; 32] {
+                msg!("Commitment is required by the commit-reveal randomness");
+                return Err(LotteryError::InvalidCommitment.into());
+            }
+            // Secret is revealed by the program, it becomes the randomness account
+            let (reveal_key, _) = find_reveal_address(program_id, &lottery_key);
+            if randomness_account != reveal_key {
+                msg!("Randomness account must be the lottery commit-reveal account");
+                return Err(LotteryError::InvalidAccountAddress.into());
+            }
+            draw_time
+                .checked_add(REVEAL_PERIOD)
+                .ok_or(LotteryError::InvalidSchedule)?
+        } else {
+            0
+        };
+        let registry = load_charity_registry(program_id, charity_registry_account)?;
+        check_lottery_charities(&charities, &registry)?;
+
+        create_program_account(
+            program_id,
+            payer_account,
+            lottery_data_account,
+            system_program_info,
+            rent,
+            LotteryData::LEN,
+            &[LOTTERY_SEED, &lottery_id.to_le_bytes(), &[bump_seed]],
+        )?;
+
+        // Vault keeps its rent-exempt reserve, so it is never purged with the ticket money
+        let vault_reserve = rent
+            .minimum_balance(0)
+            .saturating_sub(vault_account.lamports());
+        if vault_reserve > 0 {
+            invoke(
+                &system_instruction::transfer(payer_account.key, vault_account.key, vault_reserve),
+                &[
+                    payer_account.clone(),
+                    vault_account.clone(),
+                    system_program_info.clone(),
+                ],
+            )?;
+        }
+
+        // Check if program owns data account
+        if lottery_data_account.owner != program_id {
+            msg!("Lottery Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
         }
 
         // Add data to account
@@ -176,21 +993,97 @@ impl<'a> Processor {
         }
 
         lottery_data.is_initialized = true;
+        lottery_data.is_cancelled = false;
         lottery_data.lottery_id = lottery_id;
-        lottery_data.charity_1 = charity_1;
-        lottery_data.charity_2 = charity_2;
-        lottery_data.charity_3 = charity_3;
-        lottery_data.charity_4 = charity_4;
-        lottery_data.charity_1_vc = 0;
-        lottery_data.charity_2_vc = 0;
-        lottery_data.charity_3_vc = 0;
-        lottery_data.charity_4_vc = 0;
-        lottery_data.holding_wallet = holding_wallet;
+        lottery_data.charity_votes = [0; MAX_LOTTERY_CHARITIES];
+        lottery_data.set_charities(&charities);
         lottery_data.rewards_wallet = rewards_wallet;
         lottery_data.slot_holders_rewards_wallet = slot_holders_rewards_wallet;
         lottery_data.sollotto_labs_wallet = sollotto_labs_wallet;
         lottery_data.randomness_account = randomness_account;
+        lottery_data.sale_start = sale_start;
+        lottery_data.sale_end = sale_end;
+        lottery_data.draw_time = draw_time;
+        lottery_data.claim_period = claim_period;
+        lottery_data.number_format = number_format;
+        lottery_data.ticket_price = ticket_price;
+        lottery_data.charity_mode = charity_mode;
+        lottery_data.randomness_provider = randomness_provider;
+        lottery_data.last_randomness_hash = [0; 32];
+        lottery_data.commitment = commitment;
+        lottery_data.reveal_deadline = reveal_deadline;
+        lottery_data.authority = *authority_account.key;
+        lottery_data.pending_authority = Pubkey::default();
         lottery_data.total_registrations = 0;
+        lottery_data.prize_pool_amount = 0;
+        lottery_data.rollover_amount = 0;
+        lottery_data.mint = Pubkey::default();
+        lottery_data.vault = vault_key;
+        lottery_data.lifetime_ticket_mint = Pubkey::default();
+        lottery_data.lifetime_ticket_amount = 0;
+        lottery_data.referral_share_bps = 0;
+        lottery_data.referral_volume = 0;
+        lottery_data.referral_rewards = 0;
+        lottery_data.draw_requested_at = 0;
+
+        // Unpaid prize pool of the previous draw is added to this one
+        if let Some(previous_lottery_account) = previous_lottery_account {
+            let previous_vault_account = next_account_info(accounts_iter)?;
+            if previous_lottery_account.owner != program_id {
+                msg!("Previous Lottery Data account does not have the correct program id");
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            let mut previous_lottery =
+                LotteryData::unpack_unchecked(&previous_lottery_account.data.borrow())?;
+            let (previous_lottery_key, _) =
+                find_lottery_address(program_id, previous_lottery.lottery_id);
+            check_account_address(previous_lottery_account, &previous_lottery_key)?;
+            if previous_lottery.is_initialized {
+                // Cancelled or abandoned draw is finished once every ticket is refunded,
+                // the rest of its prize pool is the rollover it took from its previous draw
+                let is_refunded = previous_lottery.is_cancelled
+                    || previous_lottery.is_abandoned(clock.unix_timestamp);
+                if !is_refunded || previous_lottery.total_registrations != 0 {
+                    msg!("Previous lottery is not finished");
+                    return Err(LotteryError::InvalidRollover.into());
+                }
+                previous_lottery.is_initialized = false;
+                previous_lottery.rollover_amount = previous_lottery.prize_pool_amount;
+                previous_lottery.prize_pool_amount = 0;
+            }
+            check_authority(authority_account, &previous_lottery)?;
+            check_vault_account(previous_vault_account, &previous_lottery)?;
+
+            msg!("Rollover: {}", previous_lottery.rollover_amount);
+            lottery_data.prize_pool_amount = previous_lottery.rollover_amount;
+            if previous_lottery.is_token_lottery() {
+                // Rollover tokens stay in the vault, which is shared with the previous lottery
+                check_account_address(previous_vault_account, &previous_lottery.vault)?;
+                lottery_data.mint = previous_lottery.mint;
+                lottery_data.vault = previous_lottery.vault;
+                check_vault_funds(previous_vault_account, &lottery_data)?;
+            } else if previous_lottery.rollover_amount > 0 {
+                transfer_lamports_from_vault(
+                    program_id,
+                    &previous_lottery_key,
+                    previous_vault_account,
+                    vault_account,
+                    system_program_info,
+                    previous_lottery.rollover_amount,
+                )?;
+                check_vault_funds(vault_account, &lottery_data)?;
+            }
+            previous_lottery.rollover_amount = 0;
+            // Randomness account keeps the last result until the next one is produced
+            if previous_lottery.randomness_account == randomness_account {
+                lottery_data.last_randomness_hash = previous_lottery.last_randomness_hash;
+            }
+            LotteryData::pack(
+                previous_lottery,
+                &mut previous_lottery_account.data.borrow_mut(),
+            )?;
+        }
+
         LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
 
         msg!("Data stored");
@@ -201,19 +1094,18 @@ impl<'a> Processor {
     pub fn process_ticket_purchase(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        charity: Pubkey,
+        charity_index: u16,
         user_wallet_pk: Pubkey,
-        ticket_number_arr: [u8; 6],
+        mut ticket_numbers: Vec<LotteryNumbers>,
+        ticket_nonce: u32,
     ) -> ProgramResult {
         let accounts_iter = &mut accounts.iter();
         let lottery_data_account = next_account_info(accounts_iter)?;
         let ticket_data_account = next_account_info(accounts_iter)?;
         let user_funding_account = next_account_info(accounts_iter)?;
-        let holding_wallet_account = next_account_info(accounts_iter)?;
-        let user_lifetime_ticket_account = next_account_info(accounts_iter)?;
-        let lifetime_ticket_owner_account = next_account_info(accounts_iter)?;
-        let lifetime_ticket_mint_account = next_account_info(accounts_iter)?;
+        let vault_account = next_account_info(accounts_iter)?;
         let rent = &Rent::from_account_info(next_account_info(accounts_iter)?)?;
+        let clock = &Clock::from_account_info(next_account_info(accounts_iter)?)?;
         let system_program_info = next_account_info(accounts_iter)?;
         let spl_token_info = next_account_info(accounts_iter)?;
 
@@ -221,20 +1113,10 @@ impl<'a> Processor {
             msg!("Lottery Data account does not have the correct program id");
             return Err(ProgramError::IncorrectProgramId);
         }
-        if ticket_data_account.owner != program_id {
-            msg!("Ticket Data account does not have the correct program id");
-            return Err(ProgramError::IncorrectProgramId);
-        }
 
-        if !lottery_data_account.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
         if !user_funding_account.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
-        if !lifetime_ticket_owner_account.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
 
         let mut lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
         //Check if lottery initisalised
@@ -246,115 +1128,336 @@ impl<'a> Processor {
             msg!("Lottery data account already finaled");
             return Err(LotteryError::IsFinaled.into());
         }
+        if lottery_data.is_cancelled {
+            msg!("Lottery is cancelled");
+            return Err(LotteryError::IsCancelled.into());
+        }
+
+        if clock.unix_timestamp < lottery_data.sale_start {
+            msg!("Ticket sale is not started");
+            return Err(LotteryError::SaleNotStarted.into());
+        }
+        if clock.unix_timestamp >= lottery_data.sale_end {
+            msg!("Ticket sale is closed");
+            return Err(LotteryError::SaleClosed.into());
+        }
 
-        if *holding_wallet_account.key != lottery_data.holding_wallet {
-            msg!("Missing holding wallet");
-            return Err(LotteryError::InvalidSollottoAccount.into());
+        let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+
+        if *user_funding_account.key != user_wallet_pk {
+            msg!("User funding account does not match user wallet");
+            return Err(LotteryError::InvalidParticipantsAccounts.into());
         }
 
-        if user_funding_account.lamports() < sol_to_lamports(0.1) {
+        let (ticket_key, bump_seed) =
+            find_ticket_address(program_id, &lottery_key, &user_wallet_pk, ticket_nonce);
+        check_account_address(ticket_data_account, &ticket_key)?;
+
+        check_vault_account(vault_account, &lottery_data)?;
+        check_vault_funds(vault_account, &lottery_data)?;
+
+        let charity_pos = lottery_data
+            .charities()
+            .iter()
+            .position(|index| *index == charity_index)
+            .ok_or_else(|| {
+                msg!("Charity {} is not voted in the lottery", charity_index);
+                LotteryError::InvalidCharity
+            })?;
+
+        // Every line is paid at the lottery ticket price
+        let lines_count = ticket_numbers.len() as u32;
+        let ticket_price = lottery_data
+            .ticket_price
+            .checked_mul(lines_count as u64)
+            .ok_or(LotteryError::AmountOverflow)?;
+        // Lifetime Tickets are minted to the user token account of the lottery Lifetime Ticket mint
+        let lifetime_ticket_accounts = if lottery_data.has_lifetime_ticket() {
+            let user_lifetime_ticket_account = next_account_info(accounts_iter)?;
+            let lifetime_ticket_mint_account = next_account_info(accounts_iter)?;
+            let lifetime_ticket_authority_account = next_account_info(accounts_iter)?;
+            check_account_address(
+                lifetime_ticket_mint_account,
+                &lottery_data.lifetime_ticket_mint,
+            )?;
+            load_token_account(
+                user_lifetime_ticket_account,
+                &lottery_data.lifetime_ticket_mint,
+                &user_wallet_pk,
+            )?;
+            Some((
+                user_lifetime_ticket_account,
+                lifetime_ticket_mint_account,
+                lifetime_ticket_authority_account,
+            ))
+        } else {
+            None
+        };
+        // Token lotteries take the price from the user token account,
+        // the ticket rent is paid in lamports
+        let user_token_account = if lottery_data.is_token_lottery() {
+            let user_token_account = next_account_info(accounts_iter)?;
+            let token_account =
+                load_token_account(user_token_account, &lottery_data.mint, &user_wallet_pk)?;
+            if token_account.amount < ticket_price {
+                msg!("User cannot pay for ticket");
+                return Err(ProgramError::InsufficientFunds);
+            }
+            Some(user_token_account)
+        } else {
+            None
+        };
+        // Referrer accounts follow all other purchase accounts
+        let referral_accounts = match next_account_info(accounts_iter).ok() {
+            Some(referrer_account) => {
+                let referral_account = next_account_info(accounts_iter)?;
+                if lottery_data.referral_share_bps == 0 {
+                    msg!("Lottery does not accept referrals");
+                    return Err(LotteryError::InvalidReferrer.into());
+                }
+                if *referrer_account.key == user_wallet_pk {
+                    msg!("User cannot refer own tickets");
+                    return Err(LotteryError::InvalidReferrer.into());
+                }
+                let (referral_key, referral_bump_seed) =
+                    find_referral_address(program_id, &lottery_key, referrer_account.key);
+                check_account_address(referral_account, &referral_key)?;
+                Some((referrer_account, referral_account, referral_bump_seed))
+            }
+            None => None,
+        };
+        let referral_amount = if referral_accounts.is_some() {
+            share_of(ticket_price, lottery_data.referral_share_bps)?
+        } else {
+            0
+        };
+        // Referral ledger is created by the first purchase referred by its referrer
+        let referral_rent = match referral_accounts {
+            Some((_, referral_account, _)) if referral_account.data_is_empty() => {
+                rent.minimum_balance(ReferralData::LEN)
+            }
+            _ => 0,
+        };
+        let lamports_price = if user_token_account.is_some() {
+            0
+        } else {
+            ticket_price
+        };
+        let required_lamports = lamports_price
+            .checked_add(rent.minimum_balance(TicketData::LEN))
+            .and_then(|amount| amount.checked_add(referral_rent))
+            .ok_or(LotteryError::AmountOverflow)?;
+        if user_funding_account.lamports() < required_lamports {
             msg!("User cannot pay for ticket");
             return Err(ProgramError::InsufficientFunds);
         }
 
-        if !rent.is_exempt(
-            lottery_data_account.lamports(),
-            lottery_data_account.data_len(),
-        ) {
-            return Err(LotteryError::NotRentExempt.into());
-        }
+        create_program_account(
+            program_id,
+            user_funding_account,
+            ticket_data_account,
+            system_program_info,
+            rent,
+            TicketData::LEN,
+            &[
+                TICKET_SEED,
+                lottery_key.as_ref(),
+                user_wallet_pk.as_ref(),
+                &ticket_nonce.to_le_bytes(),
+                &[bump_seed],
+            ],
+        )?;
 
-        if !rent.is_exempt(
-            ticket_data_account.lamports(),
-            ticket_data_account.data_len(),
-        ) {
-            return Err(LotteryError::NotRentExempt.into());
+        if ticket_data_account.owner != program_id {
+            msg!("Ticket Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
         }
 
+        let referral = match referral_accounts {
+            Some((referrer_account, referral_account, referral_bump_seed)) => {
+                if referral_account.data_is_empty() {
+                    create_program_account(
+                        program_id,
+                        user_funding_account,
+                        referral_account,
+                        system_program_info,
+                        rent,
+                        ReferralData::LEN,
+                        &[
+                            REFERRAL_SEED,
+                            lottery_key.as_ref(),
+                            referrer_account.key.as_ref(),
+                            &[referral_bump_seed],
+                        ],
+                    )?;
+                }
+                if referral_account.owner != program_id {
+                    msg!("Referral Data account does not have the correct program id");
+                    return Err(ProgramError::IncorrectProgramId);
+                }
+                let mut referral_data =
+                    ReferralData::unpack_unchecked(&referral_account.data.borrow())?;
+                if !referral_data.is_initialized {
+                    referral_data.is_initialized = true;
+                    referral_data.referrer = *referrer_account.key;
+                    referral_data.lottery_id = lottery_data.lottery_id;
+                }
+                Some((referral_account, referral_data))
+            }
+            None => None,
+        };
+
         let mut ticket_data = TicketData::unpack_unchecked(&ticket_data_account.data.borrow())?;
         if ticket_data.is_purchased {
             msg!("Ticket data account already purchased");
             return Err(LotteryError::AlreadyPurchased.into());
         }
 
-        for i in 0..5 {
-            if ticket_number_arr[i] < 1 || ticket_number_arr[i] > 69 {
-                msg!("Invalid value for one of from 1 to 5 number");
-                return Err(LotteryError::InvalidNumber.into());
-            }
-        }
-        if ticket_number_arr[5] < 1 || ticket_number_arr[5] > 29 {
-            msg!("Invalid value for 6 number");
+        if ticket_numbers.is_empty() || ticket_numbers.len() > MAX_TICKET_LINES {
+            msg!("Invalid ticket lines count: {}", ticket_numbers.len());
             return Err(LotteryError::InvalidNumber.into());
         }
+        for ticket_number_arr in ticket_numbers.iter_mut() {
+            lottery_data
+                .number_format
+                .check_numbers(ticket_number_arr)?;
+            lottery_data
+                .number_format
+                .sort_main_numbers(ticket_number_arr);
+        }
 
         ticket_data.is_purchased = true;
-        ticket_data.charity = charity;
+        ticket_data.lottery_id = lottery_data.lottery_id;
+        ticket_data.charity_index = charity_index;
         ticket_data.user_wallet_pk = user_wallet_pk;
-        ticket_data.ticket_number_arr = ticket_number_arr;
-
-        lottery_data.total_registrations += 1;
-        let charity_arr = [
-            lottery_data.charity_1,
-            lottery_data.charity_2,
-            lottery_data.charity_3,
-            lottery_data.charity_4,
-        ];
-        msg!("Charity Ids: {:?}", charity_arr);
-        for (pos, key) in charity_arr.iter().enumerate() {
-            msg!("Entered Loop");
-            msg!("Current Charity: {}", *key);
-            msg!("Receieved Charity: {}", charity);
-            if *key == charity {
-                msg!("Matched ID Loop");
-                match pos {
-                    0 => lottery_data.charity_1_vc += 1,
-                    1 => lottery_data.charity_2_vc += 1,
-                    2 => lottery_data.charity_3_vc += 1,
-                    3 => lottery_data.charity_4_vc += 1,
-                    _ => return Err(LotteryError::InvalidCharity.into()),
-                }
-                break;
-            }
+        ticket_data.ticket_nonce = ticket_nonce;
+        ticket_data.purchase_slot = clock.slot;
+        ticket_data.is_tallied = false;
+        ticket_data.is_paid = false;
+        ticket_data.referral_amount = referral_amount;
+        ticket_data.referrer = match referral {
+            Some((_, referral_data)) => referral_data.referrer,
+            None => Pubkey::default(),
+        };
+        ticket_data.lines = ticket_numbers
+            .into_iter()
+            .map(|ticket_number_arr| TicketLine {
+                ticket_number_arr,
+                matched: 0,
+                bonus_matched: false,
+            })
+            .collect();
+
+        lottery_data.total_registrations = lottery_data
+            .total_registrations
+            .checked_add(1)
+            .ok_or(LotteryError::AmountOverflow)?;
+        // Every line is a vote
+        lottery_data.charity_votes[charity_pos] = lottery_data.charity_votes[charity_pos]
+            .checked_add(lines_count)
+            .ok_or(LotteryError::AmountOverflow)?;
+        // Referral share of the price is not added to the prize pool
+        let prize_pool_amount = ticket_price
+            .checked_sub(referral_amount)
+            .ok_or(LotteryError::AmountOverflow)?;
+
+        if let Some(user_token_account) = user_token_account {
+            // Transfer the price of all lines into the vault from the user token account
+            invoke(
+                &spl_token::instruction::transfer(
+                    &spl_token::id(),
+                    user_token_account.key,
+                    &lottery_data.vault,
+                    &user_wallet_pk,
+                    &[],
+                    ticket_price,
+                )?,
+                &[
+                    user_token_account.clone(),
+                    vault_account.clone(),
+                    user_funding_account.clone(),
+                    spl_token_info.clone(),
+                ],
+            )?;
+        } else {
+            // Transfer the price of all lines into the lottery vault from user_wallet,
+            // without the referral share
+            invoke(
+                &system_instruction::transfer(
+                    &user_wallet_pk,
+                    &lottery_data.vault,
+                    prize_pool_amount,
+                ),
+                &[
+                    user_funding_account.clone(),
+                    vault_account.clone(),
+                    system_program_info.clone(),
+                ],
+            )?;
         }
 
-        // Transfer 0.1 SOL into holding wallet from user_wallet
-        let ticket_price = sol_to_lamports(0.1);
-        invoke(
-            &system_instruction::transfer(
-                &user_wallet_pk,
-                &lottery_data.holding_wallet,
-                ticket_price,
-            ),
-            &[
-                user_funding_account.clone(),
-                holding_wallet_account.clone(),
-                system_program_info.clone(),
-            ],
-        )?;
+        if let Some((referral_account, mut referral_data)) = referral {
+            // Referral share is held by the referral ledger until claimed
+            invoke(
+                &system_instruction::transfer(
+                    &user_wallet_pk,
+                    referral_account.key,
+                    referral_amount,
+                ),
+                &[
+                    user_funding_account.clone(),
+                    referral_account.clone(),
+                    system_program_info.clone(),
+                ],
+            )?;
+            referral_data.accrued_amount = referral_data
+                .accrued_amount
+                .checked_add(referral_amount)
+                .ok_or(LotteryError::AmountOverflow)?;
+            lottery_data.referral_volume = lottery_data
+                .referral_volume
+                .checked_add(ticket_price)
+                .ok_or(LotteryError::AmountOverflow)?;
+            lottery_data.referral_rewards = lottery_data
+                .referral_rewards
+                .checked_add(referral_amount)
+                .ok_or(LotteryError::AmountOverflow)?;
+            ReferralData::pack(referral_data, &mut referral_account.data.borrow_mut())?;
+            msg!(
+                "Referral reward of {}: {}",
+                referral_data.referrer,
+                referral_amount
+            );
+        }
 
-        // Mint 1.0 Lifetime Ticket Token to user
-        let decimals = Mint::unpack(&lifetime_ticket_mint_account.data.borrow())?.decimals;
-        let amount = ui_amount_to_amount(1.0, decimals);
-        invoke(
-            &spl_token::instruction::mint_to(
-                &spl_token::id(),
-                lifetime_ticket_mint_account.key,
-                user_lifetime_ticket_account.key,
-                lifetime_ticket_owner_account.key,
-                &[],
+        if let Some((
+            user_lifetime_ticket_account,
+            lifetime_ticket_mint_account,
+            lifetime_ticket_authority_account,
+        )) = lifetime_ticket_accounts
+        {
+            // Lifetime Tickets are minted for every line
+            let amount = lottery_data
+                .lifetime_ticket_amount
+                .checked_mul(lines_count as u64)
+                .ok_or(LotteryError::AmountOverflow)?;
+            mint_lifetime_tickets(
+                program_id,
+                lifetime_ticket_mint_account,
+                user_lifetime_ticket_account,
+                lifetime_ticket_authority_account,
+                spl_token_info,
                 amount,
-            )
-            .unwrap(),
-            &[
-                spl_token_info.clone(),
-                lifetime_ticket_mint_account.clone(),
-                user_lifetime_ticket_account.clone(),
-                lifetime_ticket_owner_account.clone(),
-            ],
-        )?;
+            )?;
+            msg!("Lifetime Tickets minted: {}", amount);
+        }
 
-        lottery_data.prize_pool_amount += ticket_price;
+        lottery_data.prize_pool_amount = lottery_data
+            .prize_pool_amount
+            .checked_add(prize_pool_amount)
+            .ok_or(LotteryError::AmountOverflow)?;
+        check_vault_funds(vault_account, &lottery_data)?;
 
         TicketData::pack(ticket_data, &mut ticket_data_account.data.borrow_mut())?;
         LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
@@ -362,20 +1465,63 @@ impl<'a> Processor {
         Ok(())
     }
 
-    pub fn process_store_winning_numbers(
+    pub fn process_quick_pick_purchase(
         program_id: &Pubkey,
-        accounts: &'a [AccountInfo<'a>],
+        accounts: &[AccountInfo],
+        charity_index: u16,
+        user_wallet_pk: Pubkey,
+        ticket_nonce: u32,
     ) -> ProgramResult {
         let accounts_iter = &mut accounts.iter();
         let lottery_data_account = next_account_info(accounts_iter)?;
+        // SlotHashes sysvar follows the purchase accounts, which are checked by the ticket purchase
+        let slot_hashes_account = accounts
+            .get(PURCHASE_ACCOUNTS_LEN)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
 
         if lottery_data_account.owner != program_id {
             msg!("Lottery Data account does not have the correct program id");
             return Err(ProgramError::IncorrectProgramId);
         }
 
-        if !lottery_data_account.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
+        let lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
+
+        let (_, slot_hash) = most_recent_slot_hash(slot_hashes_account)?;
+        let ticket_number_arr = derive_quick_pick_numbers(
+            &slot_hash,
+            &user_wallet_pk,
+            lottery_data.total_registrations,
+            &lottery_data.number_format,
+        )?;
+        msg!("Quick pick numbers: {:?}", ticket_number_arr);
+
+        // Ticket purchase takes the same accounts without the SlotHashes sysvar
+        let purchase_accounts = [
+            &accounts[..PURCHASE_ACCOUNTS_LEN],
+            &accounts[PURCHASE_ACCOUNTS_LEN + 1..],
+        ]
+        .concat();
+        Self::process_ticket_purchase(
+            program_id,
+            &purchase_accounts,
+            charity_index,
+            user_wallet_pk,
+            vec![ticket_number_arr],
+            ticket_nonce,
+        )
+    }
+
+    pub fn process_store_winning_numbers(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let lottery_data_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+
+        if lottery_data_account.owner != program_id {
+            msg!("Lottery Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
         }
 
         let mut lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
@@ -387,381 +1533,694 @@ impl<'a> Processor {
             msg!("Lottery Data account already finaled");
             return Err(LotteryError::IsFinaled.into());
         }
+        if lottery_data.is_cancelled {
+            msg!("Lottery is cancelled");
+            return Err(LotteryError::IsCancelled.into());
+        }
 
-        // if *vrf_account_info.key != lottery_data.randomness_account {
-        //     return Err(LotteryError::InvalidSollottoAccount.into());
-        // }
+        let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+        check_authority(authority_account, &lottery_data)?;
 
-        let vrf_account_info = next_account_info(accounts_iter)?;
-        let vrf_account = VrfAccount::new(vrf_account_info)?;
-        let random_numbers = vrf_account.get_verified_randomness()?;
-        // drop(vrf_account);
-        if random_numbers.len() < 6 {
-            return Err(LotteryError::InvalidRandomResult.into());
+        let randomness_account_info = next_account_info(accounts_iter)?;
+        check_account_address(randomness_account_info, &lottery_data.randomness_account)?;
+        let clock = &Clock::from_account_info(next_account_info(accounts_iter)?)?;
+        if clock.unix_timestamp < lottery_data.draw_time {
+            msg!("Draw time is not reached");
+            return Err(LotteryError::DrawNotReady.into());
         }
-
-        let mut winning_numbers_arr: [u8; 6] = [0; 6];
-        for i in 0..4 {
-            winning_numbers_arr[i] = random_numbers[i] % 49 + 1;
+        // Tickets are refunded once the reveal deadline is passed
+        if lottery_data.randomness_provider == RandomnessProvider::CommitReveal
+            && clock.unix_timestamp > lottery_data.reveal_deadline
+        {
+            msg!("Reveal deadline is passed");
+            return Err(LotteryError::RevealExpired.into());
         }
-        winning_numbers_arr[5] = random_numbers[5] % 26 + 1;
-
-        for i in 0..5 {
-            if winning_numbers_arr[i] < 1 || winning_numbers_arr[i] > 69 {
-                msg!("Invalid value for one of from 1 to 5 number");
-                return Err(LotteryError::InvalidNumber.into());
-            }
+        // Result held by the account when the sale was closed may be known to anyone
+        if lottery_data.randomness_provider != RandomnessProvider::CommitReveal
+            && lottery_data.draw_requested_at == 0
+        {
+            msg!("Draw is not requested");
+            return Err(LotteryError::DrawNotReady.into());
         }
-        if winning_numbers_arr[5] < 1 || winning_numbers_arr[5] > 29 {
-            msg!("Invalid value for 6 number");
-            return Err(LotteryError::InvalidNumber.into());
+
+        let random_numbers =
+            load_randomness(lottery_data.randomness_provider, randomness_account_info)?;
+        // Randomness is recorded for the audit, so only its first bytes are used
+        let randomness_len = random_numbers.len().min(MAX_RANDOMNESS_LEN);
+        let randomness = &random_numbers[..randomness_len];
+        let randomness_hash = hashv(&[randomness]).to_bytes();
+        if randomness_hash == lottery_data.last_randomness_hash {
+            msg!("Randomness result is not produced after the draw request");
+            return Err(LotteryError::StaleRandomness.into());
         }
+        let number_format = lottery_data.number_format;
+        let mut winning_numbers_arr = derive_winning_numbers(randomness, &number_format)?;
+        number_format.check_numbers(&winning_numbers_arr)?;
+        number_format.sort_main_numbers(&mut winning_numbers_arr);
 
         lottery_data.is_finaled = true;
         lottery_data.winning_numbers = winning_numbers_arr;
+        lottery_data.randomness_len = randomness_len as u8;
+        lottery_data.randomness = [0; MAX_RANDOMNESS_LEN];
+        lottery_data.randomness[..randomness_len].copy_from_slice(randomness);
+        lottery_data.last_randomness_hash = randomness_hash;
+        lottery_data.draw_slot = clock.slot;
+        lottery_data.drawn_at = clock.unix_timestamp;
 
         LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
 
         Ok(())
     }
 
-    pub fn process_reward_winners(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    pub fn process_request_draw(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
         let accounts_iter = &mut accounts.iter();
         let lottery_data_account = next_account_info(accounts_iter)?;
-        let lottery_result_account = next_account_info(accounts_iter)?;
-        let holding_wallet_account = next_account_info(accounts_iter)?;
-        let rewards_wallet_account = next_account_info(accounts_iter)?;
-        let slot_holders_wallet_account = next_account_info(accounts_iter)?;
-        let sollotto_labs_wallet_account = next_account_info(accounts_iter)?;
-        let charity_1_wallet_account = next_account_info(accounts_iter)?;
-        let charity_2_wallet_account = next_account_info(accounts_iter)?;
-        let charity_3_wallet_account = next_account_info(accounts_iter)?;
-        let charity_4_wallet_account = next_account_info(accounts_iter)?;
-        let system_program_info = next_account_info(accounts_iter)?;
-        let participants_accounts = accounts_iter.as_slice();
+        let authority_account = next_account_info(accounts_iter)?;
+        let randomness_account_info = next_account_info(accounts_iter)?;
+        let clock = &Clock::from_account_info(next_account_info(accounts_iter)?)?;
 
         if lottery_data_account.owner != program_id {
             msg!("Lottery Data account does not have the correct program id");
             return Err(ProgramError::IncorrectProgramId);
         }
-        if lottery_result_account.owner != program_id {
-            msg!("Lottery Result Data account does not have the correct program id");
-            return Err(ProgramError::IncorrectProgramId);
-        }
-
-        if !lottery_data_account.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-        if !holding_wallet_account.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
 
         let mut lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
         if !lottery_data.is_initialized {
             msg!("Lottery Data account is not initialized");
             return Err(LotteryError::NotInitialized.into());
         }
-        if !lottery_data.is_finaled {
-            msg!("Lottery Data account have not winning numbers");
-            return Err(LotteryError::NotFinaled.into());
+        if lottery_data.is_finaled {
+            msg!("Lottery Data account already finaled");
+            return Err(LotteryError::IsFinaled.into());
+        }
+        if lottery_data.is_cancelled {
+            msg!("Lottery is cancelled");
+            return Err(LotteryError::IsCancelled.into());
         }
 
-        // Check all sollotto keys
-        if *holding_wallet_account.key != lottery_data.holding_wallet {
-            return Err(LotteryError::InvalidSollottoAccount.into());
+        let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+        check_authority(authority_account, &lottery_data)?;
+        check_account_address(randomness_account_info, &lottery_data.randomness_account)?;
+
+        if lottery_data.randomness_provider == RandomnessProvider::CommitReveal {
+            msg!("Commit-reveal lottery locks the reveal slot instead");
+            return Err(LotteryError::InvalidRandomnessProvider.into());
         }
-        if *rewards_wallet_account.key != lottery_data.rewards_wallet {
-            return Err(LotteryError::InvalidSollottoAccount.into());
+        if clock.unix_timestamp < lottery_data.sale_end {
+            msg!("Sale is not closed");
+            return Err(LotteryError::DrawNotReady.into());
         }
-        if *slot_holders_wallet_account.key != lottery_data.slot_holders_rewards_wallet {
-            return Err(LotteryError::InvalidSollottoAccount.into());
+        if lottery_data.draw_requested_at != 0 {
+            msg!("Draw is already requested");
+            return Err(LotteryError::Initialized.into());
         }
-        if *sollotto_labs_wallet_account.key != lottery_data.sollotto_labs_wallet {
-            return Err(LotteryError::InvalidSollottoAccount.into());
+
+        // Account may hold no result yet, then any result it gets is produced after the request
+        if let Ok(random_numbers) =
+            load_randomness(lottery_data.randomness_provider, randomness_account_info)
+        {
+            let randomness_len = random_numbers.len().min(MAX_RANDOMNESS_LEN);
+            lottery_data.last_randomness_hash =
+                hashv(&[&random_numbers[..randomness_len]]).to_bytes();
         }
-        if *charity_1_wallet_account.key != lottery_data.charity_1 {
-            return Err(LotteryError::InvalidSollottoAccount.into());
+        lottery_data.draw_requested_at = clock.unix_timestamp;
+
+        LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
+        msg!("Draw is requested: {}", clock.unix_timestamp);
+
+        Ok(())
+    }
+
+    pub fn process_tally_batch(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let lottery_data_account = next_account_info(accounts_iter)?;
+        let settlement_account = next_account_info(accounts_iter)?;
+        let prize_table_account = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+        let rent = &Rent::from_account_info(next_account_info(accounts_iter)?)?;
+        let system_program_info = next_account_info(accounts_iter)?;
+        let ticket_accounts = accounts_iter.as_slice();
+
+        if lottery_data_account.owner != program_id {
+            msg!("Lottery Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
         }
-        if *charity_2_wallet_account.key != lottery_data.charity_2 {
-            return Err(LotteryError::InvalidSollottoAccount.into());
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
         }
-        if *charity_3_wallet_account.key != lottery_data.charity_3 {
-            return Err(LotteryError::InvalidSollottoAccount.into());
+
+        let lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
+        if !lottery_data.is_initialized {
+            msg!("Lottery Data account is not initialized");
+            return Err(LotteryError::NotInitialized.into());
         }
-        if *charity_4_wallet_account.key != lottery_data.charity_4 {
-            return Err(LotteryError::InvalidSollottoAccount.into());
+        if !lottery_data.is_finaled {
+            msg!("Lottery Data account have not winning numbers");
+            return Err(LotteryError::NotFinaled.into());
         }
 
-        if (participants_accounts.len() / 2) as u32 != lottery_data.total_registrations {
-            msg!(
-                "Invalid participants accounts size: {}",
-                participants_accounts.len()
-            );
-            return Err(LotteryError::InvalidParticipantsAccounts.into());
+        let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+        let (settlement_key, settlement_bump_seed) =
+            find_settlement_address(program_id, &lottery_key);
+        check_account_address(settlement_account, &settlement_key)?;
+        check_authority(authority_account, &lottery_data)?;
+        let prize_table = load_prize_table(program_id, prize_table_account, &lottery_key)?;
+
+        // Settlement acc is created by the first batch
+        if settlement_account.data_is_empty() {
+            create_program_account(
+                program_id,
+                payer_account,
+                settlement_account,
+                system_program_info,
+                rent,
+                SettlementData::LEN,
+                &[
+                    SETTLEMENT_SEED,
+                    lottery_key.as_ref(),
+                    &[settlement_bump_seed],
+                ],
+            )?;
+        }
+        if settlement_account.owner != program_id {
+            msg!("Settlement Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
         }
-        msg!(
-            "Participants accounts size: {}",
-            participants_accounts.len()
-        );
 
-        for i in (0..participants_accounts.len()).step_by(2) {
-            if participants_accounts[i].owner != program_id {
-                msg!("Ticket Data account does not have the correct program id");
-                return Err(ProgramError::IncorrectProgramId);
-            }
-            let data = TicketData::unpack_unchecked(&participants_accounts[i].data.borrow())?;
-            if !data.is_purchased {
-                msg!("Ticket data account is not purchased");
-                return Err(LotteryError::NotInitialized.into());
+        let mut settlement = SettlementData::unpack_unchecked(&settlement_account.data.borrow())?;
+        if !settlement.is_initialized {
+            settlement.is_initialized = true;
+            settlement.lottery_id = lottery_data.lottery_id;
+            // Prizes of a lamports lottery are escrowed by the settlement account itself
+            if lottery_data.is_token_lottery() {
+                settlement.vault = lottery_data.vault;
             }
+        } else if settlement.tallied_count == lottery_data.total_registrations {
+            msg!("All tickets are already tallied");
+            return Err(LotteryError::TicketAlreadyProcessed.into());
+        }
 
-            if data.user_wallet_pk != *participants_accounts[i + 1].key {
-                msg!("Bad user_wallet_pk in ticket data account");
-                return Err(LotteryError::InvalidParticipantsAccounts.into());
+        for ticket_account in ticket_accounts {
+            let mut ticket = load_ticket(program_id, ticket_account)?;
+            // Tickets of other draws can't take part in the settlement
+            check_ticket_lottery(&ticket, lottery_data.lottery_id)?;
+
+            if ticket.is_tallied {
+                msg!("Ticket {} is already tallied", ticket_account.key);
+                return Err(LotteryError::TicketAlreadyProcessed.into());
             }
-        }
 
-        // Check winning numbers and find winner
-        let mut winners6 = Vec::new();
-        let mut winners5 = Vec::new();
-        let mut winners4 = Vec::new();
-        let mut winners3 = Vec::new();
-        for i in (0..participants_accounts.len()).step_by(2) {
-            let ticket = TicketData::unpack_unchecked(&participants_accounts[i].data.borrow())?;
-            let mut matched: i32 = 0;
-            for j in 0..5 {
-                if ticket.ticket_number_arr[j] == lottery_data.winning_numbers[j] {
-                    matched = matched + 1;
+            for line in ticket.lines.iter_mut() {
+                let matches = lottery_data
+                    .number_format
+                    .count_matches(&line.ticket_number_arr, &lottery_data.winning_numbers);
+                if let Some(tier) = prize_table.find_tier(&matches) {
+                    settlement.winners[tier] = settlement.winners[tier]
+                        .checked_add(1)
+                        .ok_or(LotteryError::AmountOverflow)?;
                 }
+                line.matched = matches.main;
+                line.bonus_matched = matches.bonus;
             }
-            if matched == 6 {
-                msg!("Found winner {}", participants_accounts[i + 1].key);
-                winners6.push(&participants_accounts[i + 1]);
-            }
-            if matched == 5 {
-                msg!("Found tier 5 {}", participants_accounts[i + 1].key);
-                winners5.push(&participants_accounts[i + 1]);
-            }
-            if matched == 4 {
-                msg!("Found tier 4 {}", participants_accounts[i + 1].key);
-                winners4.push(&participants_accounts[i + 1]);
-            }
-            if matched == 3 {
-                msg!("Found tier 3 {}", participants_accounts[i + 1].key);
-                winners3.push(&participants_accounts[i + 1]);
-            }
-        }
 
-        if holding_wallet_account.lamports() < lottery_data.prize_pool_amount {
-            msg!("Model 1 holding wallet InsufficientFunds error");
-            return Err(ProgramError::InsufficientFunds);
-        }
+            ticket.is_tallied = true;
+            settlement.tallied_count = settlement
+                .tallied_count
+                .checked_add(1)
+                .ok_or(LotteryError::AmountOverflow)?;
 
-        let prize_pool = lamports_to_sol(lottery_data.prize_pool_amount);
-        msg!("Prize pool in SOL: {}", prize_pool);
-
-        // 6. The charity with the most votes is transferred 30% of the total prize pool
-        let charity_pool = prize_pool * 0.3;
-        msg!("Charity pool in SOL: {}", charity_pool);
-
-        let mut win_charities = Vec::new();
-        let charity_arr = [
-            lottery_data.charity_1_vc,
-            lottery_data.charity_2_vc,
-            lottery_data.charity_3_vc,
-            lottery_data.charity_4_vc,
-        ];
-        let max_vc = charity_arr.iter().max().unwrap();
-        for (pos, charity_vc) in charity_arr.iter().enumerate() {
-            if charity_vc == max_vc {
-                match pos {
-                    0 => win_charities.push(charity_1_wallet_account),
-                    1 => win_charities.push(charity_2_wallet_account),
-                    2 => win_charities.push(charity_3_wallet_account),
-                    3 => win_charities.push(charity_4_wallet_account),
-                    _ => return Err(LotteryError::InvalidCharity.into()),
-                }
-            }
+            TicketData::pack(ticket, &mut ticket_account.data.borrow_mut())?;
         }
+        msg!(
+            "Tallied tickets: {} of {}",
+            settlement.tallied_count,
+            lottery_data.total_registrations
+        );
 
-        let charity_reward;
-        if win_charities.len() != 0 {
-            charity_reward = sol_to_lamports(charity_pool / win_charities.len() as f64);
-        } else {
-            charity_reward = 0;
+        // Rewards depend on the winners count of every tier,
+        // so they are fixed only after the last ticket is tallied
+        if settlement.tallied_count == lottery_data.total_registrations {
+            settlement.prize_pool_amount = lottery_data.prize_pool_amount;
+            calculate_tier_rewards(&mut settlement, &prize_table)?;
         }
-        msg!("Winning charities number {}", win_charities.len());
-        msg!("Charity reward in lamports: {}", charity_reward);
-        for charity in win_charities {
-            // Transfer from lottery_data.holding_wallet to charity_wallet
-            invoke(
-                &system_instruction::transfer(
-                    &lottery_data.holding_wallet,
-                    &charity.key,
-                    charity_reward,
-                ),
-                &[
-                    holding_wallet_account.clone(),
-                    charity.clone(),
-                    system_program_info.clone(),
-                ],
-            )?;
 
-            lottery_data.prize_pool_amount -= charity_reward;
-        }
+        SettlementData::pack(settlement, &mut settlement_account.data.borrow_mut())?;
 
-        // 7. 4% of the prize pool is transferred to the "Sollotto Rewards" wallet address
-        let solloto_reward = sol_to_lamports(prize_pool * 0.04);
-        msg!("Solloto reward in lamports: {}", solloto_reward);
-        // Transfer from lottery_data.holding_wallet to solloto_rewards_wallet
-        invoke(
-            &system_instruction::transfer(
-                &lottery_data.holding_wallet,
-                &lottery_data.rewards_wallet,
-                solloto_reward,
-            ),
-            &[
-                holding_wallet_account.clone(),
-                rewards_wallet_account.clone(),
-                system_program_info.clone(),
-            ],
-        )?;
+        Ok(())
+    }
+
+    pub fn process_payout_batch(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let lottery_data_account = next_account_info(accounts_iter)?;
+        let settlement_account = next_account_info(accounts_iter)?;
+        let prize_table_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+        let vault_account = next_account_info(accounts_iter)?;
+        let system_program_info = next_account_info(accounts_iter)?;
+        let clock = &Clock::from_account_info(next_account_info(accounts_iter)?)?;
+        let ticket_accounts = accounts_iter.as_slice();
 
-        lottery_data.prize_pool_amount -= solloto_reward;
+        if lottery_data_account.owner != program_id {
+            msg!("Lottery Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
 
-        // 8. 0.6% of the prize pool is transferred to a "SLOT Holder Rewards" wallet address
-        let slot_holders_reward = sol_to_lamports(prize_pool * 0.006);
-        msg!("SLOT holders reward in lamports: {}", slot_holders_reward);
-        // Transfer from lottery_data.holding_wallet to slot_holders_wallet
-        invoke(
-            &system_instruction::transfer(
-                &lottery_data.holding_wallet,
-                &lottery_data.slot_holders_rewards_wallet,
-                slot_holders_reward,
-            ),
-            &[
-                holding_wallet_account.clone(),
-                slot_holders_wallet_account.clone(),
-                system_program_info.clone(),
-            ],
-        )?;
+        let mut lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
+        if !lottery_data.is_initialized {
+            msg!("Lottery Data account is not initialized");
+            return Err(LotteryError::NotInitialized.into());
+        }
+        if !lottery_data.is_finaled {
+            msg!("Lottery Data account have not winning numbers");
+            return Err(LotteryError::NotFinaled.into());
+        }
 
-        lottery_data.prize_pool_amount -= slot_holders_reward;
+        let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+        let (settlement_key, _) = find_settlement_address(program_id, &lottery_key);
+        check_account_address(settlement_account, &settlement_key)?;
+        check_authority(authority_account, &lottery_data)?;
+        let prize_table = load_prize_table(program_id, prize_table_account, &lottery_key)?;
 
-        // 9. 0.4% of the prize pool is transferred to a "Sollotto Labs" wallet address
-        let sollotto_labs_reward = sol_to_lamports(prize_pool * 0.004);
-        msg!("Sollotto labs reward in lamports: {}", sollotto_labs_reward);
-        // Transfer from lottery_data.holding_wallet to solloto_labs_wallet
-        invoke(
-            &system_instruction::transfer(
-                &lottery_data.holding_wallet,
-                &lottery_data.sollotto_labs_wallet,
-                sollotto_labs_reward,
-            ),
-            &[
-                holding_wallet_account.clone(),
-                sollotto_labs_wallet_account.clone(),
-                system_program_info.clone(),
-            ],
-        )?;
+        check_vault_account(vault_account, &lottery_data)?;
 
-        lottery_data.prize_pool_amount -= sollotto_labs_reward;
-
-        // 14. Process rewards
-        let mut all_winners = Vec::new();
-        let mut winner_rewards = Vec::new();
-        let mut winners6_pool = prize_pool * 0.65;
-
-        // 3 tiers
-        let winner3_reward = sol_to_lamports(0.1);
-        msg!("Winners(3 tier) number {}", winners3.len());
-        msg!("Winner(3 tier) reward in lamports: {}", winner3_reward);
-        for winner3 in winners3 {
-            all_winners.push(winner3);
-            winner_rewards.push(winner3_reward);
-            winners6_pool = winners6_pool - 0.1;
-        }
-
-        // 4 tiers
-        let winners4_pool = prize_pool * 0.04;
-        let winner4_reward ;
-        if winners4.len() != 0 {
-            winner4_reward = sol_to_lamports(winners4_pool / winners4.len() as f64);
-            winners6_pool = winners6_pool - winners4_pool;
-        } else {
-            winner4_reward = 0;
+        if settlement_account.owner != program_id {
+            msg!("Settlement Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
         }
-        msg!("Winners(4 tier) number {}", winners4.len());
-        msg!("Winner(4 tier) reward in lamports: {}", winner4_reward);
-        for winner4 in winners4 {
-            all_winners.push(winner4);
-            winner_rewards.push(winner4_reward);
+        let mut settlement = SettlementData::unpack_unchecked(&settlement_account.data.borrow())?;
+        if !settlement.is_initialized
+            || settlement.tallied_count != lottery_data.total_registrations
+        {
+            msg!("Not all tickets are tallied");
+            return Err(LotteryError::TallyNotComplete.into());
         }
 
-        // 5 tiers
-        let winners5_pool = prize_pool * 0.5;
-        let winner5_reward ;
-        if winners5.len() != 0 {
-            winner5_reward = sol_to_lamports(winners5_pool / winners5.len() as f64);
-            winners6_pool = winners6_pool - winners5_pool;
-        } else {
-            winner5_reward = 0;
-        }
-        msg!("Winners(5 tier) number {}", winners5.len());
-        msg!("Winner(5 tier) reward in lamports: {}", winner5_reward);
-        for winner5 in winners5 {
-            all_winners.push(winner5);
-            winner_rewards.push(winner5_reward);
+        check_vault_funds(vault_account, &lottery_data)?;
+
+        let mut batch_amount: u64 = 0;
+        for ticket_account in ticket_accounts {
+            let mut ticket = load_ticket(program_id, ticket_account)?;
+            // Tickets of other draws can't take part in the settlement
+            check_ticket_lottery(&ticket, lottery_data.lottery_id)?;
+
+            if ticket.is_paid {
+                msg!("Ticket {} is already paid", ticket_account.key);
+                return Err(LotteryError::TicketAlreadyProcessed.into());
+            }
+
+            let mut reward: u64 = 0;
+            let mut winning_lines = 0;
+            for line in ticket.lines.iter() {
+                if let Some(tier) = prize_table.find_tier(&line.matches()) {
+                    msg!(
+                        "Winner(tier {}+{}) {} reward: {}",
+                        line.matched,
+                        line.bonus_matched as u8,
+                        ticket.user_wallet_pk,
+                        settlement.rewards[tier]
+                    );
+                    reward = reward
+                        .checked_add(settlement.rewards[tier])
+                        .ok_or(LotteryError::AmountOverflow)?;
+                    winning_lines += 1;
+                }
+            }
+            // Nothing to pay
+            if winning_lines == 0 {
+                continue;
+            }
+
+            ticket.is_paid = true;
+            ticket.prize_amount = reward;
+            settlement.paid_count = settlement
+                .paid_count
+                .checked_add(winning_lines)
+                .ok_or(LotteryError::AmountOverflow)?;
+            batch_amount = batch_amount
+                .checked_add(reward)
+                .ok_or(LotteryError::AmountOverflow)?;
+
+            TicketData::pack(ticket, &mut ticket_account.data.borrow_mut())?;
         }
 
-        // 6 tiers - perfect match
-        let winner6_reward ;
-        if winners6.len() != 0 {
-            winner6_reward = sol_to_lamports(winners6_pool / winners6.len() as f64);
-        } else {
-            winner6_reward = 0;
+        // Vault of a token lottery keeps the prizes, nothing is moved
+        if batch_amount != 0 && !lottery_data.is_token_lottery() {
+            // Transfer from the lottery vault to the settlement escrow
+            transfer_lamports_from_vault(
+                program_id,
+                &lottery_key,
+                vault_account,
+                settlement_account,
+                system_program_info,
+                batch_amount,
+            )?;
         }
-        msg!("Winners number {}", winners6.len());
-        msg!("Winner reward in lamports: {}", winner6_reward);
-        for winner6 in winners6 {
-            all_winners.push(winner6);
-            winner_rewards.push(winner6_reward);
+        lottery_data.prize_pool_amount = lottery_data
+            .prize_pool_amount
+            .checked_sub(batch_amount)
+            .ok_or(LotteryError::AmountOverflow)?;
+        settlement.escrowed_amount = settlement
+            .escrowed_amount
+            .checked_add(batch_amount)
+            .ok_or(LotteryError::AmountOverflow)?;
+        check_vault_funds(vault_account, &lottery_data)?;
+
+        // Claim period starts when the last winner is paid,
+        // however late the authority runs the settlement
+        if settlement.claim_deadline == 0 && settlement.paid_count == settlement.winners_count() {
+            settlement.claim_deadline = clock
+                .unix_timestamp
+                .saturating_add(lottery_data.claim_period);
+            msg!("Claim deadline: {}", settlement.claim_deadline);
         }
 
-        for i in 0..all_winners.len() {
-            // Transfer from lottery_data.holding_wallet to winner_wallet
-            invoke(
-                &system_instruction::transfer(
-                    &lottery_data.holding_wallet,
-                    &all_winners[i].key,
-                    winner_rewards[i],
-                ),
-                &[
-                    holding_wallet_account.clone(),
-                    all_winners[i].clone(),
-                    system_program_info.clone(),
-                ],
+        SettlementData::pack(settlement, &mut settlement_account.data.borrow_mut())?;
+        LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    pub fn process_reward_winners(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let lottery_data_account = next_account_info(accounts_iter)?;
+        let lottery_result_account = next_account_info(accounts_iter)?;
+        let settlement_account = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+        let vault_account = next_account_info(accounts_iter)?;
+        let rewards_wallet_account = next_account_info(accounts_iter)?;
+        let slot_holders_wallet_account = next_account_info(accounts_iter)?;
+        let sollotto_labs_wallet_account = next_account_info(accounts_iter)?;
+        let charity_registry_account = next_account_info(accounts_iter)?;
+        let rent = &Rent::from_account_info(next_account_info(accounts_iter)?)?;
+        let system_program_info = next_account_info(accounts_iter)?;
+        let trailing_accounts = accounts_iter.as_slice();
+
+        if lottery_data_account.owner != program_id {
+            msg!("Lottery Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let mut lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
+        if !lottery_data.is_initialized {
+            msg!("Lottery Data account is not initialized");
+            return Err(LotteryError::NotInitialized.into());
+        }
+        if !lottery_data.is_finaled {
+            msg!("Lottery Data account have not winning numbers");
+            return Err(LotteryError::NotFinaled.into());
+        }
+
+        let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+        let (lottery_result_key, result_bump_seed) =
+            find_lottery_result_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_result_account, &lottery_result_key)?;
+        let (settlement_key, _) = find_settlement_address(program_id, &lottery_key);
+        check_account_address(settlement_account, &settlement_key)?;
+        check_authority(authority_account, &lottery_data)?;
+
+        // Check all sollotto keys
+        check_vault_account(vault_account, &lottery_data)?;
+        check_payout_account(
+            &lottery_data,
+            rewards_wallet_account,
+            &lottery_data.rewards_wallet,
+        )?;
+        check_payout_account(
+            &lottery_data,
+            slot_holders_wallet_account,
+            &lottery_data.slot_holders_rewards_wallet,
+        )?;
+        check_payout_account(
+            &lottery_data,
+            sollotto_labs_wallet_account,
+            &lottery_data.sollotto_labs_wallet,
+        )?;
+
+        // Charity wallets follow the lottery charities order,
+        // token lotteries pass the vault authority and the token program after them
+        let registry = load_charity_registry(program_id, charity_registry_account)?;
+        let token_accounts_count = if lottery_data.is_token_lottery() {
+            2
+        } else {
+            0
+        };
+        if trailing_accounts.len() != lottery_data.charities().len() + token_accounts_count {
+            msg!(
+                "Expected {} charity wallets",
+                lottery_data.charities().len()
+            );
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        let (charity_wallet_accounts, token_accounts) =
+            trailing_accounts.split_at(lottery_data.charities().len());
+        let token_accounts = match token_accounts {
+            [vault_authority_account, spl_token_info] => {
+                Some((vault_authority_account, spl_token_info))
+            }
+            _ => None,
+        };
+        let mut charity_wallets = [Pubkey::default(); MAX_LOTTERY_CHARITIES];
+        let mut active_charities = Vec::with_capacity(MAX_LOTTERY_CHARITIES);
+        for (pos, charity_index) in lottery_data.charities().iter().enumerate() {
+            let charity = registry
+                .charities
+                .get(*charity_index as usize)
+                .ok_or(LotteryError::InvalidCharity)?;
+            check_payout_account(
+                &lottery_data,
+                &charity_wallet_accounts[pos],
+                &charity.wallet,
+            )?;
+            charity_wallets[pos] = charity.wallet;
+            // Charities deactivated during the sale are not paid
+            if charity.is_active {
+                active_charities.push(pos);
+            }
+        }
+
+        if settlement_account.owner != program_id {
+            msg!("Settlement Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let settlement = SettlementData::unpack_unchecked(&settlement_account.data.borrow())?;
+        if !settlement.is_initialized
+            || settlement.tallied_count != lottery_data.total_registrations
+        {
+            msg!("Not all tickets are tallied");
+            return Err(LotteryError::TallyNotComplete.into());
+        }
+        let winners_count = settlement.winners_count();
+        if settlement.paid_count != winners_count {
+            msg!(
+                "Paid winners: {} of {}",
+                settlement.paid_count,
+                winners_count
+            );
+            return Err(LotteryError::PayoutNotComplete.into());
+        }
+
+        check_vault_funds(vault_account, &lottery_data)?;
+
+        let prize_pool = settlement.prize_pool_amount;
+        if lottery_data.is_token_lottery() {
+            msg!("Amounts in base units of mint {}", lottery_data.mint);
+        }
+        msg!("Prize pool: {}", prize_pool);
+
+        // 6. 30% of the total prize pool is split between the charities by their votes
+        let charity_pool = share_of(prize_pool, CHARITY_SHARE_BPS)?;
+        msg!("Charity pool: {}", charity_pool);
+
+        let charity_votes: Vec<u32> = active_charities
+            .iter()
+            .map(|pos| lottery_data.charity_votes[*pos])
+            .collect();
+        msg!("Charity mode: {:?}", lottery_data.charity_mode);
+        let mut charity_rewards = [0; MAX_LOTTERY_CHARITIES];
+        let active_rewards =
+            self::charity_rewards(charity_pool, &charity_votes, lottery_data.charity_mode)?;
+        for (pos, charity_reward) in active_charities.iter().zip(active_rewards.iter()) {
+            charity_rewards[*pos] = *charity_reward;
+        }
+        let charity_paid: u64 = charity_rewards.iter().sum();
+        // Without any active charity the charity pool is not paid, it rolls over to the next draw
+        let unpaid_charity_pool = if active_charities.is_empty() {
+            msg!(
+                "No active charity, charity pool rolls over: {}",
+                charity_pool
+            );
+            charity_pool
+        } else {
+            0
+        };
+        for (charity, charity_reward) in charity_wallet_accounts.iter().zip(charity_rewards.iter())
+        {
+            if *charity_reward == 0 {
+                continue;
+            }
+            msg!("Charity {} reward: {}", charity.key, charity_reward);
+            // Transfer from the lottery vault to charity_wallet
+            pay_from_vault(
+                program_id,
+                &lottery_key,
+                vault_account,
+                charity,
+                system_program_info,
+                token_accounts,
+                *charity_reward,
             )?;
 
-            lottery_data.prize_pool_amount -= winner_rewards[i];
+            lottery_data.prize_pool_amount = lottery_data
+                .prize_pool_amount
+                .checked_sub(*charity_reward)
+                .ok_or(LotteryError::AmountOverflow)?;
         }
 
+        // 7. 4% of the prize pool is transferred to the "Sollotto Rewards" wallet address
+        let solloto_reward = share_of(prize_pool, SOLLOTTO_REWARDS_SHARE_BPS)?;
+        let slot_holders_reward = share_of(prize_pool, SLOT_HOLDERS_SHARE_BPS)?;
+        let sollotto_labs_reward = share_of(prize_pool, SOLLOTTO_LABS_SHARE_BPS)?;
+
+        // Rounding dust of the non winners shares goes to the "Sollotto Rewards" wallet as well
+        let dust = prize_pool
+            .checked_sub(share_of(prize_pool, WINNERS_SHARE_BPS)?)
+            .and_then(|amount| amount.checked_sub(charity_paid))
+            .and_then(|amount| amount.checked_sub(unpaid_charity_pool))
+            .and_then(|amount| amount.checked_sub(solloto_reward))
+            .and_then(|amount| amount.checked_sub(slot_holders_reward))
+            .and_then(|amount| amount.checked_sub(sollotto_labs_reward))
+            .ok_or(LotteryError::AmountOverflow)?;
+        let solloto_reward = solloto_reward
+            .checked_add(dust)
+            .ok_or(LotteryError::AmountOverflow)?;
+        msg!("Solloto reward: {}, dust: {}", solloto_reward, dust);
+        // Transfer from the lottery vault to solloto_rewards_wallet
+        pay_from_vault(
+            program_id,
+            &lottery_key,
+            vault_account,
+            rewards_wallet_account,
+            system_program_info,
+            token_accounts,
+            solloto_reward,
+        )?;
+
+        lottery_data.prize_pool_amount = lottery_data
+            .prize_pool_amount
+            .checked_sub(solloto_reward)
+            .ok_or(LotteryError::AmountOverflow)?;
+
+        // 8. 0.6% of the prize pool is transferred to a "SLOT Holder Rewards" wallet address
+        msg!("SLOT holders reward: {}", slot_holders_reward);
+        // Transfer from the lottery vault to slot_holders_wallet
+        pay_from_vault(
+            program_id,
+            &lottery_key,
+            vault_account,
+            slot_holders_wallet_account,
+            system_program_info,
+            token_accounts,
+            slot_holders_reward,
+        )?;
+
+        lottery_data.prize_pool_amount = lottery_data
+            .prize_pool_amount
+            .checked_sub(slot_holders_reward)
+            .ok_or(LotteryError::AmountOverflow)?;
+
+        // 9. 0.4% of the prize pool is transferred to a "Sollotto Labs" wallet address
+        msg!("Sollotto labs reward: {}", sollotto_labs_reward);
+        // Transfer from the lottery vault to solloto_labs_wallet
+        pay_from_vault(
+            program_id,
+            &lottery_key,
+            vault_account,
+            sollotto_labs_wallet_account,
+            system_program_info,
+            token_accounts,
+            sollotto_labs_reward,
+        )?;
+
+        lottery_data.prize_pool_amount = lottery_data
+            .prize_pool_amount
+            .checked_sub(sollotto_labs_reward)
+            .ok_or(LotteryError::AmountOverflow)?;
+
+        check_vault_funds(vault_account, &lottery_data)?;
+
+        // Prize pool left after all payments is unpaid tier shares and the unpaid charity pool,
+        // it rolls over to the next draw
+        lottery_data.rollover_amount = lottery_data.prize_pool_amount;
+        lottery_data.prize_pool_amount = 0;
+        msg!("Rollover: {}", lottery_data.rollover_amount);
+
         // Create lottery result acc info
         let lottery_result = LotteryResultData {
+            version: LOTTERY_RESULT_VERSION,
             lottery_id: lottery_data.lottery_id,
+            number_format: lottery_data.number_format,
             winning_numbers: lottery_data.winning_numbers,
+            rollover_amount: lottery_data.rollover_amount,
+            total_tickets: lottery_data.total_registrations,
+            prize_pool_amount: prize_pool,
+            tier_winners: settlement.winners,
+            tier_rewards: settlement.rewards,
+            charities_count: lottery_data.charities_count,
+            charities: charity_wallets,
+            charity_rewards,
+            randomness_account: lottery_data.randomness_account,
+            randomness_len: lottery_data.randomness_len,
+            randomness: lottery_data.randomness,
+            draw_slot: lottery_data.draw_slot,
+            drawn_at: lottery_data.drawn_at,
+            mint: lottery_data.mint,
+            referral_volume: lottery_data.referral_volume,
+            referral_rewards: lottery_data.referral_rewards,
         };
 
-        // Clear lottery acc for new lottery
+        // Clear lottery acc, lottery_id is kept as the account address is derived from it
         lottery_data.is_initialized = false;
         lottery_data.is_finaled = false;
-        lottery_data.charity_1_vc = 0;
-        lottery_data.charity_2_vc = 0;
-        lottery_data.charity_3_vc = 0;
-        lottery_data.charity_4_vc = 0;
-        lottery_data.winning_numbers = [0, 0, 0, 0, 0, 0];
+        lottery_data.charity_votes = [0; MAX_LOTTERY_CHARITIES];
+        lottery_data.winning_numbers = [0; NUMBERS_LEN];
+        lottery_data.randomness_len = 0;
+        lottery_data.randomness = [0; MAX_RANDOMNESS_LEN];
+        lottery_data.draw_slot = 0;
+        lottery_data.drawn_at = 0;
+        lottery_data.draw_requested_at = 0;
         lottery_data.total_registrations = 0;
-        lottery_data.lottery_id = 0;
+        lottery_data.referral_volume = 0;
+        lottery_data.referral_rewards = 0;
+
+        create_program_account(
+            program_id,
+            payer_account,
+            lottery_result_account,
+            system_program_info,
+            rent,
+            LotteryResultData::LEN,
+            &[
+                LOTTERY_RESULT_SEED,
+                &lottery_result.lottery_id.to_le_bytes(),
+                &[result_bump_seed],
+            ],
+        )?;
+        if lottery_result_account.owner != program_id {
+            msg!("Lottery Result Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
 
         LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
         LotteryResultData::pack(
@@ -772,641 +2231,6454 @@ impl<'a> Processor {
         Ok(())
     }
 
-    pub fn process_update_charity(
-        program_id: &Pubkey,
-        accounts: &[AccountInfo],
-        charity_1: Pubkey,
-        charity_2: Pubkey,
-        charity_3: Pubkey,
-        charity_4: Pubkey,
-    ) -> ProgramResult {
+    pub fn process_claim_prize(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
         let accounts_iter = &mut accounts.iter();
-        let lottery_data_account = next_account_info(accounts_iter)?;
+        let settlement_account = next_account_info(accounts_iter)?;
+        let ticket_data_account = next_account_info(accounts_iter)?;
+        let user_wallet_account = next_account_info(accounts_iter)?;
+        let clock = &Clock::from_account_info(next_account_info(accounts_iter)?)?;
 
-        if lottery_data_account.owner != program_id {
-            msg!("Lottery Data account does not have the correct program id");
+        if !user_wallet_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if settlement_account.owner != program_id {
+            msg!("Settlement Data account does not have the correct program id");
             return Err(ProgramError::IncorrectProgramId);
         }
+        let mut settlement = SettlementData::unpack_unchecked(&settlement_account.data.borrow())?;
+        if !settlement.is_initialized {
+            msg!("Settlement Data account is not initialized");
+            return Err(LotteryError::NotInitialized.into());
+        }
+
+        // Lottery acc may be already reset, settlement keeps the lottery id
+        let (lottery_key, _) = find_lottery_address(program_id, settlement.lottery_id);
+        let (settlement_key, _) = find_settlement_address(program_id, &lottery_key);
+        check_account_address(settlement_account, &settlement_key)?;
+
+        let mut ticket = load_ticket(program_id, ticket_data_account)?;
+        check_ticket_lottery(&ticket, settlement.lottery_id)?;
+
+        if ticket.user_wallet_pk != *user_wallet_account.key {
+            msg!("Bad user_wallet_pk in ticket data account");
+            return Err(LotteryError::InvalidParticipantsAccounts.into());
+        }
+        if !ticket.is_paid || ticket.prize_amount == 0 {
+            msg!("Ticket has no prize");
+            return Err(LotteryError::NothingToClaim.into());
+        }
+        if ticket.is_claimed {
+            return Err(LotteryError::PrizeAlreadyClaimed.into());
+        }
+        if settlement.claim_deadline == 0 {
+            msg!("Not all winners are paid");
+            return Err(LotteryError::PayoutNotComplete.into());
+        }
+        if clock.unix_timestamp > settlement.claim_deadline {
+            msg!("Claim deadline was {}", settlement.claim_deadline);
+            return Err(LotteryError::ClaimExpired.into());
+        }
+
+        if settlement.vault != Pubkey::default() {
+            // Prizes of a token lottery are paid from its vault
+            let vault_account = next_account_info(accounts_iter)?;
+            let user_token_account = next_account_info(accounts_iter)?;
+            let vault_authority_account = next_account_info(accounts_iter)?;
+            let spl_token_info = next_account_info(accounts_iter)?;
+            check_account_address(vault_account, &settlement.vault)?;
+            transfer_from_vault(
+                program_id,
+                vault_account,
+                user_token_account,
+                vault_authority_account,
+                spl_token_info,
+                ticket.prize_amount,
+            )?;
+            msg!("Prize claimed in tokens: {}", ticket.prize_amount);
+        } else {
+            // Settlement acc is owned by the program, so lamports are moved directly
+            let settlement_lamports = settlement_account
+                .lamports()
+                .checked_sub(ticket.prize_amount)
+                .ok_or(ProgramError::InsufficientFunds)?;
+            **settlement_account.lamports.borrow_mut() = settlement_lamports;
+            **user_wallet_account.lamports.borrow_mut() = user_wallet_account
+                .lamports()
+                .checked_add(ticket.prize_amount)
+                .ok_or(ProgramError::InvalidArgument)?;
+            msg!("Prize claimed in lamports: {}", ticket.prize_amount);
+        }
+
+        settlement.claimed_amount = settlement
+            .claimed_amount
+            .checked_add(ticket.prize_amount)
+            .ok_or(LotteryError::AmountOverflow)?;
+        ticket.is_claimed = true;
+
+        TicketData::pack(ticket, &mut ticket_data_account.data.borrow_mut())?;
+        SettlementData::pack(settlement, &mut settlement_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    pub fn process_close_ticket(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let ticket_data_account = next_account_info(accounts_iter)?;
+        let user_wallet_account = next_account_info(accounts_iter)?;
+        let lottery_result_account = next_account_info(accounts_iter)?;
+        let settlement_account = next_account_info(accounts_iter)?;
+        let clock = &Clock::from_account_info(next_account_info(accounts_iter)?)?;
 
-        if !lottery_data_account.is_signer {
+        if !user_wallet_account.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
 
-        let mut lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
-        if !lottery_data.is_initialized {
-            msg!("Lottery Data account is not initialized");
-            return Err(LotteryError::NotInitialized.into());
+        let ticket = load_ticket(program_id, ticket_data_account)?;
+        if ticket.user_wallet_pk != *user_wallet_account.key {
+            msg!("Bad user_wallet_pk in ticket data account");
+            return Err(LotteryError::InvalidParticipantsAccounts.into());
         }
 
-        lottery_data.charity_1 = charity_1;
-        lottery_data.charity_2 = charity_2;
-        lottery_data.charity_3 = charity_3;
-        lottery_data.charity_4 = charity_4;
+        // Lottery result is created when the draw is finished
+        let (lottery_result_key, _) = find_lottery_result_address(program_id, ticket.lottery_id);
+        check_account_address(lottery_result_account, &lottery_result_key)?;
+        if !ticket.is_refunded && lottery_result_account.owner != program_id {
+            msg!("Draw of lottery {} is not finished", ticket.lottery_id);
+            return Err(LotteryError::TicketNotSettled.into());
+        }
 
-        LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
+        if ticket.prize_amount != 0 && !ticket.is_claimed {
+            let (lottery_key, _) = find_lottery_address(program_id, ticket.lottery_id);
+            let (settlement_key, _) = find_settlement_address(program_id, &lottery_key);
+            check_account_address(settlement_account, &settlement_key)?;
+            if settlement_account.owner != program_id {
+                msg!("Settlement Data account does not have the correct program id");
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            let settlement = SettlementData::unpack_unchecked(&settlement_account.data.borrow())?;
+            // Claim period starts after the last winner is paid
+            if settlement.claim_deadline == 0 || clock.unix_timestamp <= settlement.claim_deadline {
+                msg!("Ticket prize is not claimed");
+                return Err(LotteryError::TicketNotSettled.into());
+            }
+        }
+
+        // Ticket acc is owned by the program, so lamports are moved directly
+        **user_wallet_account.lamports.borrow_mut() = user_wallet_account
+            .lamports()
+            .checked_add(ticket_data_account.lamports())
+            .ok_or(ProgramError::InvalidArgument)?;
+        **ticket_data_account.lamports.borrow_mut() = 0;
+        for byte in ticket_data_account.data.borrow_mut().iter_mut() {
+            *byte = 0;
+        }
+        msg!("Ticket {} is closed", ticket_data_account.key);
 
         Ok(())
     }
 
-    pub fn process_update_sollotto_wallets(
+    pub fn process_lock_reveal_slot(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        holding_wallet: Pubkey,
-        rewards_wallet: Pubkey,
-        slot_holders_rewards_wallet: Pubkey,
-        sollotto_labs_wallet: Pubkey,
     ) -> ProgramResult {
         let accounts_iter = &mut accounts.iter();
         let lottery_data_account = next_account_info(accounts_iter)?;
+        let reveal_account = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+        let rent = &Rent::from_account_info(next_account_info(accounts_iter)?)?;
+        let clock = &Clock::from_account_info(next_account_info(accounts_iter)?)?;
+        let system_program_info = next_account_info(accounts_iter)?;
 
-        if lottery_data_account.owner != program_id {
-            msg!("Lottery Data account does not have the correct program id");
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let lottery_data = load_commit_reveal_lottery(program_id, lottery_data_account)?;
+        // Nobody else can lock the slot early and leave too little time for the reveal
+        check_authority(authority_account, &lottery_data)?;
+        if clock.unix_timestamp <= lottery_data.sale_end {
+            msg!("Sale is not closed");
+            return Err(LotteryError::DrawNotReady.into());
+        }
+        if clock.unix_timestamp > lottery_data.reveal_deadline {
+            msg!("Reveal deadline is passed");
+            return Err(LotteryError::RevealExpired.into());
+        }
+
+        let (reveal_key, reveal_bump_seed) =
+            find_reveal_address(program_id, lottery_data_account.key);
+        check_account_address(reveal_account, &reveal_key)?;
+        create_program_account(
+            program_id,
+            payer_account,
+            reveal_account,
+            system_program_info,
+            rent,
+            CommitRevealData::LEN,
+            &[
+                REVEAL_SEED,
+                lottery_data_account.key.as_ref(),
+                &[reveal_bump_seed],
+            ],
+        )?;
+        if reveal_account.owner != program_id {
+            msg!("Commit-reveal account does not have the correct program id");
             return Err(ProgramError::IncorrectProgramId);
         }
+        let reveal = CommitRevealData::unpack_unchecked(&reveal_account.data.borrow())?;
+        if reveal.commitment != [0; 32] {
+            msg!("Reveal slot is already locked");
+            return Err(LotteryError::Initialized.into());
+        }
 
-        if !lottery_data_account.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
+        // Hash of the current slot is not known yet, so nobody can pick the slot for the secret
+        CommitRevealData::pack(
+            CommitRevealData {
+                is_revealed: false,
+                commitment: lottery_data.commitment,
+                secret: [0; 32],
+                slot: clock.slot,
+                slot_hash: [0; 32],
+            },
+            &mut reveal_account.data.borrow_mut(),
+        )?;
+        msg!("Reveal slot is locked: {}", clock.slot);
+
+        Ok(())
+    }
+
+    pub fn process_reveal_secret(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        secret: [u8; 32],
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let lottery_data_account = next_account_info(accounts_iter)?;
+        let reveal_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+        let clock = &Clock::from_account_info(next_account_info(accounts_iter)?)?;
+        let slot_hashes_account = next_account_info(accounts_iter)?;
+
+        let lottery_data = load_commit_reveal_lottery(program_id, lottery_data_account)?;
+        check_authority(authority_account, &lottery_data)?;
+
+        if clock.unix_timestamp > lottery_data.reveal_deadline {
+            msg!("Reveal deadline is passed");
+            return Err(LotteryError::RevealExpired.into());
+        }
+        if hashv(&[&secret]).to_bytes() != lottery_data.commitment {
+            msg!("Secret does not match the commitment");
+            return Err(LotteryError::InvalidCommitment.into());
         }
 
+        let (reveal_key, _) = find_reveal_address(program_id, lottery_data_account.key);
+        check_account_address(reveal_account, &reveal_key)?;
+        if reveal_account.owner != program_id {
+            msg!("Reveal slot is not locked");
+            return Err(LotteryError::DrawNotReady.into());
+        }
+        let reveal = CommitRevealData::unpack_unchecked(&reveal_account.data.borrow())?;
+        if reveal.commitment != lottery_data.commitment {
+            msg!("Reveal slot is not locked");
+            return Err(LotteryError::DrawNotReady.into());
+        }
+        if reveal.is_revealed {
+            msg!("Secret is already revealed");
+            return Err(LotteryError::Initialized.into());
+        }
+        let slot_hash = slot_hash_at(slot_hashes_account, reveal.slot)?;
+
+        CommitRevealData::pack(
+            CommitRevealData {
+                is_revealed: true,
+                secret,
+                slot_hash,
+                ..reveal
+            },
+            &mut reveal_account.data.borrow_mut(),
+        )?;
+        msg!("Secret is revealed with the hash of slot {}", reveal.slot);
+
+        Ok(())
+    }
+
+    pub fn process_refund_ticket(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let lottery_data_account = next_account_info(accounts_iter)?;
+        let ticket_data_account = next_account_info(accounts_iter)?;
+        let user_wallet_account = next_account_info(accounts_iter)?;
+        let vault_account = next_account_info(accounts_iter)?;
+        let clock = &Clock::from_account_info(next_account_info(accounts_iter)?)?;
+        let system_program_info = next_account_info(accounts_iter)?;
+
+        if lottery_data_account.owner != program_id {
+            msg!("Lottery Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
         let mut lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
         if !lottery_data.is_initialized {
             msg!("Lottery Data account is not initialized");
             return Err(LotteryError::NotInitialized.into());
         }
+        let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
 
-        lottery_data.holding_wallet = holding_wallet;
-        lottery_data.rewards_wallet = rewards_wallet;
-        lottery_data.slot_holders_rewards_wallet = slot_holders_rewards_wallet;
-        lottery_data.sollotto_labs_wallet = sollotto_labs_wallet;
+        check_vault_account(vault_account, &lottery_data)?;
+        check_vault_funds(vault_account, &lottery_data)?;
+
+        // Commit-reveal draw which is not revealed in time is abandoned
+        if !lottery_data.is_cancelled && !lottery_data.is_abandoned(clock.unix_timestamp) {
+            msg!("Lottery draw is not cancelled or abandoned");
+            return Err(LotteryError::RefundNotAvailable.into());
+        }
+
+        let mut ticket = load_ticket(program_id, ticket_data_account)?;
+        check_ticket_lottery(&ticket, lottery_data.lottery_id)?;
+        if ticket.user_wallet_pk != *user_wallet_account.key {
+            msg!("Bad user_wallet_pk in ticket data account");
+            return Err(LotteryError::InvalidParticipantsAccounts.into());
+        }
+        if ticket.is_refunded {
+            msg!("Ticket is already refunded");
+            return Err(LotteryError::AlreadyRefunded.into());
+        }
+
+        let refund_amount = lottery_data
+            .ticket_price
+            .checked_mul(ticket.lines.len() as u64)
+            .ok_or(LotteryError::AmountOverflow)?;
+        // Referral share is taken back from the referral ledger, the rewards of the draw
+        // can't be claimed before it is settled. The vault pays the rest of the price
+        let vault_refund_amount = refund_amount
+            .checked_sub(ticket.referral_amount)
+            .ok_or(LotteryError::AmountOverflow)?;
+        let referral_account = if ticket.referral_amount != 0 {
+            let referral_account = next_account_info(accounts_iter)?;
+            let (referral_key, _) =
+                find_referral_address(program_id, &lottery_key, &ticket.referrer);
+            check_account_address(referral_account, &referral_key)?;
+            if referral_account.owner != program_id {
+                msg!("Referral Data account does not have the correct program id");
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            let mut referral_data =
+                ReferralData::unpack_unchecked(&referral_account.data.borrow())?;
+            referral_data.accrued_amount = referral_data
+                .accrued_amount
+                .checked_sub(ticket.referral_amount)
+                .ok_or(LotteryError::AmountOverflow)?;
+            lottery_data.referral_volume = lottery_data
+                .referral_volume
+                .checked_sub(refund_amount)
+                .ok_or(LotteryError::AmountOverflow)?;
+            lottery_data.referral_rewards = lottery_data
+                .referral_rewards
+                .checked_sub(ticket.referral_amount)
+                .ok_or(LotteryError::AmountOverflow)?;
+            ReferralData::pack(referral_data, &mut referral_account.data.borrow_mut())?;
+            Some(referral_account)
+        } else {
+            None
+        };
+        if lottery_data.is_token_lottery() {
+            let user_token_account = next_account_info(accounts_iter)?;
+            let vault_authority_account = next_account_info(accounts_iter)?;
+            let spl_token_info = next_account_info(accounts_iter)?;
+            load_token_account(
+                user_token_account,
+                &lottery_data.mint,
+                &ticket.user_wallet_pk,
+            )?;
+            transfer_from_vault(
+                program_id,
+                vault_account,
+                user_token_account,
+                vault_authority_account,
+                spl_token_info,
+                vault_refund_amount,
+            )?;
+            msg!("Refund in tokens: {}", refund_amount);
+        } else {
+            transfer_lamports_from_vault(
+                program_id,
+                &lottery_key,
+                vault_account,
+                user_wallet_account,
+                system_program_info,
+                vault_refund_amount,
+            )?;
+            msg!("Refund in lamports: {}", refund_amount);
+        }
+        // Referral ledger is owned by the program, so lamports are moved directly
+        // after the vault transfer
+        if let Some(referral_account) = referral_account {
+            let referral_lamports = referral_account
+                .lamports()
+                .checked_sub(ticket.referral_amount)
+                .ok_or(ProgramError::InsufficientFunds)?;
+            **referral_account.lamports.borrow_mut() = referral_lamports;
+            **user_wallet_account.lamports.borrow_mut() = user_wallet_account
+                .lamports()
+                .checked_add(ticket.referral_amount)
+                .ok_or(ProgramError::InvalidArgument)?;
+            msg!("Referral reward taken back: {}", ticket.referral_amount);
+        }
+
+        lottery_data.prize_pool_amount = lottery_data
+            .prize_pool_amount
+            .checked_sub(vault_refund_amount)
+            .ok_or(LotteryError::AmountOverflow)?;
+        check_vault_funds(vault_account, &lottery_data)?;
+        lottery_data.total_registrations = lottery_data
+            .total_registrations
+            .checked_sub(1)
+            .ok_or(LotteryError::AmountOverflow)?;
+        ticket.is_refunded = true;
 
         LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
+        TicketData::pack(ticket, &mut ticket_data_account.data.borrow_mut())?;
 
         Ok(())
     }
-}
 
-// Unit tests
-#[cfg(test)]
-mod test {
-    use super::*;
-    use solana_program::{instruction::Instruction, program_pack::Pack};
-    use solana_sdk::account::{
-        create_account_for_test, create_is_signer_account_infos, Account as SolanaAccount,
-        ReadableAccount,
-    };
-    use spl_token::state::Account;
+    pub fn process_cancel_lottery(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let lottery_data_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
 
-    fn lottery_minimum_balance() -> u64 {
-        Rent::default().minimum_balance(LotteryData::get_packed_len())
-    }
+        if lottery_data_account.owner != program_id {
+            msg!("Lottery Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
+        if !lottery_data.is_initialized {
+            msg!("Lottery Data account is not initialized");
+            return Err(LotteryError::NotInitialized.into());
+        }
+        // Winners are known once the winning numbers are stored
+        if lottery_data.is_finaled {
+            msg!("Lottery Data account already finaled");
+            return Err(LotteryError::IsFinaled.into());
+        }
+        if lottery_data.is_cancelled {
+            msg!("Lottery is cancelled");
+            return Err(LotteryError::IsCancelled.into());
+        }
 
-    fn ticket_minimum_balance() -> u64 {
-        Rent::default().minimum_balance(TicketData::get_packed_len())
-    }
+        let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+        check_authority(authority_account, &lottery_data)?;
 
-    fn lottery_result_minimum_balance() -> u64 {
-        Rent::default().minimum_balance(LotteryResultData::get_packed_len())
-    }
+        lottery_data.is_cancelled = true;
+        LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
+        msg!("Lottery {} is cancelled", lottery_data.lottery_id);
 
-    fn mint_minimum_balance() -> u64 {
-        Rent::default().minimum_balance(spl_token::state::Mint::LEN)
+        Ok(())
     }
 
-    fn account_minimum_balance() -> u64 {
-        Rent::default().minimum_balance(spl_token::state::Account::LEN)
-    }
+    pub fn process_init_vault(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let lottery_data_account = next_account_info(accounts_iter)?;
+        let vault_account = next_account_info(accounts_iter)?;
+        let mint_account = next_account_info(accounts_iter)?;
+        let vault_authority_account = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+        let rent_info = next_account_info(accounts_iter)?;
+        let rent = &Rent::from_account_info(rent_info)?;
+        let system_program_info = next_account_info(accounts_iter)?;
+        let spl_token_info = next_account_info(accounts_iter)?;
 
-    fn do_process(instruction: Instruction, accounts: Vec<&mut SolanaAccount>) -> ProgramResult {
-        let mut meta = instruction
-            .accounts
-            .iter()
-            .zip(accounts)
-            .map(|(account_meta, account)| (&account_meta.pubkey, account_meta.is_signer, account))
-            .collect::<Vec<_>>();
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
 
-        let account_infos = create_is_signer_account_infos(&mut meta);
-        Processor::process(&instruction.program_id, &account_infos, &instruction.data)
+        if lottery_data_account.owner != program_id {
+            msg!("Lottery Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
+        if !lottery_data.is_initialized {
+            msg!("Lottery Data account is not initialized");
+            return Err(LotteryError::NotInitialized.into());
+        }
+        if lottery_data.is_finaled {
+            msg!("Lottery Data account already finaled");
+            return Err(LotteryError::IsFinaled.into());
+        }
+        if lottery_data.is_cancelled {
+            msg!("Lottery is cancelled");
+            return Err(LotteryError::IsCancelled.into());
+        }
+
+        let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+        check_authority(authority_account, &lottery_data)?;
+
+        // Ticket currency is fixed once the lottery holds any funds
+        if lottery_data.is_token_lottery()
+            || lottery_data.total_registrations != 0
+            || lottery_data.prize_pool_amount != 0
+        {
+            msg!("Lottery already has funds");
+            return Err(LotteryError::InvalidVault.into());
+        }
+        if lottery_data.referral_share_bps != 0 {
+            msg!("Token lottery does not accept referrals");
+            return Err(LotteryError::InvalidShares.into());
+        }
+
+        if *mint_account.owner != spl_token::id() {
+            msg!("Mint is not owned by the token program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Mint::unpack(&mint_account.data.borrow())?;
+
+        let (vault_key, bump_seed) = find_token_vault_address(program_id, &lottery_key);
+        check_account_address(vault_account, &vault_key)?;
+        let (vault_authority_key, _) = find_vault_authority_address(program_id);
+        check_account_address(vault_authority_account, &vault_authority_key)?;
+
+        // Vault is created for the token program
+        create_program_account(
+            &spl_token::id(),
+            payer_account,
+            vault_account,
+            system_program_info,
+            rent,
+            TokenAccount::LEN,
+            &[TOKEN_VAULT_SEED, lottery_key.as_ref(), &[bump_seed]],
+        )?;
+        invoke(
+            &spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                vault_account.key,
+                mint_account.key,
+                vault_authority_account.key,
+            )?,
+            &[
+                vault_account.clone(),
+                mint_account.clone(),
+                vault_authority_account.clone(),
+                rent_info.clone(),
+                spl_token_info.clone(),
+            ],
+        )?;
+
+        lottery_data.mint = *mint_account.key;
+        lottery_data.vault = vault_key;
+        LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
+        msg!(
+            "Lottery {} is paid in {}",
+            lottery_data.lottery_id,
+            lottery_data.mint
+        );
+
+        Ok(())
     }
 
-    #[test]
-    fn test_init_lottery() {
-        let program_id = id();
-        let lottery_id = 112233;
-        let lottery_key = Pubkey::new_unique();
-        let mut lottery_acc = SolanaAccount::new(
-            lottery_minimum_balance(),
-            LotteryData::get_packed_len(),
-            &program_id,
+    pub fn process_set_lifetime_ticket(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let lottery_data_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+        let lifetime_ticket_mint_account = next_account_info(accounts_iter).ok();
+
+        if lottery_data_account.owner != program_id {
+            msg!("Lottery Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
+        if !lottery_data.is_initialized {
+            msg!("Lottery Data account is not initialized");
+            return Err(LotteryError::NotInitialized.into());
+        }
+        if lottery_data.is_finaled {
+            msg!("Lottery Data account already finaled");
+            return Err(LotteryError::IsFinaled.into());
+        }
+
+        let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+        check_authority(authority_account, &lottery_data)?;
+
+        // Lottery without the mint stops minting Lifetime Tickets
+        let lifetime_ticket_mint_account = match lifetime_ticket_mint_account {
+            Some(lifetime_ticket_mint_account) => lifetime_ticket_mint_account,
+            None => {
+                lottery_data.lifetime_ticket_mint = Pubkey::default();
+                lottery_data.lifetime_ticket_amount = 0;
+                LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
+                msg!("Lifetime Tickets are not minted");
+                return Ok(());
+            }
+        };
+
+        if amount == 0 {
+            msg!("Lifetime Ticket amount must be positive");
+            return Err(LotteryError::InvalidLifetimeTicketMint.into());
+        }
+        if *lifetime_ticket_mint_account.owner != spl_token::id() {
+            msg!("Lifetime Ticket mint is not owned by the token program");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mint = Mint::unpack(&lifetime_ticket_mint_account.data.borrow())?;
+        let (lifetime_ticket_authority_key, _) = find_lifetime_ticket_authority_address(program_id);
+        if mint.mint_authority != COption::Some(lifetime_ticket_authority_key) {
+            msg!("Lifetime Ticket mint authority must be the Lifetime Ticket authority");
+            return Err(LotteryError::InvalidLifetimeTicketMint.into());
+        }
+
+        lottery_data.lifetime_ticket_mint = *lifetime_ticket_mint_account.key;
+        lottery_data.lifetime_ticket_amount = amount;
+        LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
+        msg!(
+            "Lifetime Tickets of {} are minted, {} per line",
+            lottery_data.lifetime_ticket_mint,
+            lottery_data.lifetime_ticket_amount
         );
-        let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
-        let charity_1 = Pubkey::new_unique();
-        let charity_2 = Pubkey::new_unique();
-        let charity_3 = Pubkey::new_unique();
-        let charity_4 = Pubkey::new_unique();
-        let holding_wallet = Pubkey::new_unique();
-        let rewards_wallet = Pubkey::new_unique();
-        let slot_holders_rewards_wallet = Pubkey::new_unique();
-        let sollotto_labs_wallet = Pubkey::new_unique();
-        let randomness_account = Pubkey::new_unique();
 
-        // BadCase: rent NotRentExempt
-        let mut bad_lottery_acc = SolanaAccount::new(
-            lottery_minimum_balance() - 100,
+        Ok(())
+    }
+
+    pub fn process_set_referral_share(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        share_bps: u16,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let lottery_data_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+
+        if lottery_data_account.owner != program_id {
+            msg!("Lottery Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
+        if !lottery_data.is_initialized {
+            msg!("Lottery Data account is not initialized");
+            return Err(LotteryError::NotInitialized.into());
+        }
+        if lottery_data.is_finaled {
+            msg!("Lottery Data account already finaled");
+            return Err(LotteryError::IsFinaled.into());
+        }
+
+        let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+        check_authority(authority_account, &lottery_data)?;
+
+        if share_bps > MAX_REFERRAL_SHARE_BPS {
+            msg!(
+                "Referral share {} is over {} basis points",
+                share_bps,
+                MAX_REFERRAL_SHARE_BPS
+            );
+            return Err(LotteryError::InvalidShares.into());
+        }
+        // Referral ledger is shared by all lotteries, it accrues lamports only
+        if share_bps != 0 && lottery_data.is_token_lottery() {
+            msg!("Token lottery does not accept referrals");
+            return Err(LotteryError::InvalidShares.into());
+        }
+
+        lottery_data.referral_share_bps = share_bps;
+        LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
+        msg!("Referral share: {} basis points", share_bps);
+
+        Ok(())
+    }
+
+    pub fn process_claim_referral_rewards(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let referral_account = next_account_info(accounts_iter)?;
+        let referrer_account = next_account_info(accounts_iter)?;
+        let lottery_result_account = next_account_info(accounts_iter)?;
+
+        if !referrer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if referral_account.owner != program_id {
+            msg!("Referral Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut referral_data = ReferralData::unpack_unchecked(&referral_account.data.borrow())?;
+        if !referral_data.is_initialized {
+            msg!("Referral Data account is not initialized");
+            return Err(LotteryError::NotInitialized.into());
+        }
+        if referral_data.referrer != *referrer_account.key {
+            msg!("Referral rewards are accrued to {}", referral_data.referrer);
+            return Err(LotteryError::InvalidReferrer.into());
+        }
+        let (lottery_key, _) = find_lottery_address(program_id, referral_data.lottery_id);
+        let (referral_key, _) =
+            find_referral_address(program_id, &lottery_key, referrer_account.key);
+        check_account_address(referral_account, &referral_key)?;
+
+        // Lottery result is created once the draw is settled, its tickets can't be refunded
+        let (lottery_result_key, _) =
+            find_lottery_result_address(program_id, referral_data.lottery_id);
+        check_account_address(lottery_result_account, &lottery_result_key)?;
+        if lottery_result_account.owner != program_id {
+            msg!("Draw of the referred tickets is not settled");
+            return Err(LotteryError::NotFinaled.into());
+        }
+        let amount = referral_data
+            .unclaimed_amount()
+            .ok_or(LotteryError::AmountOverflow)?;
+        if amount == 0 {
+            msg!("No referral rewards to claim");
+            return Err(LotteryError::NothingToClaim.into());
+        }
+
+        // Referral ledger is owned by the program, so lamports are moved directly
+        let referral_lamports = referral_account
+            .lamports()
+            .checked_sub(amount)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        **referral_account.lamports.borrow_mut() = referral_lamports;
+        **referrer_account.lamports.borrow_mut() = referrer_account
+            .lamports()
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        referral_data.claimed_amount = referral_data
+            .claimed_amount
+            .checked_add(amount)
+            .ok_or(LotteryError::AmountOverflow)?;
+        ReferralData::pack(referral_data, &mut referral_account.data.borrow_mut())?;
+        msg!("Referral rewards claimed: {}", amount);
+
+        Ok(())
+    }
+
+    pub fn process_sweep_unclaimed(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let lottery_data_account = next_account_info(accounts_iter)?;
+        let settlement_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+        let rewards_wallet_account = next_account_info(accounts_iter)?;
+        let clock = &Clock::from_account_info(next_account_info(accounts_iter)?)?;
+
+        if lottery_data_account.owner != program_id {
+            msg!("Lottery Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        if settlement_account.owner != program_id {
+            msg!("Settlement Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut settlement = SettlementData::unpack_unchecked(&settlement_account.data.borrow())?;
+        if !settlement.is_initialized {
+            msg!("Settlement Data account is not initialized");
+            return Err(LotteryError::NotInitialized.into());
+        }
+
+        let lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
+        let (lottery_key, _) = find_lottery_address(program_id, settlement.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+        let (settlement_key, _) = find_settlement_address(program_id, &lottery_key);
+        check_account_address(settlement_account, &settlement_key)?;
+        check_authority(authority_account, &lottery_data)?;
+        check_payout_account(
+            &lottery_data,
+            rewards_wallet_account,
+            &lottery_data.rewards_wallet,
+        )?;
+
+        // Lottery acc is reset by RewardWinners, after all winners are paid
+        if lottery_data.is_initialized {
+            msg!("Lottery settlement is not finished");
+            return Err(LotteryError::PayoutNotComplete.into());
+        }
+        if settlement.is_swept {
+            return Err(LotteryError::AlreadySwept.into());
+        }
+        if clock.unix_timestamp <= settlement.claim_deadline {
+            msg!("Claim deadline is {}", settlement.claim_deadline);
+            return Err(LotteryError::ClaimPeriodNotOver.into());
+        }
+
+        let unclaimed_amount = settlement
+            .escrowed_amount
+            .checked_sub(settlement.claimed_amount)
+            .ok_or(LotteryError::AmountOverflow)?;
+        if settlement.vault != Pubkey::default() {
+            let vault_account = next_account_info(accounts_iter)?;
+            let vault_authority_account = next_account_info(accounts_iter)?;
+            let spl_token_info = next_account_info(accounts_iter)?;
+            check_account_address(vault_account, &settlement.vault)?;
+            transfer_from_vault(
+                program_id,
+                vault_account,
+                rewards_wallet_account,
+                vault_authority_account,
+                spl_token_info,
+                unclaimed_amount,
+            )?;
+            msg!("Unclaimed prizes in tokens: {}", unclaimed_amount);
+        } else {
+            let settlement_lamports = settlement_account
+                .lamports()
+                .checked_sub(unclaimed_amount)
+                .ok_or(ProgramError::InsufficientFunds)?;
+            **settlement_account.lamports.borrow_mut() = settlement_lamports;
+            **rewards_wallet_account.lamports.borrow_mut() = rewards_wallet_account
+                .lamports()
+                .checked_add(unclaimed_amount)
+                .ok_or(ProgramError::InvalidArgument)?;
+            msg!("Unclaimed prizes in lamports: {}", unclaimed_amount);
+        }
+
+        settlement.is_swept = true;
+
+        SettlementData::pack(settlement, &mut settlement_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    pub fn process_update_charity(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        charities: Vec<u16>,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let lottery_data_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+        let charity_registry_account = next_account_info(accounts_iter)?;
+
+        if lottery_data_account.owner != program_id {
+            msg!("Lottery Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
+        if !lottery_data.is_initialized {
+            msg!("Lottery Data account is not initialized");
+            return Err(LotteryError::NotInitialized.into());
+        }
+        // Charity pool of the draw is paid to the charities it is drawn with
+        if lottery_data.is_finaled {
+            msg!("Lottery Data account already finaled");
+            return Err(LotteryError::IsFinaled.into());
+        }
+
+        let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+        check_authority(authority_account, &lottery_data)?;
+
+        let registry = load_charity_registry(program_id, charity_registry_account)?;
+        check_lottery_charities(&charities, &registry)?;
+        lottery_data.set_charities(&charities);
+
+        LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    pub fn process_update_sollotto_wallets(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        rewards_wallet: Pubkey,
+        slot_holders_rewards_wallet: Pubkey,
+        sollotto_labs_wallet: Pubkey,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let lottery_data_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+
+        if lottery_data_account.owner != program_id {
+            msg!("Lottery Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
+        if !lottery_data.is_initialized {
+            msg!("Lottery Data account is not initialized");
+            return Err(LotteryError::NotInitialized.into());
+        }
+
+        let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+        check_authority(authority_account, &lottery_data)?;
+
+        lottery_data.rewards_wallet = rewards_wallet;
+        lottery_data.slot_holders_rewards_wallet = slot_holders_rewards_wallet;
+        lottery_data.sollotto_labs_wallet = sollotto_labs_wallet;
+
+        LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    pub fn process_set_authority(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        new_authority: Pubkey,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let lottery_data_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+
+        if lottery_data_account.owner != program_id {
+            msg!("Lottery Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
+        if !lottery_data.is_initialized {
+            msg!("Lottery Data account is not initialized");
+            return Err(LotteryError::NotInitialized.into());
+        }
+
+        let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+        check_authority(authority_account, &lottery_data)?;
+
+        lottery_data.pending_authority = new_authority;
+
+        LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    pub fn process_accept_authority(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let lottery_data_account = next_account_info(accounts_iter)?;
+        let pending_authority_account = next_account_info(accounts_iter)?;
+
+        if lottery_data_account.owner != program_id {
+            msg!("Lottery Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if !pending_authority_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let mut lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
+        if !lottery_data.is_initialized {
+            msg!("Lottery Data account is not initialized");
+            return Err(LotteryError::NotInitialized.into());
+        }
+
+        let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+
+        if lottery_data.pending_authority == Pubkey::default()
+            || *pending_authority_account.key != lottery_data.pending_authority
+        {
+            msg!(
+                "Account {} is not the pending lottery authority",
+                pending_authority_account.key
+            );
+            return Err(LotteryError::InvalidAuthority.into());
+        }
+
+        lottery_data.authority = lottery_data.pending_authority;
+        lottery_data.pending_authority = Pubkey::default();
+
+        LotteryData::pack(lottery_data, &mut lottery_data_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    pub fn process_set_prize_table(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        tiers: Vec<PrizeTier>,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let lottery_data_account = next_account_info(accounts_iter)?;
+        let prize_table_account = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+        let rent = &Rent::from_account_info(next_account_info(accounts_iter)?)?;
+        let system_program_info = next_account_info(accounts_iter)?;
+
+        if lottery_data_account.owner != program_id {
+            msg!("Lottery Data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let lottery_data = LotteryData::unpack_unchecked(&lottery_data_account.data.borrow())?;
+        if !lottery_data.is_initialized {
+            msg!("Lottery Data account is not initialized");
+            return Err(LotteryError::NotInitialized.into());
+        }
+        // Tiers can't change once the winners are known
+        if lottery_data.is_finaled {
+            msg!("Lottery Data account already finaled");
+            return Err(LotteryError::IsFinaled.into());
+        }
+
+        let (lottery_key, _) = find_lottery_address(program_id, lottery_data.lottery_id);
+        check_account_address(lottery_data_account, &lottery_key)?;
+        let (prize_table_key, prize_table_bump_seed) =
+            find_prize_table_address(program_id, &lottery_key);
+        check_account_address(prize_table_account, &prize_table_key)?;
+        check_authority(authority_account, &lottery_data)?;
+
+        check_prize_tiers(&tiers, &lottery_data.number_format)?;
+
+        if prize_table_account.data_is_empty() {
+            create_program_account(
+                program_id,
+                payer_account,
+                prize_table_account,
+                system_program_info,
+                rent,
+                PrizeTableData::LEN,
+                &[
+                    PRIZE_TABLE_SEED,
+                    lottery_key.as_ref(),
+                    &[prize_table_bump_seed],
+                ],
+            )?;
+        }
+        if prize_table_account.owner != program_id {
+            msg!("Prize table data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let prize_table = PrizeTableData {
+            is_initialized: true,
+            lottery_id: lottery_data.lottery_id,
+            tiers,
+        };
+        PrizeTableData::pack(prize_table, &mut prize_table_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    pub fn process_init_charity_registry(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let charity_registry_account = next_account_info(accounts_iter)?;
+        let payer_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+        let rent = &Rent::from_account_info(next_account_info(accounts_iter)?)?;
+        let system_program_info = next_account_info(accounts_iter)?;
+        let program_data_account = next_account_info(accounts_iter)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if !authority_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        // Registry decides the charity payouts, so only the program upgrade authority creates it
+        if *authority_account.key != load_upgrade_authority(program_id, program_data_account)? {
+            msg!("Charity registry authority must be the program upgrade authority");
+            return Err(LotteryError::InvalidAuthority.into());
+        }
+
+        let (charity_registry_key, bump_seed) = find_charity_registry_address(program_id);
+        check_account_address(charity_registry_account, &charity_registry_key)?;
+
+        create_program_account(
+            program_id,
+            payer_account,
+            charity_registry_account,
+            system_program_info,
+            rent,
+            CharityRegistryData::LEN,
+            &[CHARITY_REGISTRY_SEED, &[bump_seed]],
+        )?;
+        if charity_registry_account.owner != program_id {
+            msg!("Charity registry data account does not have the correct program id");
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut registry =
+            CharityRegistryData::unpack_unchecked(&charity_registry_account.data.borrow())?;
+        if registry.is_initialized {
+            msg!("Charity registry already initialized");
+            return Err(LotteryError::Initialized.into());
+        }
+        registry.is_initialized = true;
+        registry.authority = *authority_account.key;
+        registry.charities = Vec::new();
+
+        CharityRegistryData::pack(registry, &mut charity_registry_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    pub fn process_add_charity(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        name_hash: [u8; 32],
+        wallet: Pubkey,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let charity_registry_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+
+        let mut registry = load_charity_registry(program_id, charity_registry_account)?;
+        check_registry_authority(authority_account, &registry)?;
+
+        if registry.charities.len() >= MAX_REGISTRY_CHARITIES {
+            msg!("Charity registry is full");
+            return Err(LotteryError::InvalidCharity.into());
+        }
+        msg!("Charity index: {}", registry.charities.len());
+        registry.charities.push(RegistryCharity {
+            name_hash,
+            wallet,
+            is_active: true,
+        });
+
+        CharityRegistryData::pack(registry, &mut charity_registry_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    pub fn process_update_registry_charity(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        index: u16,
+        wallet: Pubkey,
+        is_active: bool,
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+        let charity_registry_account = next_account_info(accounts_iter)?;
+        let authority_account = next_account_info(accounts_iter)?;
+
+        let mut registry = load_charity_registry(program_id, charity_registry_account)?;
+        check_registry_authority(authority_account, &registry)?;
+
+        let charity = registry
+            .charities
+            .get_mut(index as usize)
+            .ok_or(LotteryError::InvalidCharity)?;
+        charity.wallet = wallet;
+        charity.is_active = is_active;
+
+        CharityRegistryData::pack(registry, &mut charity_registry_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+}
+
+// Unit tests
+#[cfg(test)]
+mod test {
+    use super::*;
+    use solana_program::{
+        hash::Hash, instruction::Instruction, native_token::sol_to_lamports, program_pack::Pack,
+        slot_hashes::SlotHashes,
+    };
+    use solana_sdk::account::{
+        create_account_for_test, create_is_signer_account_infos, Account as SolanaAccount,
+        ReadableAccount,
+    };
+    use spl_token::state::Account;
+
+    const SALE_START: UnixTimestamp = 1_000;
+    const SALE_END: UnixTimestamp = 2_000;
+    const DRAW_TIME: UnixTimestamp = 3_000;
+    const CLAIM_PERIOD: i64 = 1_000;
+    const DRAW_SLOT: u64 = 42;
+    const MAIN_POOL_SIZE: u8 = 69;
+    const BONUS_POOL_SIZE: u8 = 29;
+    const PICK_COUNT: u8 = 5;
+    const TICKET_PRICE: u64 = 100_000_000;
+
+    fn lottery_minimum_balance() -> u64 {
+        Rent::default().minimum_balance(LotteryData::get_packed_len())
+    }
+
+    fn ticket_minimum_balance() -> u64 {
+        Rent::default().minimum_balance(TicketData::get_packed_len())
+    }
+
+    fn lottery_result_minimum_balance() -> u64 {
+        Rent::default().minimum_balance(LotteryResultData::get_packed_len())
+    }
+
+    fn settlement_minimum_balance() -> u64 {
+        Rent::default().minimum_balance(SettlementData::get_packed_len())
+    }
+
+    fn prize_table_minimum_balance() -> u64 {
+        Rent::default().minimum_balance(PrizeTableData::get_packed_len())
+    }
+
+    fn referral_minimum_balance() -> u64 {
+        Rent::default().minimum_balance(ReferralData::get_packed_len())
+    }
+
+    /// Prize tiers of 5 numbers game with a bonus number
+    fn prize_tiers() -> Vec<PrizeTier> {
+        vec![
+            PrizeTier {
+                main_matches: 5,
+                bonus_match: true,
+                prize: Prize::Share(6_500),
+            },
+            PrizeTier {
+                main_matches: 5,
+                bonus_match: false,
+                prize: Prize::Share(1_000),
+            },
+            PrizeTier {
+                main_matches: 4,
+                bonus_match: true,
+                prize: Prize::Share(500),
+            },
+            PrizeTier {
+                main_matches: 4,
+                bonus_match: false,
+                prize: Prize::Share(200),
+            },
+            PrizeTier {
+                main_matches: 3,
+                bonus_match: false,
+                prize: Prize::Fixed(sol_to_lamports(0.1)),
+            },
+        ]
+    }
+
+    fn mint_minimum_balance() -> u64 {
+        Rent::default().minimum_balance(spl_token::state::Mint::LEN)
+    }
+
+    fn account_minimum_balance() -> u64 {
+        Rent::default().minimum_balance(spl_token::state::Account::LEN)
+    }
+
+    fn clock_account(unix_timestamp: UnixTimestamp) -> SolanaAccount {
+        create_account_for_test(&Clock {
+            unix_timestamp,
+            ..Clock::default()
+        })
+    }
+
+    /// Creates the program data account of the program upgraded by the authority
+    fn program_data_account(upgrade_authority: &Pubkey) -> SolanaAccount {
+        let mut program_data_acc = SolanaAccount::new(
+            Rent::default().minimum_balance(45),
+            45,
+            &bpf_loader_upgradeable::id(),
+        );
+        program_data_acc.data[..4].copy_from_slice(&3u32.to_le_bytes());
+        program_data_acc.data[12] = 1;
+        program_data_acc.data[13..45].copy_from_slice(upgrade_authority.as_ref());
+        program_data_acc
+    }
+
+    /// Creates the charity registry with an active charity for every wallet
+    fn charity_registry_account(wallets: &[Pubkey]) -> SolanaAccount {
+        let mut charity_registry_acc = SolanaAccount::new(
+            Rent::default().minimum_balance(CharityRegistryData::LEN),
+            CharityRegistryData::get_packed_len(),
+            &id(),
+        );
+        let registry = CharityRegistryData {
+            is_initialized: true,
+            authority: Pubkey::new_unique(),
+            charities: wallets
+                .iter()
+                .map(|wallet| RegistryCharity {
+                    name_hash: wallet.to_bytes(),
+                    wallet: *wallet,
+                    is_active: true,
+                })
+                .collect(),
+        };
+        CharityRegistryData::pack(registry, &mut charity_registry_acc.data).unwrap();
+        charity_registry_acc
+    }
+
+    /// Puts lottery into the finaled state, as if winning numbers were drawn
+    fn finalize_lottery(lottery_acc: &mut SolanaAccount, winning_numbers: &[u8]) {
+        let mut lottery = LotteryData::unpack_unchecked(lottery_acc.data()).unwrap();
+        lottery.is_finaled = true;
+        lottery.winning_numbers = crate::numbers::to_lottery_numbers(winning_numbers).unwrap();
+        lottery.randomness_len = 4;
+        lottery.randomness[..4].copy_from_slice(&[1, 2, 3, 4]);
+        lottery.draw_slot = DRAW_SLOT;
+        lottery.drawn_at = DRAW_TIME;
+        LotteryData::pack(lottery, &mut lottery_acc.data).unwrap();
+    }
+
+    fn do_process(instruction: Instruction, accounts: Vec<&mut SolanaAccount>) -> ProgramResult {
+        let mut meta = instruction
+            .accounts
+            .iter()
+            .zip(accounts)
+            .map(|(account_meta, account)| (&account_meta.pubkey, account_meta.is_signer, account))
+            .collect::<Vec<_>>();
+
+        let account_infos = create_is_signer_account_infos(&mut meta);
+        Processor::process(&instruction.program_id, &account_infos, &instruction.data)
+    }
+
+    #[test]
+    fn test_init_lottery() {
+        let program_id = id();
+        let lottery_id = 112233;
+        let payer_key = Pubkey::new_unique();
+        let mut payer_acc = SolanaAccount::default();
+        let authority_key = Pubkey::new_unique();
+        let mut authority_acc = SolanaAccount::default();
+        let mut lottery_acc = SolanaAccount::new(
+            lottery_minimum_balance(),
+            LotteryData::get_packed_len(),
+            &program_id,
+        );
+        let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
+        let mut clock_sysvar_acc = clock_account(SALE_START);
+        let mut system_acc = SolanaAccount::default();
+        let charity_1 = Pubkey::new_unique();
+        let charity_2 = Pubkey::new_unique();
+        let charity_3 = Pubkey::new_unique();
+        let charity_4 = Pubkey::new_unique();
+        let mut charity_registry_acc =
+            charity_registry_account(&[charity_1, charity_2, charity_3, charity_4]);
+        let mut program_data_acc = program_data_account(&authority_key);
+        let mut vault_acc = SolanaAccount::default();
+        let rewards_wallet = Pubkey::new_unique();
+        let slot_holders_rewards_wallet = Pubkey::new_unique();
+        let sollotto_labs_wallet = Pubkey::new_unique();
+        let randomness_account = Pubkey::new_unique();
+
+        // BadCase: lottery is created by another wallet than the program upgrade authority
+        let other_authority_key = Pubkey::new_unique();
+        assert_eq!(
+            Err(LotteryError::InvalidAuthority.into()),
+            do_process(
+                crate::instruction::initialize_lottery(
+                    &program_id,
+                    lottery_id,
+                    &[0, 1, 2, 3],
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &sollotto_labs_wallet,
+                    &randomness_account,
+                    SALE_START,
+                    SALE_END,
+                    DRAW_TIME,
+                    CLAIM_PERIOD,
+                    MAIN_POOL_SIZE,
+                    BONUS_POOL_SIZE,
+                    PICK_COUNT,
+                    TICKET_PRICE,
+                    CharityMode::WinnerTakesAll,
+                    RandomnessProvider::SwitchboardVrf,
+                    &[0; 32],
+                    None,
+                    &payer_key,
+                    &other_authority_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_registry_acc,
+                    &mut vault_acc,
+                    &mut program_data_acc,
+                ]
+            )
+        );
+
+        // BadCase: lottery account is not derived from the lottery id
+        let mut instruction = crate::instruction::initialize_lottery(
+            &program_id,
+            lottery_id,
+            &[0, 1, 2, 3],
+            &rewards_wallet,
+            &slot_holders_rewards_wallet,
+            &sollotto_labs_wallet,
+            &randomness_account,
+            SALE_START,
+            SALE_END,
+            DRAW_TIME,
+            CLAIM_PERIOD,
+            MAIN_POOL_SIZE,
+            BONUS_POOL_SIZE,
+            PICK_COUNT,
+            TICKET_PRICE,
+            CharityMode::WinnerTakesAll,
+            RandomnessProvider::SwitchboardVrf,
+            &[0; 32],
+            None,
+            &payer_key,
+            &authority_key,
+        )
+        .unwrap();
+        // BadCase: instruction data is truncated
+        let mut truncated_instruction = instruction.clone();
+        truncated_instruction.data.truncate(40);
+        assert_eq!(
+            Err(LotteryError::InvalidInstruction.into()),
+            do_process(
+                truncated_instruction,
+                vec![
+                    &mut lottery_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_registry_acc,
+                    &mut vault_acc,
+                    &mut program_data_acc
+                ]
+            )
+        );
+
+        instruction.accounts[0].pubkey = find_lottery_address(&program_id, lottery_id + 1).0;
+        assert_eq!(
+            Err(LotteryError::InvalidAccountAddress.into()),
+            do_process(
+                instruction,
+                vec![
+                    &mut lottery_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_registry_acc,
+                    &mut vault_acc,
+                    &mut program_data_acc
+                ]
+            )
+        );
+
+        // BadCase: sale window closes after the draw
+        assert_eq!(
+            Err(LotteryError::InvalidSchedule.into()),
+            do_process(
+                crate::instruction::initialize_lottery(
+                    &program_id,
+                    lottery_id,
+                    &[0, 1, 2, 3],
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &sollotto_labs_wallet,
+                    &randomness_account,
+                    SALE_START,
+                    DRAW_TIME,
+                    SALE_END,
+                    CLAIM_PERIOD,
+                    MAIN_POOL_SIZE,
+                    BONUS_POOL_SIZE,
+                    PICK_COUNT,
+                    TICKET_PRICE,
+                    CharityMode::WinnerTakesAll,
+                    RandomnessProvider::SwitchboardVrf,
+                    &[0; 32],
+                    None,
+                    &payer_key,
+                    &authority_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_registry_acc,
+                    &mut vault_acc,
+                    &mut program_data_acc
+                ]
+            )
+        );
+
+        // BadCase: sale window is already over
+        let mut late_clock_sysvar_acc = clock_account(SALE_END);
+        assert_eq!(
+            Err(LotteryError::InvalidSchedule.into()),
+            do_process(
+                crate::instruction::initialize_lottery(
+                    &program_id,
+                    lottery_id,
+                    &[0, 1, 2, 3],
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &sollotto_labs_wallet,
+                    &randomness_account,
+                    SALE_START,
+                    SALE_END,
+                    DRAW_TIME,
+                    CLAIM_PERIOD,
+                    MAIN_POOL_SIZE,
+                    BONUS_POOL_SIZE,
+                    PICK_COUNT,
+                    TICKET_PRICE,
+                    CharityMode::WinnerTakesAll,
+                    RandomnessProvider::SwitchboardVrf,
+                    &[0; 32],
+                    None,
+                    &payer_key,
+                    &authority_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut late_clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_registry_acc,
+                    &mut vault_acc,
+                    &mut program_data_acc
+                ]
+            )
+        );
+
+        // BadCase: pick count bigger than main pool
+        assert_eq!(
+            Err(LotteryError::InvalidNumberFormat.into()),
+            do_process(
+                crate::instruction::initialize_lottery(
+                    &program_id,
+                    lottery_id,
+                    &[0, 1, 2, 3],
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &sollotto_labs_wallet,
+                    &randomness_account,
+                    SALE_START,
+                    SALE_END,
+                    DRAW_TIME,
+                    CLAIM_PERIOD,
+                    4,
+                    BONUS_POOL_SIZE,
+                    PICK_COUNT,
+                    TICKET_PRICE,
+                    CharityMode::WinnerTakesAll,
+                    RandomnessProvider::SwitchboardVrf,
+                    &[0; 32],
+                    None,
+                    &payer_key,
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_registry_acc,
+                    &mut vault_acc,
+                    &mut program_data_acc
+                ]
+            )
+        );
+
+        // BadCase: too many numbers
+        assert_eq!(
+            Err(LotteryError::InvalidNumberFormat.into()),
+            do_process(
+                crate::instruction::initialize_lottery(
+                    &program_id,
+                    lottery_id,
+                    &[0, 1, 2, 3],
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &sollotto_labs_wallet,
+                    &randomness_account,
+                    SALE_START,
+                    SALE_END,
+                    DRAW_TIME,
+                    CLAIM_PERIOD,
+                    MAIN_POOL_SIZE,
+                    BONUS_POOL_SIZE,
+                    crate::numbers::MAX_PICK_COUNT as u8 + 1,
+                    TICKET_PRICE,
+                    CharityMode::WinnerTakesAll,
+                    RandomnessProvider::SwitchboardVrf,
+                    &[0; 32],
+                    None,
+                    &payer_key,
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_registry_acc,
+                    &mut vault_acc,
+                    &mut program_data_acc
+                ]
+            )
+        );
+
+        // BadCase: free tickets
+        assert_eq!(
+            Err(LotteryError::InvalidTicketPrice.into()),
+            do_process(
+                crate::instruction::initialize_lottery(
+                    &program_id,
+                    lottery_id,
+                    &[0, 1, 2, 3],
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &sollotto_labs_wallet,
+                    &randomness_account,
+                    SALE_START,
+                    SALE_END,
+                    DRAW_TIME,
+                    CLAIM_PERIOD,
+                    MAIN_POOL_SIZE,
+                    BONUS_POOL_SIZE,
+                    PICK_COUNT,
+                    0,
+                    CharityMode::WinnerTakesAll,
+                    RandomnessProvider::SwitchboardVrf,
+                    &[0; 32],
+                    None,
+                    &payer_key,
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_registry_acc,
+                    &mut vault_acc,
+                    &mut program_data_acc
+                ]
+            )
+        );
+
+        // BadCase: no charities to pay
+        assert_eq!(
+            Err(LotteryError::InvalidCharityMode.into()),
+            do_process(
+                crate::instruction::initialize_lottery(
+                    &program_id,
+                    lottery_id,
+                    &[0, 1, 2, 3],
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &sollotto_labs_wallet,
+                    &randomness_account,
+                    SALE_START,
+                    SALE_END,
+                    DRAW_TIME,
+                    CLAIM_PERIOD,
+                    MAIN_POOL_SIZE,
+                    BONUS_POOL_SIZE,
+                    PICK_COUNT,
+                    TICKET_PRICE,
+                    CharityMode::TopK(0),
+                    RandomnessProvider::SwitchboardVrf,
+                    &[0; 32],
+                    None,
+                    &payer_key,
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_registry_acc,
+                    &mut vault_acc,
+                    &mut program_data_acc
+                ]
+            )
+        );
+
+        do_process(
+            crate::instruction::initialize_lottery(
+                &program_id,
+                lottery_id,
+                &[0, 1, 2, 3],
+                &rewards_wallet,
+                &slot_holders_rewards_wallet,
+                &sollotto_labs_wallet,
+                &randomness_account,
+                SALE_START,
+                SALE_END,
+                DRAW_TIME,
+                CLAIM_PERIOD,
+                MAIN_POOL_SIZE,
+                BONUS_POOL_SIZE,
+                PICK_COUNT,
+                TICKET_PRICE,
+                CharityMode::WinnerTakesAll,
+                RandomnessProvider::SwitchboardVrf,
+                &[0; 32],
+                None,
+                &payer_key,
+                &authority_key,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut payer_acc,
+                &mut authority_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut charity_registry_acc,
+                &mut vault_acc,
+                &mut program_data_acc,
+            ],
+        )
+        .unwrap();
+
+        // BadCase: Lottery Already initialized
+        assert_eq!(
+            Err(LotteryError::Initialized.into()),
+            do_process(
+                crate::instruction::initialize_lottery(
+                    &program_id,
+                    lottery_id,
+                    &[0, 1, 2, 3],
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &sollotto_labs_wallet,
+                    &randomness_account,
+                    SALE_START,
+                    SALE_END,
+                    DRAW_TIME,
+                    CLAIM_PERIOD,
+                    MAIN_POOL_SIZE,
+                    BONUS_POOL_SIZE,
+                    PICK_COUNT,
+                    TICKET_PRICE,
+                    CharityMode::WinnerTakesAll,
+                    RandomnessProvider::SwitchboardVrf,
+                    &[0; 32],
+                    None,
+                    &payer_key,
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_registry_acc,
+                    &mut vault_acc,
+                    &mut program_data_acc
+                ]
+            )
+        );
+
+        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
+        assert_eq!(lottery.is_initialized, true);
+        assert_eq!(lottery.lottery_id, lottery_id);
+        assert_eq!(lottery.charities(), [0, 1, 2, 3]);
+        assert_eq!(lottery.votes(), [0, 0, 0, 0]);
+        assert_eq!(
+            lottery.vault,
+            find_vault_address(
+                &program_id,
+                &find_lottery_address(&program_id, lottery_id).0
+            )
+            .0
+        );
+        assert_eq!(lottery.rewards_wallet, rewards_wallet);
+        assert_eq!(
+            lottery.slot_holders_rewards_wallet,
+            slot_holders_rewards_wallet
+        );
+        assert_eq!(lottery.sollotto_labs_wallet, sollotto_labs_wallet);
+        assert_eq!(lottery.sale_start, SALE_START);
+        assert_eq!(lottery.sale_end, SALE_END);
+        assert_eq!(lottery.draw_time, DRAW_TIME);
+        assert_eq!(lottery.claim_period, CLAIM_PERIOD);
+        assert_eq!(
+            lottery.number_format,
+            NumberFormat {
+                main_pool_size: MAIN_POOL_SIZE,
+                bonus_pool_size: BONUS_POOL_SIZE,
+                pick_count: PICK_COUNT,
+            }
+        );
+        assert_eq!(lottery.ticket_price, TICKET_PRICE);
+        assert_eq!(lottery.charity_mode, CharityMode::WinnerTakesAll);
+        assert_eq!(lottery.total_registrations, 0);
+        assert_eq!(lottery.prize_pool_amount, 0);
+        for number in &lottery.winning_numbers {
+            assert_eq!(*number, 0);
+        }
+    }
+
+    #[test]
+    fn test_ticket_purchase() {
+        let program_id = id();
+        let lottery_id = 112233;
+        let (lottery_key, _) = find_lottery_address(&program_id, lottery_id);
+        let payer_key = Pubkey::new_unique();
+        let mut payer_acc = SolanaAccount::default();
+        let authority_key = Pubkey::new_unique();
+        let mut authority_acc = SolanaAccount::default();
+        let mut lottery_acc = SolanaAccount::new(
+            lottery_minimum_balance(),
+            LotteryData::get_packed_len(),
+            &program_id,
+        );
+        let user_funding_key = Pubkey::new_unique();
+        let mut user_funding_acc = SolanaAccount::default();
+        let user_ticket_nonce = 0;
+        let mut user_ticket_acc = SolanaAccount::new(
+            ticket_minimum_balance(),
+            TicketData::get_packed_len(),
+            &program_id,
+        );
+        let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
+        let mut clock_sysvar_acc = clock_account(SALE_START);
+        let mut system_acc = SolanaAccount::default();
+        let mut spl_token_acc = SolanaAccount::default();
+        let charity_1 = Pubkey::new_unique();
+        let charity_2 = Pubkey::new_unique();
+        let charity_3 = Pubkey::new_unique();
+        let charity_4 = Pubkey::new_unique();
+        let mut charity_registry_acc =
+            charity_registry_account(&[charity_1, charity_2, charity_3, charity_4]);
+        let mut program_data_acc = program_data_account(&authority_key);
+        // Transfers are not executed here, the vault holds the ticket money up front
+        let mut vault_acc = SolanaAccount::new(sol_to_lamports(10.0), 0, &Pubkey::default());
+        let rewards_wallet = Pubkey::new_unique();
+        let slot_holders_rewards_wallet = Pubkey::new_unique();
+        let sollotto_labs_wallet = Pubkey::new_unique();
+        let randomness_account = Pubkey::new_unique();
+        let user_charity = 0;
+
+        // BadCase: instruction data is truncated in the ticket numbers
+        let mut instruction = crate::instruction::purchase_ticket(
+            &program_id,
+            lottery_id,
+            user_charity,
+            &user_funding_key,
+            &[10, 20, 30, 40, 50, 15],
+            user_ticket_nonce,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        instruction.data.truncate(38);
+        assert_eq!(
+            Err(LotteryError::InvalidInstruction.into()),
+            do_process(
+                instruction,
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        // BadCase: Lottery is not initialized
+        assert_eq!(
+            Err(LotteryError::NotInitialized.into()),
+            do_process(
+                crate::instruction::purchase_ticket(
+                    &program_id,
+                    lottery_id,
+                    user_charity,
+                    &user_funding_key,
+                    &[10, 20, 30, 40, 50, 15],
+                    user_ticket_nonce,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        do_process(
+            crate::instruction::initialize_lottery(
+                &program_id,
+                lottery_id,
+                &[0, 1, 2, 3],
+                &rewards_wallet,
+                &slot_holders_rewards_wallet,
+                &sollotto_labs_wallet,
+                &randomness_account,
+                SALE_START,
+                SALE_END,
+                DRAW_TIME,
+                CLAIM_PERIOD,
+                MAIN_POOL_SIZE,
+                BONUS_POOL_SIZE,
+                PICK_COUNT,
+                TICKET_PRICE,
+                CharityMode::WinnerTakesAll,
+                RandomnessProvider::SwitchboardVrf,
+                &[0; 32],
+                None,
+                &payer_key,
+                &authority_key,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut payer_acc,
+                &mut authority_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut charity_registry_acc,
+                &mut vault_acc,
+                &mut program_data_acc,
+            ],
+        )
+        .unwrap();
+
+        // BadCase: user cannot pay
+        assert_eq!(
+            Err(ProgramError::InsufficientFunds),
+            do_process(
+                crate::instruction::purchase_ticket(
+                    &program_id,
+                    lottery_id,
+                    user_charity,
+                    &user_funding_key,
+                    &[10, 20, 30, 40, 50, 29],
+                    user_ticket_nonce,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        user_funding_acc.lamports += sol_to_lamports(0.1) + ticket_minimum_balance();
+
+        // BadCase: sale is not started
+        let mut early_clock_sysvar_acc = clock_account(SALE_START - 1);
+        assert_eq!(
+            Err(LotteryError::SaleNotStarted.into()),
+            do_process(
+                crate::instruction::purchase_ticket(
+                    &program_id,
+                    lottery_id,
+                    user_charity,
+                    &user_funding_key,
+                    &[10, 20, 30, 40, 50, 29],
+                    user_ticket_nonce,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut early_clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        // BadCase: sale is closed
+        let mut late_clock_sysvar_acc = clock_account(SALE_END);
+        assert_eq!(
+            Err(LotteryError::SaleClosed.into()),
+            do_process(
+                crate::instruction::purchase_ticket(
+                    &program_id,
+                    lottery_id,
+                    user_charity,
+                    &user_funding_key,
+                    &[10, 20, 30, 40, 50, 29],
+                    user_ticket_nonce,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut late_clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        // BadCase: ticket account is not derived from the user wallet and ticket nonce
+        let mut instruction = crate::instruction::purchase_ticket(
+            &program_id,
+            lottery_id,
+            user_charity,
+            &user_funding_key,
+            &[10, 20, 30, 40, 50, 15],
+            user_ticket_nonce,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        instruction.accounts[1].pubkey = find_ticket_address(
+            &program_id,
+            &lottery_key,
+            &user_funding_key,
+            user_ticket_nonce + 1,
+        )
+        .0;
+        assert_eq!(
+            Err(LotteryError::InvalidAccountAddress.into()),
+            do_process(
+                instruction,
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        // BadCase: charity is not voted in the lottery
+        assert_eq!(
+            Err(LotteryError::InvalidCharity.into()),
+            do_process(
+                crate::instruction::purchase_ticket(
+                    &program_id,
+                    lottery_id,
+                    4,
+                    &user_funding_key,
+                    &[10, 20, 30, 40, 50, 15],
+                    user_ticket_nonce,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        // BadCase: bad numbers
+        assert_eq!(
+            Err(LotteryError::InvalidNumber.into()),
+            do_process(
+                crate::instruction::purchase_ticket(
+                    &program_id,
+                    lottery_id,
+                    user_charity,
+                    &user_funding_key,
+                    &[70, 20, 30, 40, 50, 15],
+                    user_ticket_nonce,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        assert_eq!(
+            Err(LotteryError::InvalidNumber.into()),
+            do_process(
+                crate::instruction::purchase_ticket(
+                    &program_id,
+                    lottery_id,
+                    user_charity,
+                    &user_funding_key,
+                    &[10, 20, 30, 40, 0, 15],
+                    user_ticket_nonce,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        // BadCase: duplicate main numbers
+        assert_eq!(
+            Err(LotteryError::DuplicateNumbers.into()),
+            do_process(
+                crate::instruction::purchase_ticket(
+                    &program_id,
+                    lottery_id,
+                    user_charity,
+                    &user_funding_key,
+                    &[5, 5, 5, 5, 5, 1],
+                    user_ticket_nonce,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        assert_eq!(
+            Err(LotteryError::InvalidNumber.into()),
+            do_process(
+                crate::instruction::purchase_ticket(
+                    &program_id,
+                    lottery_id,
+                    user_charity,
+                    &user_funding_key,
+                    &[10, 20, 30, 40, 50, 30],
+                    user_ticket_nonce,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        let purchase = || {
+            crate::instruction::purchase_ticket(
+                &program_id,
+                lottery_id,
+                user_charity,
+                &user_funding_key,
+                &[50, 40, 30, 20, 10, 29],
+                user_ticket_nonce,
+                None,
+                None,
+                None,
+            )
+            .unwrap()
+        };
+
+        // BadCase: Ticket price is paid into another account than the lottery vault
+        let mut instruction = purchase();
+        instruction.accounts[3].pubkey = Pubkey::new_unique();
+        assert_eq!(
+            Err(LotteryError::InvalidVault.into()),
+            do_process(
+                instruction,
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        // BadCase: Vault balance is below the prize pool
+        let mut empty_vault_acc = SolanaAccount::default();
+        assert_eq!(
+            Err(ProgramError::InsufficientFunds),
+            do_process(
+                purchase(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut empty_vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        do_process(
+            crate::instruction::purchase_ticket(
+                &program_id,
+                lottery_id,
+                user_charity,
+                &user_funding_key,
+                &[50, 40, 30, 20, 10, 29],
+                user_ticket_nonce,
+                None,
+                None,
+                None,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut user_ticket_acc,
+                &mut user_funding_acc,
+                &mut vault_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut spl_token_acc,
+            ],
+        )
+        .unwrap();
+
+        let lottery = LotteryData::unpack(&lottery_acc.data()).unwrap();
+        assert_eq!(lottery.votes(), [1, 0, 0, 0]);
+        assert_eq!(lottery.total_registrations, 1);
+        assert_eq!(lottery.prize_pool_amount, sol_to_lamports(0.1));
+        // Main numbers are stored sorted
+        let ticket = TicketData::unpack_unchecked(&user_ticket_acc.data()).unwrap();
+        assert_eq!(ticket.lottery_id, lottery_id);
+        assert_eq!(ticket.lines.len(), 1);
+        assert_eq!(
+            ticket.lines[0].ticket_number_arr,
+            [10, 20, 30, 40, 50, 29, 0, 0]
+        );
+
+        // BadCase: Ticket already purchased
+        assert_eq!(
+            Err(LotteryError::AlreadyPurchased.into()),
+            do_process(
+                crate::instruction::purchase_ticket(
+                    &program_id,
+                    lottery_id,
+                    user_charity,
+                    &user_funding_key,
+                    &[10, 20, 30, 40, 50, 30],
+                    user_ticket_nonce,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        // Slip of several lines in one ticket
+        let slip_ticket_nonce = 1;
+        let mut slip_ticket_acc = SolanaAccount::new(
+            ticket_minimum_balance(),
+            TicketData::get_packed_len(),
+            &program_id,
+        );
+        user_funding_acc.lamports += TICKET_PRICE + ticket_minimum_balance();
+
+        // BadCase: user cannot pay for all lines
+        assert_eq!(
+            Err(ProgramError::InsufficientFunds),
+            do_process(
+                crate::instruction::purchase_tickets(
+                    &program_id,
+                    lottery_id,
+                    user_charity,
+                    &user_funding_key,
+                    &[
+                        &[1, 2, 3, 4, 5, 1],
+                        &[6, 7, 8, 9, 10, 2],
+                        &[15, 14, 13, 12, 11, 3],
+                    ],
+                    slip_ticket_nonce,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut slip_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        user_funding_acc.lamports += 2 * TICKET_PRICE;
+        do_process(
+            crate::instruction::purchase_tickets(
+                &program_id,
+                lottery_id,
+                user_charity,
+                &user_funding_key,
+                &[
+                    &[1, 2, 3, 4, 5, 1],
+                    &[6, 7, 8, 9, 10, 2],
+                    &[15, 14, 13, 12, 11, 3],
+                ],
+                slip_ticket_nonce,
+                None,
+                None,
+                None,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut slip_ticket_acc,
+                &mut user_funding_acc,
+                &mut vault_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut spl_token_acc,
+            ],
+        )
+        .unwrap();
+
+        let lottery = LotteryData::unpack(&lottery_acc.data()).unwrap();
+        assert_eq!(lottery.votes(), [4, 0, 0, 0]);
+        assert_eq!(lottery.total_registrations, 2);
+        assert_eq!(lottery.prize_pool_amount, 4 * TICKET_PRICE);
+        let ticket = TicketData::unpack_unchecked(&slip_ticket_acc.data()).unwrap();
+        assert_eq!(ticket.lines.len(), 3);
+        assert_eq!(
+            ticket.lines[2].ticket_number_arr,
+            [11, 12, 13, 14, 15, 3, 0, 0]
+        );
+
+        // Quick pick numbers are generated by the program
+        let quick_pick_ticket_nonce = 2;
+        let mut quick_pick_ticket_acc = SolanaAccount::new(
+            ticket_minimum_balance(),
+            TicketData::get_packed_len(),
+            &program_id,
+        );
+        let mut slot_hashes_sysvar_acc =
+            create_account_for_test(&SlotHashes::new(&[(SALE_START as u64, Hash::new_unique())]));
+        let quick_pick_instruction = crate::instruction::purchase_quick_pick(
+            &program_id,
+            lottery_id,
+            user_charity,
+            &user_funding_key,
+            quick_pick_ticket_nonce,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // BadCase: not the SlotHashes sysvar
+        let mut bad_instruction = quick_pick_instruction.clone();
+        bad_instruction.accounts[8].pubkey = Pubkey::new_unique();
+        assert_eq!(
+            Err(LotteryError::InvalidAccountAddress.into()),
+            do_process(
+                bad_instruction,
+                vec![
+                    &mut lottery_acc,
+                    &mut quick_pick_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                    &mut slot_hashes_sysvar_acc,
+                ]
+            )
+        );
+
+        // BadCase: SlotHashes sysvar is empty
+        let mut empty_slot_hashes_sysvar_acc = create_account_for_test(&SlotHashes::new(&[]));
+        assert_eq!(
+            Err(LotteryError::InvalidRandomResult.into()),
+            do_process(
+                quick_pick_instruction.clone(),
+                vec![
+                    &mut lottery_acc,
+                    &mut quick_pick_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                    &mut empty_slot_hashes_sysvar_acc,
+                ]
+            )
+        );
+
+        do_process(
+            quick_pick_instruction,
+            vec![
+                &mut lottery_acc,
+                &mut quick_pick_ticket_acc,
+                &mut user_funding_acc,
+                &mut vault_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut spl_token_acc,
+                &mut slot_hashes_sysvar_acc,
+            ],
+        )
+        .unwrap();
+
+        let lottery = LotteryData::unpack(&lottery_acc.data()).unwrap();
+        assert_eq!(lottery.total_registrations, 3);
+        assert_eq!(lottery.prize_pool_amount, 5 * TICKET_PRICE);
+        let ticket = TicketData::unpack_unchecked(&quick_pick_ticket_acc.data()).unwrap();
+        assert_eq!(ticket.lines.len(), 1);
+        lottery
+            .number_format
+            .check_numbers(&ticket.lines[0].ticket_number_arr)
+            .unwrap();
+
+        finalize_lottery(&mut lottery_acc, &[10, 20, 30, 40, 50, 29]);
+
+        let user_funding_key = Pubkey::new_unique();
+        let mut user_funding_acc = SolanaAccount::default();
+        let user_ticket_nonce = 0;
+        let mut user_ticket_acc = SolanaAccount::new(
+            ticket_minimum_balance(),
+            TicketData::get_packed_len(),
+            &program_id,
+        );
+
+        assert_eq!(
+            Err(LotteryError::IsFinaled.into()),
+            do_process(
+                crate::instruction::purchase_ticket(
+                    &program_id,
+                    lottery_id,
+                    user_charity,
+                    &user_funding_key,
+                    &[10, 20, 30, 40, 50, 30],
+                    user_ticket_nonce,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_funding_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_store_winning_numbers() {
+        let program_id = id();
+        let lottery_id = 112233;
+        let payer_key = Pubkey::new_unique();
+        let mut payer_acc = SolanaAccount::default();
+        let authority_key = Pubkey::new_unique();
+        let mut authority_acc = SolanaAccount::default();
+        let mut lottery_acc = SolanaAccount::new(
+            lottery_minimum_balance(),
+            LotteryData::get_packed_len(),
+            &program_id,
+        );
+        let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
+        let mut clock_sysvar_acc = clock_account(SALE_START);
+        let mut system_acc = SolanaAccount::default();
+        let charity_1 = Pubkey::new_unique();
+        let charity_2 = Pubkey::new_unique();
+        let charity_3 = Pubkey::new_unique();
+        let charity_4 = Pubkey::new_unique();
+        let mut charity_registry_acc =
+            charity_registry_account(&[charity_1, charity_2, charity_3, charity_4]);
+        let mut program_data_acc = program_data_account(&authority_key);
+        let mut vault_acc = SolanaAccount::default();
+        let rewards_wallet = Pubkey::new_unique();
+        let slot_holders_rewards_wallet = Pubkey::new_unique();
+        let sollotto_labs_wallet = Pubkey::new_unique();
+        let randomness_account = Pubkey::new_unique();
+
+        let vrf_key = randomness_account;
+        let mut vrf_acc = SolanaAccount::default();
+        vrf_acc.data = vec![7; 32];
+        let mut draw_clock_sysvar_acc = clock_account(DRAW_TIME);
+
+        // BadCase: Lottery is not initialized
+        assert_eq!(
+            Err(LotteryError::NotInitialized.into()),
+            do_process(
+                crate::instruction::store_winning_numbers(
+                    &program_id,
+                    lottery_id,
+                    &authority_key,
+                    &vrf_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut vrf_acc,
+                    &mut draw_clock_sysvar_acc
+                ]
+            )
+        );
+
+        do_process(
+            crate::instruction::initialize_lottery(
+                &program_id,
+                lottery_id,
+                &[0, 1, 2, 3],
+                &rewards_wallet,
+                &slot_holders_rewards_wallet,
+                &sollotto_labs_wallet,
+                &randomness_account,
+                SALE_START,
+                SALE_END,
+                DRAW_TIME,
+                CLAIM_PERIOD,
+                MAIN_POOL_SIZE,
+                BONUS_POOL_SIZE,
+                PICK_COUNT,
+                TICKET_PRICE,
+                CharityMode::WinnerTakesAll,
+                RandomnessProvider::Test,
+                &[0; 32],
+                None,
+                &payer_key,
+                &authority_key,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut payer_acc,
+                &mut authority_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut charity_registry_acc,
+                &mut vault_acc,
+                &mut program_data_acc,
+            ],
+        )
+        .unwrap();
+
+        // BadCase: Draw time is not reached
+        assert_eq!(
+            Err(LotteryError::DrawNotReady.into()),
+            do_process(
+                crate::instruction::store_winning_numbers(
+                    &program_id,
+                    lottery_id,
+                    &authority_key,
+                    &vrf_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut vrf_acc,
+                    &mut clock_sysvar_acc
+                ]
+            )
+        );
+
+        let mut sale_end_clock_sysvar_acc = clock_account(SALE_END);
+        assert_eq!(
+            Err(LotteryError::DrawNotReady.into()),
+            do_process(
+                crate::instruction::store_winning_numbers(
+                    &program_id,
+                    lottery_id,
+                    &authority_key,
+                    &vrf_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut vrf_acc,
+                    &mut sale_end_clock_sysvar_acc
+                ]
+            )
+        );
+
+        // BadCase: Draw is not requested
+        assert_eq!(
+            Err(LotteryError::DrawNotReady.into()),
+            do_process(
+                crate::instruction::store_winning_numbers(
+                    &program_id,
+                    lottery_id,
+                    &authority_key,
+                    &vrf_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut vrf_acc,
+                    &mut draw_clock_sysvar_acc
+                ]
+            )
+        );
+
+        // BadCase: Sale is not closed
+        assert_eq!(
+            Err(LotteryError::DrawNotReady.into()),
+            do_process(
+                crate::instruction::request_draw(&program_id, lottery_id, &authority_key, &vrf_key)
+                    .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut vrf_acc,
+                    &mut clock_sysvar_acc
+                ]
+            )
+        );
+
+        // BadCase: Draw is requested by another wallet
+        let other_authority_key = Pubkey::new_unique();
+        assert_eq!(
+            Err(LotteryError::InvalidAuthority.into()),
+            do_process(
+                crate::instruction::request_draw(
+                    &program_id,
+                    lottery_id,
+                    &other_authority_key,
+                    &vrf_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut vrf_acc,
+                    &mut draw_clock_sysvar_acc
+                ]
+            )
+        );
+
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::request_draw(&program_id, lottery_id, &authority_key, &vrf_key)
+                    .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut vrf_acc,
+                    &mut draw_clock_sysvar_acc
+                ]
+            )
+        );
+        let lottery = LotteryData::unpack_unchecked(&lottery_acc.data).unwrap();
+        assert_eq!(lottery.draw_requested_at, DRAW_TIME);
+        assert_eq!(lottery.last_randomness_hash, hashv(&[&[7; 32]]).to_bytes());
+
+        // BadCase: Draw is already requested
+        assert_eq!(
+            Err(LotteryError::Initialized.into()),
+            do_process(
+                crate::instruction::request_draw(&program_id, lottery_id, &authority_key, &vrf_key)
+                    .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut vrf_acc,
+                    &mut draw_clock_sysvar_acc
+                ]
+            )
+        );
+
+        // BadCase: Randomness result is produced before the draw request
+        assert_eq!(
+            Err(LotteryError::StaleRandomness.into()),
+            do_process(
+                crate::instruction::store_winning_numbers(
+                    &program_id,
+                    lottery_id,
+                    &authority_key,
+                    &vrf_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut vrf_acc,
+                    &mut draw_clock_sysvar_acc
+                ]
+            )
+        );
+
+        // Randomness account publishes a new result
+        vrf_acc.data = vec![8; 32];
+
+        // BadCase: Randomness account is not the lottery one
+        let other_vrf_key = Pubkey::new_unique();
+        assert_eq!(
+            Err(LotteryError::InvalidAccountAddress.into()),
+            do_process(
+                crate::instruction::store_winning_numbers(
+                    &program_id,
+                    lottery_id,
+                    &authority_key,
+                    &other_vrf_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut vrf_acc,
+                    &mut draw_clock_sysvar_acc
+                ]
+            )
+        );
+
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::store_winning_numbers(
+                    &program_id,
+                    lottery_id,
+                    &authority_key,
+                    &vrf_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut vrf_acc,
+                    &mut draw_clock_sysvar_acc
+                ]
+            )
+        );
+        let mut lottery = LotteryData::unpack_unchecked(&lottery_acc.data).unwrap();
+        assert_eq!(lottery.is_finaled, true);
+        assert_eq!(lottery.randomness(), [8; 32]);
+        assert_eq!(
+            lottery.winning_numbers,
+            derive_winning_numbers(&[8; 32], &lottery.number_format)
+                .map(|mut numbers| {
+                    lottery.number_format.sort_main_numbers(&mut numbers);
+                    numbers
+                })
+                .unwrap()
+        );
+        assert_eq!(lottery.last_randomness_hash, hashv(&[&[8; 32]]).to_bytes());
+
+        // BadCase: Randomness account still returns the used result
+        lottery.is_finaled = false;
+        LotteryData::pack(lottery, &mut lottery_acc.data).unwrap();
+        assert_eq!(
+            Err(LotteryError::StaleRandomness.into()),
+            do_process(
+                crate::instruction::store_winning_numbers(
+                    &program_id,
+                    lottery_id,
+                    &authority_key,
+                    &vrf_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut vrf_acc,
+                    &mut draw_clock_sysvar_acc
+                ]
+            )
+        );
+
+        // BadCase: Lottery is already finaled
+        finalize_lottery(&mut lottery_acc, &[10, 20, 30, 40, 50, 29]);
+        assert_eq!(
+            Err(LotteryError::IsFinaled.into()),
+            do_process(
+                crate::instruction::store_winning_numbers(
+                    &program_id,
+                    lottery_id,
+                    &authority_key,
+                    &vrf_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut vrf_acc,
+                    &mut draw_clock_sysvar_acc
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_commit_reveal() {
+        let program_id = id();
+        let lottery_id = 112233;
+        let (lottery_key, _) = find_lottery_address(&program_id, lottery_id);
+        let payer_key = Pubkey::new_unique();
+        let mut payer_acc = SolanaAccount::default();
+        let authority_key = Pubkey::new_unique();
+        let mut authority_acc = SolanaAccount::default();
+        let mut lottery_acc = SolanaAccount::new(
+            lottery_minimum_balance(),
+            LotteryData::get_packed_len(),
+            &program_id,
+        );
+        let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
+        let mut clock_sysvar_acc = clock_account(SALE_START);
+        let mut system_acc = SolanaAccount::default();
+        let mut charity_registry_acc = charity_registry_account(&[Pubkey::new_unique()]);
+        let mut program_data_acc = program_data_account(&authority_key);
+        let mut vault_acc = SolanaAccount::default();
+        let rewards_wallet = Pubkey::new_unique();
+        let slot_holders_rewards_wallet = Pubkey::new_unique();
+        let sollotto_labs_wallet = Pubkey::new_unique();
+
+        let secret = [5; 32];
+        let commitment = hashv(&[&secret]).to_bytes();
+        let (reveal_key, _) = find_reveal_address(&program_id, &lottery_key);
+        let mut reveal_acc = SolanaAccount::new(
+            Rent::default().minimum_balance(CommitRevealData::get_packed_len()),
+            CommitRevealData::get_packed_len(),
+            &program_id,
+        );
+        let slot_hash = Hash::new_from_array([9; 32]);
+        let mut slot_hashes_sysvar_acc = create_account_for_test(&SlotHashes::new(&[
+            (DRAW_SLOT + 1, Hash::new_from_array([8; 32])),
+            (DRAW_SLOT, slot_hash),
+        ]));
+        let mut stale_slot_hashes_sysvar_acc = create_account_for_test(&SlotHashes::new(&[(
+            DRAW_SLOT + 512,
+            Hash::new_from_array([8; 32]),
+        )]));
+        let mut sale_end_clock_sysvar_acc = clock_account(SALE_END);
+        let mut draw_clock_sysvar_acc = create_account_for_test(&Clock {
+            slot: DRAW_SLOT,
+            unix_timestamp: DRAW_TIME,
+            ..Clock::default()
+        });
+
+        // BadCase: Randomness account is not the lottery commit-reveal account
+        assert_eq!(
+            Err(LotteryError::InvalidAccountAddress.into()),
+            do_process(
+                crate::instruction::initialize_lottery(
+                    &program_id,
+                    lottery_id,
+                    &[0],
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &sollotto_labs_wallet,
+                    &Pubkey::new_unique(),
+                    SALE_START,
+                    SALE_END,
+                    DRAW_TIME,
+                    CLAIM_PERIOD,
+                    MAIN_POOL_SIZE,
+                    BONUS_POOL_SIZE,
+                    PICK_COUNT,
+                    TICKET_PRICE,
+                    CharityMode::WinnerTakesAll,
+                    RandomnessProvider::CommitReveal,
+                    &commitment,
+                    None,
+                    &payer_key,
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_registry_acc,
+                    &mut vault_acc,
+                    &mut program_data_acc,
+                ],
+            )
+        );
+
+        // BadCase: Commitment is missing
+        assert_eq!(
+            Err(LotteryError::InvalidCommitment.into()),
+            do_process(
+                crate::instruction::initialize_lottery(
+                    &program_id,
+                    lottery_id,
+                    &[0],
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &sollotto_labs_wallet,
+                    &reveal_key,
+                    SALE_START,
+                    SALE_END,
+                    DRAW_TIME,
+                    CLAIM_PERIOD,
+                    MAIN_POOL_SIZE,
+                    BONUS_POOL_SIZE,
+                    PICK_COUNT,
+                    TICKET_PRICE,
+                    CharityMode::WinnerTakesAll,
+                    RandomnessProvider::CommitReveal,
+                    &[0; 32],
+                    None,
+                    &payer_key,
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_registry_acc,
+                    &mut vault_acc,
+                    &mut program_data_acc,
+                ],
+            )
+        );
+
+        do_process(
+            crate::instruction::initialize_lottery(
+                &program_id,
+                lottery_id,
+                &[0],
+                &rewards_wallet,
+                &slot_holders_rewards_wallet,
+                &sollotto_labs_wallet,
+                &reveal_key,
+                SALE_START,
+                SALE_END,
+                DRAW_TIME,
+                CLAIM_PERIOD,
+                MAIN_POOL_SIZE,
+                BONUS_POOL_SIZE,
+                PICK_COUNT,
+                TICKET_PRICE,
+                CharityMode::WinnerTakesAll,
+                RandomnessProvider::CommitReveal,
+                &commitment,
+                None,
+                &payer_key,
+                &authority_key,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut payer_acc,
+                &mut authority_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut charity_registry_acc,
+                &mut vault_acc,
+                &mut program_data_acc,
+            ],
+        )
+        .unwrap();
+        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
+        assert_eq!(lottery.randomness_account, reveal_key);
+        assert_eq!(lottery.commitment, commitment);
+        assert_eq!(lottery.reveal_deadline, DRAW_TIME + REVEAL_PERIOD);
+
+        // BadCase: Reveal slot is not locked
+        assert_eq!(
+            Err(LotteryError::DrawNotReady.into()),
+            do_process(
+                crate::instruction::reveal_secret(&program_id, lottery_id, &secret, &authority_key)
+                    .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut reveal_acc,
+                    &mut authority_acc,
+                    &mut draw_clock_sysvar_acc,
+                    &mut slot_hashes_sysvar_acc,
+                ]
+            )
+        );
+
+        // BadCase: Reveal slot is locked by another wallet
+        let other_authority_key = Pubkey::new_unique();
+        assert_eq!(
+            Err(LotteryError::InvalidAuthority.into()),
+            do_process(
+                crate::instruction::lock_reveal_slot(
+                    &program_id,
+                    lottery_id,
+                    &payer_key,
+                    &other_authority_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut reveal_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut draw_clock_sysvar_acc,
+                    &mut system_acc,
+                ]
+            )
+        );
+
+        // BadCase: Sale is not closed
+        assert_eq!(
+            Err(LotteryError::DrawNotReady.into()),
+            do_process(
+                crate::instruction::lock_reveal_slot(
+                    &program_id,
+                    lottery_id,
+                    &payer_key,
+                    &authority_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut reveal_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut sale_end_clock_sysvar_acc,
+                    &mut system_acc,
+                ]
+            )
+        );
+
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::lock_reveal_slot(
+                    &program_id,
+                    lottery_id,
+                    &payer_key,
+                    &authority_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut reveal_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut draw_clock_sysvar_acc,
+                    &mut system_acc,
+                ]
+            )
+        );
+        let reveal = CommitRevealData::unpack_unchecked(&reveal_acc.data).unwrap();
+        assert_eq!(reveal.is_revealed, false);
+        assert_eq!(reveal.commitment, commitment);
+        assert_eq!(reveal.slot, DRAW_SLOT);
+
+        // BadCase: Reveal slot is locked twice
+        assert_eq!(
+            Err(LotteryError::Initialized.into()),
+            do_process(
+                crate::instruction::lock_reveal_slot(
+                    &program_id,
+                    lottery_id,
+                    &payer_key,
+                    &authority_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut reveal_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut draw_clock_sysvar_acc,
+                    &mut system_acc,
+                ]
+            )
+        );
+
+        // BadCase: Secret does not match the commitment
+        assert_eq!(
+            Err(LotteryError::InvalidCommitment.into()),
+            do_process(
+                crate::instruction::reveal_secret(
+                    &program_id,
+                    lottery_id,
+                    &[6; 32],
+                    &authority_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut reveal_acc,
+                    &mut authority_acc,
+                    &mut draw_clock_sysvar_acc,
+                    &mut slot_hashes_sysvar_acc,
+                ]
+            )
+        );
+
+        // BadCase: Hash of the reveal slot is not in the slot hashes
+        assert_eq!(
+            Err(LotteryError::InvalidRandomResult.into()),
+            do_process(
+                crate::instruction::reveal_secret(&program_id, lottery_id, &secret, &authority_key)
+                    .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut reveal_acc,
+                    &mut authority_acc,
+                    &mut draw_clock_sysvar_acc,
+                    &mut stale_slot_hashes_sysvar_acc,
+                ]
+            )
+        );
+
+        // Secret is mixed with the hash of the locked slot, not the most recent one
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::reveal_secret(&program_id, lottery_id, &secret, &authority_key)
+                    .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut reveal_acc,
+                    &mut authority_acc,
+                    &mut draw_clock_sysvar_acc,
+                    &mut slot_hashes_sysvar_acc,
+                ]
+            )
+        );
+        let reveal = CommitRevealData::unpack_unchecked(&reveal_acc.data).unwrap();
+        assert_eq!(reveal.is_revealed, true);
+        assert_eq!(reveal.commitment, commitment);
+        assert_eq!(reveal.secret, secret);
+        assert_eq!(reveal.slot, DRAW_SLOT);
+        assert_eq!(reveal.slot_hash, slot_hash.to_bytes());
+
+        // BadCase: Secret is revealed twice
+        assert_eq!(
+            Err(LotteryError::Initialized.into()),
+            do_process(
+                crate::instruction::reveal_secret(&program_id, lottery_id, &secret, &authority_key)
+                    .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut reveal_acc,
+                    &mut authority_acc,
+                    &mut draw_clock_sysvar_acc,
+                    &mut slot_hashes_sysvar_acc,
+                ]
+            )
+        );
+
+        // Secret is mixed with the slot hash
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::store_winning_numbers(
+                    &program_id,
+                    lottery_id,
+                    &authority_key,
+                    &reveal_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut reveal_acc,
+                    &mut draw_clock_sysvar_acc
+                ]
+            )
+        );
+        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
+        assert_eq!(lottery.is_finaled, true);
+        assert_eq!(
+            lottery.randomness(),
+            hashv(&[&secret, &slot_hash.to_bytes()]).to_bytes()
+        );
+    }
+
+    #[test]
+    fn test_refund_ticket() {
+        let program_id = id();
+        let lottery_id = 112233;
+        let (lottery_key, _) = find_lottery_address(&program_id, lottery_id);
+        let payer_key = Pubkey::new_unique();
+        let mut payer_acc = SolanaAccount::default();
+        let authority_key = Pubkey::new_unique();
+        let mut authority_acc = SolanaAccount::default();
+        let mut lottery_acc = SolanaAccount::new(
+            lottery_minimum_balance(),
+            LotteryData::get_packed_len(),
+            &program_id,
+        );
+        let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
+        let mut clock_sysvar_acc = clock_account(SALE_START);
+        let mut system_acc = SolanaAccount::default();
+        let mut charity_registry_acc = charity_registry_account(&[Pubkey::new_unique()]);
+        let mut program_data_acc = program_data_account(&authority_key);
+        // Transfers are not executed here, the vault holds the ticket money up front
+        let mut vault_acc = SolanaAccount::new(sol_to_lamports(10.0), 0, &Pubkey::default());
+        let rewards_wallet = Pubkey::new_unique();
+        let slot_holders_rewards_wallet = Pubkey::new_unique();
+        let sollotto_labs_wallet = Pubkey::new_unique();
+
+        let secret = [5; 32];
+        let commitment = hashv(&[&secret]).to_bytes();
+        let (reveal_key, _) = find_reveal_address(&program_id, &lottery_key);
+        let mut reveal_acc = SolanaAccount::default();
+        let mut spl_token_acc = SolanaAccount::default();
+        let user_wallet = Pubkey::new_unique();
+        let mut user_wallet_acc = SolanaAccount::default();
+        let user_ticket_nonce = 0;
+        let mut user_ticket_acc = SolanaAccount::new(
+            ticket_minimum_balance(),
+            TicketData::get_packed_len(),
+            &program_id,
+        );
+        let mut draw_clock_sysvar_acc = clock_account(DRAW_TIME);
+        let mut deadline_clock_sysvar_acc = clock_account(DRAW_TIME + REVEAL_PERIOD);
+        let mut expired_clock_sysvar_acc = clock_account(DRAW_TIME + REVEAL_PERIOD + 1);
+
+        do_process(
+            crate::instruction::initialize_lottery(
+                &program_id,
+                lottery_id,
+                &[0],
+                &rewards_wallet,
+                &slot_holders_rewards_wallet,
+                &sollotto_labs_wallet,
+                &reveal_key,
+                SALE_START,
+                SALE_END,
+                DRAW_TIME,
+                CLAIM_PERIOD,
+                MAIN_POOL_SIZE,
+                BONUS_POOL_SIZE,
+                PICK_COUNT,
+                TICKET_PRICE,
+                CharityMode::WinnerTakesAll,
+                RandomnessProvider::CommitReveal,
+                &commitment,
+                None,
+                &payer_key,
+                &authority_key,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut payer_acc,
+                &mut authority_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut charity_registry_acc,
+                &mut vault_acc,
+                &mut program_data_acc,
+            ],
+        )
+        .unwrap();
+
+        user_wallet_acc.lamports += sol_to_lamports(0.1) + ticket_minimum_balance();
+        do_process(
+            crate::instruction::purchase_ticket(
+                &program_id,
+                lottery_id,
+                0,
+                &user_wallet,
+                &[10, 20, 30, 40, 50, 29],
+                user_ticket_nonce,
+                None,
+                None,
+                None,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut user_ticket_acc,
+                &mut user_wallet_acc,
+                &mut vault_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut spl_token_acc,
+            ],
+        )
+        .unwrap();
+
+        // BadCase: Reveal deadline is not passed
+        assert_eq!(
+            Err(LotteryError::RefundNotAvailable.into()),
+            do_process(
+                crate::instruction::refund_ticket(
+                    &program_id,
+                    lottery_id,
+                    &user_wallet,
+                    user_ticket_nonce,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut deadline_clock_sysvar_acc,
+                    &mut system_acc
+                ]
+            )
+        );
+
+        // BadCase: Lottery is drawn after the reveal deadline
+        assert_eq!(
+            Err(LotteryError::RevealExpired.into()),
+            do_process(
+                crate::instruction::store_winning_numbers(
+                    &program_id,
+                    lottery_id,
+                    &authority_key,
+                    &reveal_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut reveal_acc,
+                    &mut expired_clock_sysvar_acc
+                ]
+            )
+        );
+
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::refund_ticket(
+                    &program_id,
+                    lottery_id,
+                    &user_wallet,
+                    user_ticket_nonce,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut expired_clock_sysvar_acc,
+                    &mut system_acc
+                ]
+            )
+        );
+        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
+        assert_eq!(lottery.prize_pool_amount, 0);
+        let ticket = TicketData::unpack_unchecked(&user_ticket_acc.data).unwrap();
+        assert_eq!(ticket.is_refunded, true);
+
+        // BadCase: Ticket is refunded twice
+        assert_eq!(
+            Err(LotteryError::AlreadyRefunded.into()),
+            do_process(
+                crate::instruction::refund_ticket(
+                    &program_id,
+                    lottery_id,
+                    &user_wallet,
+                    user_ticket_nonce,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut expired_clock_sysvar_acc,
+                    &mut system_acc
+                ]
+            )
+        );
+
+        // Refunded ticket is closed without the draw
+        let mut lottery_result_acc = SolanaAccount::default();
+        let mut settlement_acc = SolanaAccount::default();
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::close_ticket(
+                    &program_id,
+                    lottery_id,
+                    &user_wallet,
+                    user_ticket_nonce
+                )
+                .unwrap(),
+                vec![
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut lottery_result_acc,
+                    &mut settlement_acc,
+                    &mut draw_clock_sysvar_acc
+                ]
+            )
+        );
+        assert_eq!(user_ticket_acc.lamports, 0);
+    }
+
+    #[test]
+    fn test_cancel_lottery() {
+        let program_id = id();
+        let lottery_id = 112233;
+        let payer_key = Pubkey::new_unique();
+        let mut payer_acc = SolanaAccount::default();
+        let authority_key = Pubkey::new_unique();
+        let mut authority_acc = SolanaAccount::default();
+        let mut lottery_acc = SolanaAccount::new(
+            lottery_minimum_balance(),
+            LotteryData::get_packed_len(),
+            &program_id,
+        );
+        let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
+        let mut clock_sysvar_acc = clock_account(SALE_START);
+        let mut system_acc = SolanaAccount::default();
+        let mut charity_registry_acc = charity_registry_account(&[Pubkey::new_unique()]);
+        let mut program_data_acc = program_data_account(&authority_key);
+        // Transfers are not executed here, the vault holds the ticket money up front
+        let mut vault_acc = SolanaAccount::new(sol_to_lamports(10.0), 0, &Pubkey::default());
+        let rewards_wallet = Pubkey::new_unique();
+        let slot_holders_rewards_wallet = Pubkey::new_unique();
+        let sollotto_labs_wallet = Pubkey::new_unique();
+
+        let randomness_account = Pubkey::new_unique();
+        let mut vrf_acc = SolanaAccount::default();
+        let mut spl_token_acc = SolanaAccount::default();
+        let user_wallet = Pubkey::new_unique();
+        let mut user_wallet_acc = SolanaAccount::default();
+        let user_ticket_nonce = 0;
+        let mut user_ticket_acc = SolanaAccount::new(
+            ticket_minimum_balance(),
+            TicketData::get_packed_len(),
+            &program_id,
+        );
+        let mut draw_clock_sysvar_acc = clock_account(DRAW_TIME);
+
+        do_process(
+            crate::instruction::initialize_lottery(
+                &program_id,
+                lottery_id,
+                &[0],
+                &rewards_wallet,
+                &slot_holders_rewards_wallet,
+                &sollotto_labs_wallet,
+                &randomness_account,
+                SALE_START,
+                SALE_END,
+                DRAW_TIME,
+                CLAIM_PERIOD,
+                MAIN_POOL_SIZE,
+                BONUS_POOL_SIZE,
+                PICK_COUNT,
+                TICKET_PRICE,
+                CharityMode::WinnerTakesAll,
+                RandomnessProvider::SwitchboardVrf,
+                &[0; 32],
+                None,
+                &payer_key,
+                &authority_key,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut payer_acc,
+                &mut authority_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut charity_registry_acc,
+                &mut vault_acc,
+                &mut program_data_acc,
+            ],
+        )
+        .unwrap();
+
+        user_wallet_acc.lamports += sol_to_lamports(0.1) + ticket_minimum_balance();
+        do_process(
+            crate::instruction::purchase_ticket(
+                &program_id,
+                lottery_id,
+                0,
+                &user_wallet,
+                &[10, 20, 30, 40, 50, 29],
+                user_ticket_nonce,
+                None,
+                None,
+                None,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut user_ticket_acc,
+                &mut user_wallet_acc,
+                &mut vault_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut spl_token_acc,
+            ],
+        )
+        .unwrap();
+
+        // BadCase: Finaled lottery is cancelled
+        let mut finaled_lottery_acc = lottery_acc.clone();
+        finalize_lottery(&mut finaled_lottery_acc, &[10, 20, 30, 40, 50, 29]);
+        assert_eq!(
+            Err(LotteryError::IsFinaled.into()),
+            do_process(
+                crate::instruction::cancel_lottery(&program_id, lottery_id, &authority_key)
+                    .unwrap(),
+                vec![&mut finaled_lottery_acc, &mut authority_acc]
+            )
+        );
+
+        // BadCase: Lottery is cancelled by another authority
+        assert_eq!(
+            Err(LotteryError::InvalidAuthority.into()),
+            do_process(
+                crate::instruction::cancel_lottery(&program_id, lottery_id, &Pubkey::new_unique())
+                    .unwrap(),
+                vec![&mut lottery_acc, &mut authority_acc]
+            )
+        );
+
+        // BadCase: Lottery is not cancelled
+        assert_eq!(
+            Err(LotteryError::RefundNotAvailable.into()),
+            do_process(
+                crate::instruction::refund_ticket(
+                    &program_id,
+                    lottery_id,
+                    &user_wallet,
+                    user_ticket_nonce,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut draw_clock_sysvar_acc,
+                    &mut system_acc
+                ]
+            )
+        );
+
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::cancel_lottery(&program_id, lottery_id, &authority_key)
+                    .unwrap(),
+                vec![&mut lottery_acc, &mut authority_acc]
+            )
+        );
+        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
+        assert_eq!(lottery.is_cancelled, true);
+
+        // BadCase: Lottery is cancelled twice
+        assert_eq!(
+            Err(LotteryError::IsCancelled.into()),
+            do_process(
+                crate::instruction::cancel_lottery(&program_id, lottery_id, &authority_key)
+                    .unwrap(),
+                vec![&mut lottery_acc, &mut authority_acc]
+            )
+        );
+
+        // BadCase: Winning numbers are stored for the cancelled lottery
+        assert_eq!(
+            Err(LotteryError::IsCancelled.into()),
+            do_process(
+                crate::instruction::store_winning_numbers(
+                    &program_id,
+                    lottery_id,
+                    &authority_key,
+                    &randomness_account
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut vrf_acc,
+                    &mut draw_clock_sysvar_acc
+                ]
+            )
+        );
+
+        // BadCase: Ticket is purchased for the cancelled lottery
+        assert_eq!(
+            Err(LotteryError::IsCancelled.into()),
+            do_process(
+                crate::instruction::purchase_ticket(
+                    &program_id,
+                    lottery_id,
+                    0,
+                    &user_wallet,
+                    &[10, 20, 30, 40, 50, 29],
+                    user_ticket_nonce + 1,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ],
+            )
+        );
+
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::refund_ticket(
+                    &program_id,
+                    lottery_id,
+                    &user_wallet,
+                    user_ticket_nonce,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut draw_clock_sysvar_acc,
+                    &mut system_acc
+                ]
+            )
+        );
+        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
+        assert_eq!(lottery.prize_pool_amount, 0);
+        let ticket = TicketData::unpack_unchecked(&user_ticket_acc.data).unwrap();
+        assert_eq!(ticket.is_refunded, true);
+    }
+
+    #[test]
+    fn test_lifetime_ticket() {
+        let program_id = id();
+        let lottery_id = 223344;
+        let payer_key = Pubkey::new_unique();
+        let mut payer_acc = SolanaAccount::default();
+        let authority_key = Pubkey::new_unique();
+        let mut authority_acc = SolanaAccount::default();
+        let mut lottery_acc = SolanaAccount::new(
+            lottery_minimum_balance(),
+            LotteryData::get_packed_len(),
+            &program_id,
+        );
+        let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
+        let mut clock_sysvar_acc = clock_account(SALE_START);
+        let mut system_acc = SolanaAccount::default();
+        let mut charity_registry_acc = charity_registry_account(&[Pubkey::new_unique()]);
+        let mut program_data_acc = program_data_account(&authority_key);
+        // Transfers are not executed here, the vault holds the ticket money up front
+        let mut vault_acc = SolanaAccount::new(sol_to_lamports(10.0), 0, &Pubkey::default());
+        let mut spl_token_acc = SolanaAccount::default();
+        let user_wallet = Pubkey::new_unique();
+        let mut user_wallet_acc = SolanaAccount::default();
+        let mut user_ticket_acc = SolanaAccount::new(
+            ticket_minimum_balance(),
+            TicketData::get_packed_len(),
+            &program_id,
+        );
+
+        let (lifetime_ticket_authority_key, _) =
+            find_lifetime_ticket_authority_address(&program_id);
+        let mut lifetime_ticket_authority_acc = SolanaAccount::default();
+        let lifetime_ticket_mint_key = Pubkey::new_unique();
+        let mut lifetime_ticket_mint_acc =
+            SolanaAccount::new(mint_minimum_balance(), Mint::LEN, &spl_token::id());
+        Mint::pack(
+            Mint {
+                mint_authority: COption::Some(lifetime_ticket_authority_key),
+                is_initialized: true,
+                ..Default::default()
+            },
+            &mut lifetime_ticket_mint_acc.data,
+        )
+        .unwrap();
+        let foreign_mint_key = Pubkey::new_unique();
+        let mut foreign_mint_acc =
+            SolanaAccount::new(mint_minimum_balance(), Mint::LEN, &spl_token::id());
+        Mint::pack(
+            Mint {
+                mint_authority: COption::Some(Pubkey::new_unique()),
+                is_initialized: true,
+                ..Default::default()
+            },
+            &mut foreign_mint_acc.data,
+        )
+        .unwrap();
+        let user_lifetime_ticket_key = Pubkey::new_unique();
+        let mut user_lifetime_ticket_acc =
+            SolanaAccount::new(account_minimum_balance(), Account::LEN, &spl_token::id());
+        Account::pack(
+            Account {
+                mint: lifetime_ticket_mint_key,
+                owner: user_wallet,
+                state: spl_token::state::AccountState::Initialized,
+                ..Default::default()
+            },
+            &mut user_lifetime_ticket_acc.data,
+        )
+        .unwrap();
+        let mut other_lifetime_ticket_acc = user_lifetime_ticket_acc.clone();
+        Account::pack(
+            Account {
+                mint: lifetime_ticket_mint_key,
+                owner: Pubkey::new_unique(),
+                state: spl_token::state::AccountState::Initialized,
+                ..Default::default()
+            },
+            &mut other_lifetime_ticket_acc.data,
+        )
+        .unwrap();
+
+        do_process(
+            crate::instruction::initialize_lottery(
+                &program_id,
+                lottery_id,
+                &[0],
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                SALE_START,
+                SALE_END,
+                DRAW_TIME,
+                CLAIM_PERIOD,
+                MAIN_POOL_SIZE,
+                BONUS_POOL_SIZE,
+                PICK_COUNT,
+                TICKET_PRICE,
+                CharityMode::WinnerTakesAll,
+                RandomnessProvider::SwitchboardVrf,
+                &[0; 32],
+                None,
+                &payer_key,
+                &authority_key,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut payer_acc,
+                &mut authority_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut charity_registry_acc,
+                &mut vault_acc,
+                &mut program_data_acc,
+            ],
+        )
+        .unwrap();
+        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
+        assert_eq!(lottery.has_lifetime_ticket(), false);
+
+        // BadCase: Lifetime Ticket mint is set by another authority
+        assert_eq!(
+            Err(LotteryError::InvalidAuthority.into()),
+            do_process(
+                crate::instruction::set_lifetime_ticket(
+                    &program_id,
+                    lottery_id,
+                    Some(&lifetime_ticket_mint_key),
+                    1,
+                    &payer_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut payer_acc,
+                    &mut lifetime_ticket_mint_acc
+                ]
+            )
+        );
+
+        // BadCase: Lifetime Ticket mint is not minted by the Lifetime Ticket authority
+        assert_eq!(
+            Err(LotteryError::InvalidLifetimeTicketMint.into()),
+            do_process(
+                crate::instruction::set_lifetime_ticket(
+                    &program_id,
+                    lottery_id,
+                    Some(&foreign_mint_key),
+                    1,
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![&mut lottery_acc, &mut authority_acc, &mut foreign_mint_acc]
+            )
+        );
+
+        // BadCase: No Lifetime Tickets are minted per line
+        assert_eq!(
+            Err(LotteryError::InvalidLifetimeTicketMint.into()),
+            do_process(
+                crate::instruction::set_lifetime_ticket(
+                    &program_id,
+                    lottery_id,
+                    Some(&lifetime_ticket_mint_key),
+                    0,
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut lifetime_ticket_mint_acc
+                ]
+            )
+        );
+
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::set_lifetime_ticket(
+                    &program_id,
+                    lottery_id,
+                    Some(&lifetime_ticket_mint_key),
+                    2,
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut lifetime_ticket_mint_acc
+                ]
+            )
+        );
+        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
+        assert_eq!(lottery.lifetime_ticket_mint, lifetime_ticket_mint_key);
+        assert_eq!(lottery.lifetime_ticket_amount, 2);
+
+        user_wallet_acc.lamports += sol_to_lamports(0.2) + ticket_minimum_balance();
+        let instruction = crate::instruction::purchase_tickets(
+            &program_id,
+            lottery_id,
+            0,
+            &user_wallet,
+            &[&[10, 20, 30, 40, 50, 29], &[11, 21, 31, 41, 51, 28]],
+            0,
+            Some((&lifetime_ticket_mint_key, &user_lifetime_ticket_key)),
+            None,
+            None,
+        )
+        .unwrap();
+
+        // BadCase: Lifetime Ticket accounts are missing
+        assert_eq!(
+            Err(ProgramError::NotEnoughAccountKeys),
+            do_process(
+                crate::instruction::purchase_tickets(
+                    &program_id,
+                    lottery_id,
+                    0,
+                    &user_wallet,
+                    &[&[10, 20, 30, 40, 50, 29], &[11, 21, 31, 41, 51, 28]],
+                    0,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ],
+            )
+        );
+
+        // BadCase: Lifetime Tickets of another mint
+        let mut bad_instruction = instruction.clone();
+        bad_instruction.accounts[9].pubkey = foreign_mint_key;
+        assert_eq!(
+            Err(LotteryError::InvalidAccountAddress.into()),
+            do_process(
+                bad_instruction,
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                    &mut user_lifetime_ticket_acc,
+                    &mut foreign_mint_acc,
+                    &mut lifetime_ticket_authority_acc,
+                ],
+            )
+        );
+
+        // BadCase: Lifetime Tickets are minted to another user
+        assert_eq!(
+            Err(LotteryError::InvalidTokenAccount.into()),
+            do_process(
+                instruction.clone(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                    &mut other_lifetime_ticket_acc,
+                    &mut lifetime_ticket_mint_acc,
+                    &mut lifetime_ticket_authority_acc,
+                ],
+            )
+        );
+
+        assert_eq!(
+            Ok(()),
+            do_process(
+                instruction,
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                    &mut user_lifetime_ticket_acc,
+                    &mut lifetime_ticket_mint_acc,
+                    &mut lifetime_ticket_authority_acc,
+                ],
+            )
+        );
+        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
+        assert_eq!(lottery.total_registrations, 1);
+
+        // Lifetime Tickets are not minted anymore
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::set_lifetime_ticket(
+                    &program_id,
+                    lottery_id,
+                    None,
+                    0,
+                    &authority_key
+                )
+                .unwrap(),
+                vec![&mut lottery_acc, &mut authority_acc]
+            )
+        );
+        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
+        assert_eq!(lottery.has_lifetime_ticket(), false);
+        assert_eq!(lottery.lifetime_ticket_amount, 0);
+    }
+
+    #[test]
+    fn test_referral_rewards() {
+        let program_id = id();
+        let lottery_id = 112233;
+        let payer_key = Pubkey::new_unique();
+        let mut payer_acc = SolanaAccount::default();
+        let authority_key = Pubkey::new_unique();
+        let mut authority_acc = SolanaAccount::default();
+        let mut lottery_acc = SolanaAccount::new(
+            lottery_minimum_balance(),
+            LotteryData::get_packed_len(),
+            &program_id,
+        );
+        let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
+        let mut clock_sysvar_acc = clock_account(SALE_START);
+        let mut system_acc = SolanaAccount::default();
+        let mut charity_registry_acc = charity_registry_account(&[Pubkey::new_unique()]);
+        let mut program_data_acc = program_data_account(&authority_key);
+        // Transfers are not executed here, the vault holds the ticket money up front
+        let mut vault_acc = SolanaAccount::new(sol_to_lamports(10.0), 0, &Pubkey::default());
+        let mut spl_token_acc = SolanaAccount::default();
+        let user_wallet = Pubkey::new_unique();
+        let mut user_wallet_acc = SolanaAccount::default();
+        let user_ticket_nonce = 0;
+        let mut user_ticket_acc = SolanaAccount::new(
+            ticket_minimum_balance(),
+            TicketData::get_packed_len(),
+            &program_id,
+        );
+        let referrer_wallet = Pubkey::new_unique();
+        let mut referrer_wallet_acc = SolanaAccount::default();
+        // Referral ledger is created by the purchase, it holds the referral share up front
+        let referral_amount = TICKET_PRICE * 2 * 500 / 10_000;
+        let mut referral_acc = SolanaAccount::new(
+            referral_minimum_balance() + referral_amount,
+            ReferralData::get_packed_len(),
+            &program_id,
+        );
+        let mut user_referral_acc = referral_acc.clone();
+
+        do_process(
+            crate::instruction::initialize_lottery(
+                &program_id,
+                lottery_id,
+                &[0],
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                SALE_START,
+                SALE_END,
+                DRAW_TIME,
+                CLAIM_PERIOD,
+                MAIN_POOL_SIZE,
+                BONUS_POOL_SIZE,
+                PICK_COUNT,
+                TICKET_PRICE,
+                CharityMode::WinnerTakesAll,
+                RandomnessProvider::SwitchboardVrf,
+                &[0; 32],
+                None,
+                &payer_key,
+                &authority_key,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut payer_acc,
+                &mut authority_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut charity_registry_acc,
+                &mut vault_acc,
+                &mut program_data_acc,
+            ],
+        )
+        .unwrap();
+
+        user_wallet_acc.lamports +=
+            TICKET_PRICE * 2 + ticket_minimum_balance() + referral_minimum_balance();
+        let ticket_numbers: [&[u8]; 2] = [&[10, 20, 30, 40, 50, 29], &[1, 2, 3, 4, 5, 6]];
+
+        // BadCase: Lottery doesn't accept referrals
+        assert_eq!(
+            Err(LotteryError::InvalidReferrer.into()),
+            do_process(
+                crate::instruction::purchase_tickets(
+                    &program_id,
+                    lottery_id,
+                    0,
+                    &user_wallet,
+                    &ticket_numbers,
+                    user_ticket_nonce,
+                    None,
+                    None,
+                    Some(&referrer_wallet),
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                    &mut referrer_wallet_acc,
+                    &mut referral_acc,
+                ],
+            )
+        );
+
+        // BadCase: Referral share is over the limit
+        assert_eq!(
+            Err(LotteryError::InvalidShares.into()),
+            do_process(
+                crate::instruction::set_referral_share(
+                    &program_id,
+                    lottery_id,
+                    MAX_REFERRAL_SHARE_BPS + 1,
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![&mut lottery_acc, &mut authority_acc],
+            )
+        );
+
+        do_process(
+            crate::instruction::set_referral_share(&program_id, lottery_id, 500, &authority_key)
+                .unwrap(),
+            vec![&mut lottery_acc, &mut authority_acc],
+        )
+        .unwrap();
+
+        // BadCase: User refers own ticket
+        assert_eq!(
+            Err(LotteryError::InvalidReferrer.into()),
+            do_process(
+                crate::instruction::purchase_tickets(
+                    &program_id,
+                    lottery_id,
+                    0,
+                    &user_wallet,
+                    &ticket_numbers,
+                    user_ticket_nonce,
+                    None,
+                    None,
+                    Some(&user_wallet),
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc.clone(),
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                    &mut user_wallet_acc,
+                    &mut user_referral_acc,
+                ],
+            )
+        );
+
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::purchase_tickets(
+                    &program_id,
+                    lottery_id,
+                    0,
+                    &user_wallet,
+                    &ticket_numbers,
+                    user_ticket_nonce,
+                    None,
+                    None,
+                    Some(&referrer_wallet),
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                    &mut referrer_wallet_acc,
+                    &mut referral_acc,
+                ],
+            )
+        );
+        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
+        assert_eq!(
+            lottery.prize_pool_amount,
+            TICKET_PRICE * 2 - referral_amount
+        );
+        assert_eq!(lottery.referral_volume, TICKET_PRICE * 2);
+        assert_eq!(lottery.referral_rewards, referral_amount);
+        let ticket = TicketData::unpack_unchecked(&user_ticket_acc.data).unwrap();
+        assert_eq!(ticket.referral_amount, referral_amount);
+        let referral = ReferralData::unpack(&referral_acc.data).unwrap();
+        assert_eq!(referral.referrer, referrer_wallet);
+        assert_eq!(referral.accrued_amount, referral_amount);
+
+        // BadCase: Rewards are claimed by another wallet
+        let mut lottery_result_acc = SolanaAccount::default();
+        assert_eq!(
+            Err(LotteryError::InvalidReferrer.into()),
+            do_process(
+                crate::instruction::claim_referral_rewards(&program_id, lottery_id, &user_wallet)
+                    .unwrap(),
+                vec![
+                    &mut referral_acc,
+                    &mut user_wallet_acc,
+                    &mut lottery_result_acc
+                ],
+            )
+        );
+
+        // BadCase: Rewards are claimed before the draw is settled
+        assert_eq!(
+            Err(LotteryError::NotFinaled.into()),
+            do_process(
+                crate::instruction::claim_referral_rewards(
+                    &program_id,
+                    lottery_id,
+                    &referrer_wallet
+                )
+                .unwrap(),
+                vec![
+                    &mut referral_acc,
+                    &mut referrer_wallet_acc,
+                    &mut lottery_result_acc
+                ],
+            )
+        );
+
+        // Second ticket of the draw is referred by the same referrer
+        let other_wallet = Pubkey::new_unique();
+        let mut other_wallet_acc = SolanaAccount::default();
+        other_wallet_acc.lamports += TICKET_PRICE * 2 + ticket_minimum_balance();
+        let mut other_ticket_acc = SolanaAccount::new(
+            ticket_minimum_balance(),
+            TicketData::get_packed_len(),
+            &program_id,
+        );
+        referral_acc.lamports += referral_amount;
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::purchase_tickets(
+                    &program_id,
+                    lottery_id,
+                    0,
+                    &other_wallet,
+                    &ticket_numbers,
+                    0,
+                    None,
+                    None,
+                    Some(&referrer_wallet),
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut other_ticket_acc,
+                    &mut other_wallet_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                    &mut referrer_wallet_acc,
+                    &mut referral_acc,
+                ],
+            )
+        );
+        let ticket = TicketData::unpack_unchecked(&other_ticket_acc.data).unwrap();
+        assert_eq!(ticket.referrer, referrer_wallet);
+        let referral = ReferralData::unpack(&referral_acc.data).unwrap();
+        assert_eq!(referral.accrued_amount, referral_amount * 2);
+
+        do_process(
+            crate::instruction::cancel_lottery(&program_id, lottery_id, &authority_key).unwrap(),
+            vec![&mut lottery_acc, &mut authority_acc],
+        )
+        .unwrap();
+
+        // BadCase: Referral ledger of the ticket referrer is missing
+        assert_eq!(
+            Err(ProgramError::NotEnoughAccountKeys),
+            do_process(
+                crate::instruction::refund_ticket(
+                    &program_id,
+                    lottery_id,
+                    &user_wallet,
+                    0,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                ],
+            )
+        );
+
+        // BadCase: Referral share is taken back from another ledger
+        assert_eq!(
+            Err(LotteryError::InvalidAccountAddress.into()),
+            do_process(
+                crate::instruction::refund_ticket(
+                    &program_id,
+                    lottery_id,
+                    &user_wallet,
+                    0,
+                    None,
+                    Some(&user_wallet),
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut user_referral_acc,
+                ],
+            )
+        );
+
+        // Full ticket price is refunded, the referral share is taken back from the ledger
+        let user_balance = user_wallet_acc.lamports;
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::refund_ticket(
+                    &program_id,
+                    lottery_id,
+                    &user_wallet,
+                    0,
+                    None,
+                    Some(&referrer_wallet),
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut referral_acc,
+                ],
+            )
+        );
+        assert_eq!(user_wallet_acc.lamports, user_balance + referral_amount);
+        assert_eq!(
+            referral_acc.lamports,
+            referral_minimum_balance() + referral_amount
+        );
+        let referral = ReferralData::unpack(&referral_acc.data).unwrap();
+        assert_eq!(referral.accrued_amount, referral_amount);
+        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
+        assert_eq!(
+            lottery.prize_pool_amount,
+            TICKET_PRICE * 2 - referral_amount
+        );
+        assert_eq!(lottery.referral_volume, TICKET_PRICE * 2);
+        assert_eq!(lottery.referral_rewards, referral_amount);
+
+        let other_balance = other_wallet_acc.lamports;
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::refund_ticket(
+                    &program_id,
+                    lottery_id,
+                    &other_wallet,
+                    0,
+                    None,
+                    Some(&referrer_wallet),
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut other_ticket_acc,
+                    &mut other_wallet_acc,
+                    &mut vault_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut referral_acc,
+                ],
+            )
+        );
+        assert_eq!(other_wallet_acc.lamports, other_balance + referral_amount);
+        assert_eq!(referral_acc.lamports, referral_minimum_balance());
+        let referral = ReferralData::unpack(&referral_acc.data).unwrap();
+        assert_eq!(referral.accrued_amount, 0);
+        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
+        assert_eq!(lottery.prize_pool_amount, 0);
+        assert_eq!(lottery.referral_volume, 0);
+        assert_eq!(lottery.referral_rewards, 0);
+
+        // BadCase: Rewards of the refunded tickets are not claimed
+        lottery_result_acc.owner = program_id;
+        assert_eq!(
+            Err(LotteryError::NothingToClaim.into()),
+            do_process(
+                crate::instruction::claim_referral_rewards(
+                    &program_id,
+                    lottery_id,
+                    &referrer_wallet
+                )
+                .unwrap(),
+                vec![
+                    &mut referral_acc,
+                    &mut referrer_wallet_acc,
+                    &mut lottery_result_acc
+                ],
+            )
+        );
+    }
+
+    #[test]
+    fn test_token_lottery() {
+        let program_id = id();
+        let lottery_id = 445566;
+        let (lottery_key, _) = find_lottery_address(&program_id, lottery_id);
+        let payer_key = Pubkey::new_unique();
+        let mut payer_acc = SolanaAccount::default();
+        let authority_key = Pubkey::new_unique();
+        let mut authority_acc = SolanaAccount::default();
+        let mut lottery_acc = SolanaAccount::new(
+            lottery_minimum_balance(),
+            LotteryData::get_packed_len(),
+            &program_id,
+        );
+        let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
+        let mut clock_sysvar_acc = clock_account(SALE_START);
+        let mut draw_clock_sysvar_acc = clock_account(DRAW_TIME);
+        let mut system_acc = SolanaAccount::default();
+        let mut charity_registry_acc = charity_registry_account(&[Pubkey::new_unique()]);
+        let mut program_data_acc = program_data_account(&authority_key);
+        let (lamports_vault_key, _) = find_vault_address(&program_id, &lottery_key);
+        let mut lamports_vault_acc = SolanaAccount::default();
+
+        let mint_key = Pubkey::new_unique();
+        let mut mint_acc = SolanaAccount::new(mint_minimum_balance(), Mint::LEN, &spl_token::id());
+        Mint::pack(
+            Mint {
+                is_initialized: true,
+                decimals: 6,
+                ..Default::default()
+            },
+            &mut mint_acc.data,
+        )
+        .unwrap();
+        let (vault_key, _) = find_token_vault_address(&program_id, &lottery_key);
+        let mut vault_acc =
+            SolanaAccount::new(account_minimum_balance(), Account::LEN, &spl_token::id());
+        let (vault_authority_key, _) = find_vault_authority_address(&program_id);
+        let mut vault_authority_acc = SolanaAccount::default();
+        let mut spl_token_acc = SolanaAccount::default();
+
+        let user_wallet = Pubkey::new_unique();
+        let mut user_wallet_acc = SolanaAccount::default();
+        let user_ticket_nonce = 0;
+        let mut user_ticket_acc = SolanaAccount::new(
+            ticket_minimum_balance(),
+            TicketData::get_packed_len(),
+            &program_id,
+        );
+        let user_token_key = Pubkey::new_unique();
+        let mut user_token_acc =
+            SolanaAccount::new(account_minimum_balance(), Account::LEN, &spl_token::id());
+        let token_account = Account {
+            mint: mint_key,
+            owner: user_wallet,
+            amount: TICKET_PRICE,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        };
+        Account::pack(token_account, &mut user_token_acc.data).unwrap();
+
+        do_process(
+            crate::instruction::initialize_lottery(
+                &program_id,
+                lottery_id,
+                &[0],
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                SALE_START,
+                SALE_END,
+                DRAW_TIME,
+                CLAIM_PERIOD,
+                MAIN_POOL_SIZE,
+                BONUS_POOL_SIZE,
+                PICK_COUNT,
+                TICKET_PRICE,
+                CharityMode::WinnerTakesAll,
+                RandomnessProvider::SwitchboardVrf,
+                &[0; 32],
+                None,
+                &payer_key,
+                &authority_key,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut payer_acc,
+                &mut authority_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut charity_registry_acc,
+                &mut lamports_vault_acc,
+                &mut program_data_acc,
+            ],
+        )
+        .unwrap();
+
+        // BadCase: Vault is created by another authority
+        assert_eq!(
+            Err(LotteryError::InvalidAuthority.into()),
+            do_process(
+                crate::instruction::init_vault(
+                    &program_id,
+                    lottery_id,
+                    &mint_key,
+                    &payer_key,
+                    &Pubkey::new_unique()
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut vault_acc,
+                    &mut mint_acc,
+                    &mut vault_authority_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        // BadCase: Mint is not a token program account
+        let mut bad_mint_acc = SolanaAccount::new(mint_minimum_balance(), Mint::LEN, &program_id);
+        assert_eq!(
+            Err(ProgramError::IncorrectProgramId),
+            do_process(
+                crate::instruction::init_vault(
+                    &program_id,
+                    lottery_id,
+                    &mint_key,
+                    &payer_key,
+                    &authority_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut vault_acc,
+                    &mut bad_mint_acc,
+                    &mut vault_authority_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::set_referral_share(
+                    &program_id,
+                    lottery_id,
+                    500,
+                    &authority_key
+                )
+                .unwrap(),
+                vec![&mut lottery_acc, &mut authority_acc],
+            )
+        );
+        // BadCase: Lottery with a referral share can't take a token vault
+        assert_eq!(
+            Err(LotteryError::InvalidShares.into()),
+            do_process(
+                crate::instruction::init_vault(
+                    &program_id,
+                    lottery_id,
+                    &mint_key,
+                    &payer_key,
+                    &authority_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut vault_acc,
+                    &mut mint_acc,
+                    &mut vault_authority_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::set_referral_share(&program_id, lottery_id, 0, &authority_key)
+                    .unwrap(),
+                vec![&mut lottery_acc, &mut authority_acc],
+            )
+        );
+
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::init_vault(
+                    &program_id,
+                    lottery_id,
+                    &mint_key,
+                    &payer_key,
+                    &authority_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut vault_acc,
+                    &mut mint_acc,
+                    &mut vault_authority_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
+        assert_eq!(lottery.mint, mint_key);
+        assert_eq!(lottery.vault, vault_key);
+        assert!(lottery.is_token_lottery());
+        // Vault is initialized by the token program
+        Account::pack(
+            Account {
+                mint: mint_key,
+                owner: vault_authority_key,
+                state: spl_token::state::AccountState::Initialized,
+                ..Default::default()
+            },
+            &mut vault_acc.data,
+        )
+        .unwrap();
+
+        // BadCase: Token lottery doesn't accept referrals
+        assert_eq!(
+            Err(LotteryError::InvalidShares.into()),
+            do_process(
+                crate::instruction::set_referral_share(
+                    &program_id,
+                    lottery_id,
+                    500,
+                    &authority_key
+                )
+                .unwrap(),
+                vec![&mut lottery_acc, &mut authority_acc],
+            )
+        );
+
+        // BadCase: Ticket currency is changed
+        assert_eq!(
+            Err(LotteryError::InvalidVault.into()),
+            do_process(
+                crate::instruction::init_vault(
+                    &program_id,
+                    lottery_id,
+                    &mint_key,
+                    &payer_key,
+                    &authority_key
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut vault_acc,
+                    &mut mint_acc,
+                    &mut vault_authority_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        let purchase = |vault_key: &Pubkey, user_token_key: &Pubkey| {
+            crate::instruction::purchase_ticket(
+                &program_id,
+                lottery_id,
+                0,
+                &user_wallet,
+                &[10, 20, 30, 40, 50, 29],
+                user_ticket_nonce,
+                None,
+                Some((vault_key, user_token_key)),
+                None,
+            )
+            .unwrap()
+        };
+        // Ticket price is paid in tokens, only the rent in lamports
+        user_wallet_acc.lamports += ticket_minimum_balance();
+
+        // BadCase: Ticket price is paid into the lamports vault
+        assert_eq!(
+            Err(LotteryError::InvalidVault.into()),
+            do_process(
+                purchase(&lamports_vault_key, &user_token_key),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut lamports_vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                    &mut user_token_acc,
+                ],
+            )
+        );
+
+        // BadCase: Ticket price is paid with tokens of another mint
+        let mut other_mint_token_acc = user_token_acc.clone();
+        Account::pack(
+            Account {
+                mint: Pubkey::new_unique(),
+                ..token_account
+            },
+            &mut other_mint_token_acc.data,
+        )
+        .unwrap();
+        assert_eq!(
+            Err(LotteryError::InvalidTokenAccount.into()),
+            do_process(
+                purchase(&vault_key, &user_token_key),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                    &mut other_mint_token_acc,
+                ],
+            )
+        );
+
+        // BadCase: User token account can't pay for the ticket
+        let mut poor_token_acc = user_token_acc.clone();
+        Account::pack(
+            Account {
+                amount: TICKET_PRICE - 1,
+                ..token_account
+            },
+            &mut poor_token_acc.data,
+        )
+        .unwrap();
+        assert_eq!(
+            Err(ProgramError::InsufficientFunds),
+            do_process(
+                purchase(&vault_key, &user_token_key),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                    &mut poor_token_acc,
+                ],
+            )
+        );
+
+        // Token transfer is not executed here, the vault already holds the ticket price
+        let mut vault = Account::unpack(&vault_acc.data).unwrap();
+        vault.amount = TICKET_PRICE;
+        Account::pack(vault, &mut vault_acc.data).unwrap();
+        assert_eq!(
+            Ok(()),
+            do_process(
+                purchase(&vault_key, &user_token_key),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                    &mut user_token_acc,
+                ],
+            )
+        );
+        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
+        assert_eq!(lottery.prize_pool_amount, TICKET_PRICE);
+        let purchased_ticket_acc = user_ticket_acc.clone();
+
+        // Winner claims the prize from the vault
+        let mut settlement_acc = SolanaAccount::new(
+            settlement_minimum_balance(),
+            SettlementData::get_packed_len(),
+            &program_id,
+        );
+        SettlementData::pack(
+            SettlementData {
+                is_initialized: true,
+                lottery_id,
+                claim_deadline: DRAW_TIME + CLAIM_PERIOD,
+                escrowed_amount: TICKET_PRICE,
+                vault: vault_key,
+                ..Default::default()
+            },
+            &mut settlement_acc.data,
+        )
+        .unwrap();
+        let mut ticket = TicketData::unpack_unchecked(&user_ticket_acc.data).unwrap();
+        ticket.is_paid = true;
+        ticket.prize_amount = TICKET_PRICE;
+        TicketData::pack(ticket, &mut user_ticket_acc.data).unwrap();
+        let claim = |vault_key: &Pubkey, vault_authority_key: &Pubkey| {
+            let mut instruction = crate::instruction::claim_prize(
+                &program_id,
+                lottery_id,
+                &user_wallet,
+                0,
+                Some((vault_key, &user_token_key)),
+            )
+            .unwrap();
+            instruction.accounts[6].pubkey = *vault_authority_key;
+            instruction
+        };
+
+        // BadCase: Prize is claimed from another vault
+        let mut other_vault_acc = vault_acc.clone();
+        assert_eq!(
+            Err(LotteryError::InvalidAccountAddress.into()),
+            do_process(
+                claim(&Pubkey::new_unique(), &vault_authority_key),
+                vec![
+                    &mut settlement_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut draw_clock_sysvar_acc,
+                    &mut other_vault_acc,
+                    &mut user_token_acc,
+                    &mut vault_authority_acc,
+                    &mut spl_token_acc,
+                ],
+            )
+        );
+
+        // BadCase: Vault transfer is signed by another authority
+        assert_eq!(
+            Err(LotteryError::InvalidAccountAddress.into()),
+            do_process(
+                claim(&vault_key, &Pubkey::new_unique()),
+                vec![
+                    &mut settlement_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut draw_clock_sysvar_acc,
+                    &mut vault_acc,
+                    &mut user_token_acc,
+                    &mut vault_authority_acc,
+                    &mut spl_token_acc,
+                ],
+            )
+        );
+
+        assert_eq!(
+            Ok(()),
+            do_process(
+                claim(&vault_key, &vault_authority_key),
+                vec![
+                    &mut settlement_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut draw_clock_sysvar_acc,
+                    &mut vault_acc,
+                    &mut user_token_acc,
+                    &mut vault_authority_acc,
+                    &mut spl_token_acc,
+                ],
+            )
+        );
+        let settlement = SettlementData::unpack(&settlement_acc.data).unwrap();
+        assert_eq!(settlement.claimed_amount, TICKET_PRICE);
+        // Lamports are not moved out of the settlement account
+        assert_eq!(settlement_acc.lamports, settlement_minimum_balance());
+
+        // Cancelled token lottery refunds the tickets from the vault
+        let mut user_ticket_acc = purchased_ticket_acc;
+        do_process(
+            crate::instruction::cancel_lottery(&program_id, lottery_id, &authority_key).unwrap(),
+            vec![&mut lottery_acc, &mut authority_acc],
+        )
+        .unwrap();
+        let refund = |refund_token_key: &Pubkey| {
+            crate::instruction::refund_ticket(
+                &program_id,
+                lottery_id,
+                &user_wallet,
+                user_ticket_nonce,
+                Some((&vault_key, refund_token_key)),
+                None,
+            )
+            .unwrap()
+        };
+
+        // BadCase: Refund is paid into a token account of another wallet
+        let other_token_key = Pubkey::new_unique();
+        let mut other_token_acc = user_token_acc.clone();
+        Account::pack(
+            Account {
+                owner: Pubkey::new_unique(),
+                ..token_account
+            },
+            &mut other_token_acc.data,
+        )
+        .unwrap();
+        assert_eq!(
+            Err(LotteryError::InvalidTokenAccount.into()),
+            do_process(
+                refund(&other_token_key),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut draw_clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut other_token_acc,
+                    &mut vault_authority_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+
+        assert_eq!(
+            Ok(()),
+            do_process(
+                refund(&user_token_key),
+                vec![
+                    &mut lottery_acc,
+                    &mut user_ticket_acc,
+                    &mut user_wallet_acc,
+                    &mut vault_acc,
+                    &mut draw_clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut user_token_acc,
+                    &mut vault_authority_acc,
+                    &mut spl_token_acc,
+                ]
+            )
+        );
+        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
+        assert_eq!(lottery.prize_pool_amount, 0);
+        let ticket = TicketData::unpack_unchecked(&user_ticket_acc.data).unwrap();
+        assert_eq!(ticket.is_refunded, true);
+    }
+
+    #[test]
+    fn test_reward_winners() {
+        let program_id = id();
+        let lottery_id = 112233;
+        let (lottery_key, _) = find_lottery_address(&program_id, lottery_id);
+        let payer_key = Pubkey::new_unique();
+        let mut payer_acc = SolanaAccount::default();
+        let authority_key = Pubkey::new_unique();
+        let mut authority_acc = SolanaAccount::default();
+        let mut lottery_acc = SolanaAccount::new(
+            lottery_minimum_balance(),
+            LotteryData::get_packed_len(),
+            &program_id,
+        );
+        let mut lottery_result_acc = SolanaAccount::new(
+            lottery_result_minimum_balance(),
+            LotteryResultData::get_packed_len(),
+            &program_id,
+        );
+        let mut settlement_acc = SolanaAccount::new(
+            settlement_minimum_balance(),
+            SettlementData::get_packed_len(),
+            &program_id,
+        );
+        let mut prize_table_acc = SolanaAccount::new(
+            prize_table_minimum_balance(),
+            PrizeTableData::get_packed_len(),
+            &program_id,
+        );
+        let mut system_acc = SolanaAccount::default();
+        let mut spl_token_acc = SolanaAccount::default();
+        let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
+        let mut clock_sysvar_acc = clock_account(SALE_START);
+        let charity_1 = Pubkey::new_unique();
+        let mut charity_1_acc = SolanaAccount::default();
+        let charity_2 = Pubkey::new_unique();
+        let mut charity_2_acc = SolanaAccount::default();
+        let charity_3 = Pubkey::new_unique();
+        let mut charity_3_acc = SolanaAccount::default();
+        let charity_4 = Pubkey::new_unique();
+        let mut charity_registry_acc =
+            charity_registry_account(&[charity_1, charity_2, charity_3, charity_4]);
+        let mut program_data_acc = program_data_account(&authority_key);
+        let mut charity_4_acc = SolanaAccount::default();
+        // Transfers are not executed here, the vault holds the ticket money up front
+        let mut vault_acc = SolanaAccount::new(sol_to_lamports(10.0), 0, &Pubkey::default());
+        let rewards_wallet = Pubkey::new_unique();
+        let mut rewards_wallet_acc = SolanaAccount::default();
+        let slot_holders_rewards_wallet = Pubkey::new_unique();
+        let mut slot_holders_rewards_wallet_acc = SolanaAccount::default();
+        let sollotto_labs_wallet = Pubkey::new_unique();
+        let mut sollotto_labs_wallet_acc = SolanaAccount::default();
+
+        let randomness_account = Pubkey::new_unique();
+
+        let user1_wallet = Pubkey::new_unique();
+        let mut user1_wallet_acc = SolanaAccount::default();
+        let user1_ticket_nonce = 0;
+        let (user1_ticket, _) =
+            find_ticket_address(&program_id, &lottery_key, &user1_wallet, user1_ticket_nonce);
+        let mut user1_ticket_acc = SolanaAccount::new(
+            ticket_minimum_balance(),
+            TicketData::get_packed_len(),
+            &program_id,
+        );
+        let user2_wallet = Pubkey::new_unique();
+        let mut user2_wallet_acc = SolanaAccount::default();
+        let user2_ticket_nonce = 0;
+        let (user2_ticket, _) =
+            find_ticket_address(&program_id, &lottery_key, &user2_wallet, user2_ticket_nonce);
+        let mut user2_ticket_acc = SolanaAccount::new(
+            ticket_minimum_balance(),
+            TicketData::get_packed_len(),
+            &program_id,
+        );
+
+        // BadCase: Lottery is not initialized
+        assert_eq!(
+            Err(LotteryError::NotInitialized.into()),
+            do_process(
+                crate::instruction::reward_winners(
+                    &program_id,
+                    lottery_id,
+                    &payer_key,
+                    &authority_key,
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &sollotto_labs_wallet,
+                    &[charity_1, charity_2, charity_3, charity_4],
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut lottery_result_acc,
+                    &mut settlement_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut vault_acc,
+                    &mut rewards_wallet_acc,
+                    &mut slot_holders_rewards_wallet_acc,
+                    &mut sollotto_labs_wallet_acc,
+                    &mut charity_registry_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_1_acc,
+                    &mut charity_2_acc,
+                    &mut charity_3_acc,
+                    &mut charity_4_acc
+                ]
+            )
+        );
+
+        do_process(
+            crate::instruction::initialize_lottery(
+                &program_id,
+                lottery_id,
+                &[0, 1, 2, 3],
+                &rewards_wallet,
+                &slot_holders_rewards_wallet,
+                &sollotto_labs_wallet,
+                &randomness_account,
+                SALE_START,
+                SALE_END,
+                DRAW_TIME,
+                CLAIM_PERIOD,
+                MAIN_POOL_SIZE,
+                BONUS_POOL_SIZE,
+                PICK_COUNT,
+                TICKET_PRICE,
+                CharityMode::WinnerTakesAll,
+                RandomnessProvider::SwitchboardVrf,
+                &[0; 32],
+                None,
+                &payer_key,
+                &authority_key,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut payer_acc,
+                &mut authority_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut charity_registry_acc,
+                &mut vault_acc,
+                &mut program_data_acc,
+            ],
+        )
+        .unwrap();
+
+        // BadCase: tier doesn't fit the number format
+        assert_eq!(
+            Err(LotteryError::InvalidPrizeTable.into()),
+            do_process(
+                crate::instruction::set_prize_table(
+                    &program_id,
+                    lottery_id,
+                    &[PrizeTier {
+                        main_matches: 6,
+                        bonus_match: false,
+                        prize: Prize::Share(6_500),
+                    }],
+                    &payer_key,
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut prize_table_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc
+                ]
+            )
+        );
+
+        // BadCase: shares add up to more than 10000 basis points
+        let mut oversized_tiers = prize_tiers();
+        oversized_tiers[1].prize = Prize::Share(4_000);
+        assert_eq!(
+            Err(LotteryError::InvalidShares.into()),
+            do_process(
+                crate::instruction::set_prize_table(
+                    &program_id,
+                    lottery_id,
+                    &oversized_tiers,
+                    &payer_key,
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut prize_table_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc
+                ]
+            )
+        );
+
+        // BadCase: duplicated tier
+        let mut duplicated_tiers = prize_tiers();
+        duplicated_tiers[1].bonus_match = true;
+        assert_eq!(
+            Err(LotteryError::InvalidPrizeTable.into()),
+            do_process(
+                crate::instruction::set_prize_table(
+                    &program_id,
+                    lottery_id,
+                    &duplicated_tiers,
+                    &payer_key,
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut prize_table_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc
+                ]
+            )
+        );
+
+        do_process(
+            crate::instruction::set_prize_table(
+                &program_id,
+                lottery_id,
+                &prize_tiers(),
+                &payer_key,
+                &authority_key,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut prize_table_acc,
+                &mut payer_acc,
+                &mut authority_acc,
+                &mut rent_sysvar_acc,
+                &mut system_acc,
+            ],
+        )
+        .unwrap();
+        let prize_table = PrizeTableData::unpack(prize_table_acc.data()).unwrap();
+        assert_eq!(prize_table.lottery_id, lottery_id);
+        assert_eq!(prize_table.tiers, prize_tiers());
+
+        // BadCase: Lottery is not finaled
+        assert_eq!(
+            Err(LotteryError::NotFinaled.into()),
+            do_process(
+                crate::instruction::tally_batch(
+                    &program_id,
+                    lottery_id,
+                    &payer_key,
+                    &authority_key,
+                    &[user1_ticket, user2_ticket]
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut settlement_acc,
+                    &mut prize_table_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc,
+                    &mut user1_ticket_acc,
+                    &mut user2_ticket_acc
+                ]
+            )
+        );
+
+        // BadCase: user cannot pay for ticket
+        let user1_charity = 0;
+        assert_eq!(
+            Err(ProgramError::InsufficientFunds),
+            do_process(
+                crate::instruction::purchase_ticket(
+                    &program_id,
+                    lottery_id,
+                    user1_charity,
+                    &user1_wallet,
+                    &[1, 2, 3, 4, 55, 6],
+                    user1_ticket_nonce,
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut user1_ticket_acc,
+                    &mut user1_wallet_acc,
+                    &mut vault_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut spl_token_acc,
+                ],
+            )
+        );
+
+        // Purchase tickets
+        user1_wallet_acc.lamports += sol_to_lamports(0.1) + ticket_minimum_balance();
+        do_process(
+            crate::instruction::purchase_ticket(
+                &program_id,
+                lottery_id,
+                user1_charity,
+                &user1_wallet,
+                &[2, 3, 4, 44, 51, 1],
+                user1_ticket_nonce,
+                None,
+                None,
+                None,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut user1_ticket_acc,
+                &mut user1_wallet_acc,
+                &mut vault_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut spl_token_acc,
+            ],
+        )
+        .unwrap();
+
+        let user2_charity = 0;
+        user2_wallet_acc.lamports += sol_to_lamports(0.1) + ticket_minimum_balance();
+        do_process(
+            crate::instruction::purchase_ticket(
+                &program_id,
+                lottery_id,
+                user2_charity,
+                &user2_wallet,
+                &[2, 3, 4, 5, 66, 7],
+                user2_ticket_nonce,
+                None,
+                None,
+                None,
+            )
+            .unwrap(),
+            vec![
+                &mut lottery_acc,
+                &mut user2_ticket_acc,
+                &mut user2_wallet_acc,
+                &mut vault_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut spl_token_acc,
+            ],
+        )
+        .unwrap();
+
+        // Store winning numbers
+        finalize_lottery(&mut lottery_acc, &[2, 3, 4, 5, 66, 7]);
+
+        let lottery = LotteryData::unpack_unchecked(lottery_acc.data()).unwrap();
+        assert_eq!(lottery.total_registrations, 2);
+        assert_eq!(lottery.prize_pool_amount, sol_to_lamports(0.2));
+        assert_eq!(lottery.votes(), [2, 0, 0, 0]);
+        assert_eq!(lottery.winning_numbers, [2, 3, 4, 5, 66, 7, 0, 0]);
+
+        // BadCase: tiers can't change after the draw
+        assert_eq!(
+            Err(LotteryError::IsFinaled.into()),
+            do_process(
+                crate::instruction::set_prize_table(
+                    &program_id,
+                    lottery_id,
+                    &prize_tiers(),
+                    &payer_key,
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut prize_table_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc
+                ]
+            )
+        );
+
+        // BadCase: tickets are not tallied
+        assert_eq!(
+            Err(LotteryError::TallyNotComplete.into()),
+            do_process(
+                crate::instruction::reward_winners(
+                    &program_id,
+                    lottery_id,
+                    &payer_key,
+                    &authority_key,
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &sollotto_labs_wallet,
+                    &[charity_1, charity_2, charity_3, charity_4],
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut lottery_result_acc,
+                    &mut settlement_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut vault_acc,
+                    &mut rewards_wallet_acc,
+                    &mut slot_holders_rewards_wallet_acc,
+                    &mut sollotto_labs_wallet_acc,
+                    &mut charity_registry_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_1_acc,
+                    &mut charity_2_acc,
+                    &mut charity_3_acc,
+                    &mut charity_4_acc
+                ]
+            )
+        );
+
+        assert_eq!(
+            Err(LotteryError::TallyNotComplete.into()),
+            do_process(
+                crate::instruction::payout_batch(
+                    &program_id,
+                    lottery_id,
+                    &authority_key,
+                    &[user2_ticket],
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut settlement_acc,
+                    &mut prize_table_acc,
+                    &mut authority_acc,
+                    &mut vault_acc,
+                    &mut system_acc,
+                    &mut clock_sysvar_acc,
+                    &mut user2_ticket_acc
+                ]
+            )
+        );
+
+        // Tally tickets in two batches
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::tally_batch(
+                    &program_id,
+                    lottery_id,
+                    &payer_key,
+                    &authority_key,
+                    &[user1_ticket]
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut settlement_acc,
+                    &mut prize_table_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc,
+                    &mut user1_ticket_acc
+                ]
+            )
+        );
+
+        let settlement = SettlementData::unpack_unchecked(settlement_acc.data()).unwrap();
+        assert_eq!(settlement.is_initialized, true);
+        assert_eq!(settlement.lottery_id, lottery_id);
+        assert_eq!(settlement.tallied_count, 1);
+        let ticket = TicketData::unpack_unchecked(user1_ticket_acc.data()).unwrap();
+        assert_eq!(ticket.is_tallied, true);
+        assert_eq!(ticket.lines[0].matched, 3);
+
+        // BadCase: ticket is counted twice
+        assert_eq!(
+            Err(LotteryError::TicketAlreadyProcessed.into()),
+            do_process(
+                crate::instruction::tally_batch(
+                    &program_id,
+                    lottery_id,
+                    &payer_key,
+                    &authority_key,
+                    &[user1_ticket]
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut settlement_acc,
+                    &mut prize_table_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc,
+                    &mut user1_ticket_acc
+                ]
+            )
+        );
+
+        assert_eq!(
+            Err(LotteryError::TallyNotComplete.into()),
+            do_process(
+                crate::instruction::reward_winners(
+                    &program_id,
+                    lottery_id,
+                    &payer_key,
+                    &authority_key,
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &sollotto_labs_wallet,
+                    &[charity_1, charity_2, charity_3, charity_4],
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut lottery_result_acc,
+                    &mut settlement_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut vault_acc,
+                    &mut rewards_wallet_acc,
+                    &mut slot_holders_rewards_wallet_acc,
+                    &mut sollotto_labs_wallet_acc,
+                    &mut charity_registry_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_1_acc,
+                    &mut charity_2_acc,
+                    &mut charity_3_acc,
+                    &mut charity_4_acc
+                ]
+            )
+        );
+
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::tally_batch(
+                    &program_id,
+                    lottery_id,
+                    &payer_key,
+                    &authority_key,
+                    &[user2_ticket]
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut settlement_acc,
+                    &mut prize_table_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc,
+                    &mut user2_ticket_acc
+                ]
+            )
+        );
+
+        let settlement = SettlementData::unpack_unchecked(settlement_acc.data()).unwrap();
+        assert_eq!(settlement.tallied_count, 2);
+        assert_eq!(settlement.prize_pool_amount, sol_to_lamports(0.2));
+        // 5+1 and 3+0 winners
+        assert_eq!(settlement.winners, [1, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(settlement.winners_count(), 2);
+        assert_eq!(settlement.rewards[4], sol_to_lamports(0.1));
+        // Fixed prize comes first, jackpot share is taken from the rest of 65% of the pool
+        assert_eq!(settlement.rewards[0], 19_500_000);
+        // Claim period starts after the payout
+        assert_eq!(settlement.claim_deadline, 0);
+
+        // BadCase: all tickets are already tallied
+        assert_eq!(
+            Err(LotteryError::TicketAlreadyProcessed.into()),
+            do_process(
+                crate::instruction::tally_batch(
+                    &program_id,
+                    lottery_id,
+                    &payer_key,
+                    &authority_key,
+                    &[]
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut settlement_acc,
+                    &mut prize_table_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc
+                ]
+            )
+        );
+
+        // BadCase: winners are not paid
+        assert_eq!(
+            Err(LotteryError::PayoutNotComplete.into()),
+            do_process(
+                crate::instruction::reward_winners(
+                    &program_id,
+                    lottery_id,
+                    &payer_key,
+                    &authority_key,
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &sollotto_labs_wallet,
+                    &[charity_1, charity_2, charity_3, charity_4],
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut lottery_result_acc,
+                    &mut settlement_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut vault_acc,
+                    &mut rewards_wallet_acc,
+                    &mut slot_holders_rewards_wallet_acc,
+                    &mut sollotto_labs_wallet_acc,
+                    &mut charity_registry_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_1_acc,
+                    &mut charity_2_acc,
+                    &mut charity_3_acc,
+                    &mut charity_4_acc
+                ]
+            )
+        );
+
+        // Settlement finishes late, winners still get the whole claim period
+        let settle_time = DRAW_TIME + CLAIM_PERIOD;
+        let mut claim_clock_sysvar_acc = clock_account(settle_time);
+
+        // BadCase: prize is not recorded yet
+        assert_eq!(
+            Err(LotteryError::NothingToClaim.into()),
+            do_process(
+                crate::instruction::claim_prize(
+                    &program_id,
+                    lottery_id,
+                    &user1_wallet,
+                    user1_ticket_nonce,
+                    None
+                )
+                .unwrap(),
+                vec![
+                    &mut settlement_acc,
+                    &mut user1_ticket_acc,
+                    &mut user1_wallet_acc,
+                    &mut claim_clock_sysvar_acc
+                ]
+            )
+        );
+
+        // User2 wins the lottery, user1 matches 3 numbers
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::payout_batch(
+                    &program_id,
+                    lottery_id,
+                    &authority_key,
+                    &[user1_ticket, user2_ticket],
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut settlement_acc,
+                    &mut prize_table_acc,
+                    &mut authority_acc,
+                    &mut vault_acc,
+                    &mut system_acc,
+                    &mut claim_clock_sysvar_acc,
+                    &mut user1_ticket_acc,
+                    &mut user2_ticket_acc
+                ]
+            )
+        );
+
+        let settlement = SettlementData::unpack_unchecked(settlement_acc.data()).unwrap();
+        assert_eq!(settlement.paid_count, 2);
+        assert_eq!(settlement.claim_deadline, settle_time + CLAIM_PERIOD);
+        assert_eq!(
+            settlement.escrowed_amount,
+            settlement.rewards[4] + settlement.rewards[0]
+        );
+        let ticket = TicketData::unpack_unchecked(user1_ticket_acc.data()).unwrap();
+        assert_eq!(ticket.is_paid, true);
+        assert_eq!(ticket.prize_amount, settlement.rewards[4]);
+        let ticket = TicketData::unpack_unchecked(user2_ticket_acc.data()).unwrap();
+        assert_eq!(ticket.is_paid, true);
+        assert_eq!(ticket.prize_amount, settlement.rewards[0]);
+        let lottery = LotteryData::unpack_unchecked(lottery_acc.data()).unwrap();
+        assert_eq!(
+            lottery.prize_pool_amount,
+            sol_to_lamports(0.2) - settlement.escrowed_amount
+        );
+
+        // Native transfer into the escrow is not executed in unit tests
+        settlement_acc.lamports += settlement.escrowed_amount;
+
+        // BadCase: winner is paid twice
+        assert_eq!(
+            Err(LotteryError::TicketAlreadyProcessed.into()),
+            do_process(
+                crate::instruction::payout_batch(
+                    &program_id,
+                    lottery_id,
+                    &authority_key,
+                    &[user2_ticket],
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut settlement_acc,
+                    &mut prize_table_acc,
+                    &mut authority_acc,
+                    &mut vault_acc,
+                    &mut system_acc,
+                    &mut clock_sysvar_acc,
+                    &mut user2_ticket_acc
+                ]
+            )
+        );
+
+        // BadCase: Bad sollotto reward account
+        let fake_sollotto_labs_wallet = Pubkey::new_unique();
+        assert_eq!(
+            Err(LotteryError::InvalidSollottoAccount.into()),
+            do_process(
+                crate::instruction::reward_winners(
+                    &program_id,
+                    lottery_id,
+                    &payer_key,
+                    &authority_key,
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &fake_sollotto_labs_wallet,
+                    &[charity_1, charity_2, charity_3, charity_4],
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut lottery_result_acc,
+                    &mut settlement_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut vault_acc,
+                    &mut rewards_wallet_acc,
+                    &mut slot_holders_rewards_wallet_acc,
+                    &mut sollotto_labs_wallet_acc,
+                    &mut charity_registry_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_1_acc,
+                    &mut charity_2_acc,
+                    &mut charity_3_acc,
+                    &mut charity_4_acc
+                ]
+            )
+        );
+
+        assert_eq!(
+            Ok(()),
+            do_process(
+                crate::instruction::reward_winners(
+                    &program_id,
+                    lottery_id,
+                    &payer_key,
+                    &authority_key,
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &sollotto_labs_wallet,
+                    &[charity_1, charity_2, charity_3, charity_4],
+                    None,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut lottery_result_acc,
+                    &mut settlement_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut vault_acc,
+                    &mut rewards_wallet_acc,
+                    &mut slot_holders_rewards_wallet_acc,
+                    &mut sollotto_labs_wallet_acc,
+                    &mut charity_registry_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_1_acc,
+                    &mut charity_2_acc,
+                    &mut charity_3_acc,
+                    &mut charity_4_acc
+                ]
+            )
+        );
+
+        // Check data
+        let lottery = LotteryData::unpack_unchecked(lottery_acc.data()).unwrap();
+        assert_eq!(lottery.is_initialized, false);
+        assert_eq!(lottery.is_finaled, false);
+        assert_eq!(lottery.lottery_id, lottery_id);
+        assert_eq!(lottery.total_registrations, 0);
+        assert_eq!(lottery.winning_numbers, [0; NUMBERS_LEN]);
+        // Shares of the tiers without winners roll over to the next draw
+        assert_eq!(lottery.prize_pool_amount, 0);
+        assert_eq!(lottery.rollover_amount, 30_000_000 - 19_500_000);
+
+        let lottery_result =
+            LotteryResultData::unpack_unchecked(lottery_result_acc.data()).unwrap();
+        assert_eq!(lottery_result.version, LOTTERY_RESULT_VERSION);
+        assert_eq!(lottery_result.lottery_id, lottery_id);
+        assert_eq!(lottery_result.winning_numbers, [2, 3, 4, 5, 66, 7, 0, 0]);
+        assert_eq!(lottery_result.rollover_amount, 30_000_000 - 19_500_000);
+        assert_eq!(lottery_result.total_tickets, 2);
+        assert_eq!(lottery_result.prize_pool_amount, sol_to_lamports(0.2));
+        assert_eq!(lottery_result.tier_winners, [1, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(lottery_result.tier_rewards[0], 19_500_000);
+        assert_eq!(lottery_result.tier_rewards[4], sol_to_lamports(0.1));
+        assert_eq!(lottery_result.randomness_account, randomness_account);
+        assert_eq!(lottery_result.randomness(), [1, 2, 3, 4]);
+        assert_eq!(lottery_result.draw_slot, DRAW_SLOT);
+        assert_eq!(lottery_result.drawn_at, DRAW_TIME);
+        assert_eq!(
+            LotteryResultData::unpack_versioned(lottery_result_acc.data()),
+            Ok(lottery_result)
+        );
+        // Both tickets voted for the first charity, it takes the whole 30% charity pool
+        assert_eq!(lottery_result.charities_count, 4);
+        assert_eq!(
+            lottery_result.charities[..4],
+            [charity_1, charity_2, charity_3, charity_4]
+        );
+        assert_eq!(
+            lottery_result.charity_rewards,
+            [60_000_000, 0, 0, 0, 0, 0, 0, 0]
+        );
+
+        // Next draw takes the rollover
+        let next_lottery_id = lottery_id + 1;
+        let mut next_lottery_acc = SolanaAccount::new(
+            lottery_minimum_balance(),
             LotteryData::get_packed_len(),
             &program_id,
         );
+        let mut next_clock_sysvar_acc = clock_account(SALE_START);
+
+        // Rollover transfer is not executed here, the next vault already holds it
+        let mut next_vault_acc = SolanaAccount::new(30_000_000 - 19_500_000, 0, &Pubkey::default());
+
+        // BadCase: rollover is taken from another vault
+        let mut instruction = crate::instruction::initialize_lottery(
+            &program_id,
+            next_lottery_id,
+            &[0, 1, 2, 3],
+            &rewards_wallet,
+            &slot_holders_rewards_wallet,
+            &sollotto_labs_wallet,
+            &randomness_account,
+            SALE_START,
+            SALE_END,
+            DRAW_TIME,
+            CLAIM_PERIOD,
+            MAIN_POOL_SIZE,
+            BONUS_POOL_SIZE,
+            PICK_COUNT,
+            TICKET_PRICE,
+            CharityMode::WinnerTakesAll,
+            RandomnessProvider::SwitchboardVrf,
+            &[0; 32],
+            Some(lottery_id),
+            &payer_key,
+            &authority_key,
+        )
+        .unwrap();
+        instruction.accounts[10].pubkey = Pubkey::new_unique();
+        assert_eq!(
+            Err(LotteryError::InvalidVault.into()),
+            do_process(
+                instruction,
+                vec![
+                    &mut next_lottery_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut next_clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_registry_acc,
+                    &mut next_vault_acc,
+                    &mut program_data_acc,
+                    &mut lottery_acc,
+                    &mut vault_acc,
+                ]
+            )
+        );
+
+        // BadCase: next vault doesn't hold the rollover
+        let mut empty_vault_acc = SolanaAccount::default();
         assert_eq!(
-            Err(LotteryError::NotRentExempt.into()),
+            Err(ProgramError::InsufficientFunds),
             do_process(
                 crate::instruction::initialize_lottery(
                     &program_id,
-                    lottery_id,
-                    &charity_1,
-                    &charity_2,
-                    &charity_3,
-                    &charity_4,
-                    &holding_wallet,
+                    next_lottery_id,
+                    &[0, 1, 2, 3],
                     &rewards_wallet,
                     &slot_holders_rewards_wallet,
                     &sollotto_labs_wallet,
                     &randomness_account,
-                    &lottery_key
+                    SALE_START,
+                    SALE_END,
+                    DRAW_TIME,
+                    CLAIM_PERIOD,
+                    MAIN_POOL_SIZE,
+                    BONUS_POOL_SIZE,
+                    PICK_COUNT,
+                    TICKET_PRICE,
+                    CharityMode::WinnerTakesAll,
+                    RandomnessProvider::SwitchboardVrf,
+                    &[0; 32],
+                    Some(lottery_id),
+                    &payer_key,
+                    &authority_key,
                 )
                 .unwrap(),
-                vec![&mut bad_lottery_acc, &mut rent_sysvar_acc]
+                vec![
+                    &mut next_lottery_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut next_clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_registry_acc,
+                    &mut empty_vault_acc,
+                    &mut program_data_acc,
+                    &mut lottery_acc,
+                    &mut vault_acc,
+                ]
             )
         );
 
         do_process(
             crate::instruction::initialize_lottery(
                 &program_id,
-                lottery_id,
-                &charity_1,
-                &charity_2,
-                &charity_3,
-                &charity_4,
-                &holding_wallet,
+                next_lottery_id,
+                &[0, 1, 2, 3],
                 &rewards_wallet,
                 &slot_holders_rewards_wallet,
                 &sollotto_labs_wallet,
                 &randomness_account,
-                &lottery_key,
+                SALE_START,
+                SALE_END,
+                DRAW_TIME,
+                CLAIM_PERIOD,
+                MAIN_POOL_SIZE,
+                BONUS_POOL_SIZE,
+                PICK_COUNT,
+                TICKET_PRICE,
+                CharityMode::WinnerTakesAll,
+                RandomnessProvider::SwitchboardVrf,
+                &[0; 32],
+                Some(lottery_id),
+                &payer_key,
+                &authority_key,
             )
             .unwrap(),
-            vec![&mut lottery_acc, &mut rent_sysvar_acc],
+            vec![
+                &mut next_lottery_acc,
+                &mut payer_acc,
+                &mut authority_acc,
+                &mut rent_sysvar_acc,
+                &mut next_clock_sysvar_acc,
+                &mut system_acc,
+                &mut charity_registry_acc,
+                &mut next_vault_acc,
+                &mut program_data_acc,
+                &mut lottery_acc,
+                &mut vault_acc,
+            ],
         )
         .unwrap();
+        let next_lottery = LotteryData::unpack(next_lottery_acc.data()).unwrap();
+        assert_eq!(next_lottery.prize_pool_amount, 30_000_000 - 19_500_000);
+        assert_eq!(next_lottery.rollover_amount, 0);
+        let lottery = LotteryData::unpack_unchecked(lottery_acc.data()).unwrap();
+        assert_eq!(lottery.rollover_amount, 0);
 
-        // BadCase: Lottery Already initialized
+        // User1 claims the prize
+        let user1_lamports = user1_wallet_acc.lamports;
+        let settlement_lamports = settlement_acc.lamports;
         assert_eq!(
-            Err(LotteryError::Initialized.into()),
+            Ok(()),
             do_process(
-                crate::instruction::initialize_lottery(
+                crate::instruction::claim_prize(
                     &program_id,
                     lottery_id,
-                    &charity_1,
-                    &charity_2,
-                    &charity_3,
-                    &charity_4,
-                    &holding_wallet,
-                    &rewards_wallet,
-                    &slot_holders_rewards_wallet,
-                    &sollotto_labs_wallet,
-                    &randomness_account,
-                    &lottery_key,
+                    &user1_wallet,
+                    user1_ticket_nonce,
+                    None
                 )
                 .unwrap(),
-                vec![&mut lottery_acc, &mut rent_sysvar_acc]
+                vec![
+                    &mut settlement_acc,
+                    &mut user1_ticket_acc,
+                    &mut user1_wallet_acc,
+                    &mut claim_clock_sysvar_acc
+                ]
             )
         );
 
-        let lottery = LotteryData::unpack(&lottery_acc.data).unwrap();
-        assert_eq!(lottery.is_initialized, true);
-        assert_eq!(lottery.lottery_id, lottery_id);
-        assert_eq!(lottery.charity_1, charity_1);
-        assert_eq!(lottery.charity_2, charity_2);
-        assert_eq!(lottery.charity_3, charity_3);
-        assert_eq!(lottery.charity_4, charity_4);
-        assert_eq!(lottery.charity_1_vc, 0);
-        assert_eq!(lottery.charity_2_vc, 0);
-        assert_eq!(lottery.charity_3_vc, 0);
-        assert_eq!(lottery.charity_4_vc, 0);
-        assert_eq!(lottery.holding_wallet, holding_wallet);
-        assert_eq!(lottery.rewards_wallet, rewards_wallet);
+        let settlement = SettlementData::unpack_unchecked(settlement_acc.data()).unwrap();
         assert_eq!(
-            lottery.slot_holders_rewards_wallet,
-            slot_holders_rewards_wallet
-        );
-        assert_eq!(lottery.sollotto_labs_wallet, sollotto_labs_wallet);
-        assert_eq!(lottery.total_registrations, 0);
-        assert_eq!(lottery.prize_pool_amount, 0);
-        for number in &lottery.winning_numbers {
-            assert_eq!(*number, 0);
-        }
-    }
-
-    #[test]
-    fn test_ticket_purchase() {
-        let program_id = id();
-        let lottery_id = 112233;
-        let lottery_key = Pubkey::new_unique();
-        let mut lottery_acc = SolanaAccount::new(
-            lottery_minimum_balance(),
-            LotteryData::get_packed_len(),
-            &program_id,
+            user1_wallet_acc.lamports,
+            user1_lamports + settlement.rewards[4]
         );
-        let user_funding_key = Pubkey::new_unique();
-        let mut user_funding_acc = SolanaAccount::default();
-        let user_ticket_key = Pubkey::new_unique();
-        let mut user_ticket_acc = SolanaAccount::new(
-            ticket_minimum_balance(),
-            TicketData::get_packed_len(),
-            &program_id,
+        assert_eq!(
+            settlement_acc.lamports,
+            settlement_lamports - settlement.rewards[4]
         );
-        let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
-        let mut system_acc = SolanaAccount::default();
-        let mut spl_token_acc = SolanaAccount::default();
-        let charity_1 = Pubkey::new_unique();
-        let charity_2 = Pubkey::new_unique();
-        let charity_3 = Pubkey::new_unique();
-        let charity_4 = Pubkey::new_unique();
-        let holding_wallet = Pubkey::new_unique();
-        let mut holding_wallet_acc = SolanaAccount::default();
-        let rewards_wallet = Pubkey::new_unique();
-        let slot_holders_rewards_wallet = Pubkey::new_unique();
-        let sollotto_labs_wallet = Pubkey::new_unique();
-        let randomness_account = Pubkey::new_unique();
-        let user_charity = charity_1;
-
-        let user_lifetime_ticket_key = Pubkey::new_unique();
-        let mut user_lifetime_ticket_acc =
-            SolanaAccount::new(account_minimum_balance(), Account::LEN, &spl_token::id());
-        let lifetime_ticket_mint_key = Pubkey::new_unique();
-        let mut lifetime_ticket_mint_acc =
-            SolanaAccount::new(mint_minimum_balance(), Mint::LEN, &spl_token::id());
-        let lifetime_ticket_owner_key = Pubkey::new_unique();
-        let mut lifetime_ticket_owner_acc = SolanaAccount::default();
-
-        Mint::pack(
-            Mint {
-                is_initialized: true,
-                ..Default::default()
-            },
-            &mut lifetime_ticket_mint_acc.data,
-        )
-        .unwrap();
+        assert_eq!(settlement.claimed_amount, settlement.rewards[4]);
+        let ticket = TicketData::unpack_unchecked(user1_ticket_acc.data()).unwrap();
+        assert_eq!(ticket.is_claimed, true);
 
-        // BadCase: Lottery is not initialized
+        // BadCase: prize is claimed twice
         assert_eq!(
-            Err(LotteryError::NotInitialized.into()),
+            Err(LotteryError::PrizeAlreadyClaimed.into()),
             do_process(
-                crate::instruction::purchase_ticket(
+                crate::instruction::claim_prize(
                     &program_id,
-                    &user_charity,
-                    &user_funding_key,
-                    &[10, 20, 30, 40, 50, 15],
-                    &user_ticket_key,
-                    &holding_wallet,
-                    &lottery_key,
-                    &user_lifetime_ticket_key,
-                    &lifetime_ticket_owner_key,
-                    &lifetime_ticket_mint_key,
+                    lottery_id,
+                    &user1_wallet,
+                    user1_ticket_nonce,
+                    None
                 )
                 .unwrap(),
                 vec![
-                    &mut lottery_acc,
-                    &mut user_ticket_acc,
-                    &mut user_funding_acc,
-                    &mut holding_wallet_acc,
-                    &mut user_lifetime_ticket_acc,
-                    &mut lifetime_ticket_owner_acc,
-                    &mut lifetime_ticket_mint_acc,
-                    &mut rent_sysvar_acc,
-                    &mut system_acc,
-                    &mut spl_token_acc,
+                    &mut settlement_acc,
+                    &mut user1_ticket_acc,
+                    &mut user1_wallet_acc,
+                    &mut claim_clock_sysvar_acc
                 ]
             )
         );
 
-        do_process(
-            crate::instruction::initialize_lottery(
-                &program_id,
-                lottery_id,
-                &charity_1,
-                &charity_2,
-                &charity_3,
-                &charity_4,
-                &holding_wallet,
-                &rewards_wallet,
-                &slot_holders_rewards_wallet,
-                &sollotto_labs_wallet,
-                &randomness_account,
-                &lottery_key,
+        // BadCase: ticket is closed by another user
+        assert_eq!(
+            Err(LotteryError::InvalidAccountAddress.into()),
+            do_process(
+                crate::instruction::close_ticket(
+                    &program_id,
+                    lottery_id,
+                    &user2_wallet,
+                    user1_ticket_nonce
+                )
+                .unwrap(),
+                vec![
+                    &mut user1_ticket_acc,
+                    &mut user2_wallet_acc,
+                    &mut lottery_result_acc,
+                    &mut settlement_acc,
+                    &mut claim_clock_sysvar_acc
+                ]
             )
-            .unwrap(),
-            vec![&mut lottery_acc, &mut rent_sysvar_acc],
-        )
-        .unwrap();
+        );
 
-        // BadCase: user cannot pay
+        // BadCase: ticket prize is not claimed yet
         assert_eq!(
-            Err(ProgramError::InsufficientFunds),
+            Err(LotteryError::TicketNotSettled.into()),
             do_process(
-                crate::instruction::purchase_ticket(
+                crate::instruction::close_ticket(
                     &program_id,
-                    &user_charity,
-                    &user_funding_key,
-                    &[10, 20, 30, 40, 50, 29],
-                    &user_ticket_key,
-                    &holding_wallet,
-                    &lottery_key,
-                    &user_lifetime_ticket_key,
-                    &lifetime_ticket_owner_key,
-                    &lifetime_ticket_mint_key,
+                    lottery_id,
+                    &user2_wallet,
+                    user2_ticket_nonce
                 )
                 .unwrap(),
                 vec![
-                    &mut lottery_acc,
-                    &mut user_ticket_acc,
-                    &mut user_funding_acc,
-                    &mut holding_wallet_acc,
-                    &mut user_lifetime_ticket_acc,
-                    &mut lifetime_ticket_owner_acc,
-                    &mut lifetime_ticket_mint_acc,
-                    &mut rent_sysvar_acc,
-                    &mut system_acc,
-                    &mut spl_token_acc,
+                    &mut user2_ticket_acc,
+                    &mut user2_wallet_acc,
+                    &mut lottery_result_acc,
+                    &mut settlement_acc,
+                    &mut claim_clock_sysvar_acc
                 ]
             )
         );
 
-        user_funding_acc.lamports += sol_to_lamports(0.1);
-
-        // BadCase: rent NotRentExempt
-        let mut bad_ticket_acc = SolanaAccount::new(
-            ticket_minimum_balance() - 100,
-            TicketData::get_packed_len(),
-            &program_id,
-        );
+        // User1 closes the claimed ticket
+        let user1_lamports = user1_wallet_acc.lamports;
+        let ticket_lamports = user1_ticket_acc.lamports;
         assert_eq!(
-            Err(LotteryError::NotRentExempt.into()),
+            Ok(()),
             do_process(
-                crate::instruction::purchase_ticket(
+                crate::instruction::close_ticket(
                     &program_id,
-                    &user_charity,
-                    &user_funding_key,
-                    &[10, 20, 30, 40, 50, 15],
-                    &user_ticket_key,
-                    &holding_wallet,
-                    &lottery_key,
-                    &user_lifetime_ticket_key,
-                    &lifetime_ticket_owner_key,
-                    &lifetime_ticket_mint_key,
+                    lottery_id,
+                    &user1_wallet,
+                    user1_ticket_nonce
                 )
                 .unwrap(),
                 vec![
-                    &mut lottery_acc,
-                    &mut bad_ticket_acc,
-                    &mut user_funding_acc,
-                    &mut holding_wallet_acc,
-                    &mut user_lifetime_ticket_acc,
-                    &mut lifetime_ticket_owner_acc,
-                    &mut lifetime_ticket_mint_acc,
-                    &mut rent_sysvar_acc,
-                    &mut system_acc,
-                    &mut spl_token_acc,
+                    &mut user1_ticket_acc,
+                    &mut user1_wallet_acc,
+                    &mut lottery_result_acc,
+                    &mut settlement_acc,
+                    &mut claim_clock_sysvar_acc
                 ]
             )
         );
+        assert_eq!(user1_wallet_acc.lamports, user1_lamports + ticket_lamports);
+        assert_eq!(user1_ticket_acc.lamports, 0);
+        let ticket = TicketData::unpack_unchecked(user1_ticket_acc.data()).unwrap();
+        assert_eq!(ticket.is_purchased, false);
 
-        // BadCase: bad numbers
+        // BadCase: unclaimed prizes are swept before the deadline
+        let mut deadline_clock_sysvar_acc = clock_account(settle_time + CLAIM_PERIOD);
         assert_eq!(
-            Err(LotteryError::InvalidNumber.into()),
+            Err(LotteryError::ClaimPeriodNotOver.into()),
             do_process(
-                crate::instruction::purchase_ticket(
+                crate::instruction::sweep_unclaimed(
                     &program_id,
-                    &user_charity,
-                    &user_funding_key,
-                    &[70, 20, 30, 40, 50, 15],
-                    &user_ticket_key,
-                    &holding_wallet,
-                    &lottery_key,
-                    &user_lifetime_ticket_key,
-                    &lifetime_ticket_owner_key,
-                    &lifetime_ticket_mint_key,
+                    lottery_id,
+                    &authority_key,
+                    &rewards_wallet,
+                    None
                 )
                 .unwrap(),
                 vec![
                     &mut lottery_acc,
-                    &mut user_ticket_acc,
-                    &mut user_funding_acc,
-                    &mut holding_wallet_acc,
-                    &mut user_lifetime_ticket_acc,
-                    &mut lifetime_ticket_owner_acc,
-                    &mut lifetime_ticket_mint_acc,
-                    &mut rent_sysvar_acc,
-                    &mut system_acc,
-                    &mut spl_token_acc,
+                    &mut settlement_acc,
+                    &mut authority_acc,
+                    &mut rewards_wallet_acc,
+                    &mut deadline_clock_sysvar_acc
                 ]
             )
         );
 
+        // BadCase: User2 claims after the deadline
+        let mut expired_clock_sysvar_acc = clock_account(settle_time + CLAIM_PERIOD + 1);
         assert_eq!(
-            Err(LotteryError::InvalidNumber.into()),
+            Err(LotteryError::ClaimExpired.into()),
             do_process(
-                crate::instruction::purchase_ticket(
+                crate::instruction::claim_prize(
                     &program_id,
-                    &user_charity,
-                    &user_funding_key,
-                    &[10, 20, 30, 40, 0, 15],
-                    &user_ticket_key,
-                    &holding_wallet,
-                    &lottery_key,
-                    &user_lifetime_ticket_key,
-                    &lifetime_ticket_owner_key,
-                    &lifetime_ticket_mint_key,
+                    lottery_id,
+                    &user2_wallet,
+                    user2_ticket_nonce,
+                    None
                 )
                 .unwrap(),
                 vec![
-                    &mut lottery_acc,
-                    &mut user_ticket_acc,
-                    &mut user_funding_acc,
-                    &mut holding_wallet_acc,
-                    &mut user_lifetime_ticket_acc,
-                    &mut lifetime_ticket_owner_acc,
-                    &mut lifetime_ticket_mint_acc,
-                    &mut rent_sysvar_acc,
-                    &mut system_acc,
-                    &mut spl_token_acc,
+                    &mut settlement_acc,
+                    &mut user2_ticket_acc,
+                    &mut user2_wallet_acc,
+                    &mut expired_clock_sysvar_acc
                 ]
             )
         );
 
+        // User2 prize goes to the rewards wallet
+        let rewards_wallet_lamports = rewards_wallet_acc.lamports;
         assert_eq!(
-            Err(LotteryError::InvalidNumber.into()),
+            Ok(()),
             do_process(
-                crate::instruction::purchase_ticket(
+                crate::instruction::sweep_unclaimed(
                     &program_id,
-                    &user_charity,
-                    &user_funding_key,
-                    &[10, 20, 30, 40, 50, 30],
-                    &user_ticket_key,
-                    &holding_wallet,
-                    &lottery_key,
-                    &user_lifetime_ticket_key,
-                    &lifetime_ticket_owner_key,
-                    &lifetime_ticket_mint_key,
+                    lottery_id,
+                    &authority_key,
+                    &rewards_wallet,
+                    None
                 )
                 .unwrap(),
                 vec![
                     &mut lottery_acc,
-                    &mut user_ticket_acc,
-                    &mut user_funding_acc,
-                    &mut holding_wallet_acc,
-                    &mut user_lifetime_ticket_acc,
-                    &mut lifetime_ticket_owner_acc,
-                    &mut lifetime_ticket_mint_acc,
-                    &mut rent_sysvar_acc,
-                    &mut system_acc,
-                    &mut spl_token_acc,
+                    &mut settlement_acc,
+                    &mut authority_acc,
+                    &mut rewards_wallet_acc,
+                    &mut expired_clock_sysvar_acc
                 ]
             )
         );
 
-        do_process(
-            crate::instruction::purchase_ticket(
-                &program_id,
-                &user_charity,
-                &user_funding_key,
-                &[10, 20, 30, 40, 50, 29],
-                &user_ticket_key,
-                &holding_wallet,
-                &lottery_key,
-                &user_lifetime_ticket_key,
-                &lifetime_ticket_owner_key,
-                &lifetime_ticket_mint_key,
-            )
-            .unwrap(),
-            vec![
-                &mut lottery_acc,
-                &mut user_ticket_acc,
-                &mut user_funding_acc,
-                &mut holding_wallet_acc,
-                &mut user_lifetime_ticket_acc,
-                &mut lifetime_ticket_owner_acc,
-                &mut lifetime_ticket_mint_acc,
-                &mut rent_sysvar_acc,
-                &mut system_acc,
-                &mut spl_token_acc,
-            ],
-        )
-        .unwrap();
-
-        let lottery = LotteryData::unpack(&lottery_acc.data()).unwrap();
-        assert_eq!(lottery.charity_1_vc, 1);
-        assert_eq!(lottery.total_registrations, 1);
-        assert_eq!(lottery.prize_pool_amount, sol_to_lamports(0.1));
+        let settlement = SettlementData::unpack_unchecked(settlement_acc.data()).unwrap();
+        assert_eq!(settlement.is_swept, true);
+        assert_eq!(
+            rewards_wallet_acc.lamports,
+            rewards_wallet_lamports + settlement.rewards[0]
+        );
+        assert_eq!(settlement_acc.lamports, settlement_minimum_balance());
 
-        // BadCase: Ticket already purchased
+        // User2 closes the expired ticket
         assert_eq!(
-            Err(LotteryError::AlreadyPurchased.into()),
+            Ok(()),
             do_process(
-                crate::instruction::purchase_ticket(
+                crate::instruction::close_ticket(
                     &program_id,
-                    &user_charity,
-                    &user_funding_key,
-                    &[10, 20, 30, 40, 50, 30],
-                    &user_ticket_key,
-                    &holding_wallet,
-                    &lottery_key,
-                    &user_lifetime_ticket_key,
-                    &lifetime_ticket_owner_key,
-                    &lifetime_ticket_mint_key,
+                    lottery_id,
+                    &user2_wallet,
+                    user2_ticket_nonce
                 )
                 .unwrap(),
-                vec![
-                    &mut lottery_acc,
-                    &mut user_ticket_acc,
-                    &mut user_funding_acc,
-                    &mut holding_wallet_acc,
-                    &mut user_lifetime_ticket_acc,
-                    &mut lifetime_ticket_owner_acc,
-                    &mut lifetime_ticket_mint_acc,
-                    &mut rent_sysvar_acc,
-                    &mut system_acc,
-                    &mut spl_token_acc,
+                vec![
+                    &mut user2_ticket_acc,
+                    &mut user2_wallet_acc,
+                    &mut lottery_result_acc,
+                    &mut settlement_acc,
+                    &mut expired_clock_sysvar_acc
                 ]
             )
         );
+        assert_eq!(user2_ticket_acc.lamports, 0);
 
-        do_process(
-            crate::instruction::store_winning_numbers(
-                &program_id,
-                &[10, 20, 30, 40, 50, 29],
-                &lottery_key,
-            )
-            .unwrap(),
-            vec![&mut lottery_acc],
-        )
-        .unwrap();
-
-        let user_funding_key = Pubkey::new_unique();
-        let mut user_funding_acc = SolanaAccount::default();
-        let user_ticket_key = Pubkey::new_unique();
-        let mut user_ticket_acc = SolanaAccount::new(
-            ticket_minimum_balance(),
-            TicketData::get_packed_len(),
-            &program_id,
-        );
-
+        // BadCase: unclaimed prizes are swept twice
         assert_eq!(
-            Err(LotteryError::IsFinaled.into()),
+            Err(LotteryError::AlreadySwept.into()),
             do_process(
-                crate::instruction::purchase_ticket(
+                crate::instruction::sweep_unclaimed(
                     &program_id,
-                    &user_charity,
-                    &user_funding_key,
-                    &[10, 20, 30, 40, 50, 30],
-                    &user_ticket_key,
-                    &holding_wallet,
-                    &lottery_key,
-                    &user_lifetime_ticket_key,
-                    &lifetime_ticket_owner_key,
-                    &lifetime_ticket_mint_key,
+                    lottery_id,
+                    &authority_key,
+                    &rewards_wallet,
+                    None
                 )
                 .unwrap(),
                 vec![
                     &mut lottery_acc,
-                    &mut user_ticket_acc,
-                    &mut user_funding_acc,
-                    &mut holding_wallet_acc,
-                    &mut user_lifetime_ticket_acc,
-                    &mut lifetime_ticket_owner_acc,
-                    &mut lifetime_ticket_mint_acc,
-                    &mut rent_sysvar_acc,
-                    &mut system_acc,
-                    &mut spl_token_acc,
+                    &mut settlement_acc,
+                    &mut authority_acc,
+                    &mut rewards_wallet_acc,
+                    &mut expired_clock_sysvar_acc
                 ]
             )
         );
     }
 
     #[test]
-    fn test_store_winning_numbers() {
+    fn test_unpack_legacy_lottery_result() {
+        let mut legacy_data = 7_u32.to_le_bytes().to_vec();
+        legacy_data.extend_from_slice(&[5, 11, 22, 33, 44, 9]);
+
+        let lottery_result = LotteryResultData::unpack_versioned(&legacy_data).unwrap();
+        assert_eq!(lottery_result.version, 0);
+        assert_eq!(lottery_result.lottery_id, 7);
+        assert_eq!(lottery_result.winning_numbers, [5, 11, 22, 33, 44, 9, 0, 0]);
+        assert_eq!(lottery_result.total_tickets, 0);
+
+        // Current records keep their layout version
+        let mut data = vec![0; LotteryResultData::LEN];
+        LotteryResultData::pack(lottery_result, &mut data).unwrap();
+        assert_eq!(data[0], LOTTERY_RESULT_VERSION);
+        assert_eq!(
+            LotteryResultData::unpack_versioned(&data),
+            Ok(LotteryResultData {
+                version: LOTTERY_RESULT_VERSION,
+                ..lottery_result
+            })
+        );
+
+        // BadCase: unknown layout
+        data[0] = LOTTERY_RESULT_VERSION + 1;
+        assert_eq!(
+            LotteryResultData::unpack_versioned(&data),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            LotteryResultData::unpack_versioned(&data[..20]),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_calculate_tier_rewards() {
+        let prize_table = PrizeTableData {
+            is_initialized: true,
+            lottery_id: 1,
+            tiers: prize_tiers(),
+        };
+
+        // Winners pool is 65 SOL, 5 SOL are fixed prizes
+        let mut settlement = SettlementData {
+            prize_pool_amount: sol_to_lamports(100.0),
+            winners: [1, 0, 2, 0, 50, 0, 0, 0],
+            ..SettlementData::default()
+        };
+        calculate_tier_rewards(&mut settlement, &prize_table).unwrap();
+        assert_eq!(
+            settlement.rewards,
+            [
+                sol_to_lamports(39.0),
+                0,
+                sol_to_lamports(1.5),
+                0,
+                sol_to_lamports(0.1),
+                0,
+                0,
+                0
+            ]
+        );
+
+        // Fixed prizes over the winners pool are reduced, nothing is left for shares
+        let mut settlement = SettlementData {
+            prize_pool_amount: sol_to_lamports(1.0),
+            winners: [1, 0, 0, 0, 13, 0, 0, 0],
+            ..SettlementData::default()
+        };
+        calculate_tier_rewards(&mut settlement, &prize_table).unwrap();
+        assert_eq!(settlement.rewards[0], 0);
+        assert_eq!(settlement.rewards[4], sol_to_lamports(0.05));
+    }
+
+    #[test]
+    fn test_update_charity() {
         let program_id = id();
         let lottery_id = 112233;
-        let lottery_key = Pubkey::new_unique();
+        let payer_key = Pubkey::new_unique();
+        let mut payer_acc = SolanaAccount::default();
+        let authority_key = Pubkey::new_unique();
+        let mut authority_acc = SolanaAccount::default();
         let mut lottery_acc = SolanaAccount::new(
             lottery_minimum_balance(),
             LotteryData::get_packed_len(),
             &program_id,
         );
         let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
+        let mut clock_sysvar_acc = clock_account(SALE_START);
+        let mut system_acc = SolanaAccount::default();
         let charity_1 = Pubkey::new_unique();
         let charity_2 = Pubkey::new_unique();
         let charity_3 = Pubkey::new_unique();
         let charity_4 = Pubkey::new_unique();
-        let holding_wallet = Pubkey::new_unique();
+        let charity_5 = Pubkey::new_unique();
+        let charity_6 = Pubkey::new_unique();
+        let mut charity_registry_acc = charity_registry_account(&[
+            charity_1, charity_2, charity_3, charity_4, charity_5, charity_6,
+        ]);
+        let mut program_data_acc = program_data_account(&authority_key);
+        let mut vault_acc = SolanaAccount::default();
         let rewards_wallet = Pubkey::new_unique();
         let slot_holders_rewards_wallet = Pubkey::new_unique();
         let sollotto_labs_wallet = Pubkey::new_unique();
@@ -1416,13 +8688,60 @@ mod test {
         assert_eq!(
             Err(LotteryError::NotInitialized.into()),
             do_process(
-                crate::instruction::store_winning_numbers(
+                crate::instruction::update_charity(
                     &program_id,
-                    &[10, 20, 30, 40, 50, 29],
-                    &lottery_key,
+                    lottery_id,
+                    &[4, 0, 5, 3],
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut charity_registry_acc
+                ]
+            )
+        );
+
+        // BadCase: charity is not in the registry
+        assert_eq!(
+            Err(LotteryError::InvalidCharity.into()),
+            do_process(
+                crate::instruction::initialize_lottery(
+                    &program_id,
+                    lottery_id,
+                    &[0, 1, 6],
+                    &rewards_wallet,
+                    &slot_holders_rewards_wallet,
+                    &sollotto_labs_wallet,
+                    &randomness_account,
+                    SALE_START,
+                    SALE_END,
+                    DRAW_TIME,
+                    CLAIM_PERIOD,
+                    MAIN_POOL_SIZE,
+                    BONUS_POOL_SIZE,
+                    PICK_COUNT,
+                    TICKET_PRICE,
+                    CharityMode::WinnerTakesAll,
+                    RandomnessProvider::SwitchboardVrf,
+                    &[0; 32],
+                    None,
+                    &payer_key,
+                    &authority_key,
                 )
                 .unwrap(),
-                vec![&mut lottery_acc]
+                vec![
+                    &mut lottery_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut clock_sysvar_acc,
+                    &mut system_acc,
+                    &mut charity_registry_acc,
+                    &mut vault_acc,
+                    &mut program_data_acc
+                ]
             )
         );
 
@@ -1430,564 +8749,368 @@ mod test {
             crate::instruction::initialize_lottery(
                 &program_id,
                 lottery_id,
-                &charity_1,
-                &charity_2,
-                &charity_3,
-                &charity_4,
-                &holding_wallet,
+                &[0, 1, 2, 3],
                 &rewards_wallet,
                 &slot_holders_rewards_wallet,
                 &sollotto_labs_wallet,
                 &randomness_account,
-                &lottery_key,
+                SALE_START,
+                SALE_END,
+                DRAW_TIME,
+                CLAIM_PERIOD,
+                MAIN_POOL_SIZE,
+                BONUS_POOL_SIZE,
+                PICK_COUNT,
+                TICKET_PRICE,
+                CharityMode::WinnerTakesAll,
+                RandomnessProvider::SwitchboardVrf,
+                &[0; 32],
+                None,
+                &payer_key,
+                &authority_key,
             )
             .unwrap(),
-            vec![&mut lottery_acc, &mut rent_sysvar_acc],
+            vec![
+                &mut lottery_acc,
+                &mut payer_acc,
+                &mut authority_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut charity_registry_acc,
+                &mut vault_acc,
+                &mut program_data_acc,
+            ],
         )
         .unwrap();
 
-        // BadCase: Bad numbers
-        assert_eq!(
-            Err(LotteryError::InvalidNumber.into()),
-            do_process(
-                crate::instruction::store_winning_numbers(
-                    &program_id,
-                    &[70, 20, 30, 40, 50, 29],
-                    &lottery_key,
-                )
-                .unwrap(),
-                vec![&mut lottery_acc]
-            )
-        );
+        // Votes are kept by the charity registry index
+        let mut lottery = LotteryData::unpack(&lottery_acc.data()).unwrap();
+        assert_eq!(lottery.charities(), [0, 1, 2, 3]);
+        lottery.charity_votes[0] = 7;
+        lottery.charity_votes[1] = 5;
+        lottery.charity_votes[3] = 2;
+        LotteryData::pack(lottery, &mut lottery_acc.data).unwrap();
 
+        // BadCase: payer is not the lottery authority
         assert_eq!(
-            Err(LotteryError::InvalidNumber.into()),
+            Err(LotteryError::InvalidAuthority.into()),
             do_process(
-                crate::instruction::store_winning_numbers(
+                crate::instruction::update_charity(
                     &program_id,
-                    &[10, 20, 30, 40, 0, 29],
-                    &lottery_key,
+                    lottery_id,
+                    &[4, 0, 5, 3],
+                    &payer_key,
                 )
                 .unwrap(),
-                vec![&mut lottery_acc]
+                vec![&mut lottery_acc, &mut payer_acc, &mut charity_registry_acc]
             )
         );
 
+        // BadCase: charity is duplicated
         assert_eq!(
-            Err(LotteryError::InvalidNumber.into()),
+            Err(LotteryError::InvalidCharity.into()),
             do_process(
-                crate::instruction::store_winning_numbers(
+                crate::instruction::update_charity(
                     &program_id,
-                    &[10, 20, 30, 40, 50, 30],
-                    &lottery_key,
+                    lottery_id,
+                    &[4, 0, 4],
+                    &authority_key,
                 )
                 .unwrap(),
-                vec![&mut lottery_acc]
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut charity_registry_acc
+                ]
             )
         );
 
         do_process(
-            crate::instruction::store_winning_numbers(
+            crate::instruction::update_charity(
                 &program_id,
-                &[10, 20, 30, 40, 50, 29],
-                &lottery_key,
+                lottery_id,
+                &[4, 0, 5, 3],
+                &authority_key,
             )
             .unwrap(),
-            vec![&mut lottery_acc],
+            vec![
+                &mut lottery_acc,
+                &mut authority_acc,
+                &mut charity_registry_acc,
+            ],
         )
         .unwrap();
 
         let lottery = LotteryData::unpack(&lottery_acc.data()).unwrap();
-        assert_eq!(lottery.winning_numbers[0], 10);
-        assert_eq!(lottery.winning_numbers[1], 20);
-        assert_eq!(lottery.winning_numbers[2], 30);
-        assert_eq!(lottery.winning_numbers[3], 40);
-        assert_eq!(lottery.winning_numbers[4], 50);
-        assert_eq!(lottery.winning_numbers[5], 29);
-    }
-
-    #[test]
-    fn test_reward_winners() {
-        let program_id = id();
-        let lottery_id = 112233;
-        let lottery_key = Pubkey::new_unique();
-        let mut lottery_acc = SolanaAccount::new(
-            lottery_minimum_balance(),
-            LotteryData::get_packed_len(),
-            &program_id,
-        );
-        let lottery_result_key = Pubkey::new_unique();
-        let mut lottery_result_acc = SolanaAccount::new(
-            lottery_result_minimum_balance(),
-            LotteryResultData::get_packed_len(),
-            &program_id,
-        );
-        let mut system_acc = SolanaAccount::default();
-        let mut spl_token_acc = SolanaAccount::default();
-        let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
-        let charity_1 = Pubkey::new_unique();
-        let mut charity_1_acc = SolanaAccount::default();
-        let charity_2 = Pubkey::new_unique();
-        let mut charity_2_acc = SolanaAccount::default();
-        let charity_3 = Pubkey::new_unique();
-        let mut charity_3_acc = SolanaAccount::default();
-        let charity_4 = Pubkey::new_unique();
-        let mut charity_4_acc = SolanaAccount::default();
-        let holding_wallet = Pubkey::new_unique();
-        let mut holding_wallet_acc = SolanaAccount::default();
-        let rewards_wallet = Pubkey::new_unique();
-        let mut rewards_wallet_acc = SolanaAccount::default();
-        let slot_holders_rewards_wallet = Pubkey::new_unique();
-        let mut slot_holders_rewards_wallet_acc = SolanaAccount::default();
-        let sollotto_labs_wallet = Pubkey::new_unique();
-        let mut sollotto_labs_wallet_acc = SolanaAccount::default();
-
-        let randomness_account = Pubkey::new_unique();
-
-        let user1_wallet = Pubkey::new_unique();
-        let mut user1_wallet_acc = SolanaAccount::default();
-        let user1_ticket = Pubkey::new_unique();
-        let mut user1_ticket_acc = SolanaAccount::new(
-            ticket_minimum_balance(),
-            TicketData::get_packed_len(),
-            &program_id,
-        );
-        let user2_wallet = Pubkey::new_unique();
-        let mut user2_wallet_acc = SolanaAccount::default();
-        let user2_ticket = Pubkey::new_unique();
-        let mut user2_ticket_acc = SolanaAccount::new(
-            ticket_minimum_balance(),
-            TicketData::get_packed_len(),
-            &program_id,
-        );
-
-        let user1_lifetime_ticket_key = Pubkey::new_unique();
-        let mut user1_lifetime_ticket_acc =
-            SolanaAccount::new(account_minimum_balance(), Account::LEN, &spl_token::id());
-        let user2_lifetime_ticket_key = Pubkey::new_unique();
-        let mut user2_lifetime_ticket_acc =
-            SolanaAccount::new(account_minimum_balance(), Account::LEN, &spl_token::id());
-        let lifetime_ticket_mint_key = Pubkey::new_unique();
-        let mut lifetime_ticket_mint_acc =
-            SolanaAccount::new(mint_minimum_balance(), Mint::LEN, &spl_token::id());
-        let lifetime_ticket_owner_key = Pubkey::new_unique();
-        let mut lifetime_ticket_owner_acc = SolanaAccount::default();
-
-        Mint::pack(
-            Mint {
-                is_initialized: true,
-                ..Default::default()
-            },
-            &mut lifetime_ticket_mint_acc.data,
-        )
-        .unwrap();
+        assert_eq!(lottery.charities(), [4, 0, 5, 3]);
+        assert_eq!(lottery.votes(), [0, 7, 0, 2]);
 
-        // BadCase: Lottery is not initialized
+        // BadCase: winning numbers are already stored
+        finalize_lottery(&mut lottery_acc, &[10, 20, 30, 40, 50, 29]);
         assert_eq!(
-            Err(LotteryError::NotInitialized.into()),
+            Err(LotteryError::IsFinaled.into()),
             do_process(
-                crate::instruction::reward_winners(
+                crate::instruction::update_charity(
                     &program_id,
-                    &lottery_key,
-                    &lottery_result_key,
-                    &holding_wallet,
-                    &rewards_wallet,
-                    &slot_holders_rewards_wallet,
-                    &sollotto_labs_wallet,
-                    &[charity_1, charity_2, charity_3, charity_4],
-                    &vec![(user1_ticket, user1_wallet), (user2_ticket, user2_wallet)],
+                    lottery_id,
+                    &[0, 1, 2, 3],
+                    &authority_key,
                 )
                 .unwrap(),
                 vec![
                     &mut lottery_acc,
-                    &mut lottery_result_acc,
-                    &mut holding_wallet_acc,
-                    &mut rewards_wallet_acc,
-                    &mut slot_holders_rewards_wallet_acc,
-                    &mut sollotto_labs_wallet_acc,
-                    &mut charity_1_acc,
-                    &mut charity_2_acc,
-                    &mut charity_3_acc,
-                    &mut charity_4_acc,
-                    &mut system_acc,
-                    &mut user1_ticket_acc,
-                    &mut user1_wallet_acc,
-                    &mut user2_ticket_acc,
-                    &mut user2_wallet_acc
+                    &mut authority_acc,
+                    &mut charity_registry_acc
                 ]
             )
         );
+    }
 
-        do_process(
-            crate::instruction::initialize_lottery(
-                &program_id,
-                lottery_id,
-                &charity_1,
-                &charity_2,
-                &charity_3,
-                &charity_4,
-                &holding_wallet,
-                &rewards_wallet,
-                &slot_holders_rewards_wallet,
-                &sollotto_labs_wallet,
-                &randomness_account,
-                &lottery_key,
-            )
-            .unwrap(),
-            vec![&mut lottery_acc, &mut rent_sysvar_acc],
-        )
-        .unwrap();
+    #[test]
+    fn test_charity_registry() {
+        let program_id = id();
+        let payer_key = Pubkey::new_unique();
+        let mut payer_acc = SolanaAccount::default();
+        let authority_key = Pubkey::new_unique();
+        let mut authority_acc = SolanaAccount::default();
+        let other_authority_key = Pubkey::new_unique();
+        let mut other_authority_acc = SolanaAccount::default();
+        let mut charity_registry_acc = SolanaAccount::new(
+            Rent::default().minimum_balance(CharityRegistryData::LEN),
+            CharityRegistryData::get_packed_len(),
+            &program_id,
+        );
+        let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
+        let mut system_acc = SolanaAccount::default();
+        let charity_wallet = Pubkey::new_unique();
+        let new_charity_wallet = Pubkey::new_unique();
+        let name_hash = [7; 32];
+        let mut program_data_acc = program_data_account(&authority_key);
 
-        // BadCase: Lottery is not finaled
+        // BadCase: registry is not initialized
         assert_eq!(
-            Err(LotteryError::NotFinaled.into()),
+            Err(ProgramError::UninitializedAccount),
             do_process(
-                crate::instruction::reward_winners(
+                crate::instruction::add_charity(
                     &program_id,
-                    &lottery_key,
-                    &lottery_result_key,
-                    &holding_wallet,
-                    &rewards_wallet,
-                    &slot_holders_rewards_wallet,
-                    &sollotto_labs_wallet,
-                    &[charity_1, charity_2, charity_3, charity_4],
-                    &vec![(user1_ticket, user1_wallet), (user2_ticket, user2_wallet)],
+                    &name_hash,
+                    &charity_wallet,
+                    &authority_key
                 )
                 .unwrap(),
-                vec![
-                    &mut lottery_acc,
-                    &mut lottery_result_acc,
-                    &mut holding_wallet_acc,
-                    &mut rewards_wallet_acc,
-                    &mut slot_holders_rewards_wallet_acc,
-                    &mut sollotto_labs_wallet_acc,
-                    &mut charity_1_acc,
-                    &mut charity_2_acc,
-                    &mut charity_3_acc,
-                    &mut charity_4_acc,
-                    &mut system_acc,
-                    &mut user1_ticket_acc,
-                    &mut user1_wallet_acc,
-                    &mut user2_ticket_acc,
-                    &mut user2_wallet_acc
-                ]
+                vec![&mut charity_registry_acc, &mut authority_acc]
             )
         );
 
-        // BadCase: user cannot pay for ticket
-        let user1_charity = charity_1;
+        // BadCase: registry is created by another signer than the upgrade authority
         assert_eq!(
-            Err(ProgramError::InsufficientFunds),
+            Err(LotteryError::InvalidAuthority.into()),
             do_process(
-                crate::instruction::purchase_ticket(
+                crate::instruction::init_charity_registry(
                     &program_id,
-                    &user1_charity,
-                    &user1_wallet,
-                    &[1, 2, 3, 4, 55, 6],
-                    &user1_ticket,
-                    &holding_wallet,
-                    &lottery_key,
-                    &user1_lifetime_ticket_key,
-                    &lifetime_ticket_owner_key,
-                    &lifetime_ticket_mint_key,
-                )
-                .unwrap(),
-                vec![
-                    &mut lottery_acc,
-                    &mut user1_ticket_acc,
-                    &mut user1_wallet_acc,
-                    &mut holding_wallet_acc,
-                    &mut user1_lifetime_ticket_acc,
-                    &mut lifetime_ticket_owner_acc,
-                    &mut lifetime_ticket_mint_acc,
+                    &payer_key,
+                    &other_authority_key
+                )
+                .unwrap(),
+                vec![
+                    &mut charity_registry_acc,
+                    &mut payer_acc,
+                    &mut other_authority_acc,
                     &mut rent_sysvar_acc,
                     &mut system_acc,
-                    &mut spl_token_acc,
-                ],
+                    &mut program_data_acc,
+                ]
             )
         );
 
-        // Purchase tickets
-        user1_wallet_acc.lamports += sol_to_lamports(0.1);
-        do_process(
-            crate::instruction::purchase_ticket(
-                &program_id,
-                &user1_charity,
-                &user1_wallet,
-                &[11, 22, 33, 44, 51, 1],
-                &user1_ticket,
-                &holding_wallet,
-                &lottery_key,
-                &user1_lifetime_ticket_key,
-                &lifetime_ticket_owner_key,
-                &lifetime_ticket_mint_key,
+        // BadCase: program without the upgrade authority
+        let mut immutable_program_data_acc = program_data_account(&authority_key);
+        immutable_program_data_acc.data[12] = 0;
+        assert_eq!(
+            Err(LotteryError::InvalidAuthority.into()),
+            do_process(
+                crate::instruction::init_charity_registry(&program_id, &payer_key, &authority_key)
+                    .unwrap(),
+                vec![
+                    &mut charity_registry_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
+                    &mut system_acc,
+                    &mut immutable_program_data_acc,
+                ]
             )
-            .unwrap(),
-            vec![
-                &mut lottery_acc,
-                &mut user1_ticket_acc,
-                &mut user1_wallet_acc,
-                &mut holding_wallet_acc,
-                &mut user1_lifetime_ticket_acc,
-                &mut lifetime_ticket_owner_acc,
-                &mut lifetime_ticket_mint_acc,
-                &mut rent_sysvar_acc,
-                &mut system_acc,
-                &mut spl_token_acc,
-            ],
-        )
-        .unwrap();
+        );
 
-        let user2_charity = charity_1;
-        user2_wallet_acc.lamports += sol_to_lamports(0.1);
         do_process(
-            crate::instruction::purchase_ticket(
-                &program_id,
-                &user2_charity,
-                &user2_wallet,
-                &[2, 3, 4, 5, 66, 7],
-                &user2_ticket,
-                &holding_wallet,
-                &lottery_key,
-                &user2_lifetime_ticket_key,
-                &lifetime_ticket_owner_key,
-                &lifetime_ticket_mint_key,
-            )
-            .unwrap(),
+            crate::instruction::init_charity_registry(&program_id, &payer_key, &authority_key)
+                .unwrap(),
             vec![
-                &mut lottery_acc,
-                &mut user2_ticket_acc,
-                &mut user2_wallet_acc,
-                &mut holding_wallet_acc,
-                &mut user2_lifetime_ticket_acc,
-                &mut lifetime_ticket_owner_acc,
-                &mut lifetime_ticket_mint_acc,
+                &mut charity_registry_acc,
+                &mut payer_acc,
+                &mut authority_acc,
                 &mut rent_sysvar_acc,
                 &mut system_acc,
-                &mut spl_token_acc,
+                &mut program_data_acc,
             ],
         )
         .unwrap();
 
-        // Store winning numbers
-        do_process(
-            crate::instruction::store_winning_numbers(
-                &program_id,
-                &[2, 3, 4, 5, 66, 7],
-                &lottery_key,
-            )
-            .unwrap(),
-            vec![&mut lottery_acc],
-        )
-        .unwrap();
-
-        // BadCase: not enough users accounts
+        // BadCase: registry is initialized once
         assert_eq!(
-            Err(LotteryError::InvalidParticipantsAccounts.into()),
+            Err(LotteryError::Initialized.into()),
             do_process(
-                crate::instruction::reward_winners(
-                    &program_id,
-                    &lottery_key,
-                    &lottery_result_key,
-                    &holding_wallet,
-                    &rewards_wallet,
-                    &slot_holders_rewards_wallet,
-                    &sollotto_labs_wallet,
-                    &[charity_1, charity_2, charity_3, charity_4],
-                    &vec![(user2_ticket, user2_wallet)],
-                )
-                .unwrap(),
+                crate::instruction::init_charity_registry(&program_id, &payer_key, &authority_key)
+                    .unwrap(),
                 vec![
-                    &mut lottery_acc,
-                    &mut lottery_result_acc,
-                    &mut holding_wallet_acc,
-                    &mut rewards_wallet_acc,
-                    &mut slot_holders_rewards_wallet_acc,
-                    &mut sollotto_labs_wallet_acc,
-                    &mut charity_1_acc,
-                    &mut charity_2_acc,
-                    &mut charity_3_acc,
-                    &mut charity_4_acc,
+                    &mut charity_registry_acc,
+                    &mut payer_acc,
+                    &mut authority_acc,
+                    &mut rent_sysvar_acc,
                     &mut system_acc,
-                    &mut user2_ticket_acc,
-                    &mut user2_wallet_acc
+                    &mut program_data_acc,
                 ]
             )
         );
 
-        // BadCase: Bad wallet user pk in ticket data
-        let user1_fake_wallet = Pubkey::new_unique();
-        let mut user1_fake_wallet_acc = SolanaAccount::default();
+        // BadCase: charity is added by the registry authority only
         assert_eq!(
-            Err(LotteryError::InvalidParticipantsAccounts.into()),
+            Err(LotteryError::InvalidAuthority.into()),
             do_process(
-                crate::instruction::reward_winners(
+                crate::instruction::add_charity(
                     &program_id,
-                    &lottery_key,
-                    &lottery_result_key,
-                    &holding_wallet,
-                    &rewards_wallet,
-                    &slot_holders_rewards_wallet,
-                    &sollotto_labs_wallet,
-                    &[charity_1, charity_2, charity_3, charity_4],
-                    &vec![
-                        (user1_ticket, user1_fake_wallet),
-                        (user2_ticket, user2_wallet)
-                    ],
+                    &name_hash,
+                    &charity_wallet,
+                    &other_authority_key
                 )
                 .unwrap(),
-                vec![
-                    &mut lottery_acc,
-                    &mut lottery_result_acc,
-                    &mut holding_wallet_acc,
-                    &mut rewards_wallet_acc,
-                    &mut slot_holders_rewards_wallet_acc,
-                    &mut sollotto_labs_wallet_acc,
-                    &mut charity_1_acc,
-                    &mut charity_2_acc,
-                    &mut charity_3_acc,
-                    &mut charity_4_acc,
-                    &mut system_acc,
-                    &mut user1_ticket_acc,
-                    &mut user1_fake_wallet_acc,
-                    &mut user2_ticket_acc,
-                    &mut user2_wallet_acc
-                ]
+                vec![&mut charity_registry_acc, &mut other_authority_acc]
             )
         );
 
-        // BadCase: Bad sollotto reward account
-        let fake_sollotto_labs_wallet = Pubkey::new_unique();
+        do_process(
+            crate::instruction::add_charity(
+                &program_id,
+                &name_hash,
+                &charity_wallet,
+                &authority_key,
+            )
+            .unwrap(),
+            vec![&mut charity_registry_acc, &mut authority_acc],
+        )
+        .unwrap();
+
+        let registry = CharityRegistryData::unpack(&charity_registry_acc.data()).unwrap();
+        assert_eq!(registry.authority, authority_key);
         assert_eq!(
-            Err(LotteryError::InvalidSollottoAccount.into()),
+            registry.charities,
+            vec![RegistryCharity {
+                name_hash,
+                wallet: charity_wallet,
+                is_active: true,
+            }]
+        );
+
+        // BadCase: charity index is out of the registry
+        assert_eq!(
+            Err(LotteryError::InvalidCharity.into()),
             do_process(
-                crate::instruction::reward_winners(
+                crate::instruction::update_registry_charity(
                     &program_id,
-                    &lottery_key,
-                    &lottery_result_key,
-                    &holding_wallet,
-                    &rewards_wallet,
-                    &slot_holders_rewards_wallet,
-                    &fake_sollotto_labs_wallet,
-                    &[charity_1, charity_2, charity_3, charity_4],
-                    &vec![(user1_ticket, user1_wallet), (user2_ticket, user2_wallet)],
+                    1,
+                    &new_charity_wallet,
+                    false,
+                    &authority_key
                 )
                 .unwrap(),
-                vec![
-                    &mut lottery_acc,
-                    &mut lottery_result_acc,
-                    &mut holding_wallet_acc,
-                    &mut rewards_wallet_acc,
-                    &mut slot_holders_rewards_wallet_acc,
-                    &mut sollotto_labs_wallet_acc,
-                    &mut charity_1_acc,
-                    &mut charity_2_acc,
-                    &mut charity_3_acc,
-                    &mut charity_4_acc,
-                    &mut system_acc,
-                    &mut user1_ticket_acc,
-                    &mut user1_wallet_acc,
-                    &mut user2_ticket_acc,
-                    &mut user2_wallet_acc
-                ]
+                vec![&mut charity_registry_acc, &mut authority_acc]
             )
         );
 
-        let lottery = LotteryData::unpack_unchecked(lottery_acc.data()).unwrap();
-        assert_eq!(lottery.total_registrations, 2);
-        assert_eq!(lottery.prize_pool_amount, sol_to_lamports(0.2));
-        assert_eq!(lottery.charity_1_vc, 2);
-        assert_eq!(lottery.winning_numbers, [2, 3, 4, 5, 66, 7]);
-
-        // User2 wins the lottery
-        holding_wallet_acc.lamports += sol_to_lamports(10.0);
         do_process(
-            crate::instruction::reward_winners(
+            crate::instruction::update_registry_charity(
                 &program_id,
-                &lottery_key,
-                &lottery_result_key,
-                &holding_wallet,
-                &rewards_wallet,
-                &slot_holders_rewards_wallet,
-                &sollotto_labs_wallet,
-                &[charity_1, charity_2, charity_3, charity_4],
-                &vec![(user1_ticket, user1_wallet), (user2_ticket, user2_wallet)],
+                0,
+                &new_charity_wallet,
+                false,
+                &authority_key,
             )
             .unwrap(),
-            vec![
-                &mut lottery_acc,
-                &mut lottery_result_acc,
-                &mut holding_wallet_acc,
-                &mut rewards_wallet_acc,
-                &mut slot_holders_rewards_wallet_acc,
-                &mut sollotto_labs_wallet_acc,
-                &mut charity_1_acc,
-                &mut charity_2_acc,
-                &mut charity_3_acc,
-                &mut charity_4_acc,
-                &mut system_acc,
-                &mut user1_ticket_acc,
-                &mut user1_wallet_acc,
-                &mut user2_ticket_acc,
-                &mut user2_wallet_acc,
-            ],
+            vec![&mut charity_registry_acc, &mut authority_acc],
         )
         .unwrap();
 
-        // Check data
-        let lottery = LotteryData::unpack_unchecked(lottery_acc.data()).unwrap();
-        assert_eq!(lottery.is_initialized, false);
-        assert_eq!(lottery.is_finaled, false);
-        assert_eq!(lottery.total_registrations, 0);
-        assert_eq!(lottery.winning_numbers, [0, 0, 0, 0, 0, 0]);
-        assert_eq!(lottery.prize_pool_amount, 0);
+        let registry = CharityRegistryData::unpack(&charity_registry_acc.data()).unwrap();
+        assert_eq!(registry.charities[0].wallet, new_charity_wallet);
+        assert_eq!(registry.active_charity(0), None);
 
-        let lottery_result =
-            LotteryResultData::unpack_unchecked(lottery_result_acc.data()).unwrap();
-        assert_eq!(lottery_result.lottery_id, lottery_id);
-        assert_eq!(lottery_result.winning_numbers, [2, 3, 4, 5, 66, 7]);
+        // BadCase: registry is full
+        let mut registry = registry;
+        registry.charities = vec![RegistryCharity::default(); MAX_REGISTRY_CHARITIES];
+        CharityRegistryData::pack(registry, &mut charity_registry_acc.data).unwrap();
+        assert_eq!(
+            Err(LotteryError::InvalidCharity.into()),
+            do_process(
+                crate::instruction::add_charity(
+                    &program_id,
+                    &name_hash,
+                    &charity_wallet,
+                    &authority_key
+                )
+                .unwrap(),
+                vec![&mut charity_registry_acc, &mut authority_acc]
+            )
+        );
     }
 
     #[test]
-    fn test_update_charity() {
+    fn test_update_sollotto_wallets() {
         let program_id = id();
         let lottery_id = 112233;
-        let lottery_key = Pubkey::new_unique();
+        let payer_key = Pubkey::new_unique();
+        let mut payer_acc = SolanaAccount::default();
+        let authority_key = Pubkey::new_unique();
+        let mut authority_acc = SolanaAccount::default();
         let mut lottery_acc = SolanaAccount::new(
             lottery_minimum_balance(),
             LotteryData::get_packed_len(),
             &program_id,
         );
         let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
+        let mut clock_sysvar_acc = clock_account(SALE_START);
+        let mut system_acc = SolanaAccount::default();
         let charity_1 = Pubkey::new_unique();
         let charity_2 = Pubkey::new_unique();
         let charity_3 = Pubkey::new_unique();
         let charity_4 = Pubkey::new_unique();
-        let holding_wallet = Pubkey::new_unique();
+        let mut charity_registry_acc =
+            charity_registry_account(&[charity_1, charity_2, charity_3, charity_4]);
+        let mut program_data_acc = program_data_account(&authority_key);
+        let mut vault_acc = SolanaAccount::default();
         let rewards_wallet = Pubkey::new_unique();
         let slot_holders_rewards_wallet = Pubkey::new_unique();
         let sollotto_labs_wallet = Pubkey::new_unique();
         let randomness_account = Pubkey::new_unique();
 
-        let new_charity_1 = Pubkey::new_unique();
-        let new_charity_2 = charity_1;
-        let new_charity_3 = Pubkey::new_unique();
-        let new_charity_4 = charity_4;
+        let new_rewards_wallet = rewards_wallet;
+        let new_slot_holders_rewards_wallet = Pubkey::new_unique();
+        let new_sollotto_labs_wallet = sollotto_labs_wallet;
 
         // BadCase: Lottery is not initialized
         assert_eq!(
             Err(LotteryError::NotInitialized.into()),
             do_process(
-                crate::instruction::update_charity(
+                crate::instruction::update_sollotto_wallets(
                     &program_id,
-                    &new_charity_1,
-                    &new_charity_2,
-                    &new_charity_3,
-                    &new_charity_4,
-                    &lottery_key,
+                    lottery_id,
+                    &new_rewards_wallet,
+                    &new_slot_holders_rewards_wallet,
+                    &new_sollotto_labs_wallet,
+                    &authority_key,
                 )
                 .unwrap(),
-                vec![&mut lottery_acc]
+                vec![&mut lottery_acc, &mut authority_acc]
             )
         );
 
@@ -1995,89 +9118,116 @@ mod test {
             crate::instruction::initialize_lottery(
                 &program_id,
                 lottery_id,
-                &charity_1,
-                &charity_2,
-                &charity_3,
-                &charity_4,
-                &holding_wallet,
+                &[0, 1, 2, 3],
                 &rewards_wallet,
                 &slot_holders_rewards_wallet,
                 &sollotto_labs_wallet,
                 &randomness_account,
-                &lottery_key,
+                SALE_START,
+                SALE_END,
+                DRAW_TIME,
+                CLAIM_PERIOD,
+                MAIN_POOL_SIZE,
+                BONUS_POOL_SIZE,
+                PICK_COUNT,
+                TICKET_PRICE,
+                CharityMode::WinnerTakesAll,
+                RandomnessProvider::SwitchboardVrf,
+                &[0; 32],
+                None,
+                &payer_key,
+                &authority_key,
             )
             .unwrap(),
-            vec![&mut lottery_acc, &mut rent_sysvar_acc],
+            vec![
+                &mut lottery_acc,
+                &mut payer_acc,
+                &mut authority_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut charity_registry_acc,
+                &mut vault_acc,
+                &mut program_data_acc,
+            ],
         )
         .unwrap();
 
         let lottery = LotteryData::unpack(&lottery_acc.data()).unwrap();
-        assert_eq!(lottery.charity_1, charity_1);
-        assert_eq!(lottery.charity_2, charity_2);
-        assert_eq!(lottery.charity_3, charity_3);
-        assert_eq!(lottery.charity_4, charity_4);
+        assert_eq!(lottery.rewards_wallet, rewards_wallet);
+        assert_eq!(
+            lottery.slot_holders_rewards_wallet,
+            slot_holders_rewards_wallet
+        );
+        assert_eq!(lottery.sollotto_labs_wallet, sollotto_labs_wallet);
 
         do_process(
-            crate::instruction::update_charity(
+            crate::instruction::update_sollotto_wallets(
                 &program_id,
-                &new_charity_1,
-                &new_charity_2,
-                &new_charity_3,
-                &new_charity_4,
-                &lottery_key,
+                lottery_id,
+                &new_rewards_wallet,
+                &new_slot_holders_rewards_wallet,
+                &new_sollotto_labs_wallet,
+                &authority_key,
             )
             .unwrap(),
-            vec![&mut lottery_acc],
+            vec![&mut lottery_acc, &mut authority_acc],
         )
         .unwrap();
 
         let lottery = LotteryData::unpack(&lottery_acc.data()).unwrap();
-        assert_eq!(lottery.charity_1, new_charity_1);
-        assert_eq!(lottery.charity_2, new_charity_2);
-        assert_eq!(lottery.charity_3, new_charity_3);
-        assert_eq!(lottery.charity_4, new_charity_4);
+        assert_eq!(lottery.rewards_wallet, new_rewards_wallet);
+        assert_eq!(
+            lottery.slot_holders_rewards_wallet,
+            new_slot_holders_rewards_wallet
+        );
+        assert_eq!(lottery.sollotto_labs_wallet, new_sollotto_labs_wallet);
     }
 
     #[test]
-    fn test_update_sollotto_wallets() {
+    fn test_set_authority() {
         let program_id = id();
         let lottery_id = 112233;
-        let lottery_key = Pubkey::new_unique();
+        let payer_key = Pubkey::new_unique();
+        let mut payer_acc = SolanaAccount::default();
+        let authority_key = Pubkey::new_unique();
+        let mut authority_acc = SolanaAccount::default();
         let mut lottery_acc = SolanaAccount::new(
             lottery_minimum_balance(),
             LotteryData::get_packed_len(),
             &program_id,
         );
         let mut rent_sysvar_acc = create_account_for_test(&Rent::default());
+        let mut clock_sysvar_acc = clock_account(SALE_START);
+        let mut system_acc = SolanaAccount::default();
         let charity_1 = Pubkey::new_unique();
         let charity_2 = Pubkey::new_unique();
         let charity_3 = Pubkey::new_unique();
         let charity_4 = Pubkey::new_unique();
-        let holding_wallet = Pubkey::new_unique();
+        let mut charity_registry_acc =
+            charity_registry_account(&[charity_1, charity_2, charity_3, charity_4]);
+        let mut program_data_acc = program_data_account(&authority_key);
+        let mut vault_acc = SolanaAccount::default();
         let rewards_wallet = Pubkey::new_unique();
         let slot_holders_rewards_wallet = Pubkey::new_unique();
         let sollotto_labs_wallet = Pubkey::new_unique();
         let randomness_account = Pubkey::new_unique();
 
-        let new_holding_wallet = Pubkey::new_unique();
-        let new_rewards_wallet = rewards_wallet;
-        let new_slot_holders_rewards_wallet = Pubkey::new_unique();
-        let new_sollotto_labs_wallet = sollotto_labs_wallet;
+        let new_authority_key = Pubkey::new_unique();
+        let mut new_authority_acc = SolanaAccount::default();
 
         // BadCase: Lottery is not initialized
         assert_eq!(
             Err(LotteryError::NotInitialized.into()),
             do_process(
-                crate::instruction::update_sollotto_wallets(
+                crate::instruction::set_authority(
                     &program_id,
-                    &new_holding_wallet,
-                    &new_rewards_wallet,
-                    &new_slot_holders_rewards_wallet,
-                    &new_sollotto_labs_wallet,
-                    &lottery_key,
+                    lottery_id,
+                    &new_authority_key,
+                    &authority_key,
                 )
                 .unwrap(),
-                vec![&mut lottery_acc]
+                vec![&mut lottery_acc, &mut authority_acc]
             )
         );
 
@@ -2085,52 +9235,124 @@ mod test {
             crate::instruction::initialize_lottery(
                 &program_id,
                 lottery_id,
-                &charity_1,
-                &charity_2,
-                &charity_3,
-                &charity_4,
-                &holding_wallet,
+                &[0, 1, 2, 3],
                 &rewards_wallet,
                 &slot_holders_rewards_wallet,
                 &sollotto_labs_wallet,
                 &randomness_account,
-                &lottery_key,
+                SALE_START,
+                SALE_END,
+                DRAW_TIME,
+                CLAIM_PERIOD,
+                MAIN_POOL_SIZE,
+                BONUS_POOL_SIZE,
+                PICK_COUNT,
+                TICKET_PRICE,
+                CharityMode::WinnerTakesAll,
+                RandomnessProvider::SwitchboardVrf,
+                &[0; 32],
+                None,
+                &payer_key,
+                &authority_key,
             )
             .unwrap(),
-            vec![&mut lottery_acc, &mut rent_sysvar_acc],
+            vec![
+                &mut lottery_acc,
+                &mut payer_acc,
+                &mut authority_acc,
+                &mut rent_sysvar_acc,
+                &mut clock_sysvar_acc,
+                &mut system_acc,
+                &mut charity_registry_acc,
+                &mut vault_acc,
+                &mut program_data_acc,
+            ],
         )
         .unwrap();
 
         let lottery = LotteryData::unpack(&lottery_acc.data()).unwrap();
-        assert_eq!(lottery.holding_wallet, holding_wallet);
-        assert_eq!(lottery.rewards_wallet, rewards_wallet);
+        assert_eq!(lottery.authority, authority_key);
+        assert_eq!(lottery.pending_authority, Pubkey::default());
+
+        // BadCase: nothing to accept
         assert_eq!(
-            lottery.slot_holders_rewards_wallet,
-            slot_holders_rewards_wallet
+            Err(LotteryError::InvalidAuthority.into()),
+            do_process(
+                crate::instruction::accept_authority(&program_id, lottery_id, &new_authority_key)
+                    .unwrap(),
+                vec![&mut lottery_acc, &mut new_authority_acc]
+            )
+        );
+
+        // BadCase: only the current authority can propose a new one
+        assert_eq!(
+            Err(LotteryError::InvalidAuthority.into()),
+            do_process(
+                crate::instruction::set_authority(
+                    &program_id,
+                    lottery_id,
+                    &new_authority_key,
+                    &new_authority_key,
+                )
+                .unwrap(),
+                vec![&mut lottery_acc, &mut new_authority_acc]
+            )
         );
-        assert_eq!(lottery.sollotto_labs_wallet, sollotto_labs_wallet);
 
         do_process(
-            crate::instruction::update_sollotto_wallets(
+            crate::instruction::set_authority(
                 &program_id,
-                &new_holding_wallet,
-                &new_rewards_wallet,
-                &new_slot_holders_rewards_wallet,
-                &new_sollotto_labs_wallet,
-                &lottery_key,
+                lottery_id,
+                &new_authority_key,
+                &authority_key,
             )
             .unwrap(),
-            vec![&mut lottery_acc],
+            vec![&mut lottery_acc, &mut authority_acc],
         )
         .unwrap();
 
         let lottery = LotteryData::unpack(&lottery_acc.data()).unwrap();
-        assert_eq!(lottery.holding_wallet, new_holding_wallet);
-        assert_eq!(lottery.rewards_wallet, new_rewards_wallet);
+        assert_eq!(lottery.authority, authority_key);
+        assert_eq!(lottery.pending_authority, new_authority_key);
+
+        // BadCase: current authority can't accept on behalf of the pending one
         assert_eq!(
-            lottery.slot_holders_rewards_wallet,
-            new_slot_holders_rewards_wallet
+            Err(LotteryError::InvalidAuthority.into()),
+            do_process(
+                crate::instruction::accept_authority(&program_id, lottery_id, &authority_key)
+                    .unwrap(),
+                vec![&mut lottery_acc, &mut authority_acc]
+            )
+        );
+
+        do_process(
+            crate::instruction::accept_authority(&program_id, lottery_id, &new_authority_key)
+                .unwrap(),
+            vec![&mut lottery_acc, &mut new_authority_acc],
+        )
+        .unwrap();
+
+        let lottery = LotteryData::unpack(&lottery_acc.data()).unwrap();
+        assert_eq!(lottery.authority, new_authority_key);
+        assert_eq!(lottery.pending_authority, Pubkey::default());
+
+        // BadCase: previous authority lost the control
+        assert_eq!(
+            Err(LotteryError::InvalidAuthority.into()),
+            do_process(
+                crate::instruction::update_charity(
+                    &program_id,
+                    lottery_id,
+                    &[0, 1, 2, 3],
+                    &authority_key,
+                )
+                .unwrap(),
+                vec![
+                    &mut lottery_acc,
+                    &mut authority_acc,
+                    &mut charity_registry_acc
+                ]
+            )
         );
-        assert_eq!(lottery.sollotto_labs_wallet, new_sollotto_labs_wallet);
     }
 }
@@ -0,0 +1,120 @@
+//! Randomness sources of the lottery draw
+use crate::{error::LotteryError, state::CommitRevealData};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, hash::hashv, msg,
+    program_error::ProgramError, program_pack::Pack,
+};
+use switchboard_program::VrfAccount;
+
+/// Kind of the lottery randomness account
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RandomnessProvider {
+    /// Switchboard VRF account
+    SwitchboardVrf,
+    /// Commit-reveal account of the lottery, the revealed secret is mixed with a slot hash
+    CommitReveal,
+    /// Randomness account data is used as is, accepted only by test builds
+    Test,
+}
+
+impl Default for RandomnessProvider {
+    fn default() -> Self {
+        RandomnessProvider::SwitchboardVrf
+    }
+}
+
+impl RandomnessProvider {
+    /// Checks the provider is available in this build
+    pub fn validate(&self) -> ProgramResult {
+        if *self == RandomnessProvider::Test && !cfg!(any(test, feature = "test-randomness")) {
+            msg!("Test randomness is not available in this build");
+            return Err(LotteryError::InvalidRandomnessProvider.into());
+        }
+        Ok(())
+    }
+}
+
+/// Source of the randomness the winning numbers are derived from
+pub trait RandomnessSource {
+    /// Returns the verified randomness, fails if the result is not available
+    fn verified_randomness(&self) -> Result<Vec<u8>, ProgramError>;
+}
+
+/// Randomness of a Switchboard VRF account
+pub struct SwitchboardVrfSource<'a, 'b> {
+    account: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> RandomnessSource for SwitchboardVrfSource<'a, 'b> {
+    fn verified_randomness(&self) -> Result<Vec<u8>, ProgramError> {
+        // VRF account reader ties the account info to the lifetime of the account data,
+        // so the account is reborrowed for the time of the read
+        let mut lamports = self.account.try_borrow_mut_lamports()?;
+        let mut data = self.account.try_borrow_mut_data()?;
+        let account = AccountInfo::new(
+            self.account.key,
+            self.account.is_signer,
+            self.account.is_writable,
+            &mut lamports,
+            &mut data,
+            self.account.owner,
+            self.account.executable,
+            self.account.rent_epoch,
+        );
+        let vrf_account = VrfAccount::new(&account)?;
+        vrf_account.get_verified_randomness()
+    }
+}
+
+/// Randomness of a commit-reveal account
+pub struct CommitRevealSource<'a, 'b> {
+    account: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> RandomnessSource for CommitRevealSource<'a, 'b> {
+    fn verified_randomness(&self) -> Result<Vec<u8>, ProgramError> {
+        let reveal = CommitRevealData::unpack_unchecked(&self.account.data.borrow())?;
+        if !reveal.is_revealed {
+            msg!("Commit-reveal secret is not revealed");
+            return Err(LotteryError::InvalidRandomResult.into());
+        }
+        if hashv(&[&reveal.secret]).to_bytes() != reveal.commitment {
+            msg!("Revealed secret does not match the commitment");
+            return Err(LotteryError::InvalidRandomResult.into());
+        }
+        Ok(hashv(&[&reveal.secret, &reveal.slot_hash])
+            .to_bytes()
+            .to_vec())
+    }
+}
+
+/// Deterministic randomness for tests, the account data is the randomness
+#[cfg(any(test, feature = "test-randomness"))]
+pub struct TestSource<'a, 'b> {
+    account: &'a AccountInfo<'b>,
+}
+
+#[cfg(any(test, feature = "test-randomness"))]
+impl<'a, 'b> RandomnessSource for TestSource<'a, 'b> {
+    fn verified_randomness(&self) -> Result<Vec<u8>, ProgramError> {
+        Ok(self.account.data.borrow().to_vec())
+    }
+}
+
+/// Reads the verified randomness of the account by the lottery randomness provider
+pub fn load_randomness(
+    provider: RandomnessProvider,
+    account: &AccountInfo,
+) -> Result<Vec<u8>, ProgramError> {
+    provider.validate()?;
+    match provider {
+        RandomnessProvider::SwitchboardVrf => {
+            SwitchboardVrfSource { account }.verified_randomness()
+        }
+        RandomnessProvider::CommitReveal => CommitRevealSource { account }.verified_randomness(),
+        #[cfg(any(test, feature = "test-randomness"))]
+        RandomnessProvider::Test => TestSource { account }.verified_randomness(),
+        #[cfg(not(any(test, feature = "test-randomness")))]
+        RandomnessProvider::Test => Err(LotteryError::InvalidRandomnessProvider.into()),
+    }
+}
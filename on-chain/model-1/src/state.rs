@@ -1,10 +1,202 @@
 //! State transition types
+use crate::{
+    distribution::CharityMode,
+    numbers::{LotteryNumbers, Matches, NumberFormat},
+    randomness::RandomnessProvider,
+};
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 use solana_program::{
+    clock::UnixTimestamp,
     program_error::ProgramError,
     program_pack::{IsInitialized, Pack, Sealed},
     pubkey::Pubkey,
 };
+use std::convert::TryFrom;
+
+/// Seed prefix of lottery data accounts
+pub const LOTTERY_SEED: &[u8] = b"lottery";
+/// Seed prefix of ticket data accounts
+pub const TICKET_SEED: &[u8] = b"ticket";
+/// Seed prefix of lottery result data accounts
+pub const LOTTERY_RESULT_SEED: &[u8] = b"lottery_result";
+/// Seed prefix of settlement data accounts
+pub const SETTLEMENT_SEED: &[u8] = b"settlement";
+/// Seed prefix of prize table data accounts
+pub const PRIZE_TABLE_SEED: &[u8] = b"prize_table";
+/// Seed of the charity registry account
+pub const CHARITY_REGISTRY_SEED: &[u8] = b"charity_registry";
+/// Seed prefix of commit-reveal accounts
+pub const REVEAL_SEED: &[u8] = b"reveal";
+/// Seed prefix of lottery vaults, holding the lamports of the lottery
+pub const VAULT_SEED: &[u8] = b"vault";
+/// Seed prefix of vault token accounts
+pub const TOKEN_VAULT_SEED: &[u8] = b"token_vault";
+/// Seed of the vault authority, the owner of all vault token accounts
+pub const VAULT_AUTHORITY_SEED: &[u8] = b"vault_authority";
+/// Seed of the Lifetime Ticket authority, the mint authority of all Lifetime Ticket mints
+pub const LIFETIME_TICKET_AUTHORITY_SEED: &[u8] = b"lifetime_ticket_authority";
+/// Seed prefix of referral ledger accounts, a ledger per lottery and referrer
+pub const REFERRAL_SEED: &[u8] = b"referral";
+
+/// Time after the draw time the commit-reveal secret must be revealed in,
+/// the tickets are refunded after it
+pub const REVEAL_PERIOD: i64 = 24 * 60 * 60;
+
+/// Max count of tiers in a prize table
+pub const MAX_PRIZE_TIERS: usize = 8;
+
+/// Max count of number lines in a ticket
+pub const MAX_TICKET_LINES: usize = 10;
+
+/// Max count of charities in the charity registry
+pub const MAX_REGISTRY_CHARITIES: usize = 32;
+
+/// Max count of charities voted in a lottery
+pub const MAX_LOTTERY_CHARITIES: usize = 8;
+
+/// Max size of the randomness the winning numbers are derived from
+pub const MAX_RANDOMNESS_LEN: usize = 32;
+
+/// Layout version of the lottery result data written by the program
+pub const LOTTERY_RESULT_VERSION: u8 = 1;
+
+/// Size of the unversioned lottery result records: lottery id and 6 winning numbers
+pub const LEGACY_LOTTERY_RESULT_LEN: usize = 10;
+
+/// Finds the lottery data account address for the lottery id
+pub fn find_lottery_address(program_id: &Pubkey, lottery_id: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[LOTTERY_SEED, &lottery_id.to_le_bytes()], program_id)
+}
+
+/// Finds the ticket data account address for the lottery, user wallet and ticket nonce
+pub fn find_ticket_address(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    user_wallet: &Pubkey,
+    ticket_nonce: u32,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            TICKET_SEED,
+            lottery.as_ref(),
+            user_wallet.as_ref(),
+            &ticket_nonce.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Finds the lottery result data account address for the lottery id
+pub fn find_lottery_result_address(program_id: &Pubkey, lottery_id: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[LOTTERY_RESULT_SEED, &lottery_id.to_le_bytes()],
+        program_id,
+    )
+}
+
+/// Finds the settlement data account address for the lottery
+pub fn find_settlement_address(program_id: &Pubkey, lottery: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[SETTLEMENT_SEED, lottery.as_ref()], program_id)
+}
+
+/// Finds the prize table data account address for the lottery
+pub fn find_prize_table_address(program_id: &Pubkey, lottery: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PRIZE_TABLE_SEED, lottery.as_ref()], program_id)
+}
+
+/// Finds the charity registry account address
+pub fn find_charity_registry_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CHARITY_REGISTRY_SEED], program_id)
+}
+
+/// Finds the commit-reveal account address of the lottery
+pub fn find_reveal_address(program_id: &Pubkey, lottery: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REVEAL_SEED, lottery.as_ref()], program_id)
+}
+
+/// Finds the vault address of the lottery
+pub fn find_vault_address(program_id: &Pubkey, lottery: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VAULT_SEED, lottery.as_ref()], program_id)
+}
+
+/// Finds the vault token account address of the lottery
+pub fn find_token_vault_address(program_id: &Pubkey, lottery: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TOKEN_VAULT_SEED, lottery.as_ref()], program_id)
+}
+
+/// Finds the vault authority address
+pub fn find_vault_authority_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VAULT_AUTHORITY_SEED], program_id)
+}
+
+/// Finds the Lifetime Ticket authority address
+pub fn find_lifetime_ticket_authority_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[LIFETIME_TICKET_AUTHORITY_SEED], program_id)
+}
+
+/// Finds the referral ledger address of the referrer wallet in the lottery
+pub fn find_referral_address(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    referrer: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[REFERRAL_SEED, lottery.as_ref(), referrer.as_ref()],
+        program_id,
+    )
+}
+
+fn unpack_number_format(src: &[u8; 3]) -> NumberFormat {
+    NumberFormat {
+        main_pool_size: src[0],
+        bonus_pool_size: src[1],
+        pick_count: src[2],
+    }
+}
+
+fn pack_number_format(number_format: &NumberFormat, dst: &mut [u8; 3]) {
+    dst[0] = number_format.main_pool_size;
+    dst[1] = number_format.bonus_pool_size;
+    dst[2] = number_format.pick_count;
+}
+
+/// Unpacks the charity mode kind and the top charities count
+pub fn unpack_charity_mode(src: &[u8; 2]) -> Result<CharityMode, ProgramError> {
+    match src {
+        [0, _] => Ok(CharityMode::WinnerTakesAll),
+        [1, _] => Ok(CharityMode::Proportional),
+        [2, count] => Ok(CharityMode::TopK(*count)),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+/// Packs the charity mode kind and the top charities count
+pub fn pack_charity_mode(charity_mode: &CharityMode, dst: &mut [u8; 2]) {
+    *dst = match charity_mode {
+        CharityMode::WinnerTakesAll => [0, 0],
+        CharityMode::Proportional => [1, 0],
+        CharityMode::TopK(count) => [2, *count],
+    };
+}
+
+/// Unpacks the randomness provider kind
+pub fn unpack_randomness_provider(src: &[u8; 1]) -> Result<RandomnessProvider, ProgramError> {
+    match src {
+        [0] => Ok(RandomnessProvider::SwitchboardVrf),
+        [1] => Ok(RandomnessProvider::CommitReveal),
+        [2] => Ok(RandomnessProvider::Test),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+/// Packs the randomness provider kind
+pub fn pack_randomness_provider(randomness_provider: &RandomnessProvider, dst: &mut [u8; 1]) {
+    *dst = match randomness_provider {
+        RandomnessProvider::SwitchboardVrf => [0],
+        RandomnessProvider::CommitReveal => [1],
+        RandomnessProvider::Test => [2],
+    };
+}
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
@@ -12,22 +204,124 @@ pub struct LotteryData {
     pub is_initialized: bool,
     pub is_finaled: bool,
     pub lottery_id: u32,
-    pub charity_1: Pubkey,
-    pub charity_2: Pubkey,
-    pub charity_3: Pubkey,
-    pub charity_4: Pubkey,
-    pub charity_1_vc: u32,
-    pub charity_2_vc: u32,
-    pub charity_3_vc: u32,
-    pub charity_4_vc: u32,
+    /// Count of the lottery charities
+    pub charities_count: u8,
+    /// Charity registry indexes of the lottery charities
+    pub charity_indexes: [u16; MAX_LOTTERY_CHARITIES],
+    /// Votes of the lottery charities, in the charities order
+    pub charity_votes: [u32; MAX_LOTTERY_CHARITIES],
+    /// Count of the sold tickets, refunded tickets are not counted
     pub total_registrations: u32,
-    pub winning_numbers: [u8; 6],
+    pub winning_numbers: LotteryNumbers,
     pub prize_pool_amount: u64,
-    pub holding_wallet: Pubkey,
     pub rewards_wallet: Pubkey,
     pub slot_holders_rewards_wallet: Pubkey,
     pub sollotto_labs_wallet: Pubkey,
     pub randomness_account: Pubkey,
+    pub sale_start: UnixTimestamp,
+    pub sale_end: UnixTimestamp,
+    pub draw_time: UnixTimestamp,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    /// Seconds after the last winner is paid, while winners can claim their prizes
+    pub claim_period: i64,
+    pub number_format: NumberFormat,
+    /// Unpaid prize pool left by the finished draw, moved into the next draw pool on `InitLottery`
+    pub rollover_amount: u64,
+    /// Price of a single ticket line, in lamports or base units of the mint
+    pub ticket_price: u64,
+    /// Way the charity pool is split by the charity votes
+    pub charity_mode: CharityMode,
+    /// Size of the randomness used by the draw
+    pub randomness_len: u8,
+    /// Randomness the winning numbers are derived from
+    pub randomness: [u8; MAX_RANDOMNESS_LEN],
+    /// Slot of the draw
+    pub draw_slot: u64,
+    /// Time of the draw
+    pub drawn_at: UnixTimestamp,
+    /// Kind of the randomness account
+    pub randomness_provider: RandomnessProvider,
+    /// Hash of the result the randomness account holds at the draw request,
+    /// then of the drawn randomness. A result is stale while the account still returns it
+    pub last_randomness_hash: [u8; 32],
+    /// Hash of the operator secret, used by the commit-reveal randomness provider
+    pub commitment: [u8; 32],
+    /// Commit-reveal lotteries are drawn before this time, the tickets are refunded after it
+    pub reveal_deadline: UnixTimestamp,
+    /// Lottery is cancelled by the authority, the tickets are refunded
+    pub is_cancelled: bool,
+    /// Mint of the ticket currency, amounts are in its base units.
+    /// Default for lotteries paid in lamports
+    pub mint: Pubkey,
+    /// Account holding the lottery funds: the lottery vault,
+    /// or the vault token account of a token lottery
+    pub vault: Pubkey,
+    /// Mint of the Lifetime Tickets minted to the ticket buyers.
+    /// Default for lotteries without Lifetime Tickets
+    pub lifetime_ticket_mint: Pubkey,
+    /// Lifetime Ticket amount minted per ticket line, in base units of its mint
+    pub lifetime_ticket_amount: u64,
+    /// Share of the ticket price accrued to the referrer of the ticket, in basis points
+    pub referral_share_bps: u16,
+    /// Price of the tickets sold with a referrer in the current draw
+    pub referral_volume: u64,
+    /// Referral rewards accrued in the current draw
+    pub referral_rewards: u64,
+    /// Time the draw is requested after the sale is closed, zero before the request.
+    /// Randomness the account holds at the request is not accepted by the draw
+    pub draw_requested_at: UnixTimestamp,
+}
+
+impl LotteryData {
+    /// Randomness the winning numbers are derived from
+    pub fn randomness(&self) -> &[u8] {
+        &self.randomness[..self.randomness_len as usize]
+    }
+
+    /// Whether the commit-reveal draw is not drawn before the reveal deadline
+    pub fn is_abandoned(&self, unix_timestamp: UnixTimestamp) -> bool {
+        self.randomness_provider == RandomnessProvider::CommitReveal
+            && !self.is_finaled
+            && unix_timestamp > self.reveal_deadline
+    }
+
+    /// Whether the lottery is paid in tokens of its mint instead of lamports
+    pub fn is_token_lottery(&self) -> bool {
+        self.mint != Pubkey::default()
+    }
+
+    /// Whether Lifetime Tickets are minted to the ticket buyers
+    pub fn has_lifetime_ticket(&self) -> bool {
+        self.lifetime_ticket_mint != Pubkey::default()
+    }
+
+    /// Charity registry indexes of the lottery charities
+    pub fn charities(&self) -> &[u16] {
+        &self.charity_indexes[..self.charities_count as usize]
+    }
+
+    /// Votes of the lottery charities
+    pub fn votes(&self) -> &[u32] {
+        &self.charity_votes[..self.charities_count as usize]
+    }
+
+    /// Replaces the lottery charities, votes of the kept charities are kept
+    pub fn set_charities(&mut self, charities: &[u16]) {
+        let mut charity_indexes = [0; MAX_LOTTERY_CHARITIES];
+        let mut charity_votes = [0; MAX_LOTTERY_CHARITIES];
+        for (i, charity_index) in charities.iter().enumerate() {
+            charity_indexes[i] = *charity_index;
+            charity_votes[i] = self
+                .charities()
+                .iter()
+                .position(|index| index == charity_index)
+                .map_or(0, |pos| self.charity_votes[pos]);
+        }
+        self.charities_count = charities.len() as u8;
+        self.charity_indexes = charity_indexes;
+        self.charity_votes = charity_votes;
+    }
 }
 
 impl Sealed for LotteryData {}
@@ -39,32 +333,57 @@ impl IsInitialized for LotteryData {
 }
 
 impl Pack for LotteryData {
-    /// 1 + 1 + 4 + 32 + 32 + 32 + 32 + 4 + 4 + 4 + 4 + 4 + 6 + 8 + 32 + 32 + 32 + 32 + 32 = 296
-    const LEN: usize = 328;
+    /// 1 + 1 + 4 + 1 + 2 * 8 + 4 * 8 + 4 + 8 + 8 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 32 + 32 + 8 + 3 + 8 + 8 + 2
+    /// + 1 + 32 + 8 + 8 + 1 + 32 + 32 + 8 + 1 + 32 + 32 + 32 + 8 + 2 + 8 + 8 + 8 = 573
+    const LEN: usize = 573;
 
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let src = array_ref![src, 0, 328];
+        let src = array_ref![src, 0, 573];
         let (
             is_initialized,
             is_finaled,
             lottery_id,
-            charity_1,
-            charity_2,
-            charity_3,
-            charity_4,
-            charity_1_vc,
-            charity_2_vc,
-            charity_3_vc,
-            charity_4_vc,
+            charities_count,
+            charity_indexes_src,
+            charity_votes_src,
             total_registrations,
             winning_numbers,
             prize_pool_amount,
-            holding_wallet,
             rewards_wallet,
             slot_holders_rewards_wallet,
             sollotto_labs_wallet,
             randomness_account,
-        ) = array_refs![src, 1, 1, 4, 32, 32, 32, 32, 4, 4, 4, 4, 4, 6, 8, 32, 32, 32, 32, 32];
+            sale_start,
+            sale_end,
+            draw_time,
+            authority,
+            pending_authority,
+            claim_period,
+            number_format,
+            rollover_amount,
+            ticket_price,
+            charity_mode,
+            randomness_len,
+            randomness,
+            draw_slot,
+            drawn_at,
+            randomness_provider,
+            last_randomness_hash,
+            commitment,
+            reveal_deadline,
+            is_cancelled,
+            mint,
+            vault,
+            lifetime_ticket_mint,
+            lifetime_ticket_amount,
+            referral_share_bps,
+            referral_volume,
+            referral_rewards,
+            draw_requested_at,
+        ) = array_refs![
+            src, 1, 1, 4, 1, 16, 32, 4, 8, 8, 32, 32, 32, 32, 8, 8, 8, 32, 32, 8, 3, 8, 8, 2, 1,
+            32, 8, 8, 1, 32, 32, 8, 1, 32, 32, 32, 8, 2, 8, 8, 8
+        ];
 
         let is_initialized = match is_initialized {
             [0] => false,
@@ -78,155 +397,1109 @@ impl Pack for LotteryData {
             _ => return Err(ProgramError::InvalidAccountData),
         };
 
+        let is_cancelled = match is_cancelled {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        let charities_count = charities_count[0];
+        if charities_count as usize > MAX_LOTTERY_CHARITIES {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut charity_indexes = [0; MAX_LOTTERY_CHARITIES];
+        let mut charity_votes = [0; MAX_LOTTERY_CHARITIES];
+        for i in 0..MAX_LOTTERY_CHARITIES {
+            charity_indexes[i] = u16::from_le_bytes(*array_ref![charity_indexes_src, i * 2, 2]);
+            charity_votes[i] = u32::from_le_bytes(*array_ref![charity_votes_src, i * 4, 4]);
+        }
+
+        let randomness_len = randomness_len[0];
+        if randomness_len as usize > MAX_RANDOMNESS_LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
         let result = LotteryData {
             is_initialized,
             is_finaled,
             lottery_id: u32::from_le_bytes(*lottery_id),
-            charity_1: Pubkey::new_from_array(*charity_1),
-            charity_2: Pubkey::new_from_array(*charity_2),
-            charity_3: Pubkey::new_from_array(*charity_3),
-            charity_4: Pubkey::new_from_array(*charity_4),
-            charity_1_vc: u32::from_le_bytes(*charity_1_vc),
-            charity_2_vc: u32::from_le_bytes(*charity_2_vc),
-            charity_3_vc: u32::from_le_bytes(*charity_3_vc),
-            charity_4_vc: u32::from_le_bytes(*charity_4_vc),
+            charities_count,
+            charity_indexes,
+            charity_votes,
             total_registrations: u32::from_le_bytes(*total_registrations),
             winning_numbers: *winning_numbers,
             prize_pool_amount: u64::from_le_bytes(*prize_pool_amount),
-            holding_wallet: Pubkey::new_from_array(*holding_wallet),
             rewards_wallet: Pubkey::new_from_array(*rewards_wallet),
             slot_holders_rewards_wallet: Pubkey::new_from_array(*slot_holders_rewards_wallet),
             sollotto_labs_wallet: Pubkey::new_from_array(*sollotto_labs_wallet),
             randomness_account: Pubkey::new_from_array(*randomness_account),
+            sale_start: i64::from_le_bytes(*sale_start),
+            sale_end: i64::from_le_bytes(*sale_end),
+            draw_time: i64::from_le_bytes(*draw_time),
+            authority: Pubkey::new_from_array(*authority),
+            pending_authority: Pubkey::new_from_array(*pending_authority),
+            claim_period: i64::from_le_bytes(*claim_period),
+            number_format: unpack_number_format(number_format),
+            rollover_amount: u64::from_le_bytes(*rollover_amount),
+            ticket_price: u64::from_le_bytes(*ticket_price),
+            charity_mode: unpack_charity_mode(charity_mode)?,
+            randomness_len,
+            randomness: *randomness,
+            draw_slot: u64::from_le_bytes(*draw_slot),
+            drawn_at: i64::from_le_bytes(*drawn_at),
+            randomness_provider: unpack_randomness_provider(randomness_provider)?,
+            last_randomness_hash: *last_randomness_hash,
+            commitment: *commitment,
+            reveal_deadline: i64::from_le_bytes(*reveal_deadline),
+            is_cancelled,
+            mint: Pubkey::new_from_array(*mint),
+            vault: Pubkey::new_from_array(*vault),
+            lifetime_ticket_mint: Pubkey::new_from_array(*lifetime_ticket_mint),
+            lifetime_ticket_amount: u64::from_le_bytes(*lifetime_ticket_amount),
+            referral_share_bps: u16::from_le_bytes(*referral_share_bps),
+            referral_volume: u64::from_le_bytes(*referral_volume),
+            referral_rewards: u64::from_le_bytes(*referral_rewards),
+            draw_requested_at: i64::from_le_bytes(*draw_requested_at),
         };
 
         Ok(result)
     }
 
     fn pack_into_slice(&self, dst: &mut [u8]) {
-        let dst = array_mut_ref![dst, 0, 328];
+        let dst = array_mut_ref![dst, 0, 573];
         let (
             is_initialized_dst,
             is_finaled_dst,
             lottery_id_dst,
-            charity_1_dst,
-            charity_2_dst,
-            charity_3_dst,
-            charity_4_dst,
-            charity_1_vc_dst,
-            charity_2_vc_dst,
-            charity_3_vc_dst,
-            charity_4_vc_dst,
+            charities_count_dst,
+            charity_indexes_dst,
+            charity_votes_dst,
             total_registrations_dst,
             winning_numbers_dst,
             prize_pool_amount_dst,
-            holding_wallet_dst,
             rewards_wallet_dst,
             slot_holders_rewards_wallet_dst,
             sollotto_labs_wallet_dst,
             randomness_account_dst,
-        ) = mut_array_refs![dst, 1, 1, 4, 32, 32, 32, 32, 4, 4, 4, 4, 4, 6, 8, 32, 32, 32, 32, 32];
+            sale_start_dst,
+            sale_end_dst,
+            draw_time_dst,
+            authority_dst,
+            pending_authority_dst,
+            claim_period_dst,
+            number_format_dst,
+            rollover_amount_dst,
+            ticket_price_dst,
+            charity_mode_dst,
+            randomness_len_dst,
+            randomness_dst,
+            draw_slot_dst,
+            drawn_at_dst,
+            randomness_provider_dst,
+            last_randomness_hash_dst,
+            commitment_dst,
+            reveal_deadline_dst,
+            is_cancelled_dst,
+            mint_dst,
+            vault_dst,
+            lifetime_ticket_mint_dst,
+            lifetime_ticket_amount_dst,
+            referral_share_bps_dst,
+            referral_volume_dst,
+            referral_rewards_dst,
+            draw_requested_at_dst,
+        ) = mut_array_refs![
+            dst, 1, 1, 4, 1, 16, 32, 4, 8, 8, 32, 32, 32, 32, 8, 8, 8, 32, 32, 8, 3, 8, 8, 2, 1,
+            32, 8, 8, 1, 32, 32, 8, 1, 32, 32, 32, 8, 2, 8, 8, 8
+        ];
 
         is_initialized_dst[0] = self.is_initialized as u8;
         is_finaled_dst[0] = self.is_finaled as u8;
         *lottery_id_dst = self.lottery_id.to_le_bytes();
-        charity_1_dst.copy_from_slice(self.charity_1.as_ref());
-        charity_2_dst.copy_from_slice(self.charity_2.as_ref());
-        charity_3_dst.copy_from_slice(self.charity_3.as_ref());
-        charity_4_dst.copy_from_slice(self.charity_4.as_ref());
-        *charity_1_vc_dst = self.charity_1_vc.to_le_bytes();
-        *charity_2_vc_dst = self.charity_2_vc.to_le_bytes();
-        *charity_3_vc_dst = self.charity_3_vc.to_le_bytes();
-        *charity_4_vc_dst = self.charity_4_vc.to_le_bytes();
+        charities_count_dst[0] = self.charities_count;
+        for i in 0..MAX_LOTTERY_CHARITIES {
+            *array_mut_ref![charity_indexes_dst, i * 2, 2] = self.charity_indexes[i].to_le_bytes();
+            *array_mut_ref![charity_votes_dst, i * 4, 4] = self.charity_votes[i].to_le_bytes();
+        }
         *total_registrations_dst = self.total_registrations.to_le_bytes();
         *winning_numbers_dst = self.winning_numbers;
         *prize_pool_amount_dst = self.prize_pool_amount.to_le_bytes();
-        holding_wallet_dst.copy_from_slice(self.holding_wallet.as_ref());
         rewards_wallet_dst.copy_from_slice(self.rewards_wallet.as_ref());
         slot_holders_rewards_wallet_dst.copy_from_slice(self.slot_holders_rewards_wallet.as_ref());
         sollotto_labs_wallet_dst.copy_from_slice(self.sollotto_labs_wallet.as_ref());
         randomness_account_dst.copy_from_slice(self.randomness_account.as_ref());
+        *sale_start_dst = self.sale_start.to_le_bytes();
+        *sale_end_dst = self.sale_end.to_le_bytes();
+        *draw_time_dst = self.draw_time.to_le_bytes();
+        authority_dst.copy_from_slice(self.authority.as_ref());
+        pending_authority_dst.copy_from_slice(self.pending_authority.as_ref());
+        *claim_period_dst = self.claim_period.to_le_bytes();
+        pack_number_format(&self.number_format, number_format_dst);
+        *rollover_amount_dst = self.rollover_amount.to_le_bytes();
+        *ticket_price_dst = self.ticket_price.to_le_bytes();
+        pack_charity_mode(&self.charity_mode, charity_mode_dst);
+        randomness_len_dst[0] = self.randomness_len;
+        *randomness_dst = self.randomness;
+        *draw_slot_dst = self.draw_slot.to_le_bytes();
+        *drawn_at_dst = self.drawn_at.to_le_bytes();
+        pack_randomness_provider(&self.randomness_provider, randomness_provider_dst);
+        *last_randomness_hash_dst = self.last_randomness_hash;
+        *commitment_dst = self.commitment;
+        *reveal_deadline_dst = self.reveal_deadline.to_le_bytes();
+        is_cancelled_dst[0] = self.is_cancelled as u8;
+        mint_dst.copy_from_slice(self.mint.as_ref());
+        vault_dst.copy_from_slice(self.vault.as_ref());
+        lifetime_ticket_mint_dst.copy_from_slice(self.lifetime_ticket_mint.as_ref());
+        *lifetime_ticket_amount_dst = self.lifetime_ticket_amount.to_le_bytes();
+        *referral_share_bps_dst = self.referral_share_bps.to_le_bytes();
+        *referral_volume_dst = self.referral_volume.to_le_bytes();
+        *referral_rewards_dst = self.referral_rewards.to_le_bytes();
+        *draw_requested_at_dst = self.draw_requested_at.to_le_bytes();
     }
 }
 
+/// Number line of a ticket
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TicketLine {
+    pub ticket_number_arr: LotteryNumbers,
+    /// Count of the line main numbers matching the winning main numbers
+    pub matched: u8,
+    /// Line bonus number matches the winning bonus number
+    pub bonus_matched: bool,
+}
+
+impl TicketLine {
+    /// Matches counted by the tally
+    pub fn matches(&self) -> Matches {
+        Matches {
+            main: self.matched,
+            bonus: self.bonus_matched,
+        }
+    }
+}
+
+impl Sealed for TicketLine {}
+
+impl Pack for TicketLine {
+    /// 1 * 8 + 1 + 1 = 10
+    const LEN: usize = 10;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 10];
+        let (ticket_number_arr, matched, bonus_matched) = array_refs![src, 8, 1, 1];
+
+        let bonus_matched = match bonus_matched {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(TicketLine {
+            ticket_number_arr: *ticket_number_arr,
+            matched: matched[0],
+            bonus_matched,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 10];
+        let (ticket_number_arr_dst, matched_dst, bonus_matched_dst) = mut_array_refs![dst, 8, 1, 1];
+
+        ticket_number_arr_dst.copy_from_slice(self.ticket_number_arr.as_ref());
+        matched_dst[0] = self.matched;
+        bonus_matched_dst[0] = self.bonus_matched as u8;
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct TicketData {
     pub is_purchased: bool,
-    pub charity: Pubkey,
+    /// Lottery the ticket is purchased for
+    pub lottery_id: u32,
+    /// Charity registry index voted by the ticket
+    pub charity_index: u16,
     pub user_wallet_pk: Pubkey,
-    pub ticket_number_arr: [u8; 6],
+    pub ticket_nonce: u32,
+    /// Slot of the purchase
+    pub purchase_slot: u64,
+    /// Ticket matches are counted in the settlement
+    pub is_tallied: bool,
+    /// Ticket prize is recorded and moved to the settlement escrow
+    pub is_paid: bool,
+    /// Prize the ticket owner is entitled to for all lines, in lamports or base units of the mint
+    pub prize_amount: u64,
+    /// Ticket prize is claimed by its owner
+    pub is_claimed: bool,
+    /// Ticket price is returned to its owner, the ticket doesn't take part in the draw
+    pub is_refunded: bool,
+    /// Up to `MAX_TICKET_LINES` number lines, every line takes part in the draw on its own
+    pub lines: Vec<TicketLine>,
+    /// Part of the ticket price accrued to the referrer, taken back from the referral ledger on refund
+    pub referral_amount: u64,
+    /// Referrer of the ticket, default for tickets purchased without a referrer
+    pub referrer: Pubkey,
 }
 
 impl Sealed for TicketData {}
 
 impl Pack for TicketData {
-    /// 1 + 32 + 32 + 1 * 6 = 70
-    const LEN: usize = 71;
+    /// 1 + 4 + 2 + 32 + 4 + 8 + 1 + 1 + 8 + 1 + 1 + 1 + 10 * 10 + 8 + 32 = 204
+    const LEN: usize = 204;
 
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let src = array_ref![src, 0, 71];
-        let (is_purchased, charity, user_wallet_pk, ticket_number_arr) =
-            array_refs![src, 1, 32, 32, 6];
+        let src = array_ref![src, 0, 204];
+        let (
+            is_purchased,
+            lottery_id,
+            charity_index,
+            user_wallet_pk,
+            ticket_nonce,
+            purchase_slot,
+            is_tallied,
+            is_paid,
+            prize_amount,
+            is_claimed,
+            is_refunded,
+            lines_count,
+            lines_src,
+            referral_amount,
+            referrer,
+        ) = array_refs![src, 1, 4, 2, 32, 4, 8, 1, 1, 8, 1, 1, 1, 100, 8, 32];
 
         let is_purchased = match is_purchased {
             [0] => false,
             [1] => true,
             _ => return Err(ProgramError::InvalidAccountData),
         };
+        let is_tallied = match is_tallied {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let is_paid = match is_paid {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let is_claimed = match is_claimed {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let is_refunded = match is_refunded {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        let lines_count = lines_count[0] as usize;
+        if lines_count > MAX_TICKET_LINES {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut lines = Vec::with_capacity(lines_count);
+        for i in 0..lines_count {
+            lines.push(TicketLine::unpack_from_slice(
+                &lines_src[i * TicketLine::LEN..(i + 1) * TicketLine::LEN],
+            )?);
+        }
 
         let result = TicketData {
             is_purchased: is_purchased,
-            charity: Pubkey::new_from_array(*charity),
+            lottery_id: u32::from_le_bytes(*lottery_id),
+            charity_index: u16::from_le_bytes(*charity_index),
             user_wallet_pk: Pubkey::new_from_array(*user_wallet_pk),
-            ticket_number_arr: *ticket_number_arr,
+            ticket_nonce: u32::from_le_bytes(*ticket_nonce),
+            purchase_slot: u64::from_le_bytes(*purchase_slot),
+            is_tallied: is_tallied,
+            is_paid: is_paid,
+            prize_amount: u64::from_le_bytes(*prize_amount),
+            is_claimed: is_claimed,
+            is_refunded: is_refunded,
+            lines,
+            referral_amount: u64::from_le_bytes(*referral_amount),
+            referrer: Pubkey::new_from_array(*referrer),
         };
 
         Ok(result)
     }
 
     fn pack_into_slice(&self, dst: &mut [u8]) {
-        let dst = array_mut_ref![dst, 0, 71];
-        let (is_purchased_dst, charity_dst, user_wallet_pk_dst, ticket_number_arr_dst) =
-            mut_array_refs![dst, 1, 32, 32, 6];
+        let dst = array_mut_ref![dst, 0, 204];
+        let (
+            is_purchased_dst,
+            lottery_id_dst,
+            charity_index_dst,
+            user_wallet_pk_dst,
+            ticket_nonce_dst,
+            purchase_slot_dst,
+            is_tallied_dst,
+            is_paid_dst,
+            prize_amount_dst,
+            is_claimed_dst,
+            is_refunded_dst,
+            lines_count_dst,
+            lines_dst,
+            referral_amount_dst,
+            referrer_dst,
+        ) = mut_array_refs![dst, 1, 4, 2, 32, 4, 8, 1, 1, 8, 1, 1, 1, 100, 8, 32];
 
         is_purchased_dst[0] = self.is_purchased as u8;
-        charity_dst.copy_from_slice(self.charity.as_ref());
+        *lottery_id_dst = self.lottery_id.to_le_bytes();
+        *charity_index_dst = self.charity_index.to_le_bytes();
         user_wallet_pk_dst.copy_from_slice(self.user_wallet_pk.as_ref());
-        ticket_number_arr_dst.copy_from_slice(self.ticket_number_arr.as_ref());
+        *ticket_nonce_dst = self.ticket_nonce.to_le_bytes();
+        *purchase_slot_dst = self.purchase_slot.to_le_bytes();
+        is_tallied_dst[0] = self.is_tallied as u8;
+        is_paid_dst[0] = self.is_paid as u8;
+        *prize_amount_dst = self.prize_amount.to_le_bytes();
+        is_claimed_dst[0] = self.is_claimed as u8;
+        is_refunded_dst[0] = self.is_refunded as u8;
+        lines_count_dst[0] = self.lines.len() as u8;
+        for byte in lines_dst.iter_mut() {
+            *byte = 0;
+        }
+        for (i, line) in self.lines.iter().enumerate() {
+            line.pack_into_slice(&mut lines_dst[i * TicketLine::LEN..(i + 1) * TicketLine::LEN]);
+        }
+        *referral_amount_dst = self.referral_amount.to_le_bytes();
+        referrer_dst.copy_from_slice(self.referrer.as_ref());
     }
 }
 
+/// Audit record of a finished draw, enough to verify the draw from chain data alone
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct LotteryResultData {
+    /// Layout version, zero for the legacy records
+    pub version: u8,
     pub lottery_id: u32,
-    pub winning_numbers: [u8; 6],
+    pub number_format: NumberFormat,
+    pub winning_numbers: LotteryNumbers,
+    /// Unpaid prize pool rolled over to the next draw
+    pub rollover_amount: u64,
+    /// Count of the sold tickets
+    pub total_tickets: u32,
+    /// Prize pool of the draw, in base units of the mint
+    pub prize_pool_amount: u64,
+    /// Count of winners per prize table tier
+    pub tier_winners: [u32; MAX_PRIZE_TIERS],
+    /// Reward of a single winner per prize table tier
+    pub tier_rewards: [u64; MAX_PRIZE_TIERS],
+    /// Count of the lottery charities
+    pub charities_count: u8,
+    /// Wallets of the lottery charities
+    pub charities: [Pubkey; MAX_LOTTERY_CHARITIES],
+    /// Charity pool paid to every charity, in the charities order
+    pub charity_rewards: [u64; MAX_LOTTERY_CHARITIES],
+    /// VRF account the randomness is read from
+    pub randomness_account: Pubkey,
+    /// Size of the randomness used by the draw
+    pub randomness_len: u8,
+    /// Randomness the winning numbers are derived from
+    pub randomness: [u8; MAX_RANDOMNESS_LEN],
+    /// Slot of the draw
+    pub draw_slot: u64,
+    /// Time of the draw
+    pub drawn_at: UnixTimestamp,
+    /// Mint of the amounts, default for lamports
+    pub mint: Pubkey,
+    /// Price of the tickets sold with a referrer
+    pub referral_volume: u64,
+    /// Referral rewards accrued in the draw
+    pub referral_rewards: u64,
+}
+
+impl LotteryResultData {
+    /// Randomness the winning numbers are derived from
+    pub fn randomness(&self) -> &[u8] {
+        &self.randomness[..self.randomness_len as usize]
+    }
+
+    /// Unpacks the lottery result of any layout version.
+    /// Legacy records only have the lottery id and 6 winning numbers, other fields are zero.
+    pub fn unpack_versioned(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() == LEGACY_LOTTERY_RESULT_LEN {
+            let src = array_ref![src, 0, 10];
+            let (lottery_id, winning_numbers) = array_refs![src, 4, 6];
+
+            let mut result = LotteryResultData {
+                version: 0,
+                lottery_id: u32::from_le_bytes(*lottery_id),
+                ..LotteryResultData::default()
+            };
+            result.winning_numbers[..6].copy_from_slice(winning_numbers);
+            return Ok(result);
+        }
+        // Later versions only append fields
+        if src.len() < Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::unpack_from_slice(src)
+    }
 }
 
 impl Sealed for LotteryResultData {}
 
 impl Pack for LotteryResultData {
-    /// 4 + 6 = 10
-    const LEN: usize = 10;
+    /// 1 + 4 + 3 + 8 + 8 + 4 + 8 + 4 * 8 + 8 * 8 + 1 + 32 * 8 + 8 * 8 + 32 + 1 + 32 + 8 + 8 + 32
+    /// + 8 + 8 = 582
+    const LEN: usize = 582;
 
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let src = array_ref![src, 0, 10];
-        let (lottery_id, winning_numbers) = array_refs![src, 4, 6];
+        let src = array_ref![src, 0, 582];
+        let (
+            version,
+            lottery_id,
+            number_format,
+            winning_numbers,
+            rollover_amount,
+            total_tickets,
+            prize_pool_amount,
+            tier_winners_src,
+            tier_rewards_src,
+            charities_count,
+            charities_src,
+            charity_rewards_src,
+            randomness_account,
+            randomness_len,
+            randomness,
+            draw_slot,
+            drawn_at,
+            mint,
+            referral_volume,
+            referral_rewards,
+        ) = array_refs![src, 1, 4, 3, 8, 8, 4, 8, 32, 64, 1, 256, 64, 32, 1, 32, 8, 8, 32, 8, 8];
+
+        if version[0] == 0 || version[0] > LOTTERY_RESULT_VERSION {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let randomness_len = randomness_len[0];
+        if randomness_len as usize > MAX_RANDOMNESS_LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut tier_winners = [0; MAX_PRIZE_TIERS];
+        let mut tier_rewards = [0; MAX_PRIZE_TIERS];
+        for i in 0..MAX_PRIZE_TIERS {
+            tier_winners[i] = u32::from_le_bytes(*array_ref![tier_winners_src, i * 4, 4]);
+            tier_rewards[i] = u64::from_le_bytes(*array_ref![tier_rewards_src, i * 8, 8]);
+        }
+        let mut charities = [Pubkey::default(); MAX_LOTTERY_CHARITIES];
+        let mut charity_rewards = [0; MAX_LOTTERY_CHARITIES];
+        for i in 0..MAX_LOTTERY_CHARITIES {
+            charities[i] = Pubkey::new_from_array(*array_ref![charities_src, i * 32, 32]);
+            charity_rewards[i] = u64::from_le_bytes(*array_ref![charity_rewards_src, i * 8, 8]);
+        }
 
         let result = LotteryResultData {
+            version: version[0],
             lottery_id: u32::from_le_bytes(*lottery_id),
+            number_format: unpack_number_format(number_format),
             winning_numbers: *winning_numbers,
+            rollover_amount: u64::from_le_bytes(*rollover_amount),
+            total_tickets: u32::from_le_bytes(*total_tickets),
+            prize_pool_amount: u64::from_le_bytes(*prize_pool_amount),
+            tier_winners,
+            tier_rewards,
+            charities_count: charities_count[0],
+            charities,
+            charity_rewards,
+            randomness_account: Pubkey::new_from_array(*randomness_account),
+            randomness_len,
+            randomness: *randomness,
+            draw_slot: u64::from_le_bytes(*draw_slot),
+            drawn_at: i64::from_le_bytes(*drawn_at),
+            mint: Pubkey::new_from_array(*mint),
+            referral_volume: u64::from_le_bytes(*referral_volume),
+            referral_rewards: u64::from_le_bytes(*referral_rewards),
         };
 
         Ok(result)
     }
 
     fn pack_into_slice(&self, dst: &mut [u8]) {
-        let dst = array_mut_ref![dst, 0, 10];
-        let (lottery_id_dst, winning_numbers_dst) = mut_array_refs![dst, 4, 6];
+        let dst = array_mut_ref![dst, 0, 582];
+        let (
+            version_dst,
+            lottery_id_dst,
+            number_format_dst,
+            winning_numbers_dst,
+            rollover_amount_dst,
+            total_tickets_dst,
+            prize_pool_amount_dst,
+            tier_winners_dst,
+            tier_rewards_dst,
+            charities_count_dst,
+            charities_dst,
+            charity_rewards_dst,
+            randomness_account_dst,
+            randomness_len_dst,
+            randomness_dst,
+            draw_slot_dst,
+            drawn_at_dst,
+            mint_dst,
+            referral_volume_dst,
+            referral_rewards_dst,
+        ) = mut_array_refs![
+            dst, 1, 4, 3, 8, 8, 4, 8, 32, 64, 1, 256, 64, 32, 1, 32, 8, 8, 32, 8, 8
+        ];
 
+        // Records are always written in the current layout
+        version_dst[0] = LOTTERY_RESULT_VERSION;
         *lottery_id_dst = self.lottery_id.to_le_bytes();
+        pack_number_format(&self.number_format, number_format_dst);
         *winning_numbers_dst = self.winning_numbers;
+        *rollover_amount_dst = self.rollover_amount.to_le_bytes();
+        *total_tickets_dst = self.total_tickets.to_le_bytes();
+        *prize_pool_amount_dst = self.prize_pool_amount.to_le_bytes();
+        for i in 0..MAX_PRIZE_TIERS {
+            *array_mut_ref![tier_winners_dst, i * 4, 4] = self.tier_winners[i].to_le_bytes();
+            *array_mut_ref![tier_rewards_dst, i * 8, 8] = self.tier_rewards[i].to_le_bytes();
+        }
+        charities_count_dst[0] = self.charities_count;
+        for i in 0..MAX_LOTTERY_CHARITIES {
+            array_mut_ref![charities_dst, i * 32, 32].copy_from_slice(self.charities[i].as_ref());
+            *array_mut_ref![charity_rewards_dst, i * 8, 8] = self.charity_rewards[i].to_le_bytes();
+        }
+        randomness_account_dst.copy_from_slice(self.randomness_account.as_ref());
+        randomness_len_dst[0] = self.randomness_len;
+        *randomness_dst = self.randomness;
+        *draw_slot_dst = self.draw_slot.to_le_bytes();
+        *drawn_at_dst = self.drawn_at.to_le_bytes();
+        mint_dst.copy_from_slice(self.mint.as_ref());
+        *referral_volume_dst = self.referral_volume.to_le_bytes();
+        *referral_rewards_dst = self.referral_rewards.to_le_bytes();
+    }
+}
+
+/// Progress of the lottery settlement, which is processed in batches of tickets
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SettlementData {
+    pub is_initialized: bool,
+    pub lottery_id: u32,
+    /// Prize pool snapshot taken when the tally is complete
+    pub prize_pool_amount: u64,
+    pub tallied_count: u32,
+    pub paid_count: u32,
+    /// Count of winners per prize table tier
+    pub winners: [u32; MAX_PRIZE_TIERS],
+    /// Reward of a single winner per prize table tier, set when the tally is complete
+    pub rewards: [u64; MAX_PRIZE_TIERS],
+    /// Prizes can't be claimed after this time, set when the last winner is paid
+    pub claim_deadline: UnixTimestamp,
+    /// Prizes moved from the lottery vault into the settlement account
+    pub escrowed_amount: u64,
+    pub claimed_amount: u64,
+    /// Unclaimed prizes are moved to the rewards wallet
+    pub is_swept: bool,
+    /// Vault token account of a token lottery, its prizes stay in the vault until claimed
+    pub vault: Pubkey,
+}
+
+impl Sealed for SettlementData {}
+
+impl IsInitialized for SettlementData {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl SettlementData {
+    /// Count of winners of all tiers
+    pub fn winners_count(&self) -> u32 {
+        self.winners.iter().sum()
+    }
+}
+
+impl Pack for SettlementData {
+    /// 1 + 4 + 8 + 4 + 4 + 4 * 8 + 8 * 8 + 8 + 8 + 8 + 1 + 32 = 174
+    const LEN: usize = 174;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 174];
+        let (
+            is_initialized,
+            lottery_id,
+            prize_pool_amount,
+            tallied_count,
+            paid_count,
+            winners_src,
+            rewards_src,
+            claim_deadline,
+            escrowed_amount,
+            claimed_amount,
+            is_swept,
+            vault,
+        ) = array_refs![src, 1, 4, 8, 4, 4, 32, 64, 8, 8, 8, 1, 32];
+
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        let is_swept = match is_swept {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        let mut winners = [0; MAX_PRIZE_TIERS];
+        let mut rewards = [0; MAX_PRIZE_TIERS];
+        for i in 0..MAX_PRIZE_TIERS {
+            winners[i] = u32::from_le_bytes(*array_ref![winners_src, i * 4, 4]);
+            rewards[i] = u64::from_le_bytes(*array_ref![rewards_src, i * 8, 8]);
+        }
+
+        Ok(SettlementData {
+            is_initialized,
+            lottery_id: u32::from_le_bytes(*lottery_id),
+            prize_pool_amount: u64::from_le_bytes(*prize_pool_amount),
+            tallied_count: u32::from_le_bytes(*tallied_count),
+            paid_count: u32::from_le_bytes(*paid_count),
+            winners,
+            rewards,
+            claim_deadline: i64::from_le_bytes(*claim_deadline),
+            escrowed_amount: u64::from_le_bytes(*escrowed_amount),
+            claimed_amount: u64::from_le_bytes(*claimed_amount),
+            is_swept,
+            vault: Pubkey::new_from_array(*vault),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 174];
+        let (
+            is_initialized_dst,
+            lottery_id_dst,
+            prize_pool_amount_dst,
+            tallied_count_dst,
+            paid_count_dst,
+            winners_dst,
+            rewards_dst,
+            claim_deadline_dst,
+            escrowed_amount_dst,
+            claimed_amount_dst,
+            is_swept_dst,
+            vault_dst,
+        ) = mut_array_refs![dst, 1, 4, 8, 4, 4, 32, 64, 8, 8, 8, 1, 32];
+
+        is_initialized_dst[0] = self.is_initialized as u8;
+        *lottery_id_dst = self.lottery_id.to_le_bytes();
+        *prize_pool_amount_dst = self.prize_pool_amount.to_le_bytes();
+        *tallied_count_dst = self.tallied_count.to_le_bytes();
+        *paid_count_dst = self.paid_count.to_le_bytes();
+        for i in 0..MAX_PRIZE_TIERS {
+            *array_mut_ref![winners_dst, i * 4, 4] = self.winners[i].to_le_bytes();
+            *array_mut_ref![rewards_dst, i * 8, 8] = self.rewards[i].to_le_bytes();
+        }
+        *claim_deadline_dst = self.claim_deadline.to_le_bytes();
+        *escrowed_amount_dst = self.escrowed_amount.to_le_bytes();
+        *claimed_amount_dst = self.claimed_amount.to_le_bytes();
+        is_swept_dst[0] = self.is_swept as u8;
+        vault_dst.copy_from_slice(self.vault.as_ref());
+    }
+}
+
+/// Prize paid to the winners of a tier
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Prize {
+    /// Amount paid to every winner of the tier, in lamports or base units of the mint
+    Fixed(u64),
+    /// Share of the prize pool in basis points, split equally among the winners of the tier
+    Share(u16),
+}
+
+impl Default for Prize {
+    fn default() -> Self {
+        Prize::Fixed(0)
+    }
+}
+
+/// Prize tier, winners match exactly `main_matches` main numbers and the bonus number if `bonus_match`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PrizeTier {
+    pub main_matches: u8,
+    pub bonus_match: bool,
+    pub prize: Prize,
+}
+
+impl Sealed for PrizeTier {}
+
+impl Pack for PrizeTier {
+    /// 1 + 1 + 1 + 8 = 11
+    const LEN: usize = 11;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 11];
+        let (main_matches, bonus_match, prize_kind, prize_amount) = array_refs![src, 1, 1, 1, 8];
+
+        let bonus_match = match bonus_match {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let prize_amount = u64::from_le_bytes(*prize_amount);
+        let prize = match prize_kind {
+            [0] => Prize::Fixed(prize_amount),
+            [1] => Prize::Share(
+                u16::try_from(prize_amount).map_err(|_| ProgramError::InvalidAccountData)?,
+            ),
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(PrizeTier {
+            main_matches: main_matches[0],
+            bonus_match,
+            prize,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 11];
+        let (main_matches_dst, bonus_match_dst, prize_kind_dst, prize_amount_dst) =
+            mut_array_refs![dst, 1, 1, 1, 8];
+
+        main_matches_dst[0] = self.main_matches;
+        bonus_match_dst[0] = self.bonus_match as u8;
+        let (prize_kind, prize_amount) = match self.prize {
+            Prize::Fixed(amount) => (0, amount),
+            Prize::Share(share) => (1, share as u64),
+        };
+        prize_kind_dst[0] = prize_kind;
+        *prize_amount_dst = prize_amount.to_le_bytes();
+    }
+}
+
+/// Prize tiers of the lottery
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PrizeTableData {
+    pub is_initialized: bool,
+    pub lottery_id: u32,
+    /// Up to `MAX_PRIZE_TIERS` tiers, settlement counts winners in the same order
+    pub tiers: Vec<PrizeTier>,
+}
+
+impl PrizeTableData {
+    /// Finds the tier index of the ticket matches
+    pub fn find_tier(&self, matches: &Matches) -> Option<usize> {
+        self.tiers
+            .iter()
+            .position(|tier| tier.main_matches == matches.main && tier.bonus_match == matches.bonus)
+    }
+}
+
+impl Sealed for PrizeTableData {}
+
+impl IsInitialized for PrizeTableData {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for PrizeTableData {
+    /// 1 + 4 + 1 + 11 * 8 = 94
+    const LEN: usize = 94;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 94];
+        let (is_initialized, lottery_id, tiers_count, tiers_src) = array_refs![src, 1, 4, 1, 88];
+
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        let tiers_count = tiers_count[0] as usize;
+        if tiers_count > MAX_PRIZE_TIERS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut tiers = Vec::with_capacity(tiers_count);
+        for i in 0..tiers_count {
+            tiers.push(PrizeTier::unpack_from_slice(
+                &tiers_src[i * PrizeTier::LEN..(i + 1) * PrizeTier::LEN],
+            )?);
+        }
+
+        Ok(PrizeTableData {
+            is_initialized,
+            lottery_id: u32::from_le_bytes(*lottery_id),
+            tiers,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 94];
+        let (is_initialized_dst, lottery_id_dst, tiers_count_dst, tiers_dst) =
+            mut_array_refs![dst, 1, 4, 1, 88];
+
+        is_initialized_dst[0] = self.is_initialized as u8;
+        *lottery_id_dst = self.lottery_id.to_le_bytes();
+        tiers_count_dst[0] = self.tiers.len() as u8;
+        for byte in tiers_dst.iter_mut() {
+            *byte = 0;
+        }
+        for (i, tier) in self.tiers.iter().enumerate() {
+            tier.pack_into_slice(&mut tiers_dst[i * PrizeTier::LEN..(i + 1) * PrizeTier::LEN]);
+        }
+    }
+}
+
+/// Charity approved in the charity registry
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RegistryCharity {
+    /// Hash of the charity name
+    pub name_hash: [u8; 32],
+    /// Wallet the charity rewards are paid to
+    pub wallet: Pubkey,
+    /// Inactive charities can't be added to lotteries and are not paid.
+    /// Tickets can still vote for a lottery charity deactivated during the sale,
+    /// such votes are ignored at payout
+    pub is_active: bool,
+}
+
+impl Sealed for RegistryCharity {}
+
+impl Pack for RegistryCharity {
+    /// 32 + 32 + 1 = 65
+    const LEN: usize = 65;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 65];
+        let (name_hash, wallet, is_active) = array_refs![src, 32, 32, 1];
+
+        let is_active = match is_active {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(RegistryCharity {
+            name_hash: *name_hash,
+            wallet: Pubkey::new_from_array(*wallet),
+            is_active,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 65];
+        let (name_hash_dst, wallet_dst, is_active_dst) = mut_array_refs![dst, 32, 32, 1];
+
+        *name_hash_dst = self.name_hash;
+        wallet_dst.copy_from_slice(self.wallet.as_ref());
+        is_active_dst[0] = self.is_active as u8;
+    }
+}
+
+/// Charities approved to be voted in lotteries
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CharityRegistryData {
+    pub is_initialized: bool,
+    /// Authority approving the charities
+    pub authority: Pubkey,
+    /// Up to `MAX_REGISTRY_CHARITIES` charities, lotteries reference them by index,
+    /// so charities are deactivated instead of removed
+    pub charities: Vec<RegistryCharity>,
+}
+
+impl CharityRegistryData {
+    /// Finds the active charity by its registry index
+    pub fn active_charity(&self, index: u16) -> Option<&RegistryCharity> {
+        self.charities
+            .get(index as usize)
+            .filter(|charity| charity.is_active)
+    }
+}
+
+impl Sealed for CharityRegistryData {}
+
+impl IsInitialized for CharityRegistryData {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for CharityRegistryData {
+    /// 1 + 32 + 1 + 65 * 32 = 2114
+    const LEN: usize = 2114;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 2114];
+        let (is_initialized, authority, charities_count, charities_src) =
+            array_refs![src, 1, 32, 1, 2080];
+
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        let charities_count = charities_count[0] as usize;
+        if charities_count > MAX_REGISTRY_CHARITIES {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut charities = Vec::with_capacity(charities_count);
+        for i in 0..charities_count {
+            charities.push(RegistryCharity::unpack_from_slice(
+                &charities_src[i * RegistryCharity::LEN..(i + 1) * RegistryCharity::LEN],
+            )?);
+        }
+
+        Ok(CharityRegistryData {
+            is_initialized,
+            authority: Pubkey::new_from_array(*authority),
+            charities,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 2114];
+        let (is_initialized_dst, authority_dst, charities_count_dst, charities_dst) =
+            mut_array_refs![dst, 1, 32, 1, 2080];
+
+        is_initialized_dst[0] = self.is_initialized as u8;
+        authority_dst.copy_from_slice(self.authority.as_ref());
+        charities_count_dst[0] = self.charities.len() as u8;
+        for byte in charities_dst.iter_mut() {
+            *byte = 0;
+        }
+        for (i, charity) in self.charities.iter().enumerate() {
+            charity.pack_into_slice(
+                &mut charities_dst[i * RegistryCharity::LEN..(i + 1) * RegistryCharity::LEN],
+            );
+        }
+    }
+}
+
+/// Commit-reveal account of the lottery, the operator commits to the hash of a secret
+/// in `InitLottery` and reveals the secret after the sale is closed
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CommitRevealData {
+    pub is_revealed: bool,
+    /// Hash of the secret
+    pub commitment: [u8; 32],
+    /// Revealed secret
+    pub secret: [u8; 32],
+    /// Reveal slot locked after the sale is closed, its hash is mixed with the secret
+    pub slot: u64,
+    /// Hash of the reveal slot, the secret can't be chosen for it
+    pub slot_hash: [u8; 32],
+}
+
+impl Sealed for CommitRevealData {}
+
+impl Pack for CommitRevealData {
+    /// 1 + 32 + 32 + 8 + 32 = 105
+    const LEN: usize = 105;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 105];
+        let (is_revealed, commitment, secret, slot, slot_hash) = array_refs![src, 1, 32, 32, 8, 32];
+
+        let is_revealed = match is_revealed {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(CommitRevealData {
+            is_revealed,
+            commitment: *commitment,
+            secret: *secret,
+            slot: u64::from_le_bytes(*slot),
+            slot_hash: *slot_hash,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 105];
+        let (is_revealed_dst, commitment_dst, secret_dst, slot_dst, slot_hash_dst) =
+            mut_array_refs![dst, 1, 32, 32, 8, 32];
+
+        is_revealed_dst[0] = self.is_revealed as u8;
+        *commitment_dst = self.commitment;
+        *secret_dst = self.secret;
+        *slot_dst = self.slot.to_le_bytes();
+        *slot_hash_dst = self.slot_hash;
+    }
+}
+
+/// Referral ledger of a referrer wallet in a draw, the rewards accrued by its referred tickets
+/// are held in the ledger until claimed. Refunds take the rewards back, so they can be claimed
+/// only after the draw is settled
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ReferralData {
+    pub is_initialized: bool,
+    /// Wallet the rewards are paid to
+    pub referrer: Pubkey,
+    /// Draw of the referred tickets
+    pub lottery_id: u32,
+    /// Rewards accrued by the referred tickets, in lamports
+    pub accrued_amount: u64,
+    /// Rewards paid to the referrer, in lamports
+    pub claimed_amount: u64,
+}
+
+impl ReferralData {
+    /// Rewards accrued and not claimed yet
+    pub fn unclaimed_amount(&self) -> Option<u64> {
+        self.accrued_amount.checked_sub(self.claimed_amount)
+    }
+}
+
+impl Sealed for ReferralData {}
+
+impl IsInitialized for ReferralData {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for ReferralData {
+    /// 1 + 32 + 4 + 8 + 8 = 53
+    const LEN: usize = 53;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, 53];
+        let (is_initialized, referrer, lottery_id, accrued_amount, claimed_amount) =
+            array_refs![src, 1, 32, 4, 8, 8];
+
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(ReferralData {
+            is_initialized,
+            referrer: Pubkey::new_from_array(*referrer),
+            lottery_id: u32::from_le_bytes(*lottery_id),
+            accrued_amount: u64::from_le_bytes(*accrued_amount),
+            claimed_amount: u64::from_le_bytes(*claimed_amount),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, 53];
+        let (
+            is_initialized_dst,
+            referrer_dst,
+            lottery_id_dst,
+            accrued_amount_dst,
+            claimed_amount_dst,
+        ) = mut_array_refs![dst, 1, 32, 4, 8, 8];
+
+        is_initialized_dst[0] = self.is_initialized as u8;
+        referrer_dst.copy_from_slice(self.referrer.as_ref());
+        *lottery_id_dst = self.lottery_id.to_le_bytes();
+        *accrued_amount_dst = self.accrued_amount.to_le_bytes();
+        *claimed_amount_dst = self.claimed_amount.to_le_bytes();
     }
 }
@@ -1,1929 +1,1559 @@
-// use solana_program::{
-//     hash::Hash,
-//     instruction::InstructionError,
-//     native_token::{lamports_to_sol, sol_to_lamports},
-//     program_pack::Pack,
-//     system_instruction,
-// };
-// use solana_program_test::*;
-// use solana_sdk::{
-//     signature::Keypair, system_transaction, transaction::TransactionError,
-//     transport::TransportError,
-// };
-// use sollotto::{
-//     processor::id,
-//     processor::Processor,
-//     state::{LotteryData, LotteryResultData, TicketData},
-// };
-// use {
-//     solana_program::pubkey::Pubkey,
-//     solana_sdk::{signature::Signer, transaction::Transaction},
-// };
-
-// // Helpers
-
-// async fn initialize_lottery(
-//     banks_client: &mut BanksClient,
-//     payer: &Keypair,
-//     recent_blockhash: &Hash,
-//     lottery_data_rent: u64,
-//     lottery_id: u32,
-//     charities: &Vec<Pubkey>,
-//     holding_wallet: &Pubkey,
-//     rewards_wallet: &Pubkey,
-//     slot_holders_rewards_wallet: &Pubkey,
-//     sollotto_labs_wallet: &Pubkey,
-//     randomness_account: &Pubkey,
-//     lottery_authority: &Keypair,
-// ) -> Result<(), TransportError> {
-//     assert_eq!(charities.len(), 4);
-//     let mut transaction = Transaction::new_with_payer(
-//         &[
-//             system_instruction::create_account(
-//                 &payer.pubkey(),
-//                 &lottery_authority.pubkey(),
-//                 lottery_data_rent,
-//                 sollotto::state::LotteryData::LEN as u64,
-//                 &id(),
-//             ),
-//             sollotto::instruction::initialize_lottery(
-//                 &id(),
-//                 lottery_id,
-//                 &charities[0],
-//                 &charities[1],
-//                 &charities[2],
-//                 &charities[3],
-//                 holding_wallet,
-//                 rewards_wallet,
-//                 slot_holders_rewards_wallet,
-//                 sollotto_labs_wallet,
-//                 randomness_account,
-//                 &lottery_authority.pubkey(),
-//             )
-//             .unwrap(),
-//         ],
-//         Some(&payer.pubkey()),
-//     );
-//     transaction.sign(&[payer, lottery_authority], *recent_blockhash);
-//     banks_client.process_transaction(transaction).await?;
-//     Ok(())
-// }
-
-// async fn initialize_lottery_without_creation(
-//     banks_client: &mut BanksClient,
-//     payer: &Keypair,
-//     recent_blockhash: &Hash,
-//     lottery_id: u32,
-//     charities: &Vec<Pubkey>,
-//     holding_wallet: &Pubkey,
-//     rewards_wallet: &Pubkey,
-//     slot_holders_rewards_wallet: &Pubkey,
-//     sollotto_labs_wallet: &Pubkey,
-//     randomness_account: &Pubkey,
-//     lottery_authority: &Keypair,
-// ) -> Result<(), TransportError> {
-//     assert_eq!(charities.len(), 4);
-//     let mut transaction = Transaction::new_with_payer(
-//         &[sollotto::instruction::initialize_lottery(
-//             &id(),
-//             lottery_id,
-//             &charities[0],
-//             &charities[1],
-//             &charities[2],
-//             &charities[3],
-//             holding_wallet,
-//             rewards_wallet,
-//             slot_holders_rewards_wallet,
-//             sollotto_labs_wallet,
-//             randomness_account,
-//             &lottery_authority.pubkey(),
-//         )
-//         .unwrap()],
-//         Some(&payer.pubkey()),
-//     );
-//     transaction.sign(&[payer, lottery_authority], *recent_blockhash);
-//     banks_client.process_transaction(transaction).await?;
-//     Ok(())
-// }
-
-// async fn purchase_ticket(
-//     banks_client: &mut BanksClient,
-//     payer: &Keypair,
-//     recent_blockhash: &Hash,
-//     ticket_data_rent: u64,
-//     charity: &Pubkey,
-//     ticket_number: &[u8; 6],
-//     holding_wallet: &Pubkey,
-//     ticket_authority: &Keypair,
-//     user_authority: &Keypair,
-//     lottery_authority: &Keypair,
-// ) -> Result<(), TransportError> {
-//     let mut transaction = Transaction::new_with_payer(
-//         &[
-//             system_instruction::create_account(
-//                 &payer.pubkey(),
-//                 &ticket_authority.pubkey(),
-//                 ticket_data_rent,
-//                 sollotto::state::TicketData::LEN as u64,
-//                 &id(),
-//             ),
-//             sollotto::instruction::purchase_ticket(
-//                 &id(),
-//                 charity,
-//                 &user_authority.pubkey(),
-//                 ticket_number,
-//                 &ticket_authority.pubkey(),
-//                 holding_wallet,
-//                 &lottery_authority.pubkey(),
-//             )
-//             .unwrap(),
-//         ],
-//         Some(&payer.pubkey()),
-//     );
-//     transaction.sign(
-//         &[payer, ticket_authority, lottery_authority, user_authority],
-//         *recent_blockhash,
-//     );
-//     banks_client.process_transaction(transaction).await?;
-//     Ok(())
-// }
-
-// async fn create_ticket_account(
-//     banks_client: &mut BanksClient,
-//     payer: &Keypair,
-//     recent_blockhash: &Hash,
-//     ticket_data_rent: u64,
-//     ticket_authority: &Keypair,
-// ) -> Result<(), TransportError> {
-//     let mut transaction = Transaction::new_with_payer(
-//         &[system_instruction::create_account(
-//             &payer.pubkey(),
-//             &ticket_authority.pubkey(),
-//             ticket_data_rent,
-//             sollotto::state::TicketData::LEN as u64,
-//             &id(),
-//         )],
-//         Some(&payer.pubkey()),
-//     );
-//     transaction.sign(&[payer, ticket_authority], *recent_blockhash);
-//     banks_client.process_transaction(transaction).await?;
-//     Ok(())
-// }
-
-// async fn purchase_ticket_without_creation(
-//     banks_client: &mut BanksClient,
-//     payer: &Keypair,
-//     recent_blockhash: &Hash,
-//     charity: &Pubkey,
-//     ticket_number: &[u8; 6],
-//     holding_wallet: &Pubkey,
-//     ticket_authority: &Keypair,
-//     user_authority: &Keypair,
-//     lottery_authority: &Keypair,
-// ) -> Result<(), TransportError> {
-//     let mut transaction = Transaction::new_with_payer(
-//         &[sollotto::instruction::purchase_ticket(
-//             &id(),
-//             charity,
-//             &user_authority.pubkey(),
-//             ticket_number,
-//             &ticket_authority.pubkey(),
-//             holding_wallet,
-//             &lottery_authority.pubkey(),
-//         )
-//         .unwrap()],
-//         Some(&payer.pubkey()),
-//     );
-//     transaction.sign(
-//         &[payer, lottery_authority, user_authority],
-//         *recent_blockhash,
-//     );
-//     banks_client.process_transaction(transaction).await?;
-//     Ok(())
-// }
-
-// async fn store_winning_numbers(
-//     banks_client: &mut BanksClient,
-//     payer: &Keypair,
-//     recent_blockhash: &Hash,
-//     winning_numbers: &[u8; 6],
-//     lottery_authority: &Keypair,
-// ) -> Result<(), TransportError> {
-//     let mut transaction = Transaction::new_with_payer(
-//         &[sollotto::instruction::store_winning_numbers(
-//             &id(),
-//             winning_numbers,
-//             &lottery_authority.pubkey(),
-//         )
-//         .unwrap()],
-//         Some(&payer.pubkey()),
-//     );
-//     transaction.sign(&[payer, lottery_authority], *recent_blockhash);
-//     banks_client.process_transaction(transaction).await?;
-//     Ok(())
-// }
-
-// async fn reward_winners(
-//     banks_client: &mut BanksClient,
-//     payer: &Keypair,
-//     recent_blockhash: &Hash,
-//     lottery_result_data_rent: u64,
-//     rewards_wallet: &Pubkey,
-//     slot_holders_rewards_wallet: &Pubkey,
-//     sollotto_labs_wallet: &Pubkey,
-//     charities: &Vec<Pubkey>,
-//     participants: &Vec<(Pubkey, Pubkey)>,
-//     holding_wallet_authority: &Keypair,
-//     lottery_result_authority: &Keypair,
-//     lottery_authority: &Keypair,
-// ) -> Result<(), TransportError> {
-//     assert_eq!(charities.len(), 4);
-//     let mut transaction = Transaction::new_with_payer(
-//         &[
-//             system_instruction::create_account(
-//                 &payer.pubkey(),
-//                 &lottery_result_authority.pubkey(),
-//                 lottery_result_data_rent,
-//                 sollotto::state::LotteryResultData::LEN as u64,
-//                 &id(),
-//             ),
-//             sollotto::instruction::reward_winners(
-//                 &id(),
-//                 &lottery_authority.pubkey(),
-//                 &lottery_result_authority.pubkey(),
-//                 &holding_wallet_authority.pubkey(),
-//                 rewards_wallet,
-//                 slot_holders_rewards_wallet,
-//                 sollotto_labs_wallet,
-//                 &[charities[0], charities[1], charities[2], charities[3]],
-//                 participants,
-//             )
-//             .unwrap(),
-//         ],
-//         Some(&payer.pubkey()),
-//     );
-//     transaction.sign(
-//         &[
-//             payer,
-//             lottery_result_authority,
-//             lottery_authority,
-//             holding_wallet_authority,
-//         ],
-//         *recent_blockhash,
-//     );
-//     banks_client.process_transaction(transaction).await?;
-//     Ok(())
-// }
-
-// async fn update_charity(
-//     banks_client: &mut BanksClient,
-//     payer: &Keypair,
-//     recent_blockhash: &Hash,
-//     charities: &Vec<Pubkey>,
-//     lottery_authority: &Keypair,
-// ) -> Result<(), TransportError> {
-//     assert_eq!(charities.len(), 4);
-//     let mut transaction = Transaction::new_with_payer(
-//         &[sollotto::instruction::update_charity(
-//             &id(),
-//             &charities[0],
-//             &charities[1],
-//             &charities[2],
-//             &charities[3],
-//             &lottery_authority.pubkey(),
-//         )
-//         .unwrap()],
-//         Some(&payer.pubkey()),
-//     );
-//     transaction.sign(&[payer, lottery_authority], *recent_blockhash);
-//     banks_client.process_transaction(transaction).await?;
-//     Ok(())
-// }
-
-// async fn update_sollotto_wallets(
-//     banks_client: &mut BanksClient,
-//     payer: &Keypair,
-//     recent_blockhash: &Hash,
-//     holding_wallet: &Pubkey,
-//     rewards_wallet: &Pubkey,
-//     slot_holders_rewards_wallet: &Pubkey,
-//     sollotto_labs_wallet: &Pubkey,
-//     lottery_authority: &Keypair,
-// ) -> Result<(), TransportError> {
-//     let mut transaction = Transaction::new_with_payer(
-//         &[sollotto::instruction::update_sollotto_wallets(
-//             &id(),
-//             holding_wallet,
-//             rewards_wallet,
-//             slot_holders_rewards_wallet,
-//             sollotto_labs_wallet,
-//             &lottery_authority.pubkey(),
-//         )
-//         .unwrap()],
-//         Some(&payer.pubkey()),
-//     );
-//     transaction.sign(&[payer, lottery_authority], *recent_blockhash);
-//     banks_client.process_transaction(transaction).await?;
-//     Ok(())
-// }
-
-// async fn transfer_sol(
-//     banks_client: &mut BanksClient,
-//     recent_blockhash: &Hash,
-//     from: &Keypair,
-//     to: &Keypair,
-//     amount_sol: f64,
-// ) -> Result<(), TransportError> {
-//     let mut transaction = system_transaction::transfer(
-//         from,
-//         &to.pubkey(),
-//         sol_to_lamports(amount_sol),
-//         *recent_blockhash,
-//     );
-//     transaction.sign(&[from], *recent_blockhash);
-//     banks_client.process_transaction(transaction).await?;
-//     Ok(())
-// }
-
-// async fn check_balance(banks_client: &mut BanksClient, address: Pubkey, check_sol: f64) {
-//     assert_eq!(
-//         banks_client.get_balance(address).await.unwrap(),
-//         sol_to_lamports(check_sol)
-//     );
-// }
-
-// #[tokio::test]
-// async fn test_one_winner() {
-//     let program = ProgramTest::new("sollotto", id(), processor!(Processor::process));
-//     let (mut banks_client, payer, recent_blockhash) = program.start().await;
-
-//     let rent = banks_client.get_rent().await.unwrap();
-//     let lottery_result_data_rent = rent.minimum_balance(LotteryResultData::LEN);
-//     let ticket_data_rent = rent.minimum_balance(TicketData::LEN);
-//     let lottery_data_rent = rent.minimum_balance(LotteryData::LEN);
-
-//     let number_of_users = 5;
-//     let lottery_id = 112233;
-//     let lottery_authority = Keypair::new();
-//     let lottery_result = Keypair::new();
-//     let holding_wallet = Keypair::new();
-//     let rewards_wallet = Keypair::new();
-//     let slot_holders_rewards_wallet = Keypair::new();
-//     let sollotto_labs_wallet = Keypair::new();
-//     let randomness_account = Keypair::new();
-//     let charities: Vec<Keypair> = (0..4).map(|_| Keypair::new()).collect();
-//     let charities_pubkeys: Vec<Pubkey> = charities.iter().map(|c| c.pubkey()).collect();
-//     let users_wallets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let users_wallets_pubkeys: Vec<Pubkey> = users_wallets.iter().map(|x| x.pubkey()).collect();
-//     let tickets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let tickets_pubkeys: Vec<Pubkey> = tickets.iter().map(|x| x.pubkey()).collect();
-//     let winning_numbers = [1, 2, 3, 4, 5, 6];
-
-//     // Set initial balances for users
-//     for user in &users_wallets {
-//         transfer_sol(&mut banks_client, &recent_blockhash, &payer, user, 1.0)
-//             .await
-//             .unwrap();
-//     }
-
-//     initialize_lottery(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_data_rent,
-//         lottery_id,
-//         &charities_pubkeys,
-//         &holding_wallet.pubkey(),
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &randomness_account.pubkey(),
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Users purchase tickets
-//     let mut ticket_numbers;
-//     for i in 0..number_of_users {
-//         if i == 0 {
-//             ticket_numbers = winning_numbers;
-//         } else {
-//             ticket_numbers = [1, 1, 1, 1, 1, 1];
-//         }
-//         purchase_ticket(
-//             &mut banks_client,
-//             &payer,
-//             &recent_blockhash,
-//             ticket_data_rent,
-//             &charities_pubkeys[0],
-//             &ticket_numbers,
-//             &holding_wallet.pubkey(),
-//             &tickets[i],
-//             &users_wallets[i],
-//             &lottery_authority,
-//         )
-//         .await
-//         .unwrap();
-//     }
-
-//     // Check balances
-//     let mut prize_pool_sol = 0.0;
-//     for user in &users_wallets {
-//         prize_pool_sol += 0.1;
-//         check_balance(&mut banks_client, user.pubkey(), 0.9).await;
-//     }
-
-//     check_balance(&mut banks_client, holding_wallet.pubkey(), prize_pool_sol).await;
-
-//     // Finaled lottery
-//     store_winning_numbers(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         &winning_numbers,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     let participants = (0..number_of_users)
-//         .map(|i| (tickets_pubkeys[i], users_wallets_pubkeys[i]))
-//         .collect();
-
-//     // Reward winners
-//     reward_winners(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_result_data_rent,
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &charities_pubkeys,
-//         &participants,
-//         &holding_wallet,
-//         &lottery_result,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Check balances
-//     check_balance(&mut banks_client, holding_wallet.pubkey(), 0.0).await;
-//     check_balance(
-//         &mut banks_client,
-//         rewards_wallet.pubkey(),
-//         prize_pool_sol * 0.04,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         slot_holders_rewards_wallet.pubkey(),
-//         prize_pool_sol * 0.006,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         sollotto_labs_wallet.pubkey(),
-//         prize_pool_sol * 0.004,
-//     )
-//     .await;
-
-//     // Charity balances
-//     check_balance(
-//         &mut banks_client,
-//         charities_pubkeys[0],
-//         prize_pool_sol * 0.3,
-//     )
-//     .await;
-//     for i in 1..4 {
-//         check_balance(&mut banks_client, charities_pubkeys[i], 0.0).await;
-//     }
-
-//     // Winner balance
-//     check_balance(
-//         &mut banks_client,
-//         users_wallets_pubkeys[0],
-//         prize_pool_sol * 0.65 + 0.9,
-//     )
-//     .await;
-//     // Loosers balances
-//     for i in 1..5 {
-//         check_balance(&mut banks_client, users_wallets_pubkeys[i], 0.9).await;
-//     }
-// }
-
-// #[tokio::test]
-// async fn test_update_wallets() {
-//     let program = ProgramTest::new("sollotto", id(), processor!(Processor::process));
-//     let (mut banks_client, payer, recent_blockhash) = program.start().await;
-
-//     let rent = banks_client.get_rent().await.unwrap();
-//     let lottery_result_data_rent = rent.minimum_balance(LotteryResultData::LEN);
-//     let ticket_data_rent = rent.minimum_balance(TicketData::LEN);
-//     let lottery_data_rent = rent.minimum_balance(LotteryData::LEN);
-
-//     let number_of_users = 5;
-//     let lottery_id = 112233;
-//     let lottery_authority = Keypair::new();
-//     let lottery_result = Keypair::new();
-//     let holding_wallet = Keypair::new();
-//     let rewards_wallet = Keypair::new();
-//     let slot_holders_rewards_wallet = Keypair::new();
-//     let sollotto_labs_wallet = Keypair::new();
-//     let randomness_account = Keypair::new();
-//     let charities: Vec<Keypair> = (0..4).map(|_| Keypair::new()).collect();
-//     let charities_pubkeys: Vec<Pubkey> = charities.iter().map(|c| c.pubkey()).collect();
-//     let users_wallets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let users_wallets_pubkeys: Vec<Pubkey> = users_wallets.iter().map(|x| x.pubkey()).collect();
-//     let tickets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let tickets_pubkeys: Vec<Pubkey> = tickets.iter().map(|x| x.pubkey()).collect();
-//     let winning_numbers = [1, 2, 3, 4, 5, 6];
-
-//     // Set initial balances for users
-//     for user in &users_wallets {
-//         transfer_sol(&mut banks_client, &recent_blockhash, &payer, user, 1.0)
-//             .await
-//             .unwrap();
-//     }
-
-//     initialize_lottery(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_data_rent,
-//         lottery_id,
-//         &charities_pubkeys,
-//         &holding_wallet.pubkey(),
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &randomness_account.pubkey(),
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Users purchase tickets
-//     for i in 0..number_of_users {
-//         purchase_ticket(
-//             &mut banks_client,
-//             &payer,
-//             &recent_blockhash,
-//             ticket_data_rent,
-//             &charities_pubkeys[0],
-//             &[3, 3, 3, 3, 3, 3],
-//             &holding_wallet.pubkey(),
-//             &tickets[i],
-//             &users_wallets[i],
-//             &lottery_authority,
-//         )
-//         .await
-//         .unwrap();
-//     }
-
-//     // Check balances
-//     let mut prize_pool_sol = 0.0;
-//     for user in &users_wallets {
-//         prize_pool_sol += 0.1;
-//         check_balance(&mut banks_client, user.pubkey(), 0.9).await;
-//     }
-
-//     check_balance(&mut banks_client, holding_wallet.pubkey(), prize_pool_sol).await;
-
-//     // Finaled lottery
-//     store_winning_numbers(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         &winning_numbers,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     let participants = (0..number_of_users)
-//         .map(|i| (tickets_pubkeys[i], users_wallets_pubkeys[i]))
-//         .collect();
-
-//     // Update charities
-//     let new_charities: Vec<Keypair> = (0..4).map(|_| Keypair::new()).collect();
-//     let new_charities_pubkeys: Vec<Pubkey> = new_charities.iter().map(|c| c.pubkey()).collect();
-//     update_charity(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         &new_charities_pubkeys,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Reward winners
-//     reward_winners(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_result_data_rent,
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &new_charities_pubkeys,
-//         &participants,
-//         &holding_wallet,
-//         &lottery_result,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Check balances
-//     let prize_pool_1_remain = prize_pool_sol * 0.65;
-//     check_balance(
-//         &mut banks_client,
-//         holding_wallet.pubkey(),
-//         prize_pool_1_remain,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         rewards_wallet.pubkey(),
-//         prize_pool_sol * 0.04,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         slot_holders_rewards_wallet.pubkey(),
-//         prize_pool_sol * 0.006,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         sollotto_labs_wallet.pubkey(),
-//         prize_pool_sol * 0.004,
-//     )
-//     .await;
-
-//     // Charity balances
-//     for i in 0..4 {
-//         check_balance(&mut banks_client, charities_pubkeys[i], 0.0).await;
-//     }
-
-//     // New charity get the reward
-//     check_balance(
-//         &mut banks_client,
-//         new_charities_pubkeys[0],
-//         prize_pool_sol * 0.3,
-//     )
-//     .await;
-//     for i in 1..4 {
-//         check_balance(&mut banks_client, new_charities_pubkeys[i], 0.0).await;
-//     }
-
-//     // Update sollotto wallets and repeat lottery
-//     let lottery_id = 222222;
-//     initialize_lottery_without_creation(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_id,
-//         &charities_pubkeys,
-//         &holding_wallet.pubkey(),
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-        
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     let new_rewards_wallet = Keypair::new();
-//     let new_slot_holders_rewards_wallet = Keypair::new();
-//     let new_sollotto_labs_wallet = Keypair::new();
-//     update_sollotto_wallets(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         &holding_wallet.pubkey(),
-//         &new_rewards_wallet.pubkey(),
-//         &new_slot_holders_rewards_wallet.pubkey(),
-//         &new_sollotto_labs_wallet.pubkey(),
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Users purchase tickets
-//     let tickets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let tickets_pubkeys: Vec<Pubkey> = tickets.iter().map(|x| x.pubkey()).collect();
-//     for i in 0..number_of_users {
-//         purchase_ticket(
-//             &mut banks_client,
-//             &payer,
-//             &recent_blockhash,
-//             ticket_data_rent,
-//             &charities_pubkeys[0],
-//             &[4, 4, 4, 4, 4, 4],
-//             &holding_wallet.pubkey(),
-//             &tickets[i],
-//             &users_wallets[i],
-//             &lottery_authority,
-//         )
-//         .await
-//         .unwrap();
-//     }
-
-//     // Check balances
-//     let mut prize_pool_sol_2 = 0.0;
-//     for user in &users_wallets {
-//         prize_pool_sol_2 += 0.1;
-//         check_balance(&mut banks_client, user.pubkey(), 0.8).await;
-//     }
-
-//     check_balance(&mut banks_client, holding_wallet.pubkey(), prize_pool_1_remain + prize_pool_sol_2).await;
-
-//     // Finaled lottery
-//     let winning_numbers = [1, 1, 1, 1, 1, 1];
-//     store_winning_numbers(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         &winning_numbers,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     let participants = (0..number_of_users)
-//         .map(|i| (tickets_pubkeys[i], users_wallets_pubkeys[i]))
-//         .collect();
-
-//     // Reward winners
-//     let lottery_result = Keypair::new();
-//     reward_winners(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_result_data_rent,
-//         &new_rewards_wallet.pubkey(),
-//         &new_slot_holders_rewards_wallet.pubkey(),
-//         &new_sollotto_labs_wallet.pubkey(),
-//         &charities_pubkeys,
-//         &participants,
-//         &holding_wallet,
-//         &lottery_result,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Check balances
-//     // lamports_to_sol(1) is division correction
-//     check_balance(
-//         &mut banks_client,
-//         holding_wallet.pubkey(),
-//         (prize_pool_sol_2 + prize_pool_1_remain) * 0.65 + lamports_to_sol(1),
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         new_rewards_wallet.pubkey(),
-//         (prize_pool_sol_2 + prize_pool_1_remain) * 0.04,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         new_slot_holders_rewards_wallet.pubkey(),
-//         (prize_pool_sol_2 + prize_pool_1_remain) * 0.006,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         new_sollotto_labs_wallet.pubkey(),
-//         (prize_pool_sol_2 + prize_pool_1_remain) * 0.004,
-//     )
-//     .await;
-// }
-
-// #[tokio::test]
-// async fn test_without_winners() {
-//     let program = ProgramTest::new("sollotto", id(), processor!(Processor::process));
-//     let (mut banks_client, payer, recent_blockhash) = program.start().await;
-
-//     let rent = banks_client.get_rent().await.unwrap();
-//     let lottery_result_data_rent = rent.minimum_balance(LotteryResultData::LEN);
-//     let ticket_data_rent = rent.minimum_balance(TicketData::LEN);
-//     let lottery_data_rent = rent.minimum_balance(LotteryData::LEN);
-
-//     let number_of_users = 5;
-//     let lottery_id = 112233;
-//     let lottery_authority = Keypair::new();
-//     let lottery_result = Keypair::new();
-//     let holding_wallet = Keypair::new();
-//     let rewards_wallet = Keypair::new();
-//     let slot_holders_rewards_wallet = Keypair::new();
-//     let sollotto_labs_wallet = Keypair::new();
-//     let charities: Vec<Keypair> = (0..4).map(|_| Keypair::new()).collect();
-//     let charities_pubkeys: Vec<Pubkey> = charities.iter().map(|c| c.pubkey()).collect();
-//     let users_wallets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let users_wallets_pubkeys: Vec<Pubkey> = users_wallets.iter().map(|x| x.pubkey()).collect();
-//     let tickets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let tickets_pubkeys: Vec<Pubkey> = tickets.iter().map(|x| x.pubkey()).collect();
-//     let winning_numbers = [1, 2, 3, 4, 5, 6];
-
-//     // Set initial balances for users
-//     for user in &users_wallets {
-//         transfer_sol(&mut banks_client, &recent_blockhash, &payer, user, 1.0)
-//             .await
-//             .unwrap();
-//     }
-
-//     initialize_lottery(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_data_rent,
-//         lottery_id,
-//         &charities_pubkeys,
-//         &holding_wallet.pubkey(),
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Users purchase tickets
-//     let ticket_numbers = [1, 1, 1, 1, 1, 1];
-//     for i in 0..number_of_users {
-//         purchase_ticket(
-//             &mut banks_client,
-//             &payer,
-//             &recent_blockhash,
-//             ticket_data_rent,
-//             &charities_pubkeys[0],
-//             &ticket_numbers,
-//             &holding_wallet.pubkey(),
-//             &tickets[i],
-//             &users_wallets[i],
-//             &lottery_authority,
-//         )
-//         .await
-//         .unwrap();
-//     }
-
-//     // Check balances
-//     let mut prize_pool_sol = 0.0;
-//     for user in &users_wallets {
-//         prize_pool_sol += 0.1;
-//         check_balance(&mut banks_client, user.pubkey(), 0.9).await;
-//     }
-
-//     check_balance(&mut banks_client, holding_wallet.pubkey(), prize_pool_sol).await;
-
-//     // Finaled lottery
-//     store_winning_numbers(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         &winning_numbers,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     let participants = (0..number_of_users)
-//         .map(|i| (tickets_pubkeys[i], users_wallets_pubkeys[i]))
-//         .collect();
-
-//     // Reward winners
-//     reward_winners(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_result_data_rent,
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &charities_pubkeys,
-//         &participants,
-//         &holding_wallet,
-//         &lottery_result,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Check balances
-//     check_balance(
-//         &mut banks_client,
-//         holding_wallet.pubkey(),
-//         prize_pool_sol * 0.65,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         rewards_wallet.pubkey(),
-//         prize_pool_sol * 0.04,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         slot_holders_rewards_wallet.pubkey(),
-//         prize_pool_sol * 0.006,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         sollotto_labs_wallet.pubkey(),
-//         prize_pool_sol * 0.004,
-//     )
-//     .await;
-
-//     // Charity balances
-//     check_balance(
-//         &mut banks_client,
-//         charities_pubkeys[0],
-//         prize_pool_sol * 0.3,
-//     )
-//     .await;
-//     for i in 1..4 {
-//         check_balance(&mut banks_client, charities_pubkeys[i], 0.0).await;
-//     }
-
-//     // Loosers balances
-//     for i in 0..5 {
-//         check_balance(&mut banks_client, users_wallets_pubkeys[i], 0.9).await;
-//     }
-// }
-
-// #[tokio::test]
-// async fn test_repeat_lottery() {
-//     let program = ProgramTest::new("sollotto", id(), processor!(Processor::process));
-//     let (mut banks_client, payer, recent_blockhash) = program.start().await;
-
-//     let rent = banks_client.get_rent().await.unwrap();
-//     let lottery_result_data_rent = rent.minimum_balance(LotteryResultData::LEN);
-//     let ticket_data_rent = rent.minimum_balance(TicketData::LEN);
-//     let lottery_data_rent = rent.minimum_balance(LotteryData::LEN);
-
-//     let number_of_users = 5;
-//     let lottery_id = 111111;
-//     let lottery_authority = Keypair::new();
-//     let lottery_result = Keypair::new();
-//     let holding_wallet = Keypair::new();
-//     let rewards_wallet = Keypair::new();
-//     let slot_holders_rewards_wallet = Keypair::new();
-//     let sollotto_labs_wallet = Keypair::new();
-//     let charities: Vec<Keypair> = (0..4).map(|_| Keypair::new()).collect();
-//     let charities_pubkeys: Vec<Pubkey> = charities.iter().map(|c| c.pubkey()).collect();
-//     let users_wallets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let users_wallets_pubkeys: Vec<Pubkey> = users_wallets.iter().map(|x| x.pubkey()).collect();
-//     let tickets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let tickets_pubkeys: Vec<Pubkey> = tickets.iter().map(|x| x.pubkey()).collect();
-//     let winning_numbers = [1, 2, 3, 4, 5, 6];
-
-//     // Set initial balances for users
-//     for user in &users_wallets {
-//         transfer_sol(&mut banks_client, &recent_blockhash, &payer, user, 1.0)
-//             .await
-//             .unwrap();
-//     }
-
-//     initialize_lottery(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_data_rent,
-//         lottery_id,
-//         &charities_pubkeys,
-//         &holding_wallet.pubkey(),
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Users purchase tickets (without winners)
-//     let ticket_numbers = [1, 1, 1, 1, 1, 1];
-//     for i in 0..number_of_users {
-//         purchase_ticket(
-//             &mut banks_client,
-//             &payer,
-//             &recent_blockhash,
-//             ticket_data_rent,
-//             &charities_pubkeys[0],
-//             &ticket_numbers,
-//             &holding_wallet.pubkey(),
-//             &tickets[i],
-//             &users_wallets[i],
-//             &lottery_authority,
-//         )
-//         .await
-//         .unwrap();
-//     }
-
-//     // Check balances
-//     let mut prize_pool_sol = 0.0;
-//     for user in &users_wallets {
-//         prize_pool_sol += 0.1;
-//         check_balance(&mut banks_client, user.pubkey(), 0.9).await;
-//     }
-
-//     check_balance(&mut banks_client, holding_wallet.pubkey(), prize_pool_sol).await;
-
-//     // Finaled lottery
-//     store_winning_numbers(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         &winning_numbers,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     let participants = (0..number_of_users)
-//         .map(|i| (tickets_pubkeys[i], users_wallets_pubkeys[i]))
-//         .collect();
-
-//     // Reward winners
-//     reward_winners(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_result_data_rent,
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &charities_pubkeys,
-//         &participants,
-//         &holding_wallet,
-//         &lottery_result,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Check balances
-//     let prize_pool_remain = prize_pool_sol * 0.65;
-//     check_balance(
-//         &mut banks_client,
-//         holding_wallet.pubkey(),
-//         prize_pool_remain,
-//     )
-//     .await;
-//     let rewards_balance = prize_pool_sol * 0.04;
-//     check_balance(&mut banks_client, rewards_wallet.pubkey(), rewards_balance).await;
-//     let slot_holders_rewards_balance = prize_pool_sol * 0.006;
-//     check_balance(
-//         &mut banks_client,
-//         slot_holders_rewards_wallet.pubkey(),
-//         slot_holders_rewards_balance,
-//     )
-//     .await;
-//     let sollotto_labs_balance = prize_pool_sol * 0.004;
-//     check_balance(
-//         &mut banks_client,
-//         sollotto_labs_wallet.pubkey(),
-//         sollotto_labs_balance,
-//     )
-//     .await;
-
-//     // Charity balances
-//     let charity_0_balance = prize_pool_sol * 0.3;
-//     check_balance(&mut banks_client, charities_pubkeys[0], charity_0_balance).await;
-//     for i in 1..4 {
-//         check_balance(&mut banks_client, charities_pubkeys[i], 0.0).await;
-//     }
-
-//     // Loosers balances
-//     for i in 0..5 {
-//         check_balance(&mut banks_client, users_wallets_pubkeys[i], 0.9).await;
-//     }
-
-//     // Repeate lottery
-//     let lottery_id = 222222;
-//     let tickets_2: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let tickets_pubkeys_2: Vec<Pubkey> = tickets_2.iter().map(|x| x.pubkey()).collect();
-//     let winning_numbers_2 = [1, 1, 2, 2, 3, 3];
-
-//     initialize_lottery_without_creation(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_id,
-//         &charities_pubkeys,
-//         &holding_wallet.pubkey(),
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Users purchase tickets (user_1 wins)
-//     let mut ticket_numbers;
-//     for i in 0..number_of_users {
-//         if i == 1 {
-//             ticket_numbers = winning_numbers_2;
-//         } else {
-//             ticket_numbers = winning_numbers;
-//         }
-//         purchase_ticket(
-//             &mut banks_client,
-//             &payer,
-//             &recent_blockhash,
-//             ticket_data_rent,
-//             &charities_pubkeys[1],
-//             &ticket_numbers,
-//             &holding_wallet.pubkey(),
-//             &tickets_2[i],
-//             &users_wallets[i],
-//             &lottery_authority,
-//         )
-//         .await
-//         .unwrap();
-//     }
-
-//     // Check balances
-//     let mut prize_pool_sol_2 = 0.0;
-//     for i in 0..number_of_users {
-//         prize_pool_sol_2 += 0.1;
-//         check_balance(&mut banks_client, users_wallets[i].pubkey(), 0.8).await;
-//     }
-
-//     check_balance(
-//         &mut banks_client,
-//         holding_wallet.pubkey(),
-//         prize_pool_remain + prize_pool_sol_2,
-//     )
-//     .await;
-
-//     // Finaled lottery
-//     store_winning_numbers(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         &winning_numbers_2,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     let participants = (0..number_of_users)
-//         .map(|i| (tickets_pubkeys_2[i], users_wallets_pubkeys[i]))
-//         .collect();
-
-//     // Reward winners
-//     let lottery_result_2 = Keypair::new();
-//     reward_winners(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_result_data_rent,
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &charities_pubkeys,
-//         &participants,
-//         &holding_wallet,
-//         &lottery_result_2,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Check balances
-//     // lamports_to_sol(1) is division correction
-//     let prize_pool_sol_full = prize_pool_remain + prize_pool_sol_2;
-//     check_balance(
-//         &mut banks_client,
-//         holding_wallet.pubkey(),
-//         0.0 + lamports_to_sol(1),
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         rewards_wallet.pubkey(),
-//         rewards_balance + prize_pool_sol_full * 0.04,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         slot_holders_rewards_wallet.pubkey(),
-//         slot_holders_rewards_balance + prize_pool_sol_full * 0.006 + lamports_to_sol(1),
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         sollotto_labs_wallet.pubkey(),
-//         sollotto_labs_balance + prize_pool_sol_full * 0.004,
-//     )
-//     .await;
-
-//     // Charity balances
-//     check_balance(&mut banks_client, charities_pubkeys[0], charity_0_balance).await;
-//     check_balance(
-//         &mut banks_client,
-//         charities_pubkeys[1],
-//         prize_pool_sol_full * 0.3,
-//     )
-//     .await;
-//     for i in 2..4 {
-//         check_balance(&mut banks_client, charities_pubkeys[i], 0.0).await;
-//     }
-
-//     // Loosers balances
-//     for i in 0..5 {
-//         // Winner balance
-//         if i == 1 {
-//             check_balance(
-//                 &mut banks_client,
-//                 users_wallets_pubkeys[1],
-//                 prize_pool_sol_full * 0.65 + 0.8,
-//             )
-//             .await;
-//             continue;
-//         }
-
-//         check_balance(&mut banks_client, users_wallets_pubkeys[i], 0.8).await;
-//     }
-// }
-
-// #[tokio::test]
-// async fn test_charities_share() {
-//     let program = ProgramTest::new("sollotto", id(), processor!(Processor::process));
-//     let (mut banks_client, payer, recent_blockhash) = program.start().await;
-
-//     let rent = banks_client.get_rent().await.unwrap();
-//     let lottery_result_data_rent = rent.minimum_balance(LotteryResultData::LEN);
-//     let ticket_data_rent = rent.minimum_balance(TicketData::LEN);
-//     let lottery_data_rent = rent.minimum_balance(LotteryData::LEN);
-
-//     let number_of_users = 5;
-//     let lottery_id = 112233;
-//     let lottery_authority = Keypair::new();
-//     let lottery_result = Keypair::new();
-//     let holding_wallet = Keypair::new();
-//     let rewards_wallet = Keypair::new();
-//     let slot_holders_rewards_wallet = Keypair::new();
-//     let sollotto_labs_wallet = Keypair::new();
-//     let charities: Vec<Keypair> = (0..4).map(|_| Keypair::new()).collect();
-//     let charities_pubkeys: Vec<Pubkey> = charities.iter().map(|c| c.pubkey()).collect();
-//     let users_wallets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let users_wallets_pubkeys: Vec<Pubkey> = users_wallets.iter().map(|x| x.pubkey()).collect();
-//     let tickets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let tickets_pubkeys: Vec<Pubkey> = tickets.iter().map(|x| x.pubkey()).collect();
-//     let winning_numbers = [1, 2, 3, 4, 5, 6];
-
-//     // Set initial balances for users
-//     for user in &users_wallets {
-//         transfer_sol(&mut banks_client, &recent_blockhash, &payer, user, 1.0)
-//             .await
-//             .unwrap();
-//     }
-
-//     initialize_lottery(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_data_rent,
-//         lottery_id,
-//         &charities_pubkeys,
-//         &holding_wallet.pubkey(),
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Users purchase tickets
-//     let mut ticket_numbers;
-//     let mut user_charity;
-//     for i in 0..number_of_users {
-//         if i == 0 {
-//             ticket_numbers = winning_numbers;
-//         } else {
-//             ticket_numbers = [1, 1, 1, 1, 1, 1];
-//         }
-
-//         if i == 0 || i == 1 {
-//             user_charity = charities_pubkeys[0];
-//         } else if i == 2 || i == 3 {
-//             user_charity = charities_pubkeys[1];
-//         } else {
-//             user_charity = charities_pubkeys[2];
-//         }
-
-//         purchase_ticket(
-//             &mut banks_client,
-//             &payer,
-//             &recent_blockhash,
-//             ticket_data_rent,
-//             &user_charity,
-//             &ticket_numbers,
-//             &holding_wallet.pubkey(),
-//             &tickets[i],
-//             &users_wallets[i],
-//             &lottery_authority,
-//         )
-//         .await
-//         .unwrap();
-//     }
-
-//     // Check balances
-//     let mut prize_pool_sol = 0.0;
-//     for user in &users_wallets {
-//         prize_pool_sol += 0.1;
-//         check_balance(&mut banks_client, user.pubkey(), 0.9).await;
-//     }
-
-//     check_balance(&mut banks_client, holding_wallet.pubkey(), prize_pool_sol).await;
-
-//     // Finaled lottery
-//     store_winning_numbers(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         &winning_numbers,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     let participants = (0..number_of_users)
-//         .map(|i| (tickets_pubkeys[i], users_wallets_pubkeys[i]))
-//         .collect();
-
-//     // Reward winners
-//     reward_winners(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_result_data_rent,
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &charities_pubkeys,
-//         &participants,
-//         &holding_wallet,
-//         &lottery_result,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Check balances
-//     check_balance(&mut banks_client, holding_wallet.pubkey(), 0.0).await;
-//     check_balance(
-//         &mut banks_client,
-//         rewards_wallet.pubkey(),
-//         prize_pool_sol * 0.04,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         slot_holders_rewards_wallet.pubkey(),
-//         prize_pool_sol * 0.006,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         sollotto_labs_wallet.pubkey(),
-//         prize_pool_sol * 0.004,
-//     )
-//     .await;
-
-//     // Charity balances (2 charity wins)
-//     check_balance(
-//         &mut banks_client,
-//         charities_pubkeys[0],
-//         (prize_pool_sol * 0.3) / 2.0,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         charities_pubkeys[1],
-//         (prize_pool_sol * 0.3) / 2.0,
-//     )
-//     .await;
-//     for i in 2..4 {
-//         check_balance(&mut banks_client, charities_pubkeys[i], 0.0).await;
-//     }
-
-//     // Winner balance
-//     check_balance(
-//         &mut banks_client,
-//         users_wallets_pubkeys[0],
-//         prize_pool_sol * 0.65 + 0.9,
-//     )
-//     .await;
-//     // Loosers balances
-//     for i in 1..5 {
-//         check_balance(&mut banks_client, users_wallets_pubkeys[i], 0.9).await;
-//     }
-// }
-
-// #[tokio::test]
-// async fn test_many_users_many_winners() {
-//     let program = ProgramTest::new("sollotto", id(), processor!(Processor::process));
-//     let (mut banks_client, payer, recent_blockhash) = program.start().await;
-
-//     let rent = banks_client.get_rent().await.unwrap();
-//     let lottery_result_data_rent = rent.minimum_balance(LotteryResultData::LEN);
-//     let ticket_data_rent = rent.minimum_balance(TicketData::LEN);
-//     let lottery_data_rent = rent.minimum_balance(LotteryData::LEN);
-
-//     let number_of_users = 25;
-//     let number_of_winners = 10;
-//     let lottery_id = 112233;
-//     let lottery_authority = Keypair::new();
-//     let lottery_result = Keypair::new();
-//     let holding_wallet = Keypair::new();
-//     let rewards_wallet = Keypair::new();
-//     let slot_holders_rewards_wallet = Keypair::new();
-//     let sollotto_labs_wallet = Keypair::new();
-//     let charities: Vec<Keypair> = (0..4).map(|_| Keypair::new()).collect();
-//     let charities_pubkeys: Vec<Pubkey> = charities.iter().map(|c| c.pubkey()).collect();
-//     let users_wallets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let users_wallets_pubkeys: Vec<Pubkey> = users_wallets.iter().map(|x| x.pubkey()).collect();
-//     let tickets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let tickets_pubkeys: Vec<Pubkey> = tickets.iter().map(|x| x.pubkey()).collect();
-//     let winning_numbers = [1, 2, 3, 4, 5, 6];
-//     let not_winning_numbers = [1, 1, 1, 1, 1, 1];
-
-//     // Set initial balances for users
-//     for user in &users_wallets {
-//         transfer_sol(&mut banks_client, &recent_blockhash, &payer, user, 1.0)
-//             .await
-//             .unwrap();
-//     }
-
-//     initialize_lottery(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_data_rent,
-//         lottery_id,
-//         &charities_pubkeys,
-//         &holding_wallet.pubkey(),
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Users purchase tickets
-//     let mut ticket_numbers;
-//     for i in 0..number_of_users {
-//         if i < number_of_winners {
-//             ticket_numbers = winning_numbers;
-//         } else {
-//             ticket_numbers = not_winning_numbers;
-//         }
-
-//         purchase_ticket(
-//             &mut banks_client,
-//             &payer,
-//             &recent_blockhash,
-//             ticket_data_rent,
-//             &charities_pubkeys[0],
-//             &ticket_numbers,
-//             &holding_wallet.pubkey(),
-//             &tickets[i],
-//             &users_wallets[i],
-//             &lottery_authority,
-//         )
-//         .await
-//         .unwrap();
-//     }
-
-//     // Check balances
-//     let mut prize_pool_sol = 0.0;
-//     for user in &users_wallets {
-//         prize_pool_sol += 0.1;
-//         check_balance(&mut banks_client, user.pubkey(), 0.9).await;
-//     }
-
-//     check_balance(&mut banks_client, holding_wallet.pubkey(), prize_pool_sol).await;
-
-//     // Finaled lottery
-//     store_winning_numbers(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         &winning_numbers,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     let participants = (0..number_of_users)
-//         .map(|i| (tickets_pubkeys[i], users_wallets_pubkeys[i]))
-//         .collect();
-
-//     // Reward winners
-//     reward_winners(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_result_data_rent,
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &charities_pubkeys,
-//         &participants,
-//         &holding_wallet,
-//         &lottery_result,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Check balances
-//     check_balance(&mut banks_client, holding_wallet.pubkey(), 0.0).await;
-//     check_balance(
-//         &mut banks_client,
-//         rewards_wallet.pubkey(),
-//         prize_pool_sol * 0.04,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         slot_holders_rewards_wallet.pubkey(),
-//         prize_pool_sol * 0.006,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         sollotto_labs_wallet.pubkey(),
-//         prize_pool_sol * 0.004,
-//     )
-//     .await;
-
-//     // Charity balances
-//     check_balance(
-//         &mut banks_client,
-//         charities_pubkeys[0],
-//         prize_pool_sol * 0.3,
-//     )
-//     .await;
-//     for i in 1..4 {
-//         check_balance(&mut banks_client, charities_pubkeys[i], 0.0).await;
-//     }
-
-//     // Winner balance
-//     for i in 0..number_of_winners {
-//         check_balance(
-//             &mut banks_client,
-//             users_wallets_pubkeys[i],
-//             (prize_pool_sol * 0.65) / 10.0 + 0.9,
-//         )
-//         .await;
-//     }
-//     // Loosers balances
-//     for i in number_of_winners..number_of_users {
-//         check_balance(&mut banks_client, users_wallets_pubkeys[i], 0.9).await;
-//     }
-// }
-
-// #[tokio::test]
-// async fn test_many_users_without_winners() {
-//     let program = ProgramTest::new("sollotto", id(), processor!(Processor::process));
-//     let (mut banks_client, payer, recent_blockhash) = program.start().await;
-
-//     let rent = banks_client.get_rent().await.unwrap();
-//     let lottery_result_data_rent = rent.minimum_balance(LotteryResultData::LEN);
-//     let ticket_data_rent = rent.minimum_balance(TicketData::LEN);
-//     let lottery_data_rent = rent.minimum_balance(LotteryData::LEN);
-
-//     let number_of_users = 30;
-//     let lottery_id = 112233;
-//     let lottery_authority = Keypair::new();
-//     let lottery_result = Keypair::new();
-//     let holding_wallet = Keypair::new();
-//     let rewards_wallet = Keypair::new();
-//     let slot_holders_rewards_wallet = Keypair::new();
-//     let sollotto_labs_wallet = Keypair::new();
-//     let charities: Vec<Keypair> = (0..4).map(|_| Keypair::new()).collect();
-//     let charities_pubkeys: Vec<Pubkey> = charities.iter().map(|c| c.pubkey()).collect();
-//     let users_wallets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let users_wallets_pubkeys: Vec<Pubkey> = users_wallets.iter().map(|x| x.pubkey()).collect();
-//     let tickets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let tickets_pubkeys: Vec<Pubkey> = tickets.iter().map(|x| x.pubkey()).collect();
-//     let winning_numbers = [1, 2, 3, 4, 5, 6];
-//     let not_winning_numbers = [1, 1, 1, 1, 1, 1];
-
-//     // Set initial balances for users
-//     for user in &users_wallets {
-//         transfer_sol(&mut banks_client, &recent_blockhash, &payer, user, 1.0)
-//             .await
-//             .unwrap();
-//     }
-
-//     initialize_lottery(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_data_rent,
-//         lottery_id,
-//         &charities_pubkeys,
-//         &holding_wallet.pubkey(),
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Users purchase tickets
-//     let mut user_charity;
-//     for i in 0..number_of_users {
-//         if i < 9 {
-//             user_charity = charities_pubkeys[0];
-//         } else if i >= 9 && i < 18 {
-//             user_charity = charities_pubkeys[2];
-//         } else if i >= 18 && i < 27 {
-//             user_charity = charities_pubkeys[3];
-//         } else {
-//             user_charity = charities_pubkeys[1];
-//         }
-
-//         purchase_ticket(
-//             &mut banks_client,
-//             &payer,
-//             &recent_blockhash,
-//             ticket_data_rent,
-//             &user_charity,
-//             &not_winning_numbers,
-//             &holding_wallet.pubkey(),
-//             &tickets[i],
-//             &users_wallets[i],
-//             &lottery_authority,
-//         )
-//         .await
-//         .unwrap();
-//     }
-
-//     // Check balances
-//     let mut prize_pool_sol = 0.0;
-//     for user in &users_wallets {
-//         prize_pool_sol += 0.1;
-//         check_balance(&mut banks_client, user.pubkey(), 0.9).await;
-//     }
-
-//     check_balance(&mut banks_client, holding_wallet.pubkey(), prize_pool_sol).await;
-
-//     // Finaled lottery
-//     store_winning_numbers(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         &winning_numbers,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     let participants = (0..number_of_users)
-//         .map(|i| (tickets_pubkeys[i], users_wallets_pubkeys[i]))
-//         .collect();
-
-//     // Reward winners
-//     reward_winners(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_result_data_rent,
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &charities_pubkeys,
-//         &participants,
-//         &holding_wallet,
-//         &lottery_result,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Check balances
-//     check_balance(
-//         &mut banks_client,
-//         holding_wallet.pubkey(),
-//         prize_pool_sol * 0.65,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         rewards_wallet.pubkey(),
-//         prize_pool_sol * 0.04,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         slot_holders_rewards_wallet.pubkey(),
-//         prize_pool_sol * 0.006,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         sollotto_labs_wallet.pubkey(),
-//         prize_pool_sol * 0.004,
-//     )
-//     .await;
-
-//     // Charity balances (3 wins)
-//     check_balance(
-//         &mut banks_client,
-//         charities_pubkeys[0],
-//         (prize_pool_sol * 0.3) / 3.0,
-//     )
-//     .await;
-//     check_balance(&mut banks_client, charities_pubkeys[1], 0.0).await;
-//     check_balance(
-//         &mut banks_client,
-//         charities_pubkeys[2],
-//         (prize_pool_sol * 0.3) / 3.0,
-//     )
-//     .await;
-//     check_balance(
-//         &mut banks_client,
-//         charities_pubkeys[3],
-//         (prize_pool_sol * 0.3) / 3.0,
-//     )
-//     .await;
-
-//     // Loosers balances
-//     for i in 0..number_of_users {
-//         check_balance(&mut banks_client, users_wallets_pubkeys[i], 0.9).await;
-//     }
-// }
-
-// #[tokio::test]
-// async fn test_insufficient_funds() {
-//     let program = ProgramTest::new("sollotto", id(), processor!(Processor::process));
-//     let (mut banks_client, payer, recent_blockhash) = program.start().await;
-
-//     let rent = banks_client.get_rent().await.unwrap();
-//     let lottery_result_data_rent = rent.minimum_balance(LotteryResultData::LEN);
-//     let ticket_data_rent = rent.minimum_balance(TicketData::LEN);
-//     let lottery_data_rent = rent.minimum_balance(LotteryData::LEN);
-
-//     let number_of_users = 5;
-//     let lottery_id = 112233;
-//     let lottery_authority = Keypair::new();
-//     let lottery_result = Keypair::new();
-//     let holding_wallet = Keypair::new();
-//     let rewards_wallet = Keypair::new();
-//     let slot_holders_rewards_wallet = Keypair::new();
-//     let sollotto_labs_wallet = Keypair::new();
-//     let charities: Vec<Keypair> = (0..4).map(|_| Keypair::new()).collect();
-//     let charities_pubkeys: Vec<Pubkey> = charities.iter().map(|c| c.pubkey()).collect();
-//     let users_wallets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let users_wallets_pubkeys: Vec<Pubkey> = users_wallets.iter().map(|x| x.pubkey()).collect();
-//     let tickets: Vec<Keypair> = (0..number_of_users).map(|_| Keypair::new()).collect();
-//     let tickets_pubkeys: Vec<Pubkey> = tickets.iter().map(|x| x.pubkey()).collect();
-//     let winning_numbers = [1, 2, 3, 4, 5, 6];
-//     let not_winning_numbers = [1, 1, 1, 1, 1, 1];
-
-//     initialize_lottery(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         lottery_data_rent,
-//         lottery_id,
-//         &charities_pubkeys,
-//         &holding_wallet.pubkey(),
-//         &rewards_wallet.pubkey(),
-//         &slot_holders_rewards_wallet.pubkey(),
-//         &sollotto_labs_wallet.pubkey(),
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Create tickets accounts
-//     for i in 0..number_of_users {
-//         create_ticket_account(
-//             &mut banks_client,
-//             &payer,
-//             &recent_blockhash,
-//             ticket_data_rent,
-//             &tickets[i],
-//         )
-//         .await
-//         .unwrap();
-//     }
-
-//     // Users try to purchase tickets
-//     for i in 0..number_of_users {
-//         assert_eq!(
-//             TransactionError::InstructionError(0, InstructionError::InsufficientFunds),
-//             purchase_ticket_without_creation(
-//                 &mut banks_client,
-//                 &payer,
-//                 &recent_blockhash,
-//                 &charities_pubkeys[0],
-//                 &not_winning_numbers,
-//                 &holding_wallet.pubkey(),
-//                 &tickets[i],
-//                 &users_wallets[i],
-//                 &lottery_authority,
-//             )
-//             .await
-//             .unwrap_err()
-//             .unwrap(),
-//         );
-//     }
-
-//     // Set initial balances for users
-//     for i in 0..number_of_users {
-//         transfer_sol(
-//             &mut banks_client,
-//             &recent_blockhash,
-//             &payer,
-//             &users_wallets[i],
-//             1.0,
-//         )
-//         .await
-//         .unwrap();
-//     }
-
-//     // Users purchase tickets
-//     for i in 0..number_of_users {
-//         purchase_ticket_without_creation(
-//             &mut banks_client,
-//             &payer,
-//             &recent_blockhash,
-//             &charities_pubkeys[1],
-//             &not_winning_numbers,
-//             &holding_wallet.pubkey(),
-//             &tickets[i],
-//             &users_wallets[i],
-//             &lottery_authority,
-//         )
-//         .await
-//         .unwrap();
-//     }
-
-//     // Finaled lottery
-//     store_winning_numbers(
-//         &mut banks_client,
-//         &payer,
-//         &recent_blockhash,
-//         &winning_numbers,
-//         &lottery_authority,
-//     )
-//     .await
-//     .unwrap();
-
-//     let participants = (0..number_of_users)
-//         .map(|i| (tickets_pubkeys[i], users_wallets_pubkeys[i]))
-//         .collect();
-
-//     // Spend prize pool from holding wallet acc
-//     transfer_sol(
-//         &mut banks_client,
-//         &recent_blockhash,
-//         &holding_wallet,
-//         &payer,
-//         0.4,
-//     )
-//     .await
-//     .unwrap();
-
-//     // Try to Reward winners
-//     assert_eq!(
-//         TransactionError::InstructionError(1, InstructionError::InsufficientFunds),
-//         reward_winners(
-//             &mut banks_client,
-//             &payer,
-//             &recent_blockhash,
-//             lottery_result_data_rent,
-//             &rewards_wallet.pubkey(),
-//             &slot_holders_rewards_wallet.pubkey(),
-//             &sollotto_labs_wallet.pubkey(),
-//             &charities_pubkeys,
-//             &participants,
-//             &holding_wallet,
-//             &lottery_result,
-//             &lottery_authority,
-//         )
-//         .await
-//         .unwrap_err()
-//         .unwrap(),
-//     );
-// }
+use solana_program::{
+    bpf_loader_upgradeable,
+    clock::{Clock, UnixTimestamp},
+    instruction::{Instruction, InstructionError},
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use sollotto_model_1::{
+    distribution::CharityMode,
+    error::LotteryError,
+    numbers::{derive_winning_numbers, LotteryNumbers, NumberFormat},
+    processor::{id, Processor},
+    randomness::RandomnessProvider,
+    state::{
+        find_lottery_address, find_lottery_result_address, find_referral_address,
+        find_settlement_address, find_ticket_address, find_token_vault_address, find_vault_address,
+        LotteryData, LotteryResultData, Prize, PrizeTier, ReferralData, SettlementData,
+    },
+};
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+use switchboard_program::SwitchboardAccountType;
+
+const TICKET_PRICE: u64 = 100_000_000;
+const MAIN_POOL_SIZE: u8 = 69;
+const BONUS_POOL_SIZE: u8 = 29;
+const PICK_COUNT: u8 = 5;
+const SALE_PERIOD: i64 = 1_000;
+const CLAIM_PERIOD: i64 = 1_000;
+/// Payout wallets exist before the draw, so rewards below the rent-exempt minimum are accepted
+const WALLET_BALANCE: u64 = 1_000_000_000;
+const USER_BALANCE: u64 = 10_000_000_000;
+
+const NUMBER_FORMAT: NumberFormat = NumberFormat {
+    main_pool_size: MAIN_POOL_SIZE,
+    bonus_pool_size: BONUS_POOL_SIZE,
+    pick_count: PICK_COUNT,
+};
+
+// Helpers
+
+fn prize_tiers() -> Vec<PrizeTier> {
+    vec![
+        PrizeTier {
+            main_matches: 5,
+            bonus_match: true,
+            prize: Prize::Share(7_000),
+        },
+        PrizeTier {
+            main_matches: 4,
+            bonus_match: true,
+            prize: Prize::Share(3_000),
+        },
+    ]
+}
+
+fn bps(amount: u64, share: u64) -> u64 {
+    amount * share / 10_000
+}
+
+/// Data of a Switchboard VRF account holding the verified randomness: the account type,
+/// then the length delimited `VrfAccountData` protobuf message with the value and the proof
+/// confirmations. Field tags are the protobuf keys, the lengths fit in one byte varints
+fn vrf_account_data(randomness: &[u8]) -> Vec<u8> {
+    // min_proof_confirmations = 1
+    let mut message = vec![32, 1];
+    // value
+    message.extend_from_slice(&[66, randomness.len() as u8]);
+    message.extend_from_slice(randomness);
+    // num_proof_confirmations = 1
+    message.extend_from_slice(&[80, 1]);
+
+    let mut data = vec![SwitchboardAccountType::TYPE_VRF as u8, message.len() as u8];
+    data.extend_from_slice(&message);
+    data
+}
+
+/// Winning numbers the draw derives from the randomness
+fn winning_numbers(randomness: &[u8]) -> LotteryNumbers {
+    let mut numbers = derive_winning_numbers(randomness, &NUMBER_FORMAT).unwrap();
+    NUMBER_FORMAT.sort_main_numbers(&mut numbers);
+    numbers
+}
+
+/// Ticket line matching the first `main_matches` winning main numbers,
+/// other main numbers and a missed bonus number are never drawn
+fn ticket_line(
+    winning_numbers: &LotteryNumbers,
+    main_matches: usize,
+    bonus_match: bool,
+) -> Vec<u8> {
+    let pick_count = PICK_COUNT as usize;
+    let mut line = winning_numbers[..main_matches].to_vec();
+    line.extend(
+        (1..=MAIN_POOL_SIZE)
+            .filter(|number| !winning_numbers[..pick_count].contains(number))
+            .take(pick_count - main_matches),
+    );
+    let bonus = winning_numbers[pick_count];
+    line.push(if bonus_match {
+        bonus
+    } else {
+        bonus % BONUS_POOL_SIZE + 1
+    });
+    line
+}
+
+/// Program with the charity registry, the payout wallets and funded users
+struct TestEnv {
+    context: ProgramTestContext,
+    authority: Keypair,
+    charities: Vec<Pubkey>,
+    rewards_wallet: Pubkey,
+    slot_holders_rewards_wallet: Pubkey,
+    sollotto_labs_wallet: Pubkey,
+    users: Vec<Keypair>,
+    randomness_account: Pubkey,
+}
+
+impl TestEnv {
+    async fn new(users_count: usize, charities_count: usize) -> Self {
+        let mut program_test =
+            ProgramTest::new("sollotto_model_1", id(), processor!(Processor::process));
+
+        let wallet = || Account::new(WALLET_BALANCE, 0, &solana_program::system_program::id());
+        let charities: Vec<Pubkey> = (0..charities_count).map(|_| Pubkey::new_unique()).collect();
+        for charity in charities.iter() {
+            program_test.add_account(*charity, wallet());
+        }
+        let rewards_wallet = Pubkey::new_unique();
+        let slot_holders_rewards_wallet = Pubkey::new_unique();
+        let sollotto_labs_wallet = Pubkey::new_unique();
+        program_test.add_account(rewards_wallet, wallet());
+        program_test.add_account(slot_holders_rewards_wallet, wallet());
+        program_test.add_account(sollotto_labs_wallet, wallet());
+
+        // Charity registry and lotteries are created by the program upgrade authority
+        let authority = Keypair::new();
+        let (program_data_key, _) =
+            Pubkey::find_program_address(&[id().as_ref()], &bpf_loader_upgradeable::id());
+        let mut program_data = vec![0; 45];
+        program_data[..4].copy_from_slice(&3u32.to_le_bytes());
+        program_data[12] = 1;
+        program_data[13..].copy_from_slice(authority.pubkey().as_ref());
+        program_test.add_account(
+            program_data_key,
+            Account {
+                lamports: Rent::default().minimum_balance(program_data.len()),
+                data: program_data,
+                owner: bpf_loader_upgradeable::id(),
+                ..Account::default()
+            },
+        );
+
+        let users: Vec<Keypair> = (0..users_count).map(|_| Keypair::new()).collect();
+        for user in users.iter() {
+            program_test.add_account(
+                user.pubkey(),
+                Account::new(USER_BALANCE, 0, &solana_program::system_program::id()),
+            );
+        }
+
+        let mut env = Self {
+            context: program_test.start_with_context().await,
+            authority,
+            charities,
+            rewards_wallet,
+            slot_holders_rewards_wallet,
+            sollotto_labs_wallet,
+            users,
+            randomness_account: Pubkey::new_unique(),
+        };
+
+        let payer = env.context.payer.pubkey();
+        let authority = env.authority.pubkey();
+        let mut instructions =
+            vec![
+                sollotto_model_1::instruction::init_charity_registry(&id(), &payer, &authority)
+                    .unwrap(),
+            ];
+        for charity in env.charities.iter() {
+            instructions.push(
+                sollotto_model_1::instruction::add_charity(
+                    &id(),
+                    &charity.to_bytes(),
+                    charity,
+                    &authority,
+                )
+                .unwrap(),
+            );
+        }
+        let authority = env.authority();
+        env.process(&instructions, &[&authority]).await.unwrap();
+
+        env
+    }
+
+    async fn process(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), TransactionError> {
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            self.context.last_blockhash,
+        );
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|err| err.unwrap())
+    }
+
+    async fn balance(&mut self, address: &Pubkey) -> u64 {
+        self.context
+            .banks_client
+            .get_balance(*address)
+            .await
+            .unwrap()
+    }
+
+    async fn unpack<T: Pack>(&mut self, address: &Pubkey) -> T {
+        let account = self
+            .context
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+            .unwrap();
+        T::unpack_unchecked(&account.data).unwrap()
+    }
+
+    async fn clock(&mut self) -> Clock {
+        self.context.banks_client.get_sysvar().await.unwrap()
+    }
+
+    async fn set_time(&mut self, unix_timestamp: UnixTimestamp) {
+        let clock = self.clock().await;
+        self.context.set_sysvar(&Clock {
+            unix_timestamp,
+            ..clock
+        });
+        self.refresh_blockhash().await;
+    }
+
+    /// Transactions failed before the change are retried with a new signature
+    async fn refresh_blockhash(&mut self) {
+        self.context.get_new_latest_blockhash().await.unwrap();
+    }
+
+    /// Publishes a new verified result of the mocked Switchboard VRF account
+    async fn set_randomness(&mut self, randomness: &[u8]) {
+        let data = vrf_account_data(randomness);
+        let mut account = AccountSharedData::new(
+            Rent::default().minimum_balance(data.len()),
+            data.len(),
+            &solana_program::system_program::id(),
+        );
+        account.set_data_from_slice(&data);
+        let randomness_account = self.randomness_account;
+        self.context.set_account(&randomness_account, &account);
+        self.refresh_blockhash().await;
+    }
+
+    /// Creates an account of the token program holding the packed state
+    fn set_token_program_account<T: Pack>(&mut self, address: &Pubkey, state: T) {
+        let mut account = AccountSharedData::new(
+            Rent::default().minimum_balance(T::LEN),
+            T::LEN,
+            &spl_token::id(),
+        );
+        let mut data = vec![0; T::LEN];
+        T::pack(state, &mut data).unwrap();
+        account.set_data_from_slice(&data);
+        self.context.set_account(address, &account);
+    }
+
+    /// Creates a mint and a token account of the mint for every wallet
+    fn create_token_accounts(&mut self, wallets: &[Pubkey], amount: u64) -> (Pubkey, Vec<Pubkey>) {
+        let mint = Pubkey::new_unique();
+        self.set_token_program_account(
+            &mint,
+            Mint {
+                mint_authority: COption::None,
+                supply: amount * wallets.len() as u64,
+                decimals: 6,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            },
+        );
+        let mut token_accounts = Vec::with_capacity(wallets.len());
+        for wallet in wallets.iter() {
+            let token_account = Pubkey::new_unique();
+            self.set_token_program_account(
+                &token_account,
+                TokenAccount {
+                    mint,
+                    owner: *wallet,
+                    amount,
+                    state: AccountState::Initialized,
+                    ..TokenAccount::default()
+                },
+            );
+            token_accounts.push(token_account);
+        }
+        (mint, token_accounts)
+    }
+
+    async fn token_balance(&mut self, address: &Pubkey) -> u64 {
+        self.unpack::<TokenAccount>(address).await.amount
+    }
+
+    fn user(&self, user: usize) -> Keypair {
+        Keypair::from_bytes(&self.users[user].to_bytes()).unwrap()
+    }
+
+    fn authority(&self) -> Keypair {
+        Keypair::from_bytes(&self.authority.to_bytes()).unwrap()
+    }
+
+    fn ticket_key(&self, lottery_id: u32, user: usize, ticket_nonce: u32) -> Pubkey {
+        let (lottery_key, _) = find_lottery_address(&id(), lottery_id);
+        find_ticket_address(
+            &id(),
+            &lottery_key,
+            &self.users[user].pubkey(),
+            ticket_nonce,
+        )
+        .0
+    }
+
+    /// Opens the sale of the lottery now, paid out by the prize table tiers
+    async fn init_lottery(
+        &mut self,
+        lottery_id: u32,
+        charity_mode: CharityMode,
+        previous_lottery_id: Option<u32>,
+    ) -> UnixTimestamp {
+        self.try_init_lottery(lottery_id, charity_mode, previous_lottery_id)
+            .await
+            .unwrap()
+    }
+
+    async fn try_init_lottery(
+        &mut self,
+        lottery_id: u32,
+        charity_mode: CharityMode,
+        previous_lottery_id: Option<u32>,
+    ) -> Result<UnixTimestamp, TransactionError> {
+        let now = self.clock().await.unix_timestamp;
+        let draw_time = now + SALE_PERIOD;
+        let charities: Vec<u16> = (0..self.charities.len() as u16).collect();
+        let payer = self.context.payer.pubkey();
+        let authority = self.authority();
+        self.process(
+            &[
+                sollotto_model_1::instruction::initialize_lottery(
+                    &id(),
+                    lottery_id,
+                    &charities,
+                    &self.rewards_wallet,
+                    &self.slot_holders_rewards_wallet,
+                    &self.sollotto_labs_wallet,
+                    &self.randomness_account,
+                    now,
+                    draw_time,
+                    draw_time,
+                    CLAIM_PERIOD,
+                    MAIN_POOL_SIZE,
+                    BONUS_POOL_SIZE,
+                    PICK_COUNT,
+                    TICKET_PRICE,
+                    charity_mode,
+                    RandomnessProvider::SwitchboardVrf,
+                    &[0; 32],
+                    previous_lottery_id,
+                    &payer,
+                    &authority.pubkey(),
+                )
+                .unwrap(),
+                sollotto_model_1::instruction::set_prize_table(
+                    &id(),
+                    lottery_id,
+                    &prize_tiers(),
+                    &payer,
+                    &authority.pubkey(),
+                )
+                .unwrap(),
+            ],
+            &[&authority],
+        )
+        .await?;
+        Ok(draw_time)
+    }
+
+    async fn purchase(
+        &mut self,
+        lottery_id: u32,
+        user: usize,
+        charity_index: u16,
+        lines: &[Vec<u8>],
+        ticket_nonce: u32,
+    ) -> Pubkey {
+        self.purchase_referred(lottery_id, user, charity_index, lines, ticket_nonce, None)
+            .await
+    }
+
+    async fn purchase_referred(
+        &mut self,
+        lottery_id: u32,
+        user: usize,
+        charity_index: u16,
+        lines: &[Vec<u8>],
+        ticket_nonce: u32,
+        referrer: Option<&Pubkey>,
+    ) -> Pubkey {
+        let user_wallet = self.user(user);
+        let lines: Vec<&[u8]> = lines.iter().map(|line| line.as_slice()).collect();
+        self.process(
+            &[sollotto_model_1::instruction::purchase_tickets(
+                &id(),
+                lottery_id,
+                charity_index,
+                &user_wallet.pubkey(),
+                &lines,
+                ticket_nonce,
+                None,
+                None,
+                referrer,
+            )
+            .unwrap()],
+            &[&user_wallet],
+        )
+        .await
+        .unwrap();
+        self.ticket_key(lottery_id, user, ticket_nonce)
+    }
+
+    async fn set_referral_share(&mut self, lottery_id: u32, share_bps: u16) {
+        let authority = self.authority();
+        self.process(
+            &[sollotto_model_1::instruction::set_referral_share(
+                &id(),
+                lottery_id,
+                share_bps,
+                &authority.pubkey(),
+            )
+            .unwrap()],
+            &[&authority],
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn claim_referral_rewards(
+        &mut self,
+        lottery_id: u32,
+        user: usize,
+    ) -> Result<(), TransactionError> {
+        let referrer = self.user(user);
+        self.process(
+            &[sollotto_model_1::instruction::claim_referral_rewards(
+                &id(),
+                lottery_id,
+                &referrer.pubkey(),
+            )
+            .unwrap()],
+            &[&referrer],
+        )
+        .await
+    }
+
+    async fn cancel_lottery(&mut self, lottery_id: u32) {
+        let authority = self.authority();
+        self.process(
+            &[sollotto_model_1::instruction::cancel_lottery(
+                &id(),
+                lottery_id,
+                &authority.pubkey(),
+            )
+            .unwrap()],
+            &[&authority],
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn refund_ticket(&mut self, lottery_id: u32, user: usize, ticket_nonce: u32) {
+        self.refund_referred_ticket(lottery_id, user, ticket_nonce, None)
+            .await
+    }
+
+    async fn refund_referred_ticket(
+        &mut self,
+        lottery_id: u32,
+        user: usize,
+        ticket_nonce: u32,
+        referrer: Option<&Pubkey>,
+    ) {
+        let user_wallet = self.user(user);
+        self.process(
+            &[sollotto_model_1::instruction::refund_ticket(
+                &id(),
+                lottery_id,
+                &user_wallet.pubkey(),
+                ticket_nonce,
+                None,
+                referrer,
+            )
+            .unwrap()],
+            &[],
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn request_draw(&mut self, lottery_id: u32) {
+        let authority = self.authority();
+        let randomness_account = self.randomness_account;
+        self.process(
+            &[sollotto_model_1::instruction::request_draw(
+                &id(),
+                lottery_id,
+                &authority.pubkey(),
+                &randomness_account,
+            )
+            .unwrap()],
+            &[&authority],
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn store_winning_numbers(&mut self, lottery_id: u32) -> Result<(), TransactionError> {
+        let authority = self.authority();
+        let randomness_account = self.randomness_account;
+        self.process(
+            &[sollotto_model_1::instruction::store_winning_numbers(
+                &id(),
+                lottery_id,
+                &authority.pubkey(),
+                &randomness_account,
+            )
+            .unwrap()],
+            &[&authority],
+        )
+        .await
+    }
+
+    /// Tallies and pays out all tickets, then pays the charity and sollotto rewards
+    async fn settle(&mut self, lottery_id: u32, tickets: &[Pubkey]) {
+        let payer = self.context.payer.pubkey();
+        let authority = self.authority();
+        self.process(
+            &[
+                sollotto_model_1::instruction::tally_batch(
+                    &id(),
+                    lottery_id,
+                    &payer,
+                    &authority.pubkey(),
+                    tickets,
+                )
+                .unwrap(),
+                sollotto_model_1::instruction::payout_batch(
+                    &id(),
+                    lottery_id,
+                    &authority.pubkey(),
+                    tickets,
+                    None,
+                )
+                .unwrap(),
+                sollotto_model_1::instruction::reward_winners(
+                    &id(),
+                    lottery_id,
+                    &payer,
+                    &authority.pubkey(),
+                    &self.rewards_wallet,
+                    &self.slot_holders_rewards_wallet,
+                    &self.sollotto_labs_wallet,
+                    &self.charities,
+                    None,
+                )
+                .unwrap(),
+            ],
+            &[&authority],
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn claim_prize(&mut self, lottery_id: u32, user: usize, ticket_nonce: u32) {
+        let user_wallet = self.user(user);
+        self.process(
+            &[sollotto_model_1::instruction::claim_prize(
+                &id(),
+                lottery_id,
+                &user_wallet.pubkey(),
+                ticket_nonce,
+                None,
+            )
+            .unwrap()],
+            &[&user_wallet],
+        )
+        .await
+        .unwrap();
+    }
+
+    /// Balances of the rewards, SLOT holders and labs wallets, then the charities
+    async fn payout_balances(&mut self) -> Vec<u64> {
+        let mut wallets = vec![
+            self.rewards_wallet,
+            self.slot_holders_rewards_wallet,
+            self.sollotto_labs_wallet,
+        ];
+        wallets.extend_from_slice(&self.charities);
+        let mut balances = Vec::with_capacity(wallets.len());
+        for wallet in wallets.iter() {
+            balances.push(self.balance(wallet).await);
+        }
+        balances
+    }
+
+    /// Checks non winners shares of the prize pool are paid to the payout wallets
+    async fn check_payouts(&mut self, balances: &[u64], prize_pool: u64, charity_rewards: &[u64]) {
+        let charity_paid: u64 = charity_rewards.iter().sum();
+        let slot_holders_reward = bps(prize_pool, 60);
+        let sollotto_labs_reward = bps(prize_pool, 40);
+        // Rounding dust of the non winners shares is paid to the rewards wallet
+        let sollotto_reward = prize_pool
+            - bps(prize_pool, 6_500)
+            - charity_paid
+            - slot_holders_reward
+            - sollotto_labs_reward;
+        assert!(sollotto_reward >= bps(prize_pool, 400));
+
+        let mut expected = vec![
+            balances[0] + sollotto_reward,
+            balances[1] + slot_holders_reward,
+            balances[2] + sollotto_labs_reward,
+        ];
+        for (balance, charity_reward) in balances[3..].iter().zip(charity_rewards.iter()) {
+            expected.push(balance + charity_reward);
+        }
+        assert_eq!(self.payout_balances().await, expected);
+    }
+}
+
+#[tokio::test]
+async fn test_one_winner() {
+    let mut env = TestEnv::new(3, 2).await;
+    let lottery_id = 1;
+    let (lottery_key, _) = find_lottery_address(&id(), lottery_id);
+    let (vault_key, _) = find_vault_address(&id(), &lottery_key);
+    let (settlement_key, _) = find_settlement_address(&id(), &lottery_key);
+    let randomness = [7; 32];
+    let winning_numbers = winning_numbers(&randomness);
+    let rent = env.context.banks_client.get_rent().await.unwrap();
+
+    let draw_time = env
+        .init_lottery(lottery_id, CharityMode::WinnerTakesAll, None)
+        .await;
+    assert_eq!(env.balance(&vault_key).await, rent.minimum_balance(0));
+
+    let user_balance = env.balance(&env.users[0].pubkey()).await;
+    let tickets = vec![
+        env.purchase(
+            lottery_id,
+            0,
+            0,
+            &[ticket_line(&winning_numbers, 5, true)],
+            0,
+        )
+        .await,
+        env.purchase(
+            lottery_id,
+            1,
+            1,
+            &[ticket_line(&winning_numbers, 0, false)],
+            0,
+        )
+        .await,
+        env.purchase(
+            lottery_id,
+            2,
+            0,
+            &[ticket_line(&winning_numbers, 3, false)],
+            0,
+        )
+        .await,
+    ];
+    let ticket_rent = env.balance(&tickets[0]).await;
+    assert_eq!(
+        env.balance(&env.users[0].pubkey()).await,
+        user_balance - TICKET_PRICE - ticket_rent
+    );
+    let prize_pool = 3 * TICKET_PRICE;
+    assert_eq!(
+        env.balance(&vault_key).await,
+        rent.minimum_balance(0) + prize_pool
+    );
+
+    // Randomness account holds a result produced before the sale is closed
+    env.set_randomness(&[1; 32]).await;
+
+    // BadCase: Winning numbers are stored before the draw time
+    assert_eq!(
+        env.store_winning_numbers(lottery_id).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LotteryError::DrawNotReady as u32)
+        ))
+    );
+
+    // BadCase: Draw is not requested
+    env.set_time(draw_time).await;
+    assert_eq!(
+        env.store_winning_numbers(lottery_id).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LotteryError::DrawNotReady as u32)
+        ))
+    );
+
+    // BadCase: Randomness result is produced before the draw request
+    env.request_draw(lottery_id).await;
+    env.refresh_blockhash().await;
+    assert_eq!(
+        env.store_winning_numbers(lottery_id).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LotteryError::StaleRandomness as u32)
+        ))
+    );
+
+    env.set_randomness(&randomness).await;
+    env.store_winning_numbers(lottery_id).await.unwrap();
+    let lottery: LotteryData = env.unpack(&lottery_key).await;
+    assert_eq!(lottery.winning_numbers, winning_numbers);
+
+    // Anyone can fund the settlement and result addresses before the program creates them
+    let payer = env.context.payer.pubkey();
+    let (lottery_result_key, _) = find_lottery_result_address(&id(), lottery_id);
+    env.process(
+        &[
+            system_instruction::transfer(&payer, &settlement_key, rent.minimum_balance(0)),
+            system_instruction::transfer(&payer, &lottery_result_key, rent.minimum_balance(0)),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    // Settlement runs late, the claim period starts with the payout
+    let settle_time = draw_time + 2 * CLAIM_PERIOD;
+    env.set_time(settle_time).await;
+    let balances = env.payout_balances().await;
+    env.settle(lottery_id, &tickets).await;
+    let settlement: SettlementData = env.unpack(&settlement_key).await;
+    assert_eq!(settlement.claim_deadline, settle_time + CLAIM_PERIOD);
+
+    let winners_pool = bps(prize_pool, 6_500);
+    let jackpot = bps(winners_pool, 7_000);
+    let rollover = winners_pool - jackpot;
+    // Charity with the most votes takes the pool
+    env.check_payouts(&balances, prize_pool, &[bps(prize_pool, 3_000), 0])
+        .await;
+    assert_eq!(
+        env.balance(&vault_key).await,
+        rent.minimum_balance(0) + rollover
+    );
+
+    let lottery_result: LotteryResultData = env.unpack(&lottery_result_key).await;
+    assert_eq!(lottery_result.total_tickets, 3);
+    assert_eq!(lottery_result.prize_pool_amount, prize_pool);
+    assert_eq!(lottery_result.tier_winners[..2], [1, 0]);
+    assert_eq!(lottery_result.tier_rewards[..2], [jackpot, 0]);
+    assert_eq!(lottery_result.rollover_amount, rollover);
+    assert_eq!(lottery_result.randomness[..], randomness[..]);
+
+    let settlement_balance = env.balance(&settlement_key).await;
+    let user_balance = env.balance(&env.users[0].pubkey()).await;
+    env.claim_prize(lottery_id, 0, 0).await;
+    assert_eq!(
+        env.balance(&env.users[0].pubkey()).await,
+        user_balance + jackpot
+    );
+    assert_eq!(
+        env.balance(&settlement_key).await,
+        settlement_balance - jackpot
+    );
+    let settlement: SettlementData = env.unpack(&settlement_key).await;
+    assert_eq!(settlement.claimed_amount, jackpot);
+}
+
+#[tokio::test]
+async fn test_many_winners() {
+    let mut env = TestEnv::new(4, 2).await;
+    let lottery_id = 2;
+    let (lottery_key, _) = find_lottery_address(&id(), lottery_id);
+    let (vault_key, _) = find_vault_address(&id(), &lottery_key);
+    let (settlement_key, _) = find_settlement_address(&id(), &lottery_key);
+    let randomness = [42; 32];
+    let winning_numbers = winning_numbers(&randomness);
+    let rent = env.context.banks_client.get_rent().await.unwrap();
+
+    let draw_time = env
+        .init_lottery(lottery_id, CharityMode::Proportional, None)
+        .await;
+    let tickets = vec![
+        env.purchase(
+            lottery_id,
+            0,
+            0,
+            &[ticket_line(&winning_numbers, 5, true)],
+            0,
+        )
+        .await,
+        env.purchase(
+            lottery_id,
+            1,
+            1,
+            &[ticket_line(&winning_numbers, 5, true)],
+            0,
+        )
+        .await,
+        env.purchase(
+            lottery_id,
+            2,
+            0,
+            &[ticket_line(&winning_numbers, 4, true)],
+            0,
+        )
+        .await,
+        env.purchase(
+            lottery_id,
+            3,
+            1,
+            &[
+                ticket_line(&winning_numbers, 4, true),
+                ticket_line(&winning_numbers, 4, false),
+            ],
+            0,
+        )
+        .await,
+    ];
+    let prize_pool = 5 * TICKET_PRICE;
+    assert_eq!(
+        env.balance(&vault_key).await,
+        rent.minimum_balance(0) + prize_pool
+    );
+
+    env.set_time(draw_time).await;
+    env.request_draw(lottery_id).await;
+    env.set_randomness(&randomness).await;
+    env.store_winning_numbers(lottery_id).await.unwrap();
+
+    let balances = env.payout_balances().await;
+    env.settle(lottery_id, &tickets).await;
+
+    // Tier shares are split equally among the tier winners
+    let winners_pool = bps(prize_pool, 6_500);
+    let jackpot = bps(winners_pool, 7_000) / 2;
+    let second_prize = bps(winners_pool, 3_000) / 2;
+    let rollover = winners_pool - 2 * jackpot - 2 * second_prize;
+    // Charities take the pool by their share of the votes, 2 and 3 ticket lines
+    let charity_pool = bps(prize_pool, 3_000);
+    env.check_payouts(
+        &balances,
+        prize_pool,
+        &[charity_pool * 2 / 5, charity_pool * 3 / 5],
+    )
+    .await;
+    assert_eq!(
+        env.balance(&vault_key).await,
+        rent.minimum_balance(0) + rollover
+    );
+
+    let settlement_balance = env.balance(&settlement_key).await;
+    let prizes = [jackpot, jackpot, second_prize, second_prize];
+    for (user, prize) in prizes.iter().enumerate() {
+        let user_balance = env.balance(&env.users[user].pubkey()).await;
+        env.claim_prize(lottery_id, user, 0).await;
+        assert_eq!(
+            env.balance(&env.users[user].pubkey()).await,
+            user_balance + prize
+        );
+    }
+    let prizes_amount: u64 = prizes.iter().sum();
+    assert_eq!(
+        env.balance(&settlement_key).await,
+        settlement_balance - prizes_amount
+    );
+    let settlement: SettlementData = env.unpack(&settlement_key).await;
+    assert_eq!(settlement.escrowed_amount, prizes_amount);
+    assert_eq!(settlement.claimed_amount, prizes_amount);
+}
+
+#[tokio::test]
+async fn test_rollover() {
+    let mut env = TestEnv::new(2, 1).await;
+    let lottery_id = 3;
+    let next_lottery_id = 4;
+    let (lottery_key, _) = find_lottery_address(&id(), lottery_id);
+    let (vault_key, _) = find_vault_address(&id(), &lottery_key);
+    let (next_lottery_key, _) = find_lottery_address(&id(), next_lottery_id);
+    let (next_vault_key, _) = find_vault_address(&id(), &next_lottery_key);
+    let randomness = [3; 32];
+    let next_randomness = [4; 32];
+    let winning_numbers = winning_numbers(&randomness);
+    let next_winning_numbers = self::winning_numbers(&next_randomness);
+    let rent = env.context.banks_client.get_rent().await.unwrap();
+
+    // Nobody wins the first draw
+    let draw_time = env
+        .init_lottery(lottery_id, CharityMode::WinnerTakesAll, None)
+        .await;
+    let tickets = vec![
+        env.purchase(
+            lottery_id,
+            0,
+            0,
+            &[ticket_line(&winning_numbers, 4, false)],
+            0,
+        )
+        .await,
+        env.purchase(
+            lottery_id,
+            1,
+            0,
+            &[ticket_line(&winning_numbers, 0, true)],
+            0,
+        )
+        .await,
+    ];
+    env.set_time(draw_time).await;
+    env.request_draw(lottery_id).await;
+    env.set_randomness(&randomness).await;
+    env.store_winning_numbers(lottery_id).await.unwrap();
+
+    let prize_pool = 2 * TICKET_PRICE;
+    let balances = env.payout_balances().await;
+    env.settle(lottery_id, &tickets).await;
+    let rollover = bps(prize_pool, 6_500);
+    env.check_payouts(&balances, prize_pool, &[bps(prize_pool, 3_000)])
+        .await;
+    let lottery: LotteryData = env.unpack(&lottery_key).await;
+    assert_eq!(lottery.rollover_amount, rollover);
+    assert_eq!(
+        env.balance(&vault_key).await,
+        rent.minimum_balance(0) + rollover
+    );
+
+    // Next draw takes the rollover into its own vault
+    let draw_time = env
+        .init_lottery(
+            next_lottery_id,
+            CharityMode::WinnerTakesAll,
+            Some(lottery_id),
+        )
+        .await;
+    let lottery: LotteryData = env.unpack(&lottery_key).await;
+    assert_eq!(lottery.rollover_amount, 0);
+    let next_lottery: LotteryData = env.unpack(&next_lottery_key).await;
+    assert_eq!(next_lottery.prize_pool_amount, rollover);
+    assert_eq!(env.balance(&vault_key).await, rent.minimum_balance(0));
+    assert_eq!(
+        env.balance(&next_vault_key).await,
+        rent.minimum_balance(0) + rollover
+    );
+
+    let tickets = vec![
+        env.purchase(
+            next_lottery_id,
+            0,
+            0,
+            &[ticket_line(&next_winning_numbers, 5, true)],
+            1,
+        )
+        .await,
+        env.purchase(
+            next_lottery_id,
+            1,
+            0,
+            &[ticket_line(&next_winning_numbers, 2, false)],
+            1,
+        )
+        .await,
+    ];
+    env.set_time(draw_time).await;
+    env.request_draw(next_lottery_id).await;
+
+    // BadCase: Randomness account still returns the result used by the previous draw
+    assert_eq!(
+        env.store_winning_numbers(next_lottery_id).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LotteryError::StaleRandomness as u32)
+        ))
+    );
+
+    env.set_randomness(&next_randomness).await;
+    env.store_winning_numbers(next_lottery_id).await.unwrap();
+
+    let prize_pool = rollover + 2 * TICKET_PRICE;
+    let balances = env.payout_balances().await;
+    env.settle(next_lottery_id, &tickets).await;
+    let winners_pool = bps(prize_pool, 6_500);
+    let jackpot = bps(winners_pool, 7_000);
+    env.check_payouts(&balances, prize_pool, &[bps(prize_pool, 3_000)])
+        .await;
+    assert_eq!(
+        env.balance(&next_vault_key).await,
+        rent.minimum_balance(0) + winners_pool - jackpot
+    );
+
+    let user_balance = env.balance(&env.users[0].pubkey()).await;
+    env.claim_prize(next_lottery_id, 0, 1).await;
+    assert_eq!(
+        env.balance(&env.users[0].pubkey()).await,
+        user_balance + jackpot
+    );
+}
+
+#[tokio::test]
+async fn test_cancelled_rollover() {
+    let mut env = TestEnv::new(2, 1).await;
+    let lottery_id = 5;
+    let cancelled_lottery_id = 6;
+    let next_lottery_id = 7;
+    let (cancelled_lottery_key, _) = find_lottery_address(&id(), cancelled_lottery_id);
+    let (cancelled_vault_key, _) = find_vault_address(&id(), &cancelled_lottery_key);
+    let (next_lottery_key, _) = find_lottery_address(&id(), next_lottery_id);
+    let (next_vault_key, _) = find_vault_address(&id(), &next_lottery_key);
+    let randomness = [5; 32];
+    let winning_numbers = winning_numbers(&randomness);
+    let rent = env.context.banks_client.get_rent().await.unwrap();
+
+    // Nobody wins the first draw
+    let draw_time = env
+        .init_lottery(lottery_id, CharityMode::WinnerTakesAll, None)
+        .await;
+    let tickets = vec![
+        env.purchase(
+            lottery_id,
+            0,
+            0,
+            &[ticket_line(&winning_numbers, 4, false)],
+            0,
+        )
+        .await,
+    ];
+    env.set_time(draw_time).await;
+    env.request_draw(lottery_id).await;
+    env.set_randomness(&randomness).await;
+    env.store_winning_numbers(lottery_id).await.unwrap();
+    env.settle(lottery_id, &tickets).await;
+    let rollover = bps(TICKET_PRICE, 6_500);
+
+    // Cancelled draw holds the rollover of the first draw
+    env.init_lottery(
+        cancelled_lottery_id,
+        CharityMode::WinnerTakesAll,
+        Some(lottery_id),
+    )
+    .await;
+    env.purchase(
+        cancelled_lottery_id,
+        1,
+        0,
+        &[ticket_line(&winning_numbers, 1, false)],
+        0,
+    )
+    .await;
+    env.cancel_lottery(cancelled_lottery_id).await;
+
+    // BadCase: Ticket of the cancelled draw is not refunded
+    assert_eq!(
+        env.try_init_lottery(
+            next_lottery_id,
+            CharityMode::WinnerTakesAll,
+            Some(cancelled_lottery_id),
+        )
+        .await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LotteryError::InvalidRollover as u32)
+        ))
+    );
+
+    let user_balance = env.balance(&env.users[1].pubkey()).await;
+    env.refund_ticket(cancelled_lottery_id, 1, 0).await;
+    assert_eq!(
+        env.balance(&env.users[1].pubkey()).await,
+        user_balance + TICKET_PRICE
+    );
+    let lottery: LotteryData = env.unpack(&cancelled_lottery_key).await;
+    assert_eq!(lottery.total_registrations, 0);
+    assert_eq!(lottery.prize_pool_amount, rollover);
+
+    // Next draw finishes the cancelled draw and takes its rollover
+    env.refresh_blockhash().await;
+    env.init_lottery(
+        next_lottery_id,
+        CharityMode::WinnerTakesAll,
+        Some(cancelled_lottery_id),
+    )
+    .await;
+    let lottery: LotteryData = env.unpack(&cancelled_lottery_key).await;
+    assert_eq!(lottery.is_initialized, false);
+    assert_eq!(lottery.prize_pool_amount, 0);
+    assert_eq!(lottery.rollover_amount, 0);
+    let next_lottery: LotteryData = env.unpack(&next_lottery_key).await;
+    assert_eq!(next_lottery.prize_pool_amount, rollover);
+    assert_eq!(
+        env.balance(&cancelled_vault_key).await,
+        rent.minimum_balance(0)
+    );
+    assert_eq!(
+        env.balance(&next_vault_key).await,
+        rent.minimum_balance(0) + rollover
+    );
+}
+
+#[tokio::test]
+async fn test_referral_rewards() {
+    let mut env = TestEnv::new(3, 1).await;
+    let lottery_id = 5;
+    let (lottery_key, _) = find_lottery_address(&id(), lottery_id);
+    let (vault_key, _) = find_vault_address(&id(), &lottery_key);
+    let referrer = env.users[2].pubkey();
+    let (referral_key, _) = find_referral_address(&id(), &lottery_key, &referrer);
+    let randomness = [5; 32];
+    let winning_numbers = winning_numbers(&randomness);
+    let rent = env.context.banks_client.get_rent().await.unwrap();
+
+    let draw_time = env
+        .init_lottery(lottery_id, CharityMode::WinnerTakesAll, None)
+        .await;
+    env.set_referral_share(lottery_id, 500).await;
+
+    // First referred purchase pays the referral ledger rent
+    let user_balance = env.balance(&env.users[0].pubkey()).await;
+    let tickets = vec![
+        env.purchase_referred(
+            lottery_id,
+            0,
+            0,
+            &[ticket_line(&winning_numbers, 3, false)],
+            0,
+            Some(&referrer),
+        )
+        .await,
+        env.purchase_referred(
+            lottery_id,
+            1,
+            0,
+            &[
+                ticket_line(&winning_numbers, 2, false),
+                ticket_line(&winning_numbers, 1, true),
+            ],
+            0,
+            Some(&referrer),
+        )
+        .await,
+        env.purchase(
+            lottery_id,
+            2,
+            0,
+            &[ticket_line(&winning_numbers, 0, false)],
+            0,
+        )
+        .await,
+    ];
+    let ticket_rent = env.balance(&tickets[0]).await;
+    let referral_rent = rent.minimum_balance(ReferralData::LEN);
+    assert_eq!(
+        env.balance(&env.users[0].pubkey()).await,
+        user_balance - TICKET_PRICE - ticket_rent - referral_rent
+    );
+
+    let referral_volume = 3 * TICKET_PRICE;
+    let referral_rewards = bps(referral_volume, 500);
+    let prize_pool = 4 * TICKET_PRICE - referral_rewards;
+    assert_eq!(
+        env.balance(&referral_key).await,
+        referral_rent + referral_rewards
+    );
+    assert_eq!(
+        env.balance(&vault_key).await,
+        rent.minimum_balance(0) + prize_pool
+    );
+    let lottery: LotteryData = env.unpack(&lottery_key).await;
+    assert_eq!(lottery.prize_pool_amount, prize_pool);
+
+    // BadCase: Rewards are claimed before the draw is settled
+    assert_eq!(
+        env.claim_referral_rewards(lottery_id, 2).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LotteryError::NotFinaled as u32)
+        ))
+    );
+
+    env.set_time(draw_time).await;
+    env.request_draw(lottery_id).await;
+    env.set_randomness(&randomness).await;
+    env.store_winning_numbers(lottery_id).await.unwrap();
+    let balances = env.payout_balances().await;
+    env.settle(lottery_id, &tickets).await;
+    env.check_payouts(&balances, prize_pool, &[bps(prize_pool, 3_000)])
+        .await;
+
+    // Referral volume of the draw is kept in its result
+    let lottery_result: LotteryResultData = env
+        .unpack(&find_lottery_result_address(&id(), lottery_id).0)
+        .await;
+    assert_eq!(lottery_result.prize_pool_amount, prize_pool);
+    assert_eq!(lottery_result.referral_volume, referral_volume);
+    assert_eq!(lottery_result.referral_rewards, referral_rewards);
+    let lottery: LotteryData = env.unpack(&lottery_key).await;
+    assert_eq!(lottery.referral_volume, 0);
+
+    let referrer_balance = env.balance(&referrer).await;
+    env.claim_referral_rewards(lottery_id, 2).await.unwrap();
+    assert_eq!(
+        env.balance(&referrer).await,
+        referrer_balance + referral_rewards
+    );
+    assert_eq!(env.balance(&referral_key).await, referral_rent);
+    let referral: ReferralData = env.unpack(&referral_key).await;
+    assert_eq!(referral.accrued_amount, referral_rewards);
+    assert_eq!(referral.claimed_amount, referral_rewards);
+
+    // BadCase: Rewards are claimed twice
+    env.refresh_blockhash().await;
+    assert_eq!(
+        env.claim_referral_rewards(lottery_id, 2).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LotteryError::NothingToClaim as u32)
+        ))
+    );
+}
+
+#[tokio::test]
+async fn test_refunded_referral() {
+    let mut env = TestEnv::new(2, 1).await;
+    let lottery_id = 9;
+    let (lottery_key, _) = find_lottery_address(&id(), lottery_id);
+    let (vault_key, _) = find_vault_address(&id(), &lottery_key);
+    let referrer = env.users[1].pubkey();
+    let (referral_key, _) = find_referral_address(&id(), &lottery_key, &referrer);
+    let rent = env.context.banks_client.get_rent().await.unwrap();
+
+    env.init_lottery(lottery_id, CharityMode::WinnerTakesAll, None)
+        .await;
+    env.set_referral_share(lottery_id, 500).await;
+
+    let user_balance = env.balance(&env.users[0].pubkey()).await;
+    let ticket = env
+        .purchase_referred(
+            lottery_id,
+            0,
+            0,
+            &[vec![1, 2, 3, 4, 5, 6]],
+            0,
+            Some(&referrer),
+        )
+        .await;
+    let ticket_rent = env.balance(&ticket).await;
+    let referral_rent = rent.minimum_balance(ReferralData::LEN);
+    let referral_reward = bps(TICKET_PRICE, 500);
+    assert_eq!(
+        env.balance(&referral_key).await,
+        referral_rent + referral_reward
+    );
+
+    // BadCase: Rewards of a draw which can still be refunded are claimed
+    assert_eq!(
+        env.claim_referral_rewards(lottery_id, 1).await,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LotteryError::NotFinaled as u32)
+        ))
+    );
+
+    // Refund returns the full price, the referral reward comes back from the ledger
+    env.cancel_lottery(lottery_id).await;
+    env.refund_referred_ticket(lottery_id, 0, 0, Some(&referrer))
+        .await;
+    assert_eq!(
+        env.balance(&env.users[0].pubkey()).await,
+        user_balance - ticket_rent - referral_rent
+    );
+    assert_eq!(env.balance(&referral_key).await, referral_rent);
+    assert_eq!(env.balance(&vault_key).await, rent.minimum_balance(0));
+    let referral: ReferralData = env.unpack(&referral_key).await;
+    assert_eq!(referral.accrued_amount, 0);
+    let lottery: LotteryData = env.unpack(&lottery_key).await;
+    assert_eq!(lottery.total_registrations, 0);
+    assert_eq!(lottery.prize_pool_amount, 0);
+    assert_eq!(lottery.referral_rewards, 0);
+}
+
+#[tokio::test]
+async fn test_inactive_charity() {
+    let mut env = TestEnv::new(2, 1).await;
+    let lottery_id = 6;
+    let (lottery_key, _) = find_lottery_address(&id(), lottery_id);
+    let (vault_key, _) = find_vault_address(&id(), &lottery_key);
+    let randomness = [6; 32];
+    let winning_numbers = winning_numbers(&randomness);
+    let rent = env.context.banks_client.get_rent().await.unwrap();
+
+    let draw_time = env
+        .init_lottery(lottery_id, CharityMode::WinnerTakesAll, None)
+        .await;
+    let tickets = vec![
+        env.purchase(
+            lottery_id,
+            0,
+            0,
+            &[ticket_line(&winning_numbers, 2, false)],
+            0,
+        )
+        .await,
+        env.purchase(
+            lottery_id,
+            1,
+            0,
+            &[ticket_line(&winning_numbers, 1, true)],
+            0,
+        )
+        .await,
+    ];
+
+    // The only voted charity is deactivated before the draw
+    let authority = env.authority();
+    let charity = env.charities[0];
+    env.process(
+        &[sollotto_model_1::instruction::update_registry_charity(
+            &id(),
+            0,
+            &charity,
+            false,
+            &authority.pubkey(),
+        )
+        .unwrap()],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+
+    env.set_time(draw_time).await;
+    env.request_draw(lottery_id).await;
+    env.set_randomness(&randomness).await;
+    env.store_winning_numbers(lottery_id).await.unwrap();
+    let prize_pool = 2 * TICKET_PRICE;
+    let balances = env.payout_balances().await;
+    env.settle(lottery_id, &tickets).await;
+
+    // Charity pool rolls over with the unpaid winners share, the rewards wallet takes only its share
+    let rollover = bps(prize_pool, 6_500) + bps(prize_pool, 3_000);
+    assert_eq!(
+        env.payout_balances().await,
+        vec![
+            balances[0] + bps(prize_pool, 400),
+            balances[1] + bps(prize_pool, 60),
+            balances[2] + bps(prize_pool, 40),
+            balances[3],
+        ]
+    );
+    let lottery: LotteryData = env.unpack(&lottery_key).await;
+    assert_eq!(lottery.rollover_amount, rollover);
+    assert_eq!(
+        env.balance(&vault_key).await,
+        rent.minimum_balance(0) + rollover
+    );
+}
+
+#[tokio::test]
+async fn test_token_lottery() {
+    let mut env = TestEnv::new(2, 1).await;
+    let lottery_id = 8;
+    let (lottery_key, _) = find_lottery_address(&id(), lottery_id);
+    let (vault_key, _) = find_token_vault_address(&id(), &lottery_key);
+    let randomness = [8; 32];
+    let winning_numbers = winning_numbers(&randomness);
+    let payer = env.context.payer.pubkey();
+    let authority = env.authority();
+
+    // Users, payout wallets and the charity hold token accounts of the lottery mint
+    let mut wallets: Vec<Pubkey> = env.users.iter().map(|user| user.pubkey()).collect();
+    wallets.extend_from_slice(&[
+        env.rewards_wallet,
+        env.slot_holders_rewards_wallet,
+        env.sollotto_labs_wallet,
+        env.charities[0],
+    ]);
+    let (mint, token_accounts) = env.create_token_accounts(&wallets, 10 * TICKET_PRICE);
+    let balances = token_accounts[2..].to_vec();
+
+    let draw_time = env
+        .init_lottery(lottery_id, CharityMode::WinnerTakesAll, None)
+        .await;
+    env.process(
+        &[sollotto_model_1::instruction::init_vault(
+            &id(),
+            lottery_id,
+            &mint,
+            &payer,
+            &authority.pubkey(),
+        )
+        .unwrap()],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+
+    // First user wins the jackpot, second user wins the second tier
+    let lines = [
+        ticket_line(&winning_numbers, 5, true),
+        ticket_line(&winning_numbers, 4, true),
+    ];
+    let mut tickets = Vec::with_capacity(lines.len());
+    for (user, line) in lines.iter().enumerate() {
+        let user_wallet = env.user(user);
+        env.process(
+            &[sollotto_model_1::instruction::purchase_ticket(
+                &id(),
+                lottery_id,
+                0,
+                &user_wallet.pubkey(),
+                line,
+                0,
+                None,
+                Some((&vault_key, &token_accounts[user])),
+                None,
+            )
+            .unwrap()],
+            &[&user_wallet],
+        )
+        .await
+        .unwrap();
+        tickets.push(env.ticket_key(lottery_id, user, 0));
+    }
+    let prize_pool = 2 * TICKET_PRICE;
+    assert_eq!(env.token_balance(&vault_key).await, prize_pool);
+
+    env.set_time(draw_time).await;
+    env.request_draw(lottery_id).await;
+    env.set_randomness(&randomness).await;
+    env.store_winning_numbers(lottery_id).await.unwrap();
+    env.process(
+        &[
+            sollotto_model_1::instruction::tally_batch(
+                &id(),
+                lottery_id,
+                &payer,
+                &authority.pubkey(),
+                &tickets,
+            )
+            .unwrap(),
+            sollotto_model_1::instruction::payout_batch(
+                &id(),
+                lottery_id,
+                &authority.pubkey(),
+                &tickets,
+                Some(&vault_key),
+            )
+            .unwrap(),
+            sollotto_model_1::instruction::reward_winners(
+                &id(),
+                lottery_id,
+                &payer,
+                &authority.pubkey(),
+                &balances[0],
+                &balances[1],
+                &balances[2],
+                &balances[3..],
+                Some(&vault_key),
+            )
+            .unwrap(),
+        ],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+
+    let winners_pool = bps(prize_pool, 6_500);
+    let jackpot = bps(winners_pool, 7_000);
+    let second_prize = winners_pool - jackpot;
+    let mut payouts = Vec::with_capacity(balances.len());
+    for balance in balances.iter() {
+        payouts.push(env.token_balance(balance).await - 10 * TICKET_PRICE);
+    }
+    assert_eq!(
+        payouts,
+        vec![
+            bps(prize_pool, 400),
+            bps(prize_pool, 60),
+            bps(prize_pool, 40),
+            bps(prize_pool, 3_000),
+        ]
+    );
+    assert_eq!(env.token_balance(&vault_key).await, winners_pool);
+
+    let user_wallet = env.user(0);
+    env.process(
+        &[sollotto_model_1::instruction::claim_prize(
+            &id(),
+            lottery_id,
+            &user_wallet.pubkey(),
+            0,
+            Some((&vault_key, &token_accounts[0])),
+        )
+        .unwrap()],
+        &[&user_wallet],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.token_balance(&token_accounts[0]).await,
+        9 * TICKET_PRICE + jackpot
+    );
+
+    // Second prize is not claimed in time
+    env.set_time(draw_time + CLAIM_PERIOD + 1).await;
+    env.process(
+        &[sollotto_model_1::instruction::sweep_unclaimed(
+            &id(),
+            lottery_id,
+            &authority.pubkey(),
+            &balances[0],
+            Some(&vault_key),
+        )
+        .unwrap()],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    assert_eq!(env.token_balance(&vault_key).await, 0);
+    assert_eq!(
+        env.token_balance(&balances[0]).await,
+        10 * TICKET_PRICE + bps(prize_pool, 400) + second_prize
+    );
+}